@@ -0,0 +1,359 @@
+use super::*;
+
+/// A node copied by [`GraphEditorState::copy_selection`].
+#[derive(Clone, Debug)]
+struct CopiedNode<NodeData, DataType, ValueType> {
+    label: String,
+    user_data: NodeData,
+    inputs: Vec<(String, InputParam<DataType, ValueType>)>,
+    outputs: Vec<(String, OutputParam<DataType>)>,
+    /// The position relative to the top-left corner of the copied nodes.
+    offset: egui::Vec2,
+}
+
+/// A connection between two copied nodes, as indices into the copied nodes
+/// and their params, with its data.
+#[derive(Clone, Debug)]
+struct CopiedConnection<ConnectionData> {
+    output_node: usize,
+    output: usize,
+    input_node: usize,
+    input: usize,
+    data: Option<ConnectionData>,
+}
+
+/// A set of nodes copied by [`GraphEditorState::copy_selection`], along with
+/// the connections between them. Can be pasted any number of times with
+/// [`GraphEditorState::paste`].
+#[derive(Clone, Debug)]
+pub struct Clipboard<NodeData, DataType, ValueType, ConnectionData = ()> {
+    nodes: Vec<CopiedNode<NodeData, DataType, ValueType>>,
+    connections: Vec<CopiedConnection<ConnectionData>>,
+}
+
+impl<NodeData, DataType, ValueType, ConnectionData>
+    Clipboard<NodeData, DataType, ValueType, ConnectionData>
+{
+    /// The number of copied nodes.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+/// Finds the index of `node` among the copied nodes, and of `param` among its
+/// params.
+fn locate<Id: PartialEq>(
+    copied: &[NodeId],
+    node: NodeId,
+    params: &[(String, Id)],
+    param: Id,
+) -> Option<(usize, usize)> {
+    let node_index = copied.iter().position(|id| *id == node)?;
+    let param_index = params.iter().position(|(_, id)| *id == param)?;
+    Some((node_index, param_index))
+}
+
+impl<NodeData, DataType, ValueType, NodeTemplate, UserState, ConnectionData>
+    GraphEditorState<NodeData, DataType, ValueType, NodeTemplate, UserState, ConnectionData>
+where
+    NodeData: Clone,
+    DataType: Clone,
+    ValueType: Clone,
+    ConnectionData: Clone,
+{
+    /// Copies the selected nodes, with their parameter values and the
+    /// connections between them. Connections to nodes that are not selected
+    /// are left out. Returns `None` when no nodes are selected.
+    pub fn copy_selection(
+        &self,
+    ) -> Option<Clipboard<NodeData, DataType, ValueType, ConnectionData>> {
+        let selected: Vec<NodeId> = self
+            .selected_nodes
+            .iter()
+            .copied()
+            .filter(|id| self.graph.nodes.contains_key(*id))
+            .collect();
+        let position = |id: NodeId| {
+            self.node_positions
+                .get(id)
+                .copied()
+                .unwrap_or(egui::Pos2::ZERO)
+        };
+        let top_left = selected
+            .iter()
+            .map(|id| position(*id))
+            .reduce(|a, b| a.min(b))?;
+
+        let nodes = selected
+            .iter()
+            .map(|id| {
+                let node = &self.graph[*id];
+                CopiedNode {
+                    label: node.label.clone(),
+                    user_data: node.user_data.clone(),
+                    inputs: node
+                        .inputs
+                        .iter()
+                        .map(|(name, input)| (name.clone(), self.graph[*input].clone()))
+                        .collect(),
+                    outputs: node
+                        .outputs
+                        .iter()
+                        .map(|(name, output)| (name.clone(), self.graph[*output].clone()))
+                        .collect(),
+                    offset: position(*id) - top_left,
+                }
+            })
+            .collect();
+
+        let connections = self
+            .graph
+            .iter_connections()
+            .filter_map(|(input, output)| {
+                let data = self.connection_data.get(&(input, output)).cloned();
+                let input_node = self.graph[input].node;
+                let output_node = self.graph[output].node;
+                let (input_node, input) =
+                    locate(&selected, input_node, &self.graph[input_node].inputs, input)?;
+                let (output_node, output) = locate(
+                    &selected,
+                    output_node,
+                    &self.graph[output_node].outputs,
+                    output,
+                )?;
+                Some(CopiedConnection {
+                    output_node,
+                    output,
+                    input_node,
+                    input,
+                    data,
+                })
+            })
+            .collect();
+
+        Some(Clipboard { nodes, connections })
+    }
+
+    /// Creates copies of the nodes in `clipboard`, with fresh ids, placing
+    /// their top-left corner at `position`, in graph space. The copies are
+    /// drawn on top of the other nodes and become the selection.
+    ///
+    /// Returns the ids of the new nodes, in the order they were copied.
+    pub fn paste(
+        &mut self,
+        clipboard: &Clipboard<NodeData, DataType, ValueType, ConnectionData>,
+        position: egui::Pos2,
+    ) -> Vec<NodeId> {
+        let mut new_params = Vec::with_capacity(clipboard.nodes.len());
+        let new_nodes: Vec<NodeId> = clipboard
+            .nodes
+            .iter()
+            .map(|copied| {
+                let mut inputs = Vec::new();
+                let mut outputs = Vec::new();
+                let node_id = self.graph.add_node(
+                    copied.label.clone(),
+                    copied.user_data.clone(),
+                    |graph, node_id| {
+                        for (name, param) in &copied.inputs {
+                            let input = graph.add_wide_input_param(
+                                node_id,
+                                name.clone(),
+                                param.typ.clone(),
+                                param.value.clone(),
+                                param.kind,
+                                param.max_connections,
+                                param.shown_inline,
+                            );
+                            graph[input].group = param.group.clone();
+                            graph[input].required = param.required;
+                            inputs.push(input);
+                        }
+                        for (name, param) in &copied.outputs {
+                            let output =
+                                graph.add_output_param(node_id, name.clone(), param.typ.clone());
+                            graph[output].group = param.group.clone();
+                            outputs.push(output);
+                        }
+                    },
+                );
+                new_params.push((inputs, outputs));
+                self.node_positions
+                    .insert(node_id, position + copied.offset);
+                self.node_order.push(node_id);
+                node_id
+            })
+            .collect();
+
+        for connection in &clipboard.connections {
+            let output = new_params[connection.output_node].1[connection.output];
+            let input = new_params[connection.input_node].0[connection.input];
+            match &connection.data {
+                Some(data) => self.add_connection_with_data(output, input, data.clone()),
+                None => self.graph.add_connection(output, input),
+            }
+        }
+
+        self.selected_nodes = new_nodes.clone();
+        new_nodes
+    }
+
+    /// Creates copies of the selected nodes and the connections between them,
+    /// `offset` away from the originals, in graph space. The copies become the
+    /// selection. Returns the ids of the new nodes.
+    ///
+    /// With [`GraphEditorState::duplicate_incoming_connections`], the inputs
+    /// of the copies are also connected to the outputs outside of the
+    /// selection that feed the originals.
+    pub fn duplicate_selection(&mut self, offset: egui::Vec2) -> Vec<NodeId> {
+        let clipboard = match self.copy_selection() {
+            Some(clipboard) => clipboard,
+            None => return Vec::new(),
+        };
+        // In the order of the copied nodes
+        let originals: Vec<NodeId> = self
+            .selected_nodes
+            .iter()
+            .copied()
+            .filter(|id| self.graph.nodes.contains_key(*id))
+            .collect();
+        let top_left = originals
+            .iter()
+            .filter_map(|node| self.node_positions.get(*node))
+            .fold(egui::pos2(f32::INFINITY, f32::INFINITY), |a, b| a.min(*b));
+        let copies = self.paste(&clipboard, top_left + offset);
+
+        if self.duplicate_incoming_connections {
+            for (original, copy) in originals.iter().zip(&copies) {
+                let mut incoming = Vec::new();
+                let inputs = self.graph[*original].input_ids();
+                for (input, new_input) in inputs.zip(self.graph[*copy].input_ids()) {
+                    for output in self.graph.connections(input) {
+                        if !originals.contains(&self.graph[*output].node) {
+                            let data = self.connection_data.get(&(input, *output)).cloned();
+                            incoming.push((*output, new_input, data));
+                        }
+                    }
+                }
+                for (output, input, data) in incoming {
+                    match data {
+                        Some(data) => self.add_connection_with_data(output, input, data),
+                        None => self.graph.add_connection(output, input),
+                    }
+                }
+            }
+        }
+        copies
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    pub fn test_copy_paste_keeps_internal_connections() {
+        let mut state = TestEditorState::new(1.0);
+        let [a, b, c] = [0.0, 100.0, 200.0]
+            .map(|x| state.add_node(&TestTemplate::Scalar, egui::pos2(x, 50.0), &mut ()));
+        let connect = |state: &mut TestEditorState, from: NodeId, to: NodeId| {
+            let output = state.graph[from].get_output("out").unwrap();
+            let input = state.graph[to].get_input("in").unwrap();
+            state.graph.add_connection(output, input);
+        };
+        connect(&mut state, a, b);
+        connect(&mut state, b, c);
+        state.graph.rename_node(b, "renamed".into());
+
+        // The connection from `a` is outside the selection
+        state.selected_nodes = vec![b, c];
+        let clipboard = state.copy_selection().unwrap();
+        assert_eq!(clipboard.len(), 2);
+
+        let pasted = state.paste(&clipboard, egui::pos2(500.0, 500.0));
+
+        assert_eq!(pasted.len(), 2);
+        assert!(pasted.iter().all(|id| ![a, b, c].contains(id)));
+        assert_eq!(state.selected_nodes, pasted);
+        assert_eq!(state.node_order[3..], pasted[..]);
+        assert_eq!(state.graph[pasted[0]].label, "renamed");
+        assert_eq!(state.node_positions[pasted[0]], egui::pos2(500.0, 500.0));
+        assert_eq!(state.node_positions[pasted[1]], egui::pos2(600.0, 500.0));
+
+        let new_in_b = state.graph[pasted[0]].get_input("in").unwrap();
+        let new_out_b = state.graph[pasted[0]].get_output("out").unwrap();
+        let new_in_c = state.graph[pasted[1]].get_input("in").unwrap();
+        assert_eq!(state.graph.connection(new_in_c), Some(new_out_b));
+        assert_eq!(state.graph.connection(new_in_b), None);
+        assert_eq!(state.graph.iter_connections().count(), 3);
+        assert!(!state.sync_with_graph());
+    }
+
+    #[test]
+    pub fn test_duplicate_selection() {
+        let mut state = TestEditorState::new(1.0);
+        let node = state.add_node(&TestTemplate::Vector, egui::pos2(10.0, 20.0), &mut ());
+        assert!(state.duplicate_selection(egui::vec2(30.0, 30.0)).is_empty());
+
+        state.selected_nodes = vec![node];
+        let copies = state.duplicate_selection(egui::vec2(30.0, 30.0));
+        assert_eq!(copies.len(), 1);
+        assert_eq!(state.selected_nodes, copies);
+        assert_eq!(state.node_positions[copies[0]], egui::pos2(40.0, 50.0));
+        assert_eq!(state.graph[copies[0]].user_data.0, TestTemplate::Vector);
+    }
+
+    #[test]
+    pub fn test_duplicate_incoming_connections() {
+        let mut state = TestEditorState::new(1.0);
+        let [a, b, c] = [0.0, 100.0, 200.0]
+            .map(|x| state.add_node(&TestTemplate::Scalar, egui::pos2(x, 0.0), &mut ()));
+        let out_a = state.graph[a].get_output("out").unwrap();
+        let in_b = state.graph[b].get_input("in").unwrap();
+        let out_b = state.graph[b].get_output("out").unwrap();
+        let in_c = state.graph[c].get_input("in").unwrap();
+        state.add_connection_with_data(out_a, in_b, TestLink("a to b".into()));
+        state.graph.add_connection(out_b, in_c);
+
+        // Only the connection between the duplicated nodes by default
+        state.selected_nodes = vec![b, c];
+        let copies = state.duplicate_selection(egui::vec2(0.0, 100.0));
+        let in_copy_b = state.graph[copies[0]].get_input("in").unwrap();
+        assert!(!state.graph.is_input_connected(in_copy_b));
+        assert_eq!(state.graph.iter_connections().count(), 3);
+
+        // The copies can also be fed by the same outputs as the originals
+        state.duplicate_incoming_connections = true;
+        state.selected_nodes = vec![b, c];
+        let copies = state.duplicate_selection(egui::vec2(0.0, 200.0));
+        let in_copy_b = state.graph[copies[0]].get_input("in").unwrap();
+        let out_copy_b = state.graph[copies[0]].get_output("out").unwrap();
+        let in_copy_c = state.graph[copies[1]].get_input("in").unwrap();
+        assert_eq!(state.graph.connection(in_copy_b), Some(out_a));
+        assert_eq!(state.graph.connection(in_copy_c), Some(out_copy_b));
+        assert_eq!(
+            state.connection_data.get(&(in_copy_b, out_a)),
+            Some(&TestLink("a to b".into()))
+        );
+        assert_eq!(state.graph.connection(in_b), Some(out_a));
+        assert!(!state.sync_with_graph());
+    }
+
+    #[test]
+    pub fn test_copy_without_selection() {
+        let mut state = TestEditorState::new(1.0);
+        let node = state.add_node(&TestTemplate::Scalar, egui::Pos2::ZERO, &mut ());
+        assert!(state.copy_selection().is_none());
+
+        // Stale selections are ignored
+        state.selected_nodes = vec![node];
+        state.remove_node(node);
+        state.selected_nodes = vec![node];
+        assert!(state.copy_selection().is_none());
+    }
+}
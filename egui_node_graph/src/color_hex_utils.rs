@@ -43,7 +43,6 @@ pub fn color_from_hex(hex: &str) -> Result<Color32, String> {
 /// - The color string will be preceded by '#'.
 /// - If the alpha channel is completely opaque, it will be ommitted.
 /// - Characters from 'a' to 'f' will be written in lowercase.
-#[allow(dead_code)]
 pub fn color_to_hex(color: Color32) -> String {
     if color.a() < 255 {
         format!(
@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+
+use egui::epaint::{Mesh, Tessellator};
+use egui::{Color32, Pos2, Shape, Vec2};
+
+use super::*;
+
+/// Segments whose ends moved less than this, in screen units, relative to each
+/// other aren't tessellated again. Absorbs the rounding errors of the layout.
+const SEGMENT_TOLERANCE: f32 = 0.01;
+
+/// What a tessellated connection segment depends on. Positions are relative to
+/// the start of the segment, so panning doesn't invalidate it.
+#[derive(Clone, Copy, Debug)]
+struct SegmentKey {
+    style: ConnectionStyle,
+    delta: Vec2,
+    detour: f32,
+    color: Color32,
+    width: f32,
+    zoom: f32,
+}
+
+impl SegmentKey {
+    fn same_shape(&self, other: &Self) -> bool {
+        self.style == other.style
+            && self.color == other.color
+            && self.width == other.width
+            && self.zoom == other.zoom
+            && (self.delta - other.delta).length() < SEGMENT_TOLERANCE
+            && (self.detour - other.detour).abs() < SEGMENT_TOLERANCE
+    }
+}
+
+#[derive(Clone, Debug)]
+struct CachedSegment {
+    key: SegmentKey,
+    /// Tessellated with the segment starting at the origin.
+    mesh: Mesh,
+    used: bool,
+}
+
+/// The connections of the graph, tessellated into a single mesh. Segments are
+/// kept across frames and only tessellated again when their shape changes.
+/// See [`GraphEditorState::connection_meshes`].
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ConnectionMeshes {
+    segments: HashMap<(InputId, OutputId, usize), CachedSegment>,
+    /// The settings the segments were tessellated with. They're all
+    /// tessellated again when those change.
+    pixels_per_point: f32,
+    options: Option<egui::epaint::TessellationOptions>,
+    /// How many segments were tessellated during the last frame.
+    pub tessellated: usize,
+    /// The size of the last mesh, to allocate the next one at once.
+    vertex_count: usize,
+    index_count: usize,
+}
+
+/// Collects the connections drawn during a frame, see
+/// [`ConnectionMeshes::begin`].
+pub(crate) struct ConnectionBatch<'a> {
+    cache: &'a mut ConnectionMeshes,
+    tessellator: Tessellator,
+    mesh: Mesh,
+}
+
+impl ConnectionMeshes {
+    /// Starts collecting the connections of a frame.
+    pub fn begin(&mut self, ctx: &egui::Context) -> ConnectionBatch<'_> {
+        let pixels_per_point = ctx.pixels_per_point();
+        let options = ctx.tessellation_options(|options| *options);
+        if self.pixels_per_point != pixels_per_point || self.options != Some(options) {
+            self.segments.clear();
+            self.pixels_per_point = pixels_per_point;
+            self.options = Some(options);
+        }
+        for segment in self.segments.values_mut() {
+            segment.used = false;
+        }
+        self.tessellated = 0;
+        let font_image_size = ctx.fonts(|fonts| fonts.font_image_size());
+        let mesh = Mesh {
+            vertices: Vec::with_capacity(self.vertex_count),
+            indices: Vec::with_capacity(self.index_count),
+            ..Default::default()
+        };
+        ConnectionBatch {
+            tessellator: Tessellator::new(pixels_per_point, options, font_image_size, vec![]),
+            cache: self,
+            mesh,
+        }
+    }
+}
+
+impl<'a> ConnectionBatch<'a> {
+    /// Adds the segment `index` of a connection, as drawn by
+    /// [`connection_shape`]. It's only tessellated if its shape changed since
+    /// the last frame.
+    #[allow(clippy::too_many_arguments)]
+    pub fn add(
+        &mut self,
+        connection: (InputId, OutputId),
+        index: usize,
+        style: ConnectionStyle,
+        src_pos: Pos2,
+        dst_pos: Pos2,
+        detour_y: f32,
+        color: Color32,
+        width: f32,
+        zoom: f32,
+    ) {
+        let key = SegmentKey {
+            style,
+            delta: dst_pos - src_pos,
+            detour: detour_y - src_pos.y,
+            color,
+            width,
+            zoom,
+        };
+        let (input, output) = connection;
+        let segment = self
+            .cache
+            .segments
+            .entry((input, output, index))
+            .or_insert_with(|| CachedSegment {
+                key,
+                mesh: Mesh::default(),
+                used: false,
+            });
+        if !segment.key.same_shape(&key) || segment.mesh.is_empty() {
+            let shape = connection_shape(
+                style,
+                Pos2::ZERO,
+                Pos2::ZERO + key.delta,
+                key.detour,
+                color,
+                width,
+                zoom,
+            );
+            segment.key = key;
+            segment.mesh.clear();
+            self.tessellator.tessellate_shape(shape, &mut segment.mesh);
+            self.cache.tessellated += 1;
+        }
+        segment.used = true;
+
+        let offset = self.mesh.vertices.len() as u32;
+        self.mesh
+            .indices
+            .extend(segment.mesh.indices.iter().map(|index| index + offset));
+        self.mesh
+            .vertices
+            .extend(segment.mesh.vertices.iter().map(|vertex| {
+                let mut vertex = *vertex;
+                vertex.pos += src_pos.to_vec2();
+                vertex
+            }));
+    }
+
+    /// Drops the segments that weren't drawn this frame, and returns the mesh
+    /// of all the connections.
+    pub fn finish(self) -> Shape {
+        self.cache.segments.retain(|_, segment| segment.used);
+        self.cache.vertex_count = self.mesh.vertices.len();
+        self.cache.index_count = self.mesh.indices.len();
+        Shape::mesh(self.mesh)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slotmap::SlotMap;
+    use std::time::Instant;
+
+    /// A thousand connections from a column of outputs to a column of
+    /// inputs, all of different shapes.
+    fn connections() -> Vec<((InputId, OutputId), Pos2, Pos2)> {
+        let mut inputs = SlotMap::<InputId, ()>::with_key();
+        let mut outputs = SlotMap::<OutputId, ()>::with_key();
+        (0..1000)
+            .map(|index| {
+                let connection = (inputs.insert(()), outputs.insert(()));
+                let src = egui::pos2(0.0, index as f32 * 3.0);
+                let dst = egui::pos2(400.0, (index * 7 % 1000) as f32 * 3.0);
+                (connection, src, dst)
+            })
+            .collect()
+    }
+
+    /// Draws the connections moved by `pan` and zoomed by `zoom`, returning
+    /// how many segments were tessellated.
+    fn draw(
+        ctx: &egui::Context,
+        meshes: &mut ConnectionMeshes,
+        connections: &[((InputId, OutputId), Pos2, Pos2)],
+        pan: Vec2,
+        zoom: f32,
+    ) -> usize {
+        let mut batch = meshes.begin(ctx);
+        for (connection, src, dst) in connections {
+            let place = |pos: &Pos2| (pos.to_vec2() * zoom + pan).to_pos2();
+            let (src, dst) = (place(src), place(dst));
+            let (color, width) = (Color32::WHITE, 2.0 * zoom);
+            batch.add(
+                *connection,
+                0,
+                ConnectionStyle::Bezier,
+                src,
+                dst,
+                src.y,
+                color,
+                width,
+                zoom,
+            );
+        }
+        assert!(matches!(batch.finish(), Shape::Mesh(mesh) if !mesh.is_empty()));
+        meshes.tessellated
+    }
+
+    /// A context with fonts, which the tessellator needs.
+    fn context() -> egui::Context {
+        let ctx = egui::Context::default();
+        let _ = ctx.run(Default::default(), |_| {});
+        ctx
+    }
+
+    #[test]
+    pub fn test_unchanged_segments_are_reused() {
+        let ctx = context();
+        let connections = connections();
+        let mut meshes = ConnectionMeshes::default();
+        let pan = egui::vec2(10.0, 20.0);
+
+        assert_eq!(draw(&ctx, &mut meshes, &connections, Vec2::ZERO, 1.0), 1000);
+        assert_eq!(draw(&ctx, &mut meshes, &connections, Vec2::ZERO, 1.0), 0);
+        assert_eq!(draw(&ctx, &mut meshes, &connections, pan, 1.0), 0);
+        assert_eq!(draw(&ctx, &mut meshes, &connections, pan, 0.5), 1000);
+
+        // Segments that are no longer drawn are dropped
+        assert_eq!(draw(&ctx, &mut meshes, &connections[..10], pan, 0.5), 0);
+        assert_eq!(meshes.segments.len(), 10);
+        assert_eq!(draw(&ctx, &mut meshes, &connections, pan, 0.5), 990);
+    }
+
+    /// Compares drawing the connections with and without reusing the
+    /// segments. Run with `cargo test --release -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    pub fn bench_connection_meshes() {
+        let ctx = context();
+        let connections = connections();
+        let frames = 100;
+        let time = |reuse: bool| {
+            let mut meshes = ConnectionMeshes::default();
+            let start = Instant::now();
+            for frame in 0..frames {
+                if !reuse {
+                    meshes = ConnectionMeshes::default();
+                }
+                let pan = egui::vec2(frame as f32, 0.0);
+                draw(&ctx, &mut meshes, &connections, pan, 1.0);
+            }
+            start.elapsed() / frames
+        };
+        let (fresh, reused) = (time(false), time(true));
+        println!(
+            "1000 connections, per frame: {:?} tessellated, {:?} reused",
+            fresh, reused
+        );
+        assert!(reused < fresh);
+    }
+}
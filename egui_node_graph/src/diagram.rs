@@ -0,0 +1,261 @@
+use std::fmt::Write;
+
+use super::*;
+use crate::color_hex_utils::color_to_hex;
+
+/// Options of [`GraphEditorState::to_dot`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DotOptions {
+    /// Pins every node to its position in the editor with a `pos` attribute,
+    /// so that `neato -n` reproduces the on-screen layout. Otherwise GraphViz
+    /// lays the graph out by itself.
+    pub positions: bool,
+}
+
+/// Escapes text for a quoted DOT string.
+fn dot_string(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '"' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escapes text for a field of a DOT record label, where braces, bars and
+/// angle brackets have a meaning.
+fn dot_record_field(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '{' | '}' | '|' | '<' | '>' | '"' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The fields of a DOT record listing `params`, with the ports `i0`, `i1`...
+/// for the `i` prefix.
+fn record_ports<Id>(params: &[(String, Id)], prefix: &str) -> String {
+    params
+        .iter()
+        .enumerate()
+        .map(|(index, (name, _))| format!("<{}{}> {}", prefix, index, dot_record_field(name)))
+        .collect::<Vec<_>>()
+        .join("|")
+}
+
+/// Escapes text for a quoted Mermaid label, using its entity codes.
+fn mermaid_string(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("#quot;"),
+            '#' => escaped.push_str("#35;"),
+            '<' => escaped.push_str("#lt;"),
+            '>' => escaped.push_str("#gt;"),
+            '\n' => escaped.push_str("<br>"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl<NodeData, DataType, ValueType, NodeTemplate, UserState, ConnectionData>
+    GraphEditorState<NodeData, DataType, ValueType, NodeTemplate, UserState, ConnectionData>
+where
+    DataType: DataTypeTrait<UserState>,
+{
+    /// The ids of the nodes in the exported diagrams, `n0`, `n1`... following
+    /// the order of [`Graph::iter_nodes`].
+    fn diagram_ids(&self) -> SecondaryMap<NodeId, String> {
+        self.graph
+            .iter_nodes()
+            .enumerate()
+            .map(|(index, node)| (node, format!("n{}", index)))
+            .collect()
+    }
+
+    /// The color of the node in the exported diagrams: the color of the type
+    /// of its first output, or of its first input for nodes without outputs.
+    fn diagram_color(&self, node: NodeId, user_state: &mut UserState) -> Option<egui::Color32> {
+        let node = &self.graph[node];
+        let param: AnyParameterId = match node.output_ids().next() {
+            Some(output) => output.into(),
+            None => node.input_ids().next()?.into(),
+        };
+        Some(
+            self.graph
+                .resolved_type::<UserState>(param)
+                .data_type_color(user_state),
+        )
+    }
+
+    /// Exports the graph in the GraphViz DOT format. Each node is a record
+    /// listing its inputs, its label and its outputs. Each connection is an
+    /// edge between the two ports, labelled with their names. Nodes are filled
+    /// with the color of their first port, and edges take the color of their
+    /// type, see [`DataTypeTrait::data_type_color`].
+    pub fn to_dot(&self, options: DotOptions, user_state: &mut UserState) -> String {
+        let ids = self.diagram_ids();
+        let mut dot = String::new();
+        writeln!(dot, "digraph {{").unwrap();
+        writeln!(dot, "    rankdir=LR;").unwrap();
+        writeln!(dot, "    node [shape=record, style=filled];").unwrap();
+        for node_id in self.graph.iter_nodes() {
+            let node = &self.graph[node_id];
+            let mut label = dot_record_field(&node.label);
+            if !node.inputs.is_empty() {
+                label = format!("{{{}}}|{}", record_ports(&node.inputs, "i"), label);
+            }
+            if !node.outputs.is_empty() {
+                label = format!("{}|{{{}}}", label, record_ports(&node.outputs, "o"));
+            }
+            // The fields are escaped already, quotes included
+            write!(dot, "    {} [label=\"{{{}}}\"", ids[node_id], label).unwrap();
+            if let Some(color) = self.diagram_color(node_id, user_state) {
+                write!(dot, ", fillcolor=\"{}\"", color_to_hex(color)).unwrap();
+            }
+            if options.positions {
+                if let Some(pos) = self.node_positions.get(node_id) {
+                    // GraphViz points up, the editor points down
+                    write!(dot, ", pos=\"{},{}!\"", pos.x, -pos.y).unwrap();
+                }
+            }
+            writeln!(dot, "];").unwrap();
+        }
+        for (input, output) in self.graph.iter_connections() {
+            let (input_node, output_node) = (self.graph[input].node, self.graph[output].node);
+            let input_index = self.graph[input_node]
+                .input_ids()
+                .position(|id| id == input);
+            let output_index = self.graph[output_node]
+                .output_ids()
+                .position(|id| id == output);
+            let (input_index, output_index) = match (input_index, output_index) {
+                (Some(input_index), Some(output_index)) => (input_index, output_index),
+                _ => continue,
+            };
+            let label = format!(
+                "{} → {}",
+                self.graph.param_name(output.into()).unwrap_or_default(),
+                self.graph.param_name(input.into()).unwrap_or_default(),
+            );
+            let color = self
+                .graph
+                .resolved_type::<UserState>(output.into())
+                .data_type_color(user_state);
+            writeln!(
+                dot,
+                "    {}:o{} -> {}:i{} [label=\"{}\", color=\"{}\"];",
+                ids[output_node],
+                output_index,
+                ids[input_node],
+                input_index,
+                dot_string(&label),
+                color_to_hex(color),
+            )
+            .unwrap();
+        }
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+
+    /// Exports the graph as a Mermaid `flowchart LR`, to paste in markdown.
+    /// Connections are labelled with the names of their ports, and nodes are
+    /// filled like in [`Self::to_dot`].
+    pub fn to_mermaid(&self, user_state: &mut UserState) -> String {
+        let ids = self.diagram_ids();
+        let mut mermaid = String::from("flowchart LR\n");
+        for node_id in self.graph.iter_nodes() {
+            let label = mermaid_string(&self.graph[node_id].label);
+            writeln!(mermaid, "    {}[\"{}\"]", ids[node_id], label).unwrap();
+        }
+        for (input, output) in self.graph.iter_connections() {
+            let label = format!(
+                "{} → {}",
+                self.graph.param_name(output.into()).unwrap_or_default(),
+                self.graph.param_name(input.into()).unwrap_or_default(),
+            );
+            writeln!(
+                mermaid,
+                "    {} -->|\"{}\"| {}",
+                ids[self.graph[output].node],
+                mermaid_string(&label),
+                ids[self.graph[input].node],
+            )
+            .unwrap();
+        }
+        for node_id in self.graph.iter_nodes() {
+            if let Some(color) = self.diagram_color(node_id, user_state) {
+                writeln!(
+                    mermaid,
+                    "    style {} fill:{}",
+                    ids[node_id],
+                    color_to_hex(color)
+                )
+                .unwrap();
+            }
+        }
+        mermaid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    fn connected_state() -> TestEditorState {
+        let mut state = TestEditorState::default();
+        let a = state.add_node(&TestTemplate::Scalar, egui::pos2(0.0, 0.0), &mut ());
+        let b = state.add_node(&TestTemplate::Scalar, egui::pos2(250.0, 40.0), &mut ());
+        state.graph[a].label = "Camera {\"rgb\"} | <a#b>".into();
+        let output = state.graph[a].get_output("out").unwrap();
+        let input = state.graph[b].get_input("in").unwrap();
+        state.graph.add_connection(output, input);
+        state
+    }
+
+    #[test]
+    pub fn test_to_dot() {
+        let state = connected_state();
+        let dot = state.to_dot(DotOptions::default(), &mut ());
+        assert_eq!(
+            dot,
+            "digraph {\n    rankdir=LR;\n    node [shape=record, style=filled];\n    \
+             n0 [label=\"{{<i0> in}|Camera \\{\\\"rgb\\\"\\} \\| \\<a#b\\>|{<o0> out}}\", \
+             fillcolor=\"#ffffff\"];\n    \
+             n1 [label=\"{{<i0> in}|Scalar|{<o0> out}}\", fillcolor=\"#ffffff\"];\n    \
+             n0:o0 -> n1:i0 [label=\"out → in\", color=\"#ffffff\"];\n}\n"
+        );
+
+        let dot = state.to_dot(DotOptions { positions: true }, &mut ());
+        assert!(dot.contains("fillcolor=\"#ffffff\", pos=\"250,-40!\"];"));
+    }
+
+    #[test]
+    pub fn test_to_mermaid() {
+        let state = connected_state();
+        assert_eq!(
+            state.to_mermaid(&mut ()),
+            "flowchart LR\n    \
+             n0[\"Camera {#quot;rgb#quot;} | #lt;a#35;b#gt;\"]\n    \
+             n1[\"Scalar\"]\n    \
+             n0 -->|\"out → in\"| n1\n    \
+             style n0 fill:#ffffff\n    \
+             style n1 fill:#ffffff\n"
+        );
+    }
+}
@@ -1,6 +1,8 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use crate::color_hex_utils::*;
+use crate::graph_impls::Insertion;
 use crate::utils::ColorUtils;
 
 use super::*;
@@ -10,8 +12,206 @@ use egui::*;
 pub type PortLocations = std::collections::HashMap<AnyParameterId, Pos2>;
 pub type NodeRects = std::collections::HashMap<NodeId, Rect>;
 
+/// How far outside of the viewport, in screen units, a node or connection is
+/// still drawn. Covers the ports and outlines sticking out of the node rects.
+const CULL_MARGIN: f32 = 20.0;
+
 const DISTANCE_TO_CONNECT: f32 = 10.0;
 
+/// Whether [`GraphEditorState::sync_with_graph`] had to repair the state
+/// already. It's only reported the first time.
+static REPORTED_DESYNC: AtomicBool = AtomicBool::new(false);
+
+/// The size of the port stubs of a collapsed node. Ports of expanded nodes
+/// are sized by [`GraphStyle::port_radius`].
+const STUB_SIZE: f32 = 6.0;
+/// Ports aren't drawn smaller than this when zooming out, in screen units, so
+/// their shapes can still be told apart.
+const MIN_PORT_RADIUS: f32 = 2.0;
+/// The minimum vertical distance between the port stubs of a collapsed node.
+const STUB_SPACING: f32 = 12.0;
+
+/// For how long, in seconds, a rejected drop on a node body or a rejected
+/// connection is reported.
+const REJECTION_FEEDBACK_DURATION: f64 = 1.5;
+
+/// For how long, in seconds, the pointer has to stay over a port before its
+/// tooltip is shown.
+const PORT_TOOLTIP_DELAY: f64 = 0.4;
+
+/// The smallest distance, in screen units, between two lines of the grid. See
+/// [`GraphEditorState::grid`].
+const MIN_GRID_STEP: f32 = 8.0;
+
+/// The size of the minimap, see [`GraphEditorState::minimap`].
+const MINIMAP_SIZE: Vec2 = vec2(200.0, 150.0);
+
+/// How far from a connection, in screen units at 100% zoom, a click still
+/// selects it.
+const CONNECTION_HIT_DISTANCE: f32 = 6.0;
+
+/// How far the pointer can move during a pan gesture, in screen pixels, for
+/// its release to still count as a click.
+const PAN_CLICK_DISTANCE: f32 = 6.0;
+
+/// How far [`ConnectionStyle::Orthogonal`] connections run straight out of
+/// their ports, or below their nodes, before turning. And the radius of their
+/// corners.
+const ORTHOGONAL_CLEARANCE: f32 = 15.0;
+const ORTHOGONAL_CORNER_RADIUS: f32 = 8.0;
+
+/// The length and width of the arrowheads of the connections, see
+/// [`GraphStyle::arrowheads`].
+const ARROWHEAD_SIZE: f32 = 12.0;
+
+/// The length of the dashes flowing along the active connections, and of the
+/// gaps between them. See [`GraphEditorState::active_connections`].
+const FLOW_DASH_LENGTH: f32 = 8.0;
+/// How fast the dashes flow, in graph units per second.
+const FLOW_SPEED: f32 = 30.0;
+
+/// The radius of the reroute points of the connections, see
+/// [`GraphEditorState::reroutes`].
+const REROUTE_RADIUS: f32 = 5.0;
+
+/// Color of the selected connection, see
+/// [`GraphEditorState::selected_connection`].
+const SELECTED_CONNECTION_COLOR: Color32 = Color32::WHITE;
+
+/// Tint of a dragged connection over a port it can be attached to, and over
+/// a port it can't.
+const VALID_TARGET_COLOR: Color32 = Color32::from_rgb(90, 200, 90);
+const INVALID_TARGET_COLOR: Color32 = Color32::from_rgb(220, 60, 60);
+
+/// The ring around the ports of the required inputs that aren't connected,
+/// and the tint of their rows. See [`InputParam::required`].
+const REQUIRED_INPUT_COLOR: Color32 = Color32::from_rgb(220, 60, 60);
+
+/// How much bigger the ports a dragged connection can be attached to are.
+const VALID_TARGET_SCALE: f32 = 1.3;
+
+/// How much brighter the connections of the hovered node or port are drawn.
+/// See [`GraphEditorState::hover_highlight`].
+const HIGHLIGHT_BRIGHTNESS: f32 = 1.4;
+
+/// The color of the lines a dragged node is aligned to, see
+/// [`GraphEditorState::alignment_guides`].
+const ALIGNMENT_GUIDE_COLOR: Color32 = Color32::from_rgb(230, 80, 200);
+
+/// What the connections are highlighted around, see
+/// [`GraphEditorState::hover_highlight`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Highlighted {
+    Node(NodeId),
+    Port(AnyParameterId),
+}
+
+impl Highlighted {
+    /// Whether the connection from `output` to `input` is highlighted.
+    fn touches<NodeData, DataType, ValueType>(
+        self,
+        graph: &Graph<NodeData, DataType, ValueType>,
+        input: InputId,
+        output: OutputId,
+    ) -> bool {
+        match self {
+            Highlighted::Node(node) => graph[input].node == node || graph[output].node == node,
+            Highlighted::Port(AnyParameterId::Input(port)) => port == input,
+            Highlighted::Port(AnyParameterId::Output(port)) => port == output,
+        }
+    }
+}
+
+/// The width of the field editing the title of a node, see
+/// [`GraphEditorState::renaming_node`].
+const RENAME_FIELD_WIDTH: f32 = 120.0;
+
+/// Color of the connections that would be removed by a pending deletion.
+const PENDING_DELETION_COLOR: Color32 = Color32::from_rgb(230, 70, 50);
+
+/// Where a node and its ports were drawn, in graph units relative to the node
+/// position. See [`GraphEditorState::node_layouts`].
+#[derive(Clone, Debug)]
+pub(crate) struct NodeLayout {
+    pub rect: Rect,
+    pub ports: Vec<(AnyParameterId, Vec2)>,
+    pub collapsed: bool,
+    pub low_detail: bool,
+    /// The number of parameters of the node when it was drawn.
+    pub param_count: usize,
+}
+
+impl NodeLayout {
+    /// Measures the layout of `node`, drawn at `position` this frame.
+    #[allow(clippy::too_many_arguments)]
+    fn measure<NodeData>(
+        node: &Node<NodeData>,
+        position: Pos2,
+        collapsed: bool,
+        low_detail: bool,
+        node_rects: &NodeRects,
+        port_locations: &PortLocations,
+        origin: Vec2,
+        zoom: f32,
+    ) -> Option<Self> {
+        let to_graph = |pos: Pos2| (pos.to_vec2() - origin) / zoom - position.to_vec2();
+        let rect = node_rects.get(&node.id)?;
+        let ports = node
+            .input_ids()
+            .map(AnyParameterId::Input)
+            .chain(node.output_ids().map(AnyParameterId::Output))
+            .filter_map(|port| Some((port, to_graph(*port_locations.get(&port)?))))
+            .collect();
+        Some(Self {
+            rect: Rect::from_min_max(to_graph(rect.min).to_pos2(), to_graph(rect.max).to_pos2()),
+            ports,
+            collapsed,
+            low_detail,
+            param_count: node.inputs.len() + node.outputs.len(),
+        })
+    }
+
+    /// Places the node at `position`, filling in its rect and port locations.
+    /// Returns the screen rect of the node.
+    fn place(
+        &self,
+        node_id: NodeId,
+        position: Pos2,
+        node_rects: &mut NodeRects,
+        port_locations: &mut PortLocations,
+        origin: Vec2,
+        zoom: f32,
+    ) -> Rect {
+        let to_screen = |offset: Vec2| ((position + offset).to_vec2() * zoom + origin).to_pos2();
+        let rect = Rect::from_min_max(
+            to_screen(self.rect.min.to_vec2()),
+            to_screen(self.rect.max.to_vec2()),
+        );
+        node_rects.insert(node_id, rect);
+        for (port, offset) in &self.ports {
+            port_locations.insert(*port, to_screen(*offset));
+        }
+        rect
+    }
+
+    /// Whether the node can be placed from this layout instead of being
+    /// drawn, i.e. its parameters haven't changed since.
+    fn is_current<NodeData>(
+        &self,
+        node: &Node<NodeData>,
+        collapsed: bool,
+        low_detail: bool,
+    ) -> bool {
+        self.collapsed == collapsed
+            && self.low_detail == low_detail
+            && self.param_count == node.inputs.len() + node.outputs.len()
+            && self.ports.iter().all(|(port, _)| match port {
+                AnyParameterId::Input(input) => node.inputs.iter().any(|(_, id)| id == input),
+                AnyParameterId::Output(output) => node.outputs.iter().any(|(_, id)| id == output),
+            })
+    }
+}
+
 /// Nodes communicate certain events to the parent graph when drawn. There is
 /// one special `User` variant which can be used by users as the return value
 /// when executing some custom actions in the UI of the node.
@@ -22,8 +222,32 @@ pub enum NodeResponse<UserResponse: UserResponseTrait, NodeData: NodeDataTrait>
         output: OutputId,
         input: InputId,
     },
+    /// Emitted when a connection is dropped on a port of an incompatible data
+    /// type. The graph is not modified.
+    IncompatibleConnection {
+        output: OutputId,
+        input: InputId,
+    },
+    /// Emitted when a connection was refused by [`NodeDataTrait::can_connect`].
+    /// The graph is not modified.
+    RejectedConnection {
+        output: OutputId,
+        input: InputId,
+        reason: String,
+    },
+    /// Emitted when a connection is dropped on the body of a node that has no
+    /// unconnected input of a compatible type. The graph is not modified. See
+    /// [`GraphEditorState::drop_on_node_body`].
+    RejectedDrop {
+        output: OutputId,
+        node: NodeId,
+    },
     CreatedNode(NodeId),
     SelectNode(NodeId),
+    /// Selects a node and the nodes related to it with
+    /// [`GraphEditorState::select_related`], e.g. when Select upstream is
+    /// picked in its context menu.
+    SelectRelated(NodeId, NodeRelation),
     /// As a user of this library, prefer listening for `DeleteNodeFull` which
     /// will also contain the user data for the deleted node.
     DeleteNodeUi(NodeId),
@@ -34,21 +258,167 @@ pub enum NodeResponse<UserResponse: UserResponseTrait, NodeData: NodeDataTrait>
         node_id: NodeId,
         node: Node<NodeData>,
     },
+    /// Emitted instead of deleting a node protected by
+    /// [`NodeDataTrait::can_delete`], e.g. when it's selected and
+    /// <kbd>Delete</kbd> is pressed. The other selected nodes are deleted.
+    DeleteDenied(NodeId),
+    /// Emitted when Duplicate is picked in the context menu of a node. The
+    /// editor can't copy node data by itself, so it's up to user code to
+    /// react, e.g. with [`GraphEditorState::duplicate_selection`].
+    DuplicateNode(NodeId),
+    /// Removes a connection, e.g. when it's selected and <kbd>Delete</kbd> is
+    /// pressed. A `DisconnectEvent` is emitted if it existed.
+    RemoveConnection {
+        output: OutputId,
+        input: InputId,
+    },
+    /// Removes all the connections of a node, e.g. when Disconnect all is
+    /// picked in its context menu. A `DisconnectEvent` is emitted for each of
+    /// them.
+    DisconnectNode(NodeId),
+    /// Takes a node out of the middle of a pipeline with
+    /// [`Graph::extract_node`], e.g. when Extract is picked in its context
+    /// menu. A `DisconnectEvent` is emitted for each removed connection,
+    /// followed by a `ConnectEventEnded` for each added one.
+    ExtractNode(NodeId),
+    /// Changes the kind of an input with [`Graph::set_input_kind`]. A
+    /// `DisconnectEvent` is emitted for each connection it removes.
+    SetInputKind {
+        input: InputId,
+        kind: InputParamKind,
+    },
     DisconnectEvent {
         output: OutputId,
         input: InputId,
     },
+    /// Emitted when a connection is dropped on an input that was already
+    /// connected. The connection from `old_output` has been atomically replaced
+    /// by one from `new_output`.
+    ReplacedConnection {
+        input: InputId,
+        old_output: OutputId,
+        new_output: OutputId,
+    },
+    /// Adds the input described by [`NodeDataTrait::can_add_input`] to a node,
+    /// e.g. when its `+` button is clicked. An `AddedInput` is emitted if the
+    /// node offered one.
+    AddInput(NodeId),
+    /// Emitted when an input was added to a node with `AddInput`.
+    AddedInput {
+        node: NodeId,
+        input: InputId,
+    },
+    /// Removes an input from its node, e.g. when the button shown for the
+    /// inputs accepted by [`NodeDataTrait::can_remove_input`] is clicked. A
+    /// `DisconnectEvent` is emitted for each of its connections, followed by a
+    /// `RemovedInput`.
+    RemoveInput(InputId),
+    /// Emitted when an input was removed with `RemoveInput`. It no longer
+    /// exists in the graph, but its name is passed along.
+    RemovedInput {
+        node: NodeId,
+        input: InputId,
+        name: String,
+    },
+    /// Emitted when a wildcard port got bound to a port of a concrete type,
+    /// whose type it takes from now on, or when it's bound to another port.
+    /// User code can update the value of the param to match. See
+    /// [`Graph::wildcard_binding`].
+    ///
+    /// The bindings are compared once per frame, after processing the other
+    /// responses, so changes made to the graph by user code are reported on
+    /// the next frame.
+    WildcardBound {
+        node: NodeId,
+        param: AnyParameterId,
+        bound_to: AnyParameterId,
+    },
+    /// Emitted when a wildcard port lost the connection it took its type
+    /// from, and is a wildcard again. See `WildcardBound`.
+    WildcardUnbound {
+        node: NodeId,
+        param: AnyParameterId,
+    },
+    /// Emitted when the widget of an inline input changed its value, i.e. the
+    /// value differs from the one before the widget was drawn.
+    ValueChanged {
+        node: NodeId,
+        input: InputId,
+    },
+    /// Emitted when the label of a node has changed, after its title was
+    /// edited in place. User code can also report renames done through
+    /// [`Graph::rename_node`] by passing this response to the
+    /// `prepend_responses` of [`GraphEditorState::draw_graph_editor`]. The
+    /// node is given `new_label` when the response is processed.
+    RenamedNode {
+        node: NodeId,
+        old_label: String,
+        new_label: String,
+    },
+    /// Emitted when a change was reverted with [`GraphEditorState::undo`].
+    /// Lists the nodes that exist again, and the ones that no longer exist.
+    /// Like `RenamedNode`, this is only reported back when user code passes
+    /// it to `prepend_responses`.
+    Undone {
+        restored_nodes: Vec<NodeId>,
+        removed_nodes: Vec<NodeId>,
+    },
+    /// Emitted when a change was applied again with
+    /// [`GraphEditorState::redo`]. See `Undone`.
+    Redone {
+        restored_nodes: Vec<NodeId>,
+        removed_nodes: Vec<NodeId>,
+    },
     /// Emitted when a node is interacted with, and should be raised
     RaiseNode(NodeId),
+    /// Emitted when the collapse toggle of a node is clicked, or its title bar
+    /// is double-clicked. See [`GraphEditorState::collapsed_nodes`].
+    CollapseNode {
+        node: NodeId,
+        collapsed: bool,
+    },
+    /// Emitted when the header of a group of params is clicked. See
+    /// [`GraphEditorState::collapsed_port_groups`].
+    CollapsePortGroup {
+        node: NodeId,
+        group: String,
+        collapsed: bool,
+    },
+    /// Emitted when a node is dragged. The delta is in graph space, i.e. it
+    /// doesn't depend on the zoom. When the node is selected, the other
+    /// selected nodes are moved by the same delta, which is snapped to the
+    /// grid once, for the dragged node.
     MoveNode {
         node: NodeId,
         drag_delta: Vec2,
     },
+    /// Emitted once when a drag moving nodes ends, for each node it moved,
+    /// with its final position in graph space.
+    NodeMoved {
+        node: NodeId,
+        new_pos: Pos2,
+    },
+    /// Emitted when a node is double-clicked, unless that collapsed or
+    /// expanded it.
+    NodeDoubleClicked(NodeId),
+    /// Emitted when the background of the editor is clicked, away from the
+    /// nodes and connections. The position is in graph space.
+    BackgroundClicked {
+        graph_pos: Pos2,
+    },
     User(UserResponse),
 }
 
 /// The return value of [`draw_graph_editor`]. This value can be used to make
 /// user code react to specific events that happened when drawing the graph.
+///
+/// It also tells where the graph was drawn, to paint overlays over it with
+/// `ui.painter()` after the editor. Screen positions are in egui points, like
+/// everything painted by egui. Graph positions are the ones of
+/// [`GraphEditorState::node_positions`], mapped to the screen by the pan and
+/// zoom the frame was drawn with, see [`Self::graph_to_screen`]. Both are only
+/// valid for the frame just drawn: panning, zooming or moving nodes in
+/// response to this frame's input shows from the next one.
 #[derive(Clone, Debug)]
 pub struct GraphResponse<UserResponse: UserResponseTrait, NodeData: NodeDataTrait> {
     /// Events that occurred during this frame of rendering the graph. Check the
@@ -60,6 +430,42 @@ pub struct GraphResponse<UserResponse: UserResponseTrait, NodeData: NodeDataTrai
     pub cursor_in_editor: bool,
     /// Is the mouse currently hovering the node finder?
     pub cursor_in_finder: bool,
+    /// The screen position of every port drawn this frame.
+    pub port_locations: PortLocations,
+    /// The screen rect of every node drawn this frame, including the ones
+    /// outside of the viewport.
+    pub node_rects: NodeRects,
+    /// The rect the editor was drawn in.
+    pub editor_rect: Rect,
+    /// The pan and zoom the frame was drawn with.
+    pub pan_zoom: PanZoom,
+}
+
+impl<UserResponse: UserResponseTrait, NodeData: NodeDataTrait>
+    GraphResponse<UserResponse, NodeData>
+{
+    /// The screen rect of `node` in the frame just drawn, if it's in the graph.
+    pub fn node_screen_rect(&self, node: NodeId) -> Option<Rect> {
+        self.node_rects.get(&node).copied()
+    }
+
+    /// The screen position of the center of `port` in the frame just drawn.
+    /// `None` for ports that aren't drawn, e.g. constant-only inputs.
+    pub fn port_screen_pos(&self, port: AnyParameterId) -> Option<Pos2> {
+        self.port_locations.get(&port).copied()
+    }
+
+    /// Converts a position in graph space to a screen position, as drawn in
+    /// this frame.
+    pub fn graph_to_screen(&self, pos: Pos2) -> Pos2 {
+        self.pan_zoom.graph_to_screen(pos, self.editor_rect)
+    }
+
+    /// Converts a screen position to a position in graph space, as drawn in
+    /// this frame.
+    pub fn screen_to_graph(&self, pos: Pos2) -> Pos2 {
+        self.pan_zoom.screen_to_graph(pos, self.editor_rect)
+    }
 }
 impl<UserResponse: UserResponseTrait, NodeData: NodeDataTrait> Default
     for GraphResponse<UserResponse, NodeData>
@@ -69,6 +475,10 @@ impl<UserResponse: UserResponseTrait, NodeData: NodeDataTrait> Default
             node_responses: Default::default(),
             cursor_in_editor: false,
             cursor_in_finder: false,
+            port_locations: Default::default(),
+            node_rects: Default::default(),
+            editor_rect: Rect::NOTHING,
+            pan_zoom: Default::default(),
         }
     }
 }
@@ -81,10 +491,61 @@ pub struct GraphNodeWidget<'a, NodeData, DataType, ValueType> {
     pub ongoing_drag: Option<(NodeId, AnyParameterId)>,
     pub selected: bool,
     pub pan: egui::Vec2,
+    /// The zoom factor, see [`PanZoom::zoom`]. The node and its widgets are
+    /// scaled by it.
+    pub zoom: f32,
+    pub localization: &'a Localization,
+    /// The colors and sizes the node is drawn with.
+    pub style: &'a GraphStyle,
+    /// The node inserts itself here when it's about to be deleted.
+    pub pending_deletion: &'a mut HashSet<NodeId>,
+    /// Catch panics in the user callbacks. See
+    /// [`GraphEditorState::defensive`].
+    pub defensive: bool,
+    /// The node inserts itself here when one of its user callbacks panics,
+    /// and no longer calls them once it's in the set.
+    pub failed_nodes: &'a mut HashSet<NodeId>,
+    /// Whether ports or the node body win a drag starting near a port.
+    pub interaction_priority: InteractionPriority,
+    /// What the user is allowed to change. See
+    /// [`GraphEditorState::interaction_mode`].
+    pub interaction_mode: InteractionMode,
+    /// Whether clicking or dragging the node raises it. See
+    /// [`GraphEditorState::raise_on_click`].
+    pub raise_on_click: bool,
+    /// Nodes the ongoing drag can't be connected to without creating a
+    /// cycle. Their ports are grayed out. See
+    /// [`GraphEditorState::enforce_acyclic`].
+    pub cycle_nodes: &'a HashSet<NodeId>,
+    /// Whether the node is collapsed to its title bar. See
+    /// [`GraphEditorState::collapsed_nodes`].
+    pub collapsed: bool,
+    /// Whether the editor is zoomed out past
+    /// [`GraphEditorState::low_detail_width`]. The node is then drawn like a
+    /// collapsed node, unless [`NodeDataTrait::always_detailed`] says
+    /// otherwise.
+    pub low_detail: bool,
+    /// The groups of params collapsed to their header. See
+    /// [`GraphEditorState::collapsed_port_groups`].
+    pub collapsed_port_groups: &'a HashSet<(NodeId, String)>,
+    /// The node whose title is being edited. See
+    /// [`GraphEditorState::renaming_node`].
+    pub renaming_node: &'a mut Option<(NodeId, String)>,
+    /// Whether a pan gesture is ongoing, see [`InputBindings::pan_button`].
+    /// Neither the node nor its ports can be dragged then.
+    pub panning: bool,
 }
 
-impl<NodeData, DataType, ValueType, NodeTemplate, UserResponse, UserState, CategoryType>
-    GraphEditorState<NodeData, DataType, ValueType, NodeTemplate, UserState>
+impl<
+        NodeData,
+        DataType,
+        ValueType,
+        NodeTemplate,
+        UserResponse,
+        UserState,
+        CategoryType,
+        ConnectionData,
+    > GraphEditorState<NodeData, DataType, ValueType, NodeTemplate, UserState, ConnectionData>
 where
     NodeData: NodeDataTrait<
         Response = UserResponse,
@@ -93,8 +554,9 @@ where
         ValueType = ValueType,
     >,
     UserResponse: UserResponseTrait,
-    ValueType:
-        WidgetValueTrait<Response = UserResponse, UserState = UserState, NodeData = NodeData>,
+    ValueType: WidgetValueTrait<Response = UserResponse, UserState = UserState, NodeData = NodeData>
+        + Clone
+        + PartialEq,
     NodeTemplate: NodeTemplateTrait<
         NodeData = NodeData,
         DataType = DataType,
@@ -104,6 +566,7 @@ where
     >,
     DataType: DataTypeTrait<UserState>,
     CategoryType: CategoryTrait,
+    ConnectionData: ConnectionDataTrait,
 {
     #[must_use]
     pub fn draw_graph_editor(
@@ -113,6 +576,8 @@ where
         user_state: &mut UserState,
         prepend_responses: Vec<NodeResponse<UserResponse, NodeData>>,
     ) -> GraphResponse<UserResponse, NodeData> {
+        let mut timer = FrameTimer::new(self.collect_timings);
+
         // This causes the graph editor to use as much free space as it can.
         // (so for windows it will use up to the resizeably set limit
         // and for a Panel it will fill it completely)
@@ -132,6 +597,7 @@ where
         // The responses returned from node drawing have side effects that are best
         // executed at the end of this function.
         let mut delayed_responses: Vec<NodeResponse<UserResponse, NodeData>> = prepend_responses;
+        let prepended = delayed_responses.len();
 
         // Used to detect when the background was clicked
         let mut click_on_background = false;
@@ -140,12 +606,58 @@ where
         let mut drag_started_on_background = false;
         let mut drag_released_on_background = false;
 
-        debug_assert_eq!(
-            self.node_order.iter().copied().collect::<HashSet<_>>(),
-            self.graph.iter_nodes().collect::<HashSet<_>>(),
-            "The node_order field of the GraphEditorself was left in an \
-        inconsistent self. It has either more or less values than the graph."
-        );
+        // Nodes added to or removed from the graph directly leave the editor
+        // state out of sync. Heal it instead of panicking further down, and
+        // only mention it the first time, as some apps edit the graph directly
+        if self.sync_with_graph() && !REPORTED_DESYNC.swap(true, Ordering::Relaxed) {
+            eprintln!(
+                "egui_node_graph: The editor state was out of sync with the graph \
+                and has been repaired. Use `GraphEditorState::remove_node` to delete nodes."
+            );
+        }
+
+        self.pan_zoom.sanitize();
+        self.grid.sanitize();
+        self.apply_view_request(editor_rect);
+        let zoom = self.pan_zoom.zoom;
+        let drawn_pan_zoom = self.pan_zoom;
+        let graph_style = self
+            .style
+            .unwrap_or_else(|| GraphStyle::from_egui_visuals(ui.visuals()));
+        let wire_width = graph_style.wire_width * zoom;
+
+        // The port a connection is being dragged from may have been removed
+        // with `Graph::remove_input_param` or `Graph::remove_output_param`.
+        if let Some((_, param)) = self.connection_in_progress {
+            if self.graph.any_param_type(param).is_err() {
+                self.connection_in_progress = None;
+            }
+        }
+
+        // Edits in progress when the editor was locked are abandoned
+        if !self.interaction_mode.can_edit() {
+            self.connection_in_progress = None;
+            self.detaching_input = None;
+            self.node_finder = None;
+            self.pending_finder_connection = None;
+            self.pending_converter = None;
+            self.deletion_to_confirm = None;
+            self.pending_input_choice = None;
+            self.connection_menu = None;
+            self.renaming_node = None;
+        }
+
+        // The selected connection may have been removed from the graph directly
+        if let Some((input, output)) = self.selected_connection {
+            if !self.graph.connections(input).contains(&output) {
+                self.selected_connection = None;
+            }
+        }
+        if let Some((input, output, _)) = self.connection_menu {
+            if !self.graph.connections(input).contains(&output) {
+                self.connection_menu = None;
+            }
+        }
 
         // Allocate rect before the nodes, otherwise this will block the interaction
         // with the nodes.
@@ -158,8 +670,78 @@ where
             drag_released_on_background = true;
         }
 
+        // A pan gesture starts anywhere in the editor, even on a node, and
+        // keeps the nodes and ports from being dragged until it ends.
+        let bindings = self.input_bindings;
+        let pan_pressed = ui.input(|i| {
+            i.pointer.button_pressed(bindings.pan_button)
+                || (bindings.space_drag_pans
+                    && i.key_down(Key::Space)
+                    && i.pointer.button_pressed(PointerButton::Primary))
+        });
+        if pan_pressed && ui.ctx().layer_id_at(cursor_pos) == Some(ui.layer_id()) {
+            self.pan_origin = Some(cursor_pos);
+        }
+        let panning = self.pan_origin.is_some();
+
+        if graph_style.background_color != Color32::TRANSPARENT {
+            ui.painter()
+                .rect_filled(editor_rect, 0.0, graph_style.background_color);
+        }
+        if self.grid.style != GridStyle::Hidden {
+            self.draw_grid(ui, editor_rect, graph_style.grid_color);
+        }
+
+        // Behind the nodes, and allocated before them so they win the clicks
+        delayed_responses.extend(self.draw_groups(ui, editor_rect));
+
+        timer.lap(|t| &mut t.background);
+
+        // The nodes the dragged connection can't be attached to without
+        // creating a cycle. Computed once, instead of once per port.
+        let cycle_nodes = match self.connection_in_progress {
+            Some((node, AnyParameterId::Output(_))) if self.enforce_acyclic => {
+                self.graph.upstream_nodes(node)
+            }
+            Some((node, AnyParameterId::Input(_))) if self.enforce_acyclic => {
+                self.graph.downstream_nodes(node)
+            }
+            _ => HashSet::new(),
+        };
+
         /* Draw nodes */
+        let interaction_priority = self.effective_interaction_priority(ui.ctx());
+        self.pending_deletion.clear();
+        let origin = self.pan_zoom.pan + editor_rect.min.to_vec2();
+        let viewport = ui.clip_rect().intersect(editor_rect).expand(CULL_MARGIN);
+        let low_detail = GraphNodeWidget::<NodeData, DataType, ValueType>::MAX_NODE_SIZE[0] * zoom
+            < self.low_detail_width;
+        let mut culled_nodes = HashSet::new();
         for node_id in self.node_order.iter().copied() {
+            // Nodes outside of the viewport are placed where they were last
+            // drawn, so their connections are still drawn and hit. They're
+            // drawn again once their layout is outdated or they come into view.
+            let collapsed = self.collapsed_nodes.contains(&node_id);
+            let position = self.node_positions[node_id];
+            let dragged = matches!(self.connection_in_progress, Some((node, _)) if node == node_id)
+                || ui.memory(|mem| mem.is_being_dragged(Id::new((node_id, "window"))));
+            if let Some(layout) = self.node_layouts.get(node_id) {
+                if !dragged && layout.is_current(&self.graph[node_id], collapsed, low_detail) {
+                    let rect = layout.place(
+                        node_id,
+                        position,
+                        &mut node_rects,
+                        &mut port_locations,
+                        origin,
+                        zoom,
+                    );
+                    if !rect.intersects(viewport) {
+                        culled_nodes.insert(node_id);
+                        continue;
+                    }
+                }
+            }
+
             let responses = GraphNodeWidget {
                 position: self.node_positions.get_mut(node_id).unwrap(),
                 graph: &mut self.graph,
@@ -167,11 +749,23 @@ where
                 node_rects: &mut node_rects,
                 node_id,
                 ongoing_drag: self.connection_in_progress,
-                selected: self
-                    .selected_nodes
-                    .iter()
-                    .any(|selected| *selected == node_id),
-                pan: self.pan_zoom.pan + editor_rect.min.to_vec2(),
+                selected: self.selected_nodes.contains(&node_id),
+                pan: origin,
+                zoom,
+                localization: &self.localization,
+                style: &graph_style,
+                pending_deletion: &mut self.pending_deletion,
+                defensive: self.defensive,
+                failed_nodes: &mut self.failed_nodes,
+                interaction_priority,
+                interaction_mode: self.interaction_mode,
+                raise_on_click: self.raise_on_click,
+                cycle_nodes: &cycle_nodes,
+                collapsed,
+                low_detail,
+                collapsed_port_groups: &self.collapsed_port_groups,
+                renaming_node: &mut self.renaming_node,
+                panning,
             }
             .show(ui, user_state);
 
@@ -179,29 +773,74 @@ where
             delayed_responses.extend(responses);
         }
 
+        // Measured before the nodes are moved by the responses. Also used by
+        // `fit_to_screen` and `center_on_node`.
+        let mut node_layouts = SecondaryMap::new();
+        for node_id in self.node_order.iter().copied() {
+            let layout = if culled_nodes.contains(&node_id) {
+                self.node_layouts.remove(node_id)
+            } else {
+                NodeLayout::measure(
+                    &self.graph[node_id],
+                    self.node_positions[node_id],
+                    self.collapsed_nodes.contains(&node_id),
+                    low_detail,
+                    &node_rects,
+                    &port_locations,
+                    origin,
+                    zoom,
+                )
+            };
+            if let Some(layout) = layout {
+                node_layouts.insert(node_id, layout);
+            }
+        }
+        self.node_layouts = node_layouts;
+
+        // Which of the connections of a wide input is dragged off it is only
+        // known once the pointer has moved, see `dragged_connection`.
+        let mut index = prepended;
+        while index < delayed_responses.len() {
+            match delayed_responses[index] {
+                NodeResponse::DisconnectEvent { input, .. }
+                    if self.graph.connections(input).len() > 1 =>
+                {
+                    self.detaching_input = Some(input);
+                    delayed_responses.remove(index);
+                }
+                _ => index += 1,
+            }
+        }
+        if let Some(input) = self.detaching_input {
+            if !ui.input(|i| i.pointer.any_down()) {
+                self.detaching_input = None;
+            } else if let Some(output) = self.dragged_connection(input, &port_locations, ui.ctx()) {
+                self.detaching_input = None;
+                delayed_responses.push(NodeResponse::DisconnectEvent { input, output });
+            }
+        }
+
         /* Draw the node finder, if open */
-        let mut should_close_node_finder = false;
+        let mut submitted_node_kind = None;
         if let Some(ref mut node_finder) = self.node_finder {
             let mut node_finder_area = Area::new("node_finder").order(Order::Foreground);
             if let Some(pos) = node_finder.position {
                 node_finder_area = node_finder_area.current_pos(pos);
             }
+            // A connection dropped on the background restricts the templates
+            let kinds = match &self.pending_finder_connection {
+                Some(pending) => pending.templates.clone(),
+                None => all_kinds.all_kinds(),
+            };
             node_finder_area.show(ui.ctx(), |ui| {
-                if let Some(node_kind) = node_finder.show(ui, all_kinds, user_state) {
-                    let new_node = self.graph.add_node(
-                        node_kind.node_graph_label(user_state),
-                        node_kind.user_data(user_state),
-                        |graph, node_id| node_kind.build_node(graph, user_state, node_id),
-                    );
-                    self.node_positions.insert(
-                        new_node,
-                        cursor_pos - self.pan_zoom.pan - editor_rect.min.to_vec2(),
-                    );
-                    self.node_order.push(new_node);
-
-                    should_close_node_finder = true;
-                    delayed_responses.push(NodeResponse::CreatedNode(new_node));
-                }
+                submitted_node_kind = node_finder.show(
+                    ui,
+                    TemplateList(kinds),
+                    user_state,
+                    &self.localization,
+                    &graph_style,
+                    &mut self.finder_favorites,
+                );
                 let finder_rect = ui.min_rect();
                 // If the cursor is not in the main editor, check if the cursor is in the finder
                 // if the cursor is in the finder, then we can consider that also in the editor.
@@ -211,16 +850,269 @@ where
                 }
             });
         }
-        if should_close_node_finder {
+        if let Some(node_kind) = submitted_node_kind {
+            // Where the finder was opened, however the template was picked
+            let finder_pos = self.node_finder.as_ref().and_then(|finder| finder.position);
+            let position = match &self.pending_finder_connection {
+                Some(pending) => pending.position,
+                None => finder_pos.unwrap_or(cursor_pos),
+            };
+            let new_node = self.add_node(
+                &node_kind,
+                self.pan_zoom.screen_to_graph(position, editor_rect),
+                user_state,
+            );
             self.node_finder = None;
+            delayed_responses.push(NodeResponse::CreatedNode(new_node));
+            if let Some(pending) = self.pending_finder_connection.take() {
+                match (pending.port, pending.splice) {
+                    (AnyParameterId::Output(output), Some(input)) => {
+                        delayed_responses.extend(self.splice_node(new_node, input, output))
+                    }
+                    _ => delayed_responses.extend(self.connect_new_node(pending.port, new_node)),
+                }
+            }
+        }
+
+        /* Draw the find-node palette, if open */
+        let mut cursor_in_popup = false;
+        if !self.find_nodes {
+            self.node_search = None;
+        }
+        if let Some(search) = &mut self.node_search {
+            let mut picked = None;
+            Area::new("node_search")
+                .order(Order::Foreground)
+                .fixed_pos(editor_rect.center_top() + vec2(-100.0, 10.0))
+                .show(ui.ctx(), |ui| {
+                    picked = search.show(
+                        ui,
+                        &self.graph,
+                        &self.node_order,
+                        user_state,
+                        &self.localization,
+                    );
+                    cursor_in_popup = ui.min_rect().contains(cursor_pos);
+                });
+            if let Some(node) = picked {
+                delayed_responses.push(NodeResponse::SelectNode(node));
+                self.center_on_node(node);
+                self.node_search = None;
+            }
+        }
+
+        /* Draw the converter confirmation popup, if any */
+        if let Some(pending) = self.pending_converter.take() {
+            let mut accepted = None;
+            Area::new("converter_popup")
+                .order(Order::Foreground)
+                .fixed_pos(pending.position)
+                .show(ui.ctx(), |ui| {
+                    Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.label(self.localization.insert_converter_prompt.as_ref());
+                        ui.strong(pending.template.node_finder_label(user_state));
+                        ui.horizontal(|ui| {
+                            if ui
+                                .button(self.localization.insert_converter_confirm.as_ref())
+                                .clicked()
+                            {
+                                accepted = Some(true);
+                            }
+                            if ui.button(self.localization.cancel.as_ref()).clicked() {
+                                accepted = Some(false);
+                            }
+                        });
+                    });
+                    cursor_in_popup |= ui.min_rect().contains(cursor_pos);
+                });
+
+            match accepted {
+                Some(true) => delayed_responses.extend(self.insert_converter(
+                    &pending,
+                    &port_locations,
+                    editor_rect,
+                    user_state,
+                )),
+                Some(false) => {}
+                None => self.pending_converter = Some(pending),
+            }
+        }
+
+        /* Draw the confirmation of a deletion from the keyboard, if any */
+        if let Some(pending) = self.deletion_to_confirm.take() {
+            let mut accepted = ui.input(|i| i.key_pressed(Key::Enter)).then_some(true);
+            Area::new("deletion_confirmation")
+                .order(Order::Foreground)
+                .fixed_pos(pending.position)
+                .show(ui.ctx(), |ui| {
+                    Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.label(self.localization.delete_nodes_prompt.as_ref());
+                        ui.label(format!(
+                            "{} {}",
+                            self.localization.connections_to_remove, pending.connection_count
+                        ));
+                        ui.horizontal(|ui| {
+                            if ui.button(self.localization.delete_node.as_ref()).clicked() {
+                                accepted = Some(true);
+                            }
+                            if ui.button(self.localization.cancel.as_ref()).clicked() {
+                                accepted = Some(false);
+                            }
+                        });
+                    });
+                    cursor_in_popup |= ui.min_rect().contains(cursor_pos);
+                });
+
+            match accepted {
+                Some(true) => delayed_responses
+                    .extend(pending.nodes.into_iter().map(NodeResponse::DeleteNodeUi)),
+                Some(false) => {}
+                None => {
+                    self.pending_deletion.extend(pending.nodes.iter().copied());
+                    self.deletion_to_confirm = Some(pending);
+                }
+            }
+        }
+
+        /* Draw the input chooser of a drop on a node body, if any */
+        if let Some(mut choice) = self.pending_input_choice.take() {
+            let mut chosen = None;
+            ui.input(|i| {
+                if i.key_pressed(Key::ArrowDown) {
+                    choice.highlighted = (choice.highlighted + 1) % choice.inputs.len();
+                }
+                if i.key_pressed(Key::ArrowUp) {
+                    choice.highlighted =
+                        (choice.highlighted + choice.inputs.len() - 1) % choice.inputs.len();
+                }
+                if i.key_pressed(Key::Enter) {
+                    chosen = Some(choice.inputs[choice.highlighted].1);
+                }
+            });
+            Area::new("input_chooser")
+                .order(Order::Foreground)
+                .fixed_pos(choice.position)
+                .show(ui.ctx(), |ui| {
+                    Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.label(self.localization.choose_input.as_ref());
+                        for (index, (name, input)) in choice.inputs.iter().enumerate() {
+                            if ui
+                                .selectable_label(index == choice.highlighted, name)
+                                .clicked()
+                            {
+                                chosen = Some(*input);
+                            }
+                        }
+                    });
+                    cursor_in_popup |= ui.min_rect().contains(cursor_pos);
+                });
+
+            match chosen {
+                Some(input) => delayed_responses.push(NodeResponse::ConnectEventEnded {
+                    output: choice.output,
+                    input,
+                }),
+                None => self.pending_input_choice = Some(choice),
+            }
+        }
+
+        /* Draw the context menu of a connection, if any */
+        if let Some((input, output, position)) = self.connection_menu {
+            Area::new("connection_menu")
+                .order(Order::Foreground)
+                .fixed_pos(position)
+                .show(ui.ctx(), |ui| {
+                    Frame::menu(ui.style()).show(ui, |ui| {
+                        if ui
+                            .button(self.localization.remove_connection.as_ref())
+                            .clicked()
+                        {
+                            delayed_responses
+                                .push(NodeResponse::RemoveConnection { output, input });
+                            self.connection_menu = None;
+                        }
+                        // Offers the templates fitting both ends of the
+                        // connection, like a node dropped on it
+                        if ui.button(self.localization.insert_node.as_ref()).clicked() {
+                            let ports =
+                                [AnyParameterId::Output(output), AnyParameterId::Input(input)];
+                            let templates = self.compatible_templates(
+                                &ports,
+                                all_kinds.all_kinds(),
+                                user_state,
+                            );
+                            if !templates.is_empty() {
+                                self.node_finder = Some(NodeFinder::new_at(position));
+                                self.pending_finder_connection = Some(PendingFinderConnection {
+                                    port: AnyParameterId::Output(output),
+                                    templates,
+                                    position,
+                                    splice: Some(input),
+                                });
+                            }
+                            self.connection_menu = None;
+                        }
+                    });
+                    cursor_in_popup |= ui.min_rect().contains(cursor_pos);
+                });
+        }
+        if cursor_in_popup {
+            cursor_in_editor = true;
+        }
+
+        timer.lap(|t| &mut t.nodes);
+
+        /* Highlight the neighborhood of the hovered node or port */
+        let hovering = cursor_in_editor
+            && !cursor_in_finder
+            && !cursor_in_popup
+            && self.connection_in_progress.is_none();
+        let highlighted = self.update_hover_highlight(ui, hovering, cursor_pos, &node_rects);
+        if let Some(Highlighted::Node(node)) = highlighted {
+            let stroke = Stroke::new(1.5 * zoom, Color32::WHITE.lighten(0.6));
+            for neighbor in self.graph.neighbors(node) {
+                if let Some(rect) = node_rects.get(&neighbor) {
+                    ui.painter()
+                        .rect_stroke(rect.expand(2.0 * zoom), 4.0 * zoom, stroke);
+                }
+            }
+        }
+
+        /* Draw the guides the dragged node is aligned to */
+        let guide_stroke = Stroke::new(1.0, ALIGNMENT_GUIDE_COLOR);
+        for [from, to] in &self.active_guides {
+            let to_screen = |pos: Pos2| (pos.to_vec2() * zoom + origin).to_pos2();
+            ui.painter()
+                .line_segment([to_screen(*from), to_screen(*to)], guide_stroke);
         }
 
         /* Draw connections */
-        if let Some((_, ref locator)) = self.connection_in_progress {
-            let port_type = self.graph.any_param_type(*locator).unwrap();
-            let connection_color = port_type.data_type_color(user_state);
+        let style = self.connection_style;
+        if let Some((origin_node, ref locator)) = self.connection_in_progress {
+            let port_type = self.graph.resolved_type(*locator);
             let start_pos = port_locations[locator];
 
+            // Tinted by whether it can be attached to the port under the cursor
+            let target = port_locations
+                .iter()
+                .map(|(port, pos)| (*port, pos.distance(cursor_pos)))
+                .filter(|(_, distance)| *distance < DISTANCE_TO_CONNECT)
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+            let verdict = target.and_then(|(port, _)| {
+                can_attach(
+                    &self.graph,
+                    (origin_node, *locator),
+                    port,
+                    &cycle_nodes,
+                    user_state,
+                )
+            });
+            let connection_color = match verdict {
+                Some(true) => VALID_TARGET_COLOR,
+                Some(false) => INVALID_TARGET_COLOR,
+                None => port_type.data_type_color(user_state),
+            };
+
             // Find a port to connect to
             fn snap_to_ports<
                 NodeData,
@@ -281,85 +1173,682 @@ where
                     start_pos,
                 ),
             };
-            draw_connection(ui.painter(), src_pos, dst_pos, connection_color);
+            let detour_y = detour_y(node_rects.get(&origin_node), &[src_pos, dst_pos], zoom);
+            let shape = connection_shape(
+                style,
+                src_pos,
+                dst_pos,
+                detour_y,
+                connection_color,
+                wire_width,
+                zoom,
+            );
+            ui.painter().add(shape);
         }
 
-        for (input, output) in self.graph.iter_connections() {
-            let port_type = self
-                .graph
-                .any_param_type(AnyParameterId::Output(output))
-                .unwrap();
-            let connection_color = port_type.data_type_color(user_state);
-            let src_pos = port_locations[&AnyParameterId::Output(output)];
-            let dst_pos = port_locations[&AnyParameterId::Input(input)];
-            draw_connection(ui.painter(), src_pos, dst_pos, connection_color);
+        // The connection waiting for the node finder stays where it was dropped
+        if let Some(pending) = &self.pending_finder_connection {
+            if let (Ok(_), Some(port_pos)) = (
+                self.graph.any_param_type(pending.port),
+                port_locations.get(&pending.port),
+            ) {
+                let (src_pos, dst_pos) = match pending.port {
+                    AnyParameterId::Output(_) => (*port_pos, pending.position),
+                    AnyParameterId::Input(_) => (pending.position, *port_pos),
+                };
+                let connection_color = self
+                    .graph
+                    .resolved_type(pending.port)
+                    .data_type_color(user_state);
+                let origin_node = match pending.port {
+                    AnyParameterId::Output(output) => self.graph[output].node,
+                    AnyParameterId::Input(input) => self.graph[input].node,
+                };
+                let origin_rect = node_rects.get(&origin_node);
+                let detour_y = detour_y(origin_rect, &[src_pos, dst_pos], zoom);
+                let shape = connection_shape(
+                    style,
+                    src_pos,
+                    dst_pos,
+                    detour_y,
+                    connection_color,
+                    wire_width,
+                    zoom,
+                );
+                ui.painter().add(shape);
+            }
         }
 
-        /* Handle responses from drawing nodes */
-
-        // Some responses generate additional responses when processed. These
-        // are stored here to report them back to the user.
-        let mut extra_responses: Vec<NodeResponse<UserResponse, NodeData>> = Vec::new();
+        // Pulses between the warning color and a darker shade of it
+        let pulse = if self.pending_deletion.is_empty() {
+            0.0
+        } else {
+            ui.ctx().request_repaint();
+            let time = ui.input(|i| i.time);
+            (time * std::f64::consts::TAU).sin() as f32 * 0.5 + 0.5
+        };
+        let mut cursor_on_reroute = false;
+        let mut removed_reroute = None;
+        let alt_down = ui.input(|i| i.modifiers.alt);
+        // The connections are tessellated into a single mesh, below the
+        // reroute points
+        let connections_shape = ui.painter().add(Shape::Noop);
+        let mut connection_meshes = std::mem::take(&mut self.connection_meshes);
+        let mut batch = connection_meshes.begin(ui.ctx());
+        for (input, output) in self.graph.iter_connections() {
+            let port_type = self.graph.resolved_type(AnyParameterId::Output(output));
+            let to_be_removed = self.pending_deletion.contains(&self.graph[input].node)
+                || self.pending_deletion.contains(&self.graph[output].node);
+            let connection_color = if to_be_removed {
+                PENDING_DELETION_COLOR.lighten(0.6 + 0.4 * pulse)
+            } else if self.selected_connection == Some((input, output))
+                || matches!(self.splice_target, Some((_, i, o)) if (i, o) == (input, output))
+            {
+                SELECTED_CONNECTION_COLOR
+            } else {
+                port_type.data_type_color(user_state)
+            };
+            let connection_color = match highlighted {
+                Some(highlighted) if highlighted.touches(&self.graph, input, output) => {
+                    connection_color.lighten(HIGHLIGHT_BRIGHTNESS)
+                }
+                Some(_) => connection_color.linear_multiply(self.hover_highlight.faded_opacity),
+                None => connection_color,
+            };
 
-        for response in delayed_responses.iter() {
-            match response {
-                NodeResponse::ConnectEventStarted(node_id, port) => {
-                    self.connection_in_progress = Some((*node_id, *port));
+            // Reroute points are dragged around, and removed by double-clicking
+            // or alt-clicking them
+            if let Some(points) = self
+                .reroutes
+                .get_mut(&(input, output))
+                .filter(|_| self.interaction_mode.can_move())
+            {
+                for (index, point) in points.iter_mut().enumerate() {
+                    let rect = Rect::from_center_size(
+                        self.pan_zoom.graph_to_screen(*point, editor_rect),
+                        Vec2::splat(2.0 * REROUTE_RADIUS * zoom),
+                    );
+                    let resp = ui.interact(
+                        rect,
+                        ui.id().with(("reroute", input, output, index)),
+                        Sense::click_and_drag(),
+                    );
+                    cursor_on_reroute |= resp.hovered();
+                    if resp.dragged() && !panning {
+                        *point += resp.drag_delta() / zoom;
+                    }
+                    if resp.double_clicked() || (resp.clicked() && alt_down) {
+                        removed_reroute = Some(((input, output), index));
+                    }
                 }
-                NodeResponse::ConnectEventEnded { input, output } => {
-                    self.graph.add_connection(*output, *input)
+            }
+
+            if let Some(path) = self.connection_path(input, output, &port_locations, editor_rect) {
+                let detour_y = self.connection_detour_y(input, output, &path, &node_rects, zoom);
+                for (index, segment) in path.windows(2).enumerate() {
+                    // Off-screen segments are not tessellated
+                    let bounds = connection_bounds(
+                        style, segment[0], segment[1], detour_y, wire_width, zoom,
+                    );
+                    if !bounds.intersects(viewport) {
+                        continue;
+                    }
+                    batch.add(
+                        (input, output),
+                        index,
+                        style,
+                        segment[0],
+                        segment[1],
+                        detour_y,
+                        connection_color,
+                        wire_width,
+                        zoom,
+                    );
                 }
-                NodeResponse::CreatedNode(_) => {
-                    //Convenience NodeResponse for users
+                for point in &path[1..path.len() - 1] {
+                    ui.painter()
+                        .circle_filled(*point, REROUTE_RADIUS * zoom, connection_color);
                 }
-                NodeResponse::SelectNode(node_id) => {
-                    self.selected_nodes = Vec::from([*node_id]);
+
+                let label = self
+                    .connection_data
+                    .get(&(input, output))
+                    .and_then(|data| data.label());
+                let flowing = self.active_connections.contains(&(input, output));
+                if label.is_some() || flowing || graph_style.arrowheads {
+                    let points: Vec<Pos2> = path
+                        .windows(2)
+                        .flat_map(|segment| {
+                            connection_polyline(style, segment[0], segment[1], detour_y, zoom)
+                        })
+                        .collect();
+                    if flowing && Rect::from_points(&points).intersects(viewport) {
+                        let offset = ui.input(|i| i.time) as f32 * FLOW_SPEED * zoom;
+                        let dash = FLOW_DASH_LENGTH * zoom;
+                        let stroke = Stroke::new(wire_width / 2.0, connection_color.lighten(1.8));
+                        for dash in polyline_dashes(&points, dash, dash, offset) {
+                            ui.painter().add(Shape::line(dash, stroke));
+                        }
+                        ui.ctx().request_repaint();
+                    }
+                    if graph_style.arrowheads && viewport.contains(path[path.len() - 1]) {
+                        // The tip touches the edge of the input port
+                        let inset = graph_style.port_radius * zoom;
+                        draw_arrowhead(ui, &points, inset, connection_color, zoom);
+                    }
+                    if let Some(label) = label {
+                        if let Some(middle) = polyline_midpoint(&points) {
+                            draw_connection_label(
+                                ui,
+                                &label,
+                                middle,
+                                connection_color,
+                                zoom,
+                                viewport,
+                            );
+                        }
+                    }
                 }
-                NodeResponse::DeleteNodeUi(node_id) => {
-                    let (node, disc_events) = self.graph.remove_node(*node_id);
-                    // Pass the disconnection responses first so user code can perform cleanup
-                    // before node removal response.
-                    extra_responses.extend(
-                        disc_events
-                            .into_iter()
-                            .map(|(input, output)| NodeResponse::DisconnectEvent { input, output }),
-                    );
+            }
+        }
+        ui.painter().set(connections_shape, batch.finish());
+        self.connection_meshes = connection_meshes;
+        if let Some((connection, index)) = removed_reroute {
+            let points = self.reroutes.get_mut(&connection).unwrap();
+            points.remove(index);
+            if points.is_empty() {
+                self.reroutes.remove(&connection);
+            }
+        }
+
+        // Drawn over the nodes and allocated after them, so it gets the clicks
+        let cursor_in_minimap = self.minimap
+            && self.draw_minimap(ui, editor_rect, &node_rects, &port_locations, user_state);
+
+        timer.lap(|t| &mut t.connections);
+
+        /* Handle connections dropped on a node body */
+        let cursor_on_port = port_locations
+            .values()
+            .any(|port_pos| port_pos.distance(cursor_pos) < DISTANCE_TO_CONNECT);
+
+        /* Show the tooltip of the hovered port, unless a connection is dragged */
+        let hovered_port = port_locations
+            .iter()
+            .map(|(port, pos)| (*port, pos.distance(cursor_pos)))
+            .filter(|(_, distance)| *distance < DISTANCE_TO_CONNECT)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(port, _)| port)
+            .filter(|_| {
+                cursor_in_editor
+                    && !cursor_in_finder
+                    && !cursor_in_popup
+                    && !cursor_in_minimap
+                    && self.connection_in_progress.is_none()
+                    && !ui.input(|i| i.pointer.any_down())
+            });
+        let now = ui.input(|i| i.time);
+        self.hovered_port = match (hovered_port, self.hovered_port) {
+            (Some(port), Some((previous, since))) if port == previous => Some((port, since)),
+            (Some(port), _) => Some((port, now)),
+            (None, _) => None,
+        };
+        if let Some((port, since)) = self.hovered_port {
+            let waited = now - since;
+            if waited >= PORT_TOOLTIP_DELAY {
+                let position = port_locations[&port] + Vec2::splat(DISTANCE_TO_CONNECT);
+                show_tooltip_at(ui.ctx(), Id::new("port_tooltip"), Some(position), |ui| {
+                    self.port_tooltip_ui(ui, port, user_state)
+                });
+            } else {
+                ui.ctx()
+                    .request_repaint_after(std::time::Duration::from_secs_f64(
+                        PORT_TOOLTIP_DELAY - waited,
+                    ));
+            }
+        }
+        let cursor_on_node = node_rects.values().any(|rect| rect.contains(cursor_pos));
+        if let Some((origin_node, AnyParameterId::Output(output))) = self.connection_in_progress {
+            if self.drop_on_node_body && !cursor_on_port && ui.input(|i| i.pointer.any_released()) {
+                // The topmost node under the cursor
+                let target = self.node_order.iter().rev().copied().find(|node| {
+                    *node != origin_node
+                        && matches!(node_rects.get(node), Some(rect) if rect.contains(cursor_pos))
+                });
+                if let Some(node) = target {
+                    delayed_responses.extend(self.drop_on_node(output, node, ui.ctx()));
+                }
+            }
+        }
+
+        /* Handle connections dropped on the background */
+        if let Some((_, port)) = self.connection_in_progress {
+            let on_background = cursor_in_editor
+                && !cursor_on_port
+                && !cursor_in_finder
+                && !cursor_in_popup
+                && !cursor_in_minimap
+                && !cursor_on_node;
+            if self.drop_on_background && on_background && ui.input(|i| i.pointer.any_released()) {
+                let templates =
+                    self.compatible_templates(&[port], all_kinds.all_kinds(), user_state);
+                if !templates.is_empty() {
+                    self.node_finder = Some(NodeFinder::new_at(cursor_pos));
+                    self.pending_finder_connection = Some(PendingFinderConnection {
+                        port,
+                        templates,
+                        position: cursor_pos,
+                        splice: None,
+                    });
+                }
+            }
+        }
+
+        /* Handle nodes dropped on connections, see `splice_target` below */
+        if ui.input(|i| i.pointer.any_released()) {
+            if let Some((node, input, output)) = self.splice_target.take() {
+                delayed_responses.extend(self.splice_node(node, input, output));
+            }
+        }
+
+        // Report rejected drops for a little while
+        if let Some((position, time)) = self.rejected_drop {
+            let elapsed = ui.input(|i| i.time) - time;
+            if elapsed < REJECTION_FEEDBACK_DURATION {
+                ui.painter().text(
+                    position,
+                    Align2::LEFT_BOTTOM,
+                    self.localization.no_compatible_input.as_ref(),
+                    TextStyle::Body.resolve(ui.style()),
+                    PENDING_DELETION_COLOR,
+                );
+                ui.ctx().request_repaint();
+            } else {
+                self.rejected_drop = None;
+            }
+        }
+
+        /* Keyboard shortcuts, while the pointer is over the editor, unless a
+        text field or a value is being edited. The rest of the app keeps its
+        keys otherwise. */
+        if cursor_in_editor && !ui.ctx().wants_keyboard_input() {
+            let (delete, select_all, group, relation, find) = ui.input_mut(|i| {
+                let delete = i.consume_key(Modifiers::NONE, Key::Delete)
+                    | i.consume_key(Modifiers::NONE, Key::Backspace);
+                let shift_command = Modifiers::COMMAND | Modifiers::SHIFT;
+                let relation = if i.consume_key(Modifiers::COMMAND, Key::L) {
+                    Some(NodeRelation::Connected)
+                } else if i.consume_key(shift_command, Key::ArrowLeft) {
+                    Some(NodeRelation::Upstream)
+                } else if i.consume_key(shift_command, Key::ArrowRight) {
+                    Some(NodeRelation::Downstream)
+                } else {
+                    None
+                };
+                (
+                    delete,
+                    i.consume_key(Modifiers::COMMAND, Key::A),
+                    i.consume_key(Modifiers::COMMAND, Key::G),
+                    relation,
+                    i.consume_key(Modifiers::COMMAND, Key::F),
+                )
+            });
+            if delete && self.interaction_mode.can_edit() {
+                if let Some((input, output)) = self.selected_connection {
+                    delayed_responses.push(NodeResponse::RemoveConnection { output, input });
+                }
+                // Protected nodes are turned into `DeleteDenied` below
+                let nodes = self.selected_nodes.clone();
+                let deleted: HashSet<NodeId> = nodes
+                    .iter()
+                    .copied()
+                    .filter(|node| {
+                        self.graph[*node]
+                            .user_data
+                            .can_delete(*node, &self.graph, user_state)
+                    })
+                    .collect();
+                let connection_count = self
+                    .graph
+                    .iter_connections()
+                    .filter(|(input, output)| {
+                        deleted.contains(&self.graph[*input].node)
+                            || deleted.contains(&self.graph[*output].node)
+                    })
+                    .count();
+                // Ask first when the deletion would break connections
+                if connection_count > 0 {
+                    self.deletion_to_confirm = Some(PendingNodeDeletion {
+                        nodes,
+                        connection_count,
+                        position: cursor_pos,
+                    });
+                } else {
+                    delayed_responses.extend(nodes.into_iter().map(NodeResponse::DeleteNodeUi));
+                }
+            }
+            if select_all {
+                self.selected_nodes = self.node_order.clone();
+                self.selected_connection = None;
+            }
+            if find && self.find_nodes {
+                self.node_search = Some(NodeSearch::default());
+            }
+            if let Some(relation) = relation {
+                self.select_related(&self.selected_nodes.clone(), relation);
+            }
+            if group && self.interaction_mode.can_move() {
+                let title = self.localization.new_group.to_string();
+                self.group_nodes(self.selected_nodes.clone(), title);
+            }
+        }
+
+        /* Handle responses from drawing nodes */
+
+        // Some responses generate additional responses when processed. These
+        // are stored here to report them back to the user.
+        let mut extra_responses: Vec<NodeResponse<UserResponse, NodeData>> = Vec::new();
+
+        // Some responses are rewritten when processed (e.g. a connection that
+        // replaces an existing one). This holds the responses as reported back
+        // to the user.
+        let mut processed_responses = Vec::with_capacity(delayed_responses.len());
+
+        for response in delayed_responses {
+            match &response {
+                NodeResponse::ConnectEventStarted(node_id, port) => {
+                    self.connection_in_progress = Some((*node_id, *port));
+                }
+                NodeResponse::ConnectEventEnded { input, output } => {
+                    let (input, output) = (*input, *output);
+                    // Connections may come from other places than the ports,
+                    // e.g. converters or user code, so they are checked here.
+                    let input_node = self.graph.try_get_input(input).map(|i| i.node);
+                    if let (Some(input_node), Some(_)) =
+                        (input_node, self.graph.try_get_output(output))
+                    {
+                        let verdict = if self.enforce_acyclic
+                            && self.graph.would_create_cycle(output, input)
+                        {
+                            Err(self.localization.would_create_cycle.to_string())
+                        } else {
+                            self.graph[input_node].user_data.can_connect(
+                                input_node,
+                                output,
+                                input,
+                                &self.graph,
+                                user_state,
+                            )
+                        };
+                        if let Err(reason) = verdict {
+                            self.rejected_connection =
+                                Some((input, reason.clone(), ui.input(|i| i.time)));
+                            processed_responses.push(NodeResponse::RejectedConnection {
+                                output,
+                                input,
+                                reason,
+                            });
+                            continue;
+                        }
+                    }
+                    // Checked like `Graph::try_add_connection`. A rejected
+                    // connection leaves the graph untouched.
+                    let exists =
+                        input_node.is_some() && self.graph.try_get_output(output).is_some();
+                    if !exists || !self.graph.types_accept_connection(output, input) {
+                        continue;
+                    }
+                    match self.graph.insert_connection(output, input) {
+                        Insertion::Replaced(old_output) => {
+                            if self.split_replaced_connections {
+                                processed_responses.push(NodeResponse::DisconnectEvent {
+                                    input,
+                                    output: old_output,
+                                });
+                                processed_responses
+                                    .push(NodeResponse::ConnectEventEnded { input, output });
+                            } else {
+                                processed_responses.push(NodeResponse::ReplacedConnection {
+                                    input,
+                                    old_output,
+                                    new_output: output,
+                                });
+                            }
+                            continue;
+                        }
+                        // Nothing changed, so there is nothing to report
+                        Insertion::AlreadyConnected | Insertion::Invalid => continue,
+                        Insertion::Added => {}
+                    }
+                }
+                NodeResponse::ReplacedConnection {
+                    input, new_output, ..
+                } => {
+                    self.graph.add_connection(*new_output, *input);
+                }
+                NodeResponse::IncompatibleConnection { output, input } => {
+                    let from = &self.graph.get_output(*output).typ;
+                    let to = &self.graph.get_input(*input).typ;
+                    if let Some(template) = NodeTemplate::converter(from, to, user_state) {
+                        self.pending_converter = Some(PendingConverter {
+                            output: *output,
+                            input: *input,
+                            template,
+                            position: cursor_pos,
+                        });
+                    }
+                }
+                NodeResponse::RejectedConnection { input, reason, .. } => {
+                    self.rejected_connection = Some((*input, reason.clone(), ui.input(|i| i.time)));
+                }
+                NodeResponse::AddInput(node) => {
+                    let node = *node;
+                    let added = self
+                        .graph
+                        .nodes
+                        .get(node)
+                        .and_then(|n| n.user_data.can_add_input(node, &self.graph, user_state));
+                    if let Some((name, typ, value, kind)) = added {
+                        let name = self.graph[node].unique_input_name(&name);
+                        let input = self
+                            .graph
+                            .add_input_param(node, name, typ, value, kind, true);
+                        extra_responses.push(NodeResponse::AddedInput { node, input });
+                    }
+                }
+                NodeResponse::RemoveInput(input) => {
+                    let input = *input;
+                    if let Some(node) = self.graph.try_get_input(input).map(|i| i.node) {
+                        // Like for deleted nodes, the disconnections come
+                        // first so user code can clean up after them.
+                        extra_responses.extend(
+                            self.graph
+                                .connections(input)
+                                .iter()
+                                .map(|&output| NodeResponse::DisconnectEvent { input, output }),
+                        );
+                        let name = self
+                            .graph
+                            .param_name(AnyParameterId::Input(input))
+                            .unwrap_or_default()
+                            .to_owned();
+                        self.graph.remove_input_param(input);
+                        extra_responses.push(NodeResponse::RemovedInput { node, input, name });
+                    }
+                }
+                NodeResponse::CreatedNode(_)
+                | NodeResponse::AddedInput { .. }
+                | NodeResponse::RemovedInput { .. }
+                | NodeResponse::WildcardBound { .. }
+                | NodeResponse::WildcardUnbound { .. }
+                | NodeResponse::DuplicateNode(_)
+                | NodeResponse::NodeMoved { .. }
+                | NodeResponse::NodeDoubleClicked(_)
+                | NodeResponse::BackgroundClicked { .. }
+                | NodeResponse::ValueChanged { .. }
+                | NodeResponse::RejectedDrop { .. }
+                | NodeResponse::Undone { .. }
+                | NodeResponse::Redone { .. }
+                | NodeResponse::DeleteDenied(_) => {
+                    //Convenience NodeResponse for users
+                }
+                NodeResponse::SelectNode(node_id) => {
+                    self.selected_nodes = Vec::from([*node_id]);
+                    self.selected_connection = None;
+                }
+                NodeResponse::SelectRelated(node_id, relation) => {
+                    self.select_related(&[*node_id], *relation);
+                }
+                NodeResponse::DeleteNodeUi(node_id) => {
+                    let node_id = *node_id;
+                    let protected = match self.graph.nodes.get(node_id) {
+                        Some(node) => !node.user_data.can_delete(node_id, &self.graph, user_state),
+                        // Already deleted, e.g. by another response this frame
+                        None => continue,
+                    };
+                    if protected {
+                        extra_responses.push(NodeResponse::DeleteDenied(node_id));
+                        continue;
+                    }
+                    let (node, disc_events) = self.remove_node(node_id);
+                    // Pass the disconnection responses first so user code can perform cleanup
+                    // before node removal response.
+                    extra_responses.extend(
+                        disc_events
+                            .into_iter()
+                            .map(|(input, output)| NodeResponse::DisconnectEvent { input, output }),
+                    );
                     // Pass the full node as a response so library users can
                     // listen for it and get their user data.
-                    extra_responses.push(NodeResponse::DeleteNodeFull {
-                        node_id: *node_id,
-                        node,
-                    });
-                    self.node_positions.remove(*node_id);
-                    // Make sure to not leave references to old nodes hanging
-                    self.selected_nodes.retain(|id| *id != *node_id);
-                    self.node_order.retain(|id| *id != *node_id);
+                    extra_responses.push(NodeResponse::DeleteNodeFull { node_id, node });
+                }
+                NodeResponse::RemoveConnection { output, input } => {
+                    if self.graph.remove_connection_between(*output, *input) {
+                        extra_responses.push(NodeResponse::DisconnectEvent {
+                            output: *output,
+                            input: *input,
+                        });
+                    }
+                    if self.selected_connection == Some((*input, *output)) {
+                        self.selected_connection = None;
+                    }
+                }
+                NodeResponse::DisconnectNode(node_id) => {
+                    extra_responses.extend(
+                        self.graph
+                            .disconnect_node(*node_id)
+                            .into_iter()
+                            .map(|(input, output)| NodeResponse::DisconnectEvent { input, output }),
+                    );
+                }
+                NodeResponse::ExtractNode(node_id) => {
+                    let extracted = if self.graph.nodes.contains_key(*node_id) {
+                        self.graph.extract_node(*node_id)
+                    } else {
+                        None
+                    };
+                    if let Some((removed, added)) = extracted {
+                        extra_responses.extend(removed.into_iter().map(|(input, output)| {
+                            NodeResponse::DisconnectEvent { input, output }
+                        }));
+                        extra_responses.extend(added.into_iter().map(|(input, output)| {
+                            NodeResponse::ConnectEventEnded { input, output }
+                        }));
+                    }
+                }
+                NodeResponse::SetInputKind { input, kind } => {
+                    if self.graph.try_get_input(*input).is_some() {
+                        extra_responses.extend(
+                            self.graph.set_input_kind(*input, *kind).into_iter().map(
+                                |(input, output)| NodeResponse::DisconnectEvent { input, output },
+                            ),
+                        );
+                    }
                 }
                 NodeResponse::DisconnectEvent { input, output } => {
                     let other_node = self.graph.get_output(*output).node;
-                    self.graph.remove_connection(*input);
+                    self.graph.remove_connection_between(*output, *input);
                     self.connection_in_progress =
                         Some((other_node, AnyParameterId::Output(*output)));
                 }
                 NodeResponse::RaiseNode(node_id) => {
-                    let old_pos = self
-                        .node_order
-                        .iter()
-                        .position(|id| *id == *node_id)
-                        .expect("Node to be raised should be in `node_order`");
-                    self.node_order.remove(old_pos);
-                    self.node_order.push(*node_id);
+                    self.raise_node(*node_id);
+                }
+                NodeResponse::CollapseNode { node, collapsed } => {
+                    if *collapsed {
+                        self.collapsed_nodes.insert(*node);
+                    } else {
+                        self.collapsed_nodes.remove(node);
+                    }
+                }
+                NodeResponse::CollapsePortGroup {
+                    node,
+                    group,
+                    collapsed,
+                } => {
+                    let key = (*node, group.clone());
+                    if *collapsed {
+                        self.collapsed_port_groups.insert(key);
+                    } else {
+                        self.collapsed_port_groups.remove(&key);
+                    }
                 }
                 NodeResponse::MoveNode { node, drag_delta } => {
-                    self.node_positions[*node] += *drag_delta;
+                    let (node, mut drag_delta) = (*node, *drag_delta);
+                    let free = ui.input(|i| i.modifiers.command);
+                    let snap_to_grid = self.grid.snap && !free;
+                    let snap_to_guides = self.alignment_guides.enabled && !free;
+                    self.active_guides.clear();
+                    if snap_to_grid || snap_to_guides {
+                        // The node moves from grid point to grid point as the
+                        // pointer moves, so the delta is accumulated
+                        let unsnapped = match self.unsnapped_drag {
+                            Some((dragged, pos)) if dragged == node => pos,
+                            _ => self.node_positions[node],
+                        } + drag_delta;
+                        self.unsnapped_drag = Some((node, unsnapped));
+                        let mut target = unsnapped;
+                        if snap_to_grid {
+                            target = self.grid.snap(unsnapped);
+                        }
+                        if snap_to_guides {
+                            let visible = ui.clip_rect().intersect(editor_rect);
+                            target = self.align_to_guides(
+                                node,
+                                unsnapped,
+                                target,
+                                &node_rects,
+                                visible,
+                                origin,
+                                zoom,
+                            );
+                        }
+                        drag_delta = target - self.node_positions[node];
+                    } else {
+                        self.unsnapped_drag = None;
+                    }
+                    self.moved_nodes
+                        .entry(node)
+                        .or_insert(self.node_positions[node]);
+                    self.node_positions[node] += drag_delta;
                     // Handle multi-node selection movement
-                    if self.selected_nodes.contains(node) && self.selected_nodes.len() > 1 {
+                    if self.selected_nodes.contains(&node) && self.selected_nodes.len() > 1 {
                         for n in self.selected_nodes.iter().copied() {
-                            if n != *node {
-                                self.node_positions[n] += *drag_delta;
+                            if n != node {
+                                self.moved_nodes.entry(n).or_insert(self.node_positions[n]);
+                                self.node_positions[n] += drag_delta;
                             }
                         }
                     }
+                    processed_responses.push(NodeResponse::MoveNode { node, drag_delta });
+                    continue;
+                }
+                NodeResponse::RenamedNode {
+                    node, new_label, ..
+                } => {
+                    if self.graph.nodes.contains_key(*node) {
+                        self.graph[*node].label = new_label.clone();
+                    }
                 }
                 NodeResponse::User(_) => {
                     // These are handled by the user code.
@@ -368,6 +1857,38 @@ where
                     unreachable!("The UI should never produce a DeleteNodeFull event.")
                 }
             }
+            processed_responses.push(response);
+        }
+        let mut delayed_responses = processed_responses;
+
+        // Flash the input of the last rejected connection, with the reason
+        if let Some((input, reason, time)) = &self.rejected_connection {
+            let elapsed = ui.input(|i| i.time) - time;
+            match port_locations.get(&AnyParameterId::Input(*input)) {
+                Some(port_pos) if elapsed < REJECTION_FEEDBACK_DURATION => {
+                    // Blink a few times per second
+                    if (elapsed * 4.0).fract() < 0.5 {
+                        let shape = self
+                            .graph
+                            .resolved_type(AnyParameterId::Input(*input))
+                            .port_shape(user_state);
+                        ui.painter().add(shape.shape(
+                            *port_pos,
+                            7.0 * zoom,
+                            PENDING_DELETION_COLOR,
+                        ));
+                    }
+                    ui.painter().text(
+                        *port_pos - vec2(12.0 * zoom, 0.0),
+                        Align2::RIGHT_CENTER,
+                        reason,
+                        TextStyle::Body.resolve(ui.style()),
+                        PENDING_DELETION_COLOR,
+                    );
+                    ui.ctx().request_repaint();
+                }
+                _ => self.rejected_connection = None,
+            }
         }
 
         // Handle box selection
@@ -382,11 +1903,12 @@ where
                 Stroke::new(3.0, stroke_color),
             );
 
+            self.selected_connection = None;
             self.selected_nodes = node_rects
-                .into_iter()
+                .iter()
                 .filter_map(|(node_id, rect)| {
-                    if selection_rect.intersects(rect) {
-                        Some(node_id)
+                    if selection_rect.intersects(*rect) {
+                        Some(*node_id)
                     } else {
                         None
                     }
@@ -398,69 +1920,1432 @@ where
         // These are only informative for the end-user and need no special
         // treatment here.
         delayed_responses.extend(extra_responses);
+        delayed_responses.extend(self.update_wildcard_bindings());
 
         /* Mouse input handling */
 
         // This locks the context, so don't hold on to it for too long.
         let mouse = &ui.ctx().input(|i| i.pointer.clone());
 
-        if mouse.any_released() && self.connection_in_progress.is_some() {
-            self.connection_in_progress = None;
-        }
+        if mouse.any_released() && self.connection_in_progress.is_some() {
+            self.connection_in_progress = None;
+        }
+        // The connection the dragged node would be inserted into if dropped.
+        // Only a single node without connections is inserted.
+        self.splice_target = None;
+        if self.drop_node_on_connection && self.interaction_mode.can_edit() && mouse.any_down() {
+            let dragged = match self.moved_nodes.keys().collect::<SVec<_>>().as_slice() {
+                [node] => Some(**node),
+                _ => None,
+            }
+            .filter(|node| {
+                self.graph.nodes.contains_key(*node)
+                    && !self.graph.iter_connections().any(|(input, output)| {
+                        self.graph[input].node == *node || self.graph[output].node == *node
+                    })
+            });
+            let hit_distance = CONNECTION_HIT_DISTANCE * zoom;
+            self.splice_target = dragged.and_then(|node| {
+                let ((input, output), _, distance) = self.connection_at(
+                    cursor_pos,
+                    &port_locations,
+                    &node_rects,
+                    editor_rect,
+                    zoom,
+                )?;
+                self.splice_ports(node, input, output)?;
+                (distance <= hit_distance).then_some((node, input, output))
+            });
+        }
+        if !mouse.any_down() {
+            self.unsnapped_drag = None;
+            self.active_guides.clear();
+            if !self.moved_nodes.is_empty() {
+                let moved = std::mem::take(&mut self.moved_nodes);
+                self.leave_groups(&moved);
+                for node in self.node_order.iter().copied() {
+                    let new_pos = self.node_positions[node];
+                    if matches!(moved.get(&node), Some(old_pos) if *old_pos != new_pos) {
+                        delayed_responses.push(NodeResponse::NodeMoved { node, new_pos });
+                    }
+                }
+            }
+        }
+
+        // The secondary button opens the node finder or the connection menu.
+        // Releasing it after panning the view with it doesn't.
+        let secondary_trigger = match bindings.finder_trigger {
+            FinderTrigger::Release => {
+                let panned = self
+                    .pan_origin
+                    .is_some_and(|origin| origin.distance(cursor_pos) > PAN_CLICK_DISTANCE);
+                mouse.secondary_released() && !panned
+            }
+            FinderTrigger::Press => mouse.button_pressed(PointerButton::Secondary),
+        };
+
+        // Connections are only hit where the background is visible
+        let cursor_on_background = cursor_in_editor
+            && !cursor_in_finder
+            && !cursor_in_popup
+            && !cursor_in_minimap
+            && !cursor_on_port
+            && !cursor_on_reroute
+            && !cursor_on_node;
+        let hit_segment = if cursor_on_background && (click_on_background || secondary_trigger) {
+            let hit_distance = CONNECTION_HIT_DISTANCE * zoom;
+            self.connection_at(cursor_pos, &port_locations, &node_rects, editor_rect, zoom)
+                .filter(|(_, _, distance)| *distance <= hit_distance)
+                .map(|(connection, segment, _)| (connection, segment))
+        } else {
+            None
+        };
+        let hit_connection = hit_segment.map(|(connection, _)| connection);
+
+        // Double-clicking, or alt-clicking, a connection splits the hit
+        // segment with a new reroute point
+        if let Some((connection, segment)) = hit_segment {
+            if click_on_background
+                && (r.double_clicked() || alt_down)
+                && self.interaction_mode.can_move()
+            {
+                let point = self.pan_zoom.screen_to_graph(cursor_pos, editor_rect);
+                self.reroutes
+                    .entry(connection)
+                    .or_default()
+                    .insert(segment, point);
+            }
+        }
+
+        // Nodes have their own context menu, and so do connections. Ports may
+        // show one too, see `InputBindings::port_context_menus`.
+        let cursor_on_finder_port = cursor_on_port
+            && !bindings.port_context_menus
+            && cursor_in_editor
+            && !cursor_in_finder
+            && !cursor_in_popup
+            && !cursor_in_minimap
+            && !cursor_on_node;
+        if secondary_trigger && (cursor_on_background || cursor_on_finder_port) {
+            match hit_connection {
+                Some((input, output)) => {
+                    if self.interaction_mode.can_edit() {
+                        self.connection_menu = Some((input, output, cursor_pos));
+                    }
+                    self.selected_connection = Some((input, output));
+                    self.selected_nodes = Vec::new();
+                    self.node_finder = None;
+                }
+                None if self.interaction_mode.can_edit() => {
+                    self.node_finder = Some(NodeFinder::new_at(cursor_pos));
+                    self.pending_finder_connection = None;
+                    self.connection_menu = None;
+                }
+                None => self.connection_menu = None,
+            }
+        }
+        if ui.ctx().input(|i| i.key_pressed(Key::Escape)) {
+            self.node_search = None;
+            self.connection_in_progress = None;
+            self.detaching_input = None;
+            self.node_finder = None;
+            self.pending_converter = None;
+            self.deletion_to_confirm = None;
+            self.pending_input_choice = None;
+            self.connection_menu = None;
+        }
+
+        if panning {
+            self.pan_zoom.pan += mouse.delta();
+            if !mouse.any_down() {
+                self.pan_origin = None;
+            }
+        }
+
+        // Zoom around the cursor. egui reports ctrl + scroll and pinch gestures
+        // as a zoom delta, and plain scrolling as a scroll delta. Scrolling
+        // pans instead unless `InputBindings::scroll_zooms` is set or the zoom
+        // modifier is held.
+        if cursor_in_editor && !cursor_in_finder && !cursor_in_popup {
+            let (zoom_delta, scroll_delta, modifiers) =
+                ui.input(|i| (i.zoom_delta(), i.scroll_delta, i.modifiers));
+            let mut factor = zoom_delta;
+            if bindings.scroll_zooms || modifiers.matches(bindings.zoom_modifier) {
+                factor *= (scroll_delta.y / 200.0).exp();
+            } else {
+                self.pan_zoom.pan += scroll_delta;
+            }
+            if factor != 1.0 {
+                self.pan_zoom.adjust_zoom(
+                    zoom * (factor - 1.0),
+                    cursor_pos - editor_rect.min,
+                    MIN_ZOOM,
+                    MAX_ZOOM,
+                );
+            }
+        }
+
+        // Deselect and deactivate finder if the editor backround is clicked,
+        // *or* if the the mouse clicks off the ui
+        if click_on_background && hit_connection.is_none() {
+            delayed_responses.push(NodeResponse::BackgroundClicked {
+                graph_pos: self.pan_zoom.screen_to_graph(cursor_pos, editor_rect),
+            });
+        }
+        if click_on_background || (mouse.any_click() && !cursor_in_editor) {
+            self.node_search = None;
+            self.selected_nodes = Vec::new();
+            self.node_finder = None;
+            self.pending_converter = None;
+            self.deletion_to_confirm = None;
+            self.pending_input_choice = None;
+            self.connection_menu = None;
+            // Clicking a connection selects it instead
+            self.selected_connection = hit_connection;
+        }
+
+        // Closing the node finder drops the connection waiting for it
+        if self.node_finder.is_none() {
+            self.pending_finder_connection = None;
+        }
+
+        if drag_started_on_background && mouse.primary_down() && !panning {
+            self.ongoing_box_selection = Some(cursor_pos);
+        }
+        if mouse.primary_released() || drag_released_on_background {
+            self.ongoing_box_selection = None;
+        }
+
+        timer.lap(|t| &mut t.interaction);
+        self.record_timings(timer.finish());
+
+        GraphResponse {
+            node_responses: delayed_responses,
+            cursor_in_editor,
+            cursor_in_finder,
+            port_locations,
+            node_rects,
+            editor_rect,
+            pan_zoom: drawn_pan_zoom,
+        }
+    }
+
+    /// The contents of the tooltip of a port: the name and data type of its
+    /// param, the kind of inputs, the params it's connected to, and whatever
+    /// user code adds.
+    fn port_tooltip_ui(&mut self, ui: &mut Ui, port: AnyParameterId, user_state: &mut UserState) {
+        let (node_id, connected) = match port {
+            AnyParameterId::Input(input) => (
+                self.graph.get_input(input).node,
+                self.graph
+                    .connections(input)
+                    .iter()
+                    .map(|output| AnyParameterId::Output(*output))
+                    .collect::<Vec<_>>(),
+            ),
+            AnyParameterId::Output(output) => (
+                self.graph.get_output(output).node,
+                self.graph
+                    .output_connections(output)
+                    .map(AnyParameterId::Input)
+                    .collect(),
+            ),
+        };
+
+        ui.strong(self.graph.param_name(port).unwrap_or_default());
+        if let Ok(typ) = self.graph.any_param_type(port) {
+            ui.label(typ.name());
+        }
+        if let AnyParameterId::Input(input) = port {
+            ui.label(
+                match self.graph.get_input(input).kind() {
+                    InputParamKind::ConnectionOnly => &self.localization.connection_only,
+                    InputParamKind::ConstantOnly => &self.localization.constant_only,
+                    InputParamKind::ConnectionOrConstant => {
+                        &self.localization.connection_or_constant
+                    }
+                }
+                .as_ref(),
+            );
+        }
+        if !self.failed_nodes.contains(&node_id) {
+            let graph = &self.graph;
+            let description = call_user_code(self.defensive, node_id, || {
+                graph[node_id]
+                    .user_data
+                    .param_description(node_id, port, graph, user_state)
+                    .map(|description| description.into_owned())
+            });
+            match description {
+                Some(Some(description)) => {
+                    ui.label(description);
+                }
+                Some(None) => {}
+                None => {
+                    self.failed_nodes.insert(node_id);
+                }
+            }
+        }
+        if connected.is_empty() {
+            ui.weak(self.localization.not_connected.as_ref());
+        } else {
+            ui.label(self.localization.connected_to.as_ref());
+            for other in connected {
+                let other_node = match other {
+                    AnyParameterId::Input(input) => self.graph.get_input(input).node,
+                    AnyParameterId::Output(output) => self.graph.get_output(output).node,
+                };
+                ui.label(format!(
+                    "{} › {}",
+                    self.graph[other_node].label,
+                    self.graph.param_name(other).unwrap_or_default()
+                ));
+            }
+        }
+
+        if !self.failed_nodes.contains(&node_id) {
+            let graph = &self.graph;
+            let completed = call_user_code(self.defensive, node_id, || {
+                graph[node_id]
+                    .user_data
+                    .port_tooltip_ui(ui, node_id, port, graph, user_state)
+            });
+            if completed.is_none() {
+                self.failed_nodes.insert(node_id);
+            }
+        }
+    }
+
+    /// The connection closest to `pos`, and its distance to it, in screen
+    /// units. Of several connections at the same distance, the one drawn last,
+    /// on top of the others, is picked.
+    fn connection_at(
+        &self,
+        pos: Pos2,
+        port_locations: &PortLocations,
+        node_rects: &NodeRects,
+        editor_rect: Rect,
+        zoom: f32,
+    ) -> Option<((InputId, OutputId), usize, f32)> {
+        let mut closest: Option<((InputId, OutputId), usize, f32)> = None;
+        for (input, output) in self.graph.iter_connections() {
+            let path = match self.connection_path(input, output, port_locations, editor_rect) {
+                Some(path) => path,
+                None => continue,
+            };
+            let detour_y = self.connection_detour_y(input, output, &path, node_rects, zoom);
+            for (segment, ends) in path.windows(2).enumerate() {
+                let distance = distance_to_connection(
+                    self.connection_style,
+                    ends[0],
+                    ends[1],
+                    detour_y,
+                    zoom,
+                    pos,
+                );
+                if !matches!(closest, Some((_, _, closest)) if closest < distance) {
+                    closest = Some(((input, output), segment, distance));
+                }
+            }
+        }
+        closest
+    }
+
+    /// The screen positions a connection goes through: its output port, its
+    /// reroute points and its input port. `None` if one of its ports wasn't
+    /// drawn.
+    fn connection_path(
+        &self,
+        input: InputId,
+        output: OutputId,
+        port_locations: &PortLocations,
+        editor_rect: Rect,
+    ) -> Option<SVec<Pos2>> {
+        let src_pos = port_locations.get(&AnyParameterId::Output(output))?;
+        let dst_pos = port_locations.get(&AnyParameterId::Input(input))?;
+        let mut path = SVec::new();
+        path.push(*src_pos);
+        if let Some(points) = self.reroutes.get(&(input, output)) {
+            path.extend(
+                points
+                    .iter()
+                    .map(|point| self.pan_zoom.graph_to_screen(*point, editor_rect)),
+            );
+        }
+        path.push(*dst_pos);
+        Some(path)
+    }
+
+    /// The height at which the backward segments of an orthogonal connection
+    /// run, below the nodes it connects and the points of its `path`.
+    fn connection_detour_y(
+        &self,
+        input: InputId,
+        output: OutputId,
+        path: &[Pos2],
+        node_rects: &NodeRects,
+        zoom: f32,
+    ) -> f32 {
+        let src_rect = node_rects.get(&self.graph[output].node);
+        let dst_rect = node_rects.get(&self.graph[input].node);
+        detour_y(src_rect.into_iter().chain(dst_rect), path, zoom)
+    }
+
+    /// Draws the group frames. Their title bar moves their members when
+    /// dragged, selects them when clicked, and has a context menu.
+    fn draw_groups(
+        &mut self,
+        ui: &mut Ui,
+        editor_rect: Rect,
+    ) -> Vec<NodeResponse<UserResponse, NodeData>> {
+        let mut responses = Vec::new();
+        let zoom = self.pan_zoom.zoom;
+        let visuals = ui.visuals().clone();
+        let fill = visuals.widgets.noninteractive.bg_fill.linear_multiply(0.4);
+        let stroke = Stroke::new(1.0, visuals.widgets.noninteractive.bg_stroke.color);
+        let groups: Vec<GroupId> = self.groups.keys().collect();
+        for group in groups {
+            let rect = match self.group_rect(group) {
+                Some(rect) => Rect::from_min_max(
+                    self.pan_zoom.graph_to_screen(rect.min, editor_rect),
+                    self.pan_zoom.graph_to_screen(rect.max, editor_rect),
+                ),
+                None => continue,
+            };
+            let title_rect =
+                Rect::from_min_size(rect.min, vec2(rect.width(), GROUP_TITLE_HEIGHT * zoom));
+            ui.painter().rect(rect, 4.0 * zoom, fill, stroke);
+            ui.painter().rect_filled(title_rect, 4.0 * zoom, fill);
+            ui.painter().text(
+                title_rect.left_center() + vec2(8.0 * zoom, 0.0),
+                Align2::LEFT_CENTER,
+                &self.groups[group].title,
+                FontId::proportional(14.0 * zoom),
+                visuals.text_color(),
+            );
+
+            let resp = ui.interact(
+                title_rect,
+                ui.id().with(("group", group)),
+                Sense::click_and_drag(),
+            );
+            let members = self.group_members(group);
+            if resp.dragged() && self.interaction_mode.can_move() && self.pan_origin.is_none() {
+                let delta = resp.drag_delta() / zoom;
+                for node in &members {
+                    self.node_positions[*node] += delta;
+                }
+            }
+            if resp.clicked() {
+                self.selected_nodes = members.clone();
+                self.selected_connection = None;
+            }
+
+            let mut remove = false;
+            let mut delete_members = false;
+            let mode = self.interaction_mode;
+            resp.context_menu(|ui| {
+                ui.add_enabled(
+                    mode.can_move(),
+                    TextEdit::singleline(&mut self.groups[group].title),
+                );
+                ui.separator();
+                if ui
+                    .add_enabled(
+                        mode.can_move(),
+                        Button::new(self.localization.remove_group.as_ref()),
+                    )
+                    .clicked()
+                {
+                    remove = true;
+                    ui.close_menu();
+                }
+                if ui
+                    .add_enabled(
+                        mode.can_edit(),
+                        Button::new(self.localization.delete_group_and_nodes.as_ref()),
+                    )
+                    .clicked()
+                {
+                    remove = true;
+                    delete_members = true;
+                    ui.close_menu();
+                }
+            });
+            if remove {
+                self.remove_group(group);
+            }
+            if delete_members {
+                // Protected members are kept, see `NodeResponse::DeleteDenied`
+                responses.extend(members.into_iter().map(NodeResponse::DeleteNodeUi));
+            }
+        }
+        responses
+    }
+
+    /// Keeps track of the node under the pointer, and returns what the
+    /// connections are highlighted around, once the pointer stayed on a node
+    /// or a port for [`HoverHighlight::delay`]. The port comes from the
+    /// previous frame, the one under the pointer isn't known yet.
+    fn update_hover_highlight(
+        &mut self,
+        ui: &Ui,
+        hovering: bool,
+        cursor_pos: Pos2,
+        node_rects: &NodeRects,
+    ) -> Option<Highlighted> {
+        let settings = self.hover_highlight;
+        // The topmost node, so moving within it keeps the same one
+        let hovered_node = self
+            .node_order
+            .iter()
+            .rev()
+            .copied()
+            .find(|node| matches!(node_rects.get(node), Some(rect) if rect.contains(cursor_pos)))
+            .filter(|_| settings.enabled && hovering);
+        let now = ui.input(|i| i.time);
+        self.hovered_node = match (hovered_node, self.hovered_node) {
+            (Some(node), Some((previous, since))) if node == previous => Some((node, since)),
+            (Some(node), _) => Some((node, now)),
+            (None, _) => None,
+        };
+        if !settings.enabled {
+            return None;
+        }
+
+        let hovered_port = self
+            .hovered_port
+            .map(|(port, since)| (Highlighted::Port(port), since));
+        let hovered_node = self
+            .hovered_node
+            .map(|(node, since)| (Highlighted::Node(node), since));
+        let mut highlighted = None;
+        for (target, since) in hovered_port.into_iter().chain(hovered_node) {
+            let waited = now - since;
+            if waited >= settings.delay {
+                highlighted = Some(target);
+                break;
+            }
+            ui.ctx()
+                .request_repaint_after(std::time::Duration::from_secs_f64(settings.delay - waited));
+        }
+        highlighted
+    }
+
+    /// Moves the `target` position of the dragged node to the edges of the
+    /// visible nodes close to its `unsnapped` position, see
+    /// [`GraphEditorState::alignment_guides`], and remembers the guides to
+    /// draw. The other selected nodes move along, so they aren't aligned to.
+    #[allow(clippy::too_many_arguments)]
+    fn align_to_guides(
+        &mut self,
+        node: NodeId,
+        unsnapped: Pos2,
+        target: Pos2,
+        node_rects: &NodeRects,
+        visible: Rect,
+        origin: Vec2,
+        zoom: f32,
+    ) -> Pos2 {
+        let size = match node_rects.get(&node) {
+            Some(rect) => rect.size() / zoom,
+            None => return target,
+        };
+        let moving_along = self.selected_nodes.contains(&node);
+        let others: Vec<Rect> = self
+            .node_order
+            .iter()
+            .filter(|other| **other != node)
+            .filter(|other| !(moving_along && self.selected_nodes.contains(other)))
+            .filter_map(|other| node_rects.get(other))
+            .filter(|rect| rect.intersects(visible))
+            .map(|rect| {
+                Rect::from_min_size(
+                    ((rect.min - origin).to_vec2() / zoom).to_pos2(),
+                    rect.size() / zoom,
+                )
+            })
+            .collect();
+
+        // The offset to the closest edge, per axis
+        let threshold = self.alignment_guides.threshold / zoom;
+        let closest = |offsets: Vec<f32>| {
+            offsets
+                .into_iter()
+                .filter(|offset| offset.abs() <= threshold)
+                .min_by(|a, b| a.abs().total_cmp(&b.abs()))
+        };
+        let center_x = unsnapped.x + size.x / 2.0;
+        let mut aligned = target;
+        if let Some(offset) = closest(
+            others
+                .iter()
+                .flat_map(|other| [other.left() - unsnapped.x, other.center().x - center_x])
+                .collect(),
+        ) {
+            aligned.x = unsnapped.x + offset;
+        }
+        if let Some(offset) = closest(
+            others
+                .iter()
+                .map(|other| other.top() - unsnapped.y)
+                .collect(),
+        ) {
+            aligned.y = unsnapped.y + offset;
+        }
+
+        // Every edge the node ends up on gets a guide, even the ones that
+        // were only reached through the grid
+        let dragged = Rect::from_min_size(aligned, size);
+        let same = |a: f32, b: f32| (a - b).abs() < 1e-3;
+        for other in &others {
+            let (top, bottom) = (
+                other.top().min(dragged.top()),
+                other.bottom().max(dragged.bottom()),
+            );
+            for x in [other.left(), other.center().x] {
+                if same(x, dragged.left()) || same(x, dragged.center().x) {
+                    self.active_guides.push([pos2(x, top), pos2(x, bottom)]);
+                }
+            }
+            if same(other.top(), dragged.top()) {
+                let (left, right) = (
+                    other.left().min(dragged.left()),
+                    other.right().max(dragged.right()),
+                );
+                self.active_guides
+                    .push([pos2(left, other.top()), pos2(right, other.top())]);
+            }
+        }
+        aligned
+    }
+
+    /// Draws the grid behind the graph, see [`GraphEditorState::grid`].
+    fn draw_grid(&self, ui: &mut Ui, editor_rect: Rect, color: Color32) {
+        let zoom = self.pan_zoom.zoom;
+        let mut step = self.grid.spacing;
+        while step * zoom < MIN_GRID_STEP {
+            step *= 2.0;
+        }
+        let visible = Rect::from_min_max(
+            self.pan_zoom.screen_to_graph(editor_rect.min, editor_rect),
+            self.pan_zoom.screen_to_graph(editor_rect.max, editor_rect),
+        );
+        let painter = ui.painter_at(editor_rect);
+        let to_screen = |x: f32, y: f32| self.pan_zoom.graph_to_screen(pos2(x, y), editor_rect);
+        let lines = |min: f32, max: f32| {
+            let first = (min / step).floor() as i64;
+            let last = (max / step).ceil() as i64;
+            (first..=last).map(move |i| i as f32 * step)
+        };
+        match self.grid.style {
+            GridStyle::Hidden => {}
+            GridStyle::Dots => {
+                let radius = (1.5 * zoom).max(1.0);
+                for x in lines(visible.min.x, visible.max.x) {
+                    for y in lines(visible.min.y, visible.max.y) {
+                        painter.circle_filled(to_screen(x, y), radius, color);
+                    }
+                }
+            }
+            GridStyle::Lines => {
+                let stroke = Stroke::new(1.0, color);
+                for x in lines(visible.min.x, visible.max.x) {
+                    painter.line_segment(
+                        [to_screen(x, visible.min.y), to_screen(x, visible.max.y)],
+                        stroke,
+                    );
+                }
+                for y in lines(visible.min.y, visible.max.y) {
+                    painter.line_segment(
+                        [to_screen(visible.min.x, y), to_screen(visible.max.x, y)],
+                        stroke,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Draws the minimap in the bottom-right corner of the editor, and pans the
+    /// view when it's clicked or dragged. Returns whether the cursor is over
+    /// the minimap.
+    fn draw_minimap(
+        &mut self,
+        ui: &mut Ui,
+        editor_rect: Rect,
+        node_rects: &NodeRects,
+        port_locations: &PortLocations,
+        user_state: &mut UserState,
+    ) -> bool {
+        let minimap_rect = Rect::from_min_size(
+            editor_rect.max - MINIMAP_SIZE - Vec2::splat(10.0),
+            MINIMAP_SIZE,
+        );
+        let resp = ui.interact(
+            minimap_rect,
+            ui.id().with("minimap"),
+            Sense::click_and_drag(),
+        );
+
+        // Everything is mapped from graph space, so the minimap doesn't
+        // depend on the zoom. The node rects were laid out with the pan of
+        // this frame, which may change below.
+        let pan_zoom = self.pan_zoom;
+        let to_graph = |rect: Rect| {
+            Rect::from_min_max(
+                pan_zoom.screen_to_graph(rect.min, editor_rect),
+                pan_zoom.screen_to_graph(rect.max, editor_rect),
+            )
+        };
+        let viewport = to_graph(editor_rect);
+        let bounds = node_rects
+            .values()
+            .fold(viewport, |bounds, rect| bounds.union(to_graph(*rect)))
+            .expand(20.0);
+        let scale = (MINIMAP_SIZE.x / bounds.width()).min(MINIMAP_SIZE.y / bounds.height());
+        // Centers the graph in the minimap
+        let offset = (MINIMAP_SIZE - bounds.size() * scale) / 2.0;
+        let to_minimap = |pos: Pos2| minimap_rect.min + offset + (pos - bounds.min) * scale;
+        let to_minimap_rect =
+            |rect: Rect| Rect::from_min_max(to_minimap(rect.min), to_minimap(rect.max));
+
+        if resp.dragged() {
+            self.pan_zoom.pan -= resp.drag_delta() / scale * self.pan_zoom.zoom;
+        } else if resp.clicked() {
+            if let Some(pointer) = resp.interact_pointer_pos() {
+                let target = bounds.min + (pointer - minimap_rect.min - offset) / scale;
+                self.pan_zoom.pan =
+                    editor_rect.size() / 2.0 - target.to_vec2() * self.pan_zoom.zoom;
+            }
+        }
+
+        let painter = ui.painter().with_clip_rect(minimap_rect);
+        let visuals = ui.visuals();
+        painter.rect(
+            minimap_rect,
+            4.0,
+            visuals.extreme_bg_color.linear_multiply(0.8),
+            visuals.window_stroke(),
+        );
+        let default_color = if visuals.dark_mode {
+            color_from_hex("#5f5f5f").unwrap()
+        } else {
+            color_from_hex("#c0c0c0").unwrap()
+        };
+        for (node_id, rect) in node_rects {
+            let color = self.graph[*node_id]
+                .user_data
+                .titlebar_color(ui, *node_id, &self.graph, user_state)
+                .unwrap_or(default_color);
+            painter.rect_filled(to_minimap_rect(to_graph(*rect)), 1.0, color);
+        }
+        for (input, output) in self.graph.iter_connections() {
+            let ports = (
+                port_locations.get(&AnyParameterId::Output(output)),
+                port_locations.get(&AnyParameterId::Input(input)),
+            );
+            if let (Some(src), Some(dst)) = ports {
+                let color = self
+                    .graph
+                    .resolved_type(AnyParameterId::Output(output))
+                    .data_type_color(user_state);
+                let mut path = SVec::new();
+                path.push(to_minimap(pan_zoom.screen_to_graph(*src, editor_rect)));
+                if let Some(points) = self.reroutes.get(&(input, output)) {
+                    path.extend(points.iter().map(|point| to_minimap(*point)));
+                }
+                path.push(to_minimap(pan_zoom.screen_to_graph(*dst, editor_rect)));
+                for segment in path.windows(2) {
+                    painter.line_segment([segment[0], segment[1]], Stroke::new(1.0, color));
+                }
+            }
+        }
+        painter.rect_stroke(
+            to_minimap_rect(viewport),
+            1.0,
+            Stroke::new(1.5, visuals.strong_text_color()),
+        );
+
+        resp.hovered()
+    }
+
+    /// For a drag starting on an input with several connections, returns the
+    /// connection whose output lies in the direction of the drag. Returns
+    /// `None` until the pointer has moved far enough to tell.
+    fn dragged_connection(
+        &self,
+        input: InputId,
+        port_locations: &PortLocations,
+        ctx: &Context,
+    ) -> Option<OutputId> {
+        let outputs = self.graph.connections(input);
+        let input_pos = port_locations.get(&AnyParameterId::Input(input))?;
+        let direction =
+            ctx.input(|i| Some(i.pointer.interact_pos()? - i.pointer.press_origin()?))?;
+        if direction.length() < DISTANCE_TO_CONNECT / 2.0 {
+            return None;
+        }
+        outputs
+            .iter()
+            .filter_map(|output| {
+                let output_pos = port_locations.get(&AnyParameterId::Output(*output))?;
+                let alignment = (*output_pos - *input_pos)
+                    .normalized()
+                    .dot(direction.normalized());
+                Some((*output, alignment))
+            })
+            .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(output, _)| output)
+    }
+
+    /// Compares the bindings of the wildcard ports with the ones of the last
+    /// frame, and reports the changes.
+    fn update_wildcard_bindings(&mut self) -> Vec<NodeResponse<UserResponse, NodeData>> {
+        let graph = &self.graph;
+        let params = graph
+            .inputs
+            .keys()
+            .map(AnyParameterId::Input)
+            .chain(graph.outputs.keys().map(AnyParameterId::Output));
+        let bindings: HashMap<_, _> = params
+            .filter_map(|param| Some((param, graph.wildcard_binding(param)?)))
+            .collect();
+        let previous = match self.wildcard_bindings.replace(bindings) {
+            Some(previous) => previous,
+            None => return Vec::new(),
+        };
+        let bindings = self.wildcard_bindings.as_ref().unwrap();
+        let node = |param| match param {
+            AnyParameterId::Input(input) => graph.try_get_input(input).map(|p| p.node),
+            AnyParameterId::Output(output) => graph.try_get_output(output).map(|p| p.node),
+        };
+
+        let mut responses = Vec::new();
+        for (param, bound_to) in bindings {
+            if previous.get(param) != Some(bound_to) {
+                responses.push(NodeResponse::WildcardBound {
+                    node: node(*param).expect("Bound param should exist"),
+                    param: *param,
+                    bound_to: *bound_to,
+                });
+            }
+        }
+        for param in previous.keys() {
+            // Params deleted along with their node aren't reported
+            if let (false, Some(node)) = (bindings.contains_key(param), node(*param)) {
+                responses.push(NodeResponse::WildcardUnbound {
+                    node,
+                    param: *param,
+                });
+            }
+        }
+        responses
+    }
+
+    /// Resolves a connection from `output` dropped on the body of `node`: see
+    /// [`GraphEditorState::drop_on_node_body`]. Returns the response to
+    /// process, if the drop could be resolved right away.
+    fn drop_on_node(
+        &mut self,
+        output: OutputId,
+        node: NodeId,
+        ctx: &Context,
+    ) -> Option<NodeResponse<UserResponse, NodeData>> {
+        let inputs: Vec<(String, InputId)> = self.graph[node]
+            .inputs
+            .iter()
+            .filter(|(_, input)| {
+                let param = self.graph.get_input(*input);
+                self.graph.types_accept_connection(output, *input)
+                    && !matches!(param.kind, InputParamKind::ConstantOnly)
+                    && !self.graph.is_input_full(*input)
+            })
+            .cloned()
+            .collect();
+        let position = ctx.input(|i| i.pointer.interact_pos().unwrap_or_default());
+
+        match inputs.as_slice() {
+            [] => {
+                self.rejected_drop = Some((position, ctx.input(|i| i.time)));
+                Some(NodeResponse::RejectedDrop { output, node })
+            }
+            [(_, input)] => Some(NodeResponse::ConnectEventEnded {
+                output,
+                input: *input,
+            }),
+            _ => {
+                self.pending_input_choice = Some(PendingInputChoice {
+                    output,
+                    inputs,
+                    highlighted: 0,
+                    position,
+                });
+                None
+            }
+        }
+    }
+
+    /// The templates among `all_kinds` building a node with a port a
+    /// connection from each of `ports` can be attached to. Each template is
+    /// built in a scratch graph to find out about its ports.
+    fn compatible_templates(
+        &self,
+        ports: &[AnyParameterId],
+        all_kinds: Vec<NodeTemplate>,
+        user_state: &mut UserState,
+    ) -> Vec<NodeTemplate> {
+        if ports
+            .iter()
+            .any(|port| self.graph.any_param_type(*port).is_err())
+        {
+            return Vec::new();
+        }
+        let mut scratch = Graph::new();
+        all_kinds
+            .into_iter()
+            .filter(|kind| {
+                let label = kind.node_graph_label(user_state);
+                let user_data = kind.user_data(user_state);
+                let node = scratch.add_node(label, user_data, |graph, node_id| {
+                    kind.build_node(graph, user_state, node_id)
+                });
+                ports.iter().all(|port| {
+                    let typ = self.graph.resolved_type(*port);
+                    first_compatible_port(&scratch, node, *port, typ).is_some()
+                })
+            })
+            .collect()
+    }
+
+    /// Connects `port` to the first compatible port of `node`, a node just
+    /// created from the node finder. See [`GraphEditorState::drop_on_background`].
+    fn connect_new_node(
+        &self,
+        port: AnyParameterId,
+        node: NodeId,
+    ) -> Option<NodeResponse<UserResponse, NodeData>> {
+        // The port may have been deleted while the finder was open
+        self.graph.any_param_type(port).ok()?;
+        let typ = self.graph.resolved_type(port);
+        match (port, first_compatible_port(&self.graph, node, port, typ)?) {
+            (AnyParameterId::Output(output), AnyParameterId::Input(input))
+            | (AnyParameterId::Input(input), AnyParameterId::Output(output)) => {
+                Some(NodeResponse::ConnectEventEnded { output, input })
+            }
+            _ => None,
+        }
+    }
+
+    /// The input and output of `node` the connection from `output` to `input`
+    /// would go through if the node was inserted into it. See
+    /// [`GraphEditorState::drop_node_on_connection`].
+    fn splice_ports(
+        &self,
+        node: NodeId,
+        input: InputId,
+        output: OutputId,
+    ) -> Option<(InputId, OutputId)> {
+        let from = AnyParameterId::Output(output);
+        let to = AnyParameterId::Input(input);
+        match (
+            first_compatible_port(&self.graph, node, from, self.graph.resolved_type(from))?,
+            first_compatible_port(&self.graph, node, to, self.graph.resolved_type(to))?,
+        ) {
+            (AnyParameterId::Input(node_input), AnyParameterId::Output(node_output)) => {
+                Some((node_input, node_output))
+            }
+            _ => None,
+        }
+    }
+
+    /// The responses replacing the connection from `output` to `input` by two
+    /// connections going through `node`.
+    fn splice_node(
+        &self,
+        node: NodeId,
+        input: InputId,
+        output: OutputId,
+    ) -> Vec<NodeResponse<UserResponse, NodeData>> {
+        let exists = self.graph.try_get_input(input).is_some()
+            && self.graph.connections(input).contains(&output);
+        match self.splice_ports(node, input, output) {
+            Some((node_input, node_output)) if exists => vec![
+                NodeResponse::RemoveConnection { output, input },
+                NodeResponse::ConnectEventEnded {
+                    output,
+                    input: node_input,
+                },
+                NodeResponse::ConnectEventEnded {
+                    output: node_output,
+                    input,
+                },
+            ],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Builds the converter node of an accepted [`PendingConverter`] halfway
+    /// between its two ports, and returns the responses that connect it.
+    fn insert_converter(
+        &mut self,
+        pending: &PendingConverter<NodeTemplate>,
+        port_locations: &PortLocations,
+        editor_rect: Rect,
+        user_state: &mut UserState,
+    ) -> Vec<NodeResponse<UserResponse, NodeData>> {
+        // Either end may have been deleted while the popup was open
+        if self.graph.try_get_output(pending.output).is_none()
+            || self.graph.try_get_input(pending.input).is_none()
+        {
+            return Vec::new();
+        }
+
+        let midpoint = match (
+            port_locations.get(&AnyParameterId::Output(pending.output)),
+            port_locations.get(&AnyParameterId::Input(pending.input)),
+        ) {
+            (Some(src_pos), Some(dst_pos)) => {
+                pos2((src_pos.x + dst_pos.x) / 2.0, (src_pos.y + dst_pos.y) / 2.0)
+            }
+            _ => pending.position,
+        };
+        let converter = self.add_node(
+            &pending.template,
+            self.pan_zoom.screen_to_graph(midpoint, editor_rect),
+            user_state,
+        );
+        let mut responses = vec![NodeResponse::CreatedNode(converter)];
+
+        let from = &self.graph[pending.output].typ;
+        let to = &self.graph[pending.input].typ;
+        let converter_input = self.graph[converter]
+            .input_ids()
+            .find(|input| self.graph[*input].typ == *from);
+        let converter_output = self.graph[converter]
+            .output_ids()
+            .find(|output| self.graph[*output].typ == *to);
+        if let Some(input) = converter_input {
+            responses.push(NodeResponse::ConnectEventEnded {
+                output: pending.output,
+                input,
+            });
+        }
+        if let Some(output) = converter_output {
+            responses.push(NodeResponse::ConnectEventEnded {
+                output,
+                input: pending.input,
+            });
+        }
+        responses
+    }
+}
+
+/// The templates listed by the node finder.
+struct TemplateList<NodeTemplate>(Vec<NodeTemplate>);
+
+impl<NodeTemplate> NodeTemplateIter for TemplateList<NodeTemplate>
+where
+    NodeTemplate: Clone,
+{
+    type Item = NodeTemplate;
+
+    fn all_kinds(&self) -> Vec<NodeTemplate> {
+        self.0.clone()
+    }
+}
+
+/// Whether the connection dragged from the `origin` port of a node could be
+/// attached to `port`, or `None` if both are inputs or outputs. Connections
+/// can't go from a node to itself, nor to the nodes in `cycle_nodes`, see
+/// [`GraphEditorState::enforce_acyclic`].
+///
+/// This is evaluated for every port while dragging, so the data types are
+/// compared before [`NodeDataTrait::can_connect`] is asked.
+fn can_attach<NodeData, DataType, ValueType, UserState>(
+    graph: &Graph<NodeData, DataType, ValueType>,
+    origin: (NodeId, AnyParameterId),
+    port: AnyParameterId,
+    cycle_nodes: &HashSet<NodeId>,
+    user_state: &mut UserState,
+) -> Option<bool>
+where
+    NodeData: NodeDataTrait<UserState = UserState, DataType = DataType, ValueType = ValueType>,
+    DataType: DataTypeTrait<UserState>,
+{
+    let (output, input) = match (origin.1, port) {
+        (AnyParameterId::Output(output), AnyParameterId::Input(input))
+        | (AnyParameterId::Input(input), AnyParameterId::Output(output)) => (output, input),
+        _ => return None,
+    };
+    let node = match port {
+        AnyParameterId::Input(input) => graph.get_input(input).node,
+        AnyParameterId::Output(output) => graph.get_output(output).node,
+    };
+    if node == origin.0
+        || cycle_nodes.contains(&node)
+        || !graph.types_accept_connection(output, input)
+    {
+        return Some(false);
+    }
+    let input_node = graph.get_input(input).node;
+    let verdict = graph[input_node]
+        .user_data
+        .can_connect(input_node, output, input, graph, user_state);
+    Some(verdict.is_ok())
+}
+
+/// The first port of `node` a connection from `port`, of resolved type `typ`,
+/// can be attached to: an input when `port` is an output, and the other way
+/// around.
+fn first_compatible_port<NodeData, DataType, ValueType, UserState>(
+    graph: &Graph<NodeData, DataType, ValueType>,
+    node: NodeId,
+    port: AnyParameterId,
+    typ: &DataType,
+) -> Option<AnyParameterId>
+where
+    DataType: DataTypeTrait<UserState>,
+{
+    match port {
+        AnyParameterId::Output(_) => graph[node]
+            .input_ids()
+            .find(|input| {
+                let param = graph.get_input(*input);
+                (param.typ.superset_of(typ) || typ.is_wildcard())
+                    && !matches!(param.kind, InputParamKind::ConstantOnly)
+            })
+            .map(AnyParameterId::Input),
+        AnyParameterId::Input(_) => graph[node]
+            .output_ids()
+            .find(|output| {
+                let other = &graph.get_output(*output).typ;
+                typ.superset_of(other) || other.is_wildcard()
+            })
+            .map(AnyParameterId::Output),
+    }
+}
+
+fn connection_bezier(src_pos: Pos2, dst_pos: Pos2, stroke: Stroke, zoom: f32) -> CubicBezierShape {
+    let control_scale = ((dst_pos.x - src_pos.x) / 2.0).max(30.0 * zoom);
+    let src_control = src_pos + Vec2::X * control_scale;
+    let dst_control = dst_pos - Vec2::X * control_scale;
+
+    CubicBezierShape::from_points_stroke(
+        [src_pos, src_control, dst_control, dst_pos],
+        false,
+        Color32::TRANSPARENT,
+        stroke,
+    )
+}
+
+/// The shape of a connection drawn from `src_pos` to `dst_pos` in `style`,
+/// `width` wide. Backward orthogonal connections run horizontally at
+/// `detour_y`.
+pub(crate) fn connection_shape(
+    style: ConnectionStyle,
+    src_pos: Pos2,
+    dst_pos: Pos2,
+    detour_y: f32,
+    color: Color32,
+    width: f32,
+    zoom: f32,
+) -> Shape {
+    let stroke = Stroke::new(width, color);
+    match style {
+        ConnectionStyle::Bezier => connection_bezier(src_pos, dst_pos, stroke, zoom).into(),
+        ConnectionStyle::Straight | ConnectionStyle::Orthogonal => Shape::line(
+            connection_polyline(style, src_pos, dst_pos, detour_y, zoom),
+            stroke,
+        ),
+    }
+}
 
-        if mouse.secondary_released() && cursor_in_editor && !cursor_in_finder {
-            self.node_finder = Some(NodeFinder::new_at(cursor_pos));
+/// A rect containing the connection drawn by [`connection_shape`], cheaper to
+/// compute than its exact bounds.
+fn connection_bounds(
+    style: ConnectionStyle,
+    src_pos: Pos2,
+    dst_pos: Pos2,
+    detour_y: f32,
+    width: f32,
+    zoom: f32,
+) -> Rect {
+    let bounds = Rect::from_two_pos(src_pos, dst_pos);
+    let bounds = match style {
+        // The curve stays within its control points
+        ConnectionStyle::Bezier => {
+            let control_scale = ((dst_pos.x - src_pos.x) / 2.0).max(30.0 * zoom);
+            bounds.expand2(Vec2::X * control_scale)
         }
-        if ui.ctx().input(|i| i.key_pressed(Key::Escape)) {
-            self.node_finder = None;
+        ConnectionStyle::Straight => bounds,
+        ConnectionStyle::Orthogonal => {
+            let clearance = ORTHOGONAL_CLEARANCE * zoom;
+            bounds
+                .expand2(Vec2::X * clearance)
+                .union(Rect::from_min_max(
+                    pos2(bounds.min.x, detour_y),
+                    pos2(bounds.max.x, detour_y),
+                ))
         }
+    };
+    bounds.expand(width / 2.0)
+}
 
-        if r.dragged() && ui.ctx().input(|i| i.pointer.middle_down()) {
-            self.pan_zoom.pan += ui.ctx().input(|i| i.pointer.delta());
+/// The points of the connection drawn by [`connection_shape`], with curves
+/// flattened.
+fn connection_polyline(
+    style: ConnectionStyle,
+    src_pos: Pos2,
+    dst_pos: Pos2,
+    detour_y: f32,
+    zoom: f32,
+) -> Vec<Pos2> {
+    match style {
+        ConnectionStyle::Bezier => {
+            connection_bezier(src_pos, dst_pos, Stroke::NONE, zoom).flatten(Some(0.5))
         }
-
-        // Deselect and deactivate finder if the editor backround is clicked,
-        // *or* if the the mouse clicks off the ui
-        if click_on_background || (mouse.any_click() && !cursor_in_editor) {
-            self.selected_nodes = Vec::new();
-            self.node_finder = None;
+        ConnectionStyle::Straight => vec![src_pos, dst_pos],
+        ConnectionStyle::Orthogonal => {
+            let clearance = ORTHOGONAL_CLEARANCE * zoom;
+            let corners = if dst_pos.x - src_pos.x >= 2.0 * clearance {
+                let x = (src_pos.x + dst_pos.x) / 2.0;
+                vec![src_pos, pos2(x, src_pos.y), pos2(x, dst_pos.y), dst_pos]
+            } else {
+                // Out of the source and into the target horizontally, going
+                // around both nodes from below
+                let (out_x, in_x) = (src_pos.x + clearance, dst_pos.x - clearance);
+                vec![
+                    src_pos,
+                    pos2(out_x, src_pos.y),
+                    pos2(out_x, detour_y),
+                    pos2(in_x, detour_y),
+                    pos2(in_x, dst_pos.y),
+                    dst_pos,
+                ]
+            };
+            round_corners(&corners, ORTHOGONAL_CORNER_RADIUS * zoom)
         }
+    }
+}
 
-        if drag_started_on_background && mouse.primary_down() {
-            self.ongoing_box_selection = Some(cursor_pos);
-        }
-        if mouse.primary_released() || drag_released_on_background {
-            self.ongoing_box_selection = None;
+/// The point halfway along a polyline.
+fn polyline_midpoint(points: &[Pos2]) -> Option<Pos2> {
+    let length: f32 = points.windows(2).map(|s| s[0].distance(s[1])).sum();
+    let mut remaining = length / 2.0;
+    for segment in points.windows(2) {
+        let segment_length = segment[0].distance(segment[1]);
+        if remaining <= segment_length && segment_length > 0.0 {
+            return Some(segment[0] + (segment[1] - segment[0]) * (remaining / segment_length));
         }
+        remaining -= segment_length;
+    }
+    points.first().copied()
+}
 
-        GraphResponse {
-            node_responses: delayed_responses,
-            cursor_in_editor,
-            cursor_in_finder,
+/// Draws an arrowhead along the end of the connection drawn through `points`,
+/// its tip `inset` before the end.
+fn draw_arrowhead(ui: &Ui, points: &[Pos2], inset: f32, color: Color32, zoom: f32) {
+    let end = match points.last() {
+        Some(end) => *end,
+        None => return,
+    };
+    // The tangent at the end, skipping the points too close to it
+    let direction = match points
+        .iter()
+        .rev()
+        .find(|point| point.distance(end) > 0.5 * zoom)
+    {
+        Some(before) => (end - *before).normalized(),
+        None => return,
+    };
+    let size = ARROWHEAD_SIZE * zoom;
+    let tip = end - direction * inset;
+    let base = tip - direction * size;
+    let side = direction.rot90() * size / 2.0;
+    ui.painter().add(Shape::convex_polygon(
+        vec![tip, base + side, base - side],
+        color,
+        Stroke::NONE,
+    ));
+}
+
+/// Splits the polyline through `points` into dashes `dash` long, separated by
+/// gaps `gap` long. The pattern is shifted `offset` along the line, so
+/// increasing it moves the dashes towards the end.
+fn polyline_dashes(points: &[Pos2], dash: f32, gap: f32, offset: f32) -> Vec<Vec<Pos2>> {
+    let period = dash + gap;
+    let mut dashes = Vec::new();
+    let mut current = Vec::new();
+    let mut traveled = 0.0;
+    for segment in points.windows(2) {
+        let (start, end) = (segment[0], segment[1]);
+        let length = start.distance(end);
+        let mut along = 0.0;
+        while along < length {
+            let phase = (traveled + along - offset).rem_euclid(period);
+            let on = phase < dash;
+            // Never stuck on a boundary because of rounding errors
+            let step = if on { dash - phase } else { period - phase }
+                .max(1e-3)
+                .min(length - along);
+            if on {
+                if current.is_empty() {
+                    current.push(start + (end - start) * (along / length));
+                }
+                current.push(start + (end - start) * ((along + step) / length));
+            } else if !current.is_empty() {
+                dashes.push(std::mem::take(&mut current));
+            }
+            along += step;
         }
+        traveled += length;
     }
+    if !current.is_empty() {
+        dashes.push(current);
+    }
+    dashes
 }
 
-fn draw_connection(painter: &Painter, src_pos: Pos2, dst_pos: Pos2, color: Color32) {
-    let connection_stroke = egui::Stroke { width: 5.0, color };
+/// Draws the [`ConnectionDataTrait::label`] of a connection in a small box
+/// centered on `middle`.
+fn draw_connection_label(
+    ui: &Ui,
+    label: &str,
+    middle: Pos2,
+    color: Color32,
+    zoom: f32,
+    viewport: Rect,
+) {
+    let galley =
+        ui.painter()
+            .layout_no_wrap(label.to_owned(), FontId::proportional(11.0 * zoom), color);
+    let rect = Rect::from_center_size(middle, galley.size() + vec2(8.0, 2.0) * zoom);
+    if !rect.intersects(viewport) {
+        return;
+    }
+    ui.painter().rect(
+        rect,
+        3.0 * zoom,
+        ui.visuals().extreme_bg_color,
+        Stroke::new(zoom, color),
+    );
+    ui.painter()
+        .galley(rect.center() - galley.size() / 2.0, galley);
+}
 
-    let control_scale = ((dst_pos.x - src_pos.x) / 2.0).max(30.0);
-    let src_control = src_pos + Vec2::X * control_scale;
-    let dst_control = dst_pos - Vec2::X * control_scale;
+/// The height at which backward orthogonal connections run: below the
+/// `rects` of the nodes they connect, and below their `points`.
+fn detour_y<'a>(rects: impl IntoIterator<Item = &'a Rect>, points: &[Pos2], zoom: f32) -> f32 {
+    let bottom = rects
+        .into_iter()
+        .map(|rect| rect.bottom())
+        .chain(points.iter().map(|point| point.y))
+        .fold(f32::NEG_INFINITY, f32::max);
+    bottom + ORTHOGONAL_CLEARANCE * zoom
+}
 
-    let bezier = CubicBezierShape::from_points_stroke(
-        [src_pos, src_control, dst_control, dst_pos],
-        false,
-        Color32::TRANSPARENT,
-        connection_stroke,
-    );
+/// Replaces the inner corners of a polyline with curves of up to `radius`,
+/// shrunk on short segments so consecutive curves don't overlap.
+fn round_corners(corners: &[Pos2], radius: f32) -> Vec<Pos2> {
+    const CURVE_STEPS: usize = 6;
+    let mut corners = corners.to_vec();
+    corners.dedup();
+    let mut points = Vec::with_capacity(corners.len() * (CURVE_STEPS + 1));
+    points.extend(corners.first());
+    for window in corners.windows(3) {
+        let (to_prev, corner, to_next) = (window[0] - window[1], window[1], window[2] - window[1]);
+        let radius = radius
+            .min(to_prev.length() / 2.0)
+            .min(to_next.length() / 2.0);
+        let start = corner + to_prev.normalized() * radius;
+        let end = corner + to_next.normalized() * radius;
+        // A quadratic curve with the corner as control point
+        for step in 0..=CURVE_STEPS {
+            let t = step as f32 / CURVE_STEPS as f32;
+            let point = start.to_vec2() * (1.0 - t) * (1.0 - t)
+                + corner.to_vec2() * 2.0 * t * (1.0 - t)
+                + end.to_vec2() * t * t;
+            points.push(point.to_pos2());
+        }
+    }
+    if corners.len() > 1 {
+        points.extend(corners.last());
+    }
+    points
+}
 
-    painter.add(bezier);
+/// The distance from `pos` to the connection drawn between `src_pos` and
+/// `dst_pos`, measured from the middle of its stroke.
+fn distance_to_connection(
+    style: ConnectionStyle,
+    src_pos: Pos2,
+    dst_pos: Pos2,
+    detour_y: f32,
+    zoom: f32,
+    pos: Pos2,
+) -> f32 {
+    let points = connection_polyline(style, src_pos, dst_pos, detour_y, zoom);
+    points
+        .windows(2)
+        .map(|segment| {
+            let (a, b) = (segment[0], segment[1]);
+            let length_sq = (b - a).length_sq();
+            let t = if length_sq > 0.0 {
+                ((pos - a).dot(b - a) / length_sq).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            pos.distance(a + (b - a) * t)
+        })
+        .fold(f32::INFINITY, f32::min)
 }
 
 #[derive(Clone, Copy, Debug)]
 struct OuterRectMemory(Rect);
 
+/// Scales the text and spacing of `style`, so the widgets of a node keep
+/// their proportions when zooming.
+fn scale_style(style: &mut Style, zoom: f32) {
+    for font in style.text_styles.values_mut() {
+        font.size *= zoom;
+    }
+    let spacing = &mut style.spacing;
+    spacing.item_spacing *= zoom;
+    spacing.button_padding *= zoom;
+    spacing.interact_size *= zoom;
+    spacing.indent *= zoom;
+    spacing.slider_width *= zoom;
+    spacing.text_edit_width *= zoom;
+    spacing.combo_width *= zoom;
+    spacing.icon_width *= zoom;
+    spacing.icon_width_inner *= zoom;
+    spacing.icon_spacing *= zoom;
+}
+
+/// Runs a user callback for the given node. When `defensive` is set, a panic
+/// inside the callback is caught and logged, and `None` is returned.
+///
+/// The callback is assumed to be unwind safe: whatever it was modifying when
+/// it panicked (user state, ui state...) is left as is.
+fn call_user_code<R>(defensive: bool, node_id: NodeId, f: impl FnOnce() -> R) -> Option<R> {
+    if !defensive {
+        return Some(f());
+    }
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(f))
+        .map_err(|payload| {
+            let message = payload
+                .downcast_ref::<&str>()
+                .copied()
+                .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+                .unwrap_or("unknown panic payload");
+            eprintln!(
+                "The UI of node {:?} panicked and is now disabled: {}",
+                node_id, message
+            );
+        })
+        .ok()
+}
+
 impl<'a, NodeData, DataType, ValueType, UserResponse, UserState>
     GraphNodeWidget<'a, NodeData, DataType, ValueType>
 where
@@ -471,8 +3356,9 @@ where
         ValueType = ValueType,
     >,
     UserResponse: UserResponseTrait,
-    ValueType:
-        WidgetValueTrait<Response = UserResponse, UserState = UserState, NodeData = NodeData>,
+    ValueType: WidgetValueTrait<Response = UserResponse, UserState = UserState, NodeData = NodeData>
+        + Clone
+        + PartialEq,
     DataType: DataTypeTrait<UserState>,
 {
     pub const MAX_NODE_SIZE: [f32; 2] = [200.0, 200.0];
@@ -483,10 +3369,16 @@ where
         user_state: &mut UserState,
     ) -> Vec<NodeResponse<UserResponse, NodeData>> {
         let mut child_ui = ui.child_ui_with_id_source(
-            Rect::from_min_size(*self.position + self.pan, Self::MAX_NODE_SIZE.into()),
+            Rect::from_min_size(
+                (self.position.to_vec2() * self.zoom + self.pan).to_pos2(),
+                Vec2::from(Self::MAX_NODE_SIZE) * self.zoom,
+            ),
             Layout::default(),
             self.node_id,
         );
+        if self.zoom != 1.0 {
+            scale_style(child_ui.style_mut(), self.zoom);
+        }
 
         Self::show_graph_node(self, &mut child_ui, user_state)
     }
@@ -498,18 +3390,12 @@ where
         ui: &mut Ui,
         user_state: &mut UserState,
     ) -> Vec<NodeResponse<UserResponse, NodeData>> {
-        let margin = egui::vec2(15.0, 5.0);
+        let zoom = self.zoom;
+        let margin = egui::vec2(15.0, 5.0) * zoom;
         let mut responses = Vec::<NodeResponse<UserResponse, NodeData>>::new();
 
-        let background_color;
-        let text_color;
-        if ui.visuals().dark_mode {
-            background_color = color_from_hex("#3f3f3f").unwrap();
-            text_color = color_from_hex("#fefefe").unwrap();
-        } else {
-            background_color = color_from_hex("#ffffff").unwrap();
-            text_color = color_from_hex("#505050").unwrap();
-        }
+        let background_color = self.style.node_color;
+        let text_color = self.style.node_text_color;
 
         ui.visuals_mut().widgets.noninteractive.fg_stroke = Stroke::new(2.0, text_color);
 
@@ -549,28 +3435,101 @@ where
         let mut input_port_heights = vec![];
         let mut output_port_heights = vec![];
 
+        // Once a user callback of this node has panicked, only the ports are
+        // drawn, with a placeholder body.
+        let mut failed = self.failed_nodes.contains(&self.node_id);
+        let can_edit = self.interaction_mode.can_edit();
+
+        let can_collapse =
+            self.graph[self.node_id]
+                .user_data
+                .can_collapse(self.node_id, self.graph, user_state);
+        let low_detail = self.low_detail
+            && !self.graph[self.node_id].user_data.always_detailed(
+                self.node_id,
+                self.graph,
+                user_state,
+            );
+        let collapsed = (self.collapsed && can_collapse) || low_detail;
+        let mut collapse_toggle = None;
+        // Where the title text was drawn, and whether its edition ended, and
+        // should be kept
+        let mut title_text_rect = None;
+        let mut renamed = None;
+
         child_ui.vertical(|ui| {
             ui.horizontal(|ui| {
-                ui.add(Label::new(
-                    RichText::new(&self.graph[self.node_id].label)
-                        .text_style(TextStyle::Button)
-                        .color(text_color),
-                ));
-                responses.extend(
-                    self.graph[self.node_id]
-                        .user_data
-                        .top_bar_ui(ui, self.node_id, self.graph, user_state)
-                        .into_iter(),
-                );
-                ui.add_space(8.0); // The size of the little cross icon
+                if can_collapse && !low_detail {
+                    let (_, toggle) = ui
+                        .allocate_exact_size(Vec2::splat(ui.spacing().icon_width), Sense::click());
+                    let openness = if collapsed { 0.0 } else { 1.0 };
+                    collapsing_header::paint_default_icon(ui, openness, &toggle);
+                    collapse_toggle = Some(toggle);
+                }
+                match self.renaming_node {
+                    Some((node, ref mut label)) if *node == self.node_id => {
+                        let edit = ui.add(
+                            TextEdit::singleline(label)
+                                .font(TextStyle::Button)
+                                .desired_width(RENAME_FIELD_WIDTH * zoom),
+                        );
+                        let cancel = ui.input(|i| i.key_pressed(Key::Escape));
+                        if cancel || edit.lost_focus() {
+                            renamed = Some(!cancel);
+                        } else if !edit.has_focus() {
+                            edit.request_focus();
+                        }
+                    }
+                    _ => {
+                        let title = ui.add(Label::new(
+                            RichText::new(&self.graph[self.node_id].label)
+                                .text_style(TextStyle::Button)
+                                .color(text_color),
+                        ));
+                        title_text_rect = Some(title.rect);
+                    }
+                }
+                if !failed {
+                    match call_user_code(self.defensive, self.node_id, || {
+                        self.graph[self.node_id].user_data.top_bar_ui(
+                            ui,
+                            self.node_id,
+                            self.graph,
+                            user_state,
+                        )
+                    }) {
+                        Some(node_responses) => responses.extend(node_responses),
+                        None => failed = true,
+                    }
+                }
+                ui.add_space(8.0 * zoom); // The size of the little cross icon
             });
             ui.add_space(margin.y);
+            // The title bar starts a margin above the child ui
+            let min_height = self.style.title_bar_height * zoom - margin.y;
+            ui.add_space((min_height - ui.min_size().y).max(0.0));
+
+            if collapsed {
+                // Make room for the port stubs along the edges of the title bar
+                let node = &self.graph[self.node_id];
+                let stubs = node.inputs.len().max(node.outputs.len());
+                let needed = (stubs + 1) as f32 * STUB_SPACING * zoom - 2.0 * margin.y;
+                ui.add_space((needed - ui.min_size().y).max(0.0));
+                title_height = ui.min_size().y;
+                return;
+            }
             title_height = ui.min_size().y;
 
             // First pass: Draw the inner fields. Compute port heights
             let inputs = self.graph[self.node_id].inputs.clone();
+            let mut group = None;
             for (param_name, param_id) in inputs {
                 if self.graph[param_id].shown_inline {
+                    let param = AnyParameterId::Input(param_id);
+                    if let Some(header) = self.group_header(ui, param, &mut group, &mut responses) {
+                        input_port_heights.push(header);
+                        continue;
+                    }
                     let height_before = ui.min_rect().bottom();
                     // NOTE: We want to pass the `user_data` to
                     // `value_widget`, but we can't since that would require
@@ -580,74 +3539,211 @@ where
                     // dummy value. This requires `ValueType` to implement
                     // Default, but results in a totally safe alternative.
                     let mut value = std::mem::take(&mut self.graph[param_id].value);
+                    let value_before = value.clone();
 
-                    if self.graph.connection(param_id).is_some() {
-                        let node_responses = value.value_widget_connected(
-                            &param_name,
+                    let removable = !failed
+                        && can_edit
+                        && self.graph[self.node_id].user_data.can_remove_input(
                             self.node_id,
-                            ui,
+                            param_id,
+                            self.graph,
                             user_state,
-                            &self.graph[self.node_id].user_data,
                         );
-
-                        responses.extend(node_responses.into_iter().map(NodeResponse::User));
+                    let mut draw_widget = |ui: &mut Ui| {
+                        if failed {
+                            ui.label(&param_name);
+                            return;
+                        }
+                        let connected = self.graph.is_input_connected(param_id);
+                        // The constant of a connected input is unused, but
+                        // may be kept visible, see `GraphStyle`
+                        let widget_when_connected = connected
+                            && self.style.show_widget_when_connected
+                            && !matches!(self.graph[param_id].kind, InputParamKind::ConnectionOnly);
+                        let node_data = &self.graph[self.node_id].user_data;
+                        let node_id = self.node_id;
+                        // Shown disabled rather than hidden when the editor
+                        // is locked, so the values can still be read
+                        let enabled = can_edit && !widget_when_connected;
+                        let widget = ui.add_enabled_ui(enabled, |ui| {
+                            call_user_code(self.defensive, node_id, || {
+                                if connected && !widget_when_connected {
+                                    value.value_widget_connected(
+                                        &param_name,
+                                        node_id,
+                                        ui,
+                                        user_state,
+                                        node_data,
+                                    )
+                                } else {
+                                    value.value_widget(
+                                        &param_name,
+                                        node_id,
+                                        ui,
+                                        user_state,
+                                        node_data,
+                                    )
+                                }
+                            })
+                        });
+                        match widget.inner {
+                            Some(node_responses) => {
+                                responses.extend(node_responses.into_iter().map(NodeResponse::User))
+                            }
+                            None => failed = true,
+                        }
+                    };
+                    if removable {
+                        let remove_tooltip = self.localization.remove_input.as_ref();
+                        let mut remove = false;
+                        ui.horizontal(|ui| {
+                            remove = ui.small_button("🗙").on_hover_text(remove_tooltip).clicked();
+                            draw_widget(ui);
+                        });
+                        if remove {
+                            responses.push(NodeResponse::RemoveInput(param_id));
+                        }
                     } else {
-                        let node_responses = value.value_widget(
-                            &param_name,
-                            self.node_id,
-                            ui,
-                            user_state,
-                            &self.graph[self.node_id].user_data,
-                        );
-
-                        responses.extend(node_responses.into_iter().map(NodeResponse::User));
+                        draw_widget(ui);
                     }
 
-                    self.graph[self.node_id].user_data.separator(
-                        ui,
-                        self.node_id,
-                        AnyParameterId::Input(param_id),
-                        self.graph,
-                        user_state,
-                    );
+                    if !failed {
+                        failed = call_user_code(self.defensive, self.node_id, || {
+                            self.graph[self.node_id].user_data.separator(
+                                ui,
+                                self.node_id,
+                                AnyParameterId::Input(param_id),
+                                self.graph,
+                                user_state,
+                            )
+                        })
+                        .is_none();
+                    }
 
+                    let changed = !failed && value != value_before;
+                    if changed {
+                        responses.push(NodeResponse::ValueChanged {
+                            node: self.node_id,
+                            input: param_id,
+                        });
+                    }
                     self.graph[param_id].value = value;
+                    if changed {
+                        self.graph.push_event(GraphEvent::ValueChanged {
+                            node: self.node_id,
+                            input: param_id,
+                        });
+                    }
 
                     let height_after = ui.min_rect().bottom();
+                    if self.graph[param_id].required && !self.graph.is_input_connected(param_id) {
+                        let row = Rect::from_x_y_ranges(
+                            ui.max_rect().x_range(),
+                            height_before..=height_after,
+                        );
+                        let [r, g, b, _] = REQUIRED_INPUT_COLOR.to_array();
+                        ui.painter().rect_filled(
+                            row,
+                            2.0 * zoom,
+                            Color32::from_rgba_unmultiplied(r, g, b, 24),
+                        );
+                    }
+                    if !failed {
+                        failed = !self.describe_row(
+                            ui,
+                            AnyParameterId::Input(param_id),
+                            height_before..=height_after,
+                            user_state,
+                        );
+                    }
                     input_port_heights.push((height_before + height_after) / 2.0);
                 }
             }
 
+            if !failed
+                && can_edit
+                && self.graph[self.node_id]
+                    .user_data
+                    .can_add_input(self.node_id, self.graph, user_state)
+                    .is_some()
+                && ui
+                    .small_button("+")
+                    .on_hover_text(self.localization.add_input.as_ref())
+                    .clicked()
+            {
+                responses.push(NodeResponse::AddInput(self.node_id));
+            }
+
             let outputs = self.graph[self.node_id].outputs.clone();
+            let mut group = None;
             for (param_name, param_id) in outputs {
+                let param = AnyParameterId::Output(param_id);
+                if let Some(header) = self.group_header(ui, param, &mut group, &mut responses) {
+                    output_port_heights.push(header);
+                    continue;
+                }
                 let height_before = ui.min_rect().bottom();
-                responses.extend(
-                    self.graph[self.node_id]
-                        .user_data
-                        .output_ui(ui, self.node_id, self.graph, user_state, &param_name)
-                        .into_iter(),
-                );
-
-                self.graph[self.node_id].user_data.separator(
-                    ui,
-                    self.node_id,
-                    AnyParameterId::Output(param_id),
-                    self.graph,
-                    user_state,
-                );
+                if failed {
+                    ui.label(&param_name);
+                } else {
+                    match call_user_code(self.defensive, self.node_id, || {
+                        let node_data = &self.graph[self.node_id].user_data;
+                        let node_responses = node_data.output_ui(
+                            ui,
+                            self.node_id,
+                            self.graph,
+                            user_state,
+                            &param_name,
+                        );
+                        node_data.separator(
+                            ui,
+                            self.node_id,
+                            AnyParameterId::Output(param_id),
+                            self.graph,
+                            user_state,
+                        );
+                        node_responses
+                    }) {
+                        Some(node_responses) => responses.extend(node_responses),
+                        None => failed = true,
+                    }
+                }
 
                 let height_after = ui.min_rect().bottom();
+                if !failed {
+                    failed = !self.describe_row(
+                        ui,
+                        AnyParameterId::Output(param_id),
+                        height_before..=height_after,
+                        user_state,
+                    );
+                }
                 output_port_heights.push((height_before + height_after) / 2.0);
             }
 
-            responses.extend(
-                self.graph[self.node_id]
-                    .user_data
-                    .bottom_ui(ui, self.node_id, self.graph, user_state)
-                    .into_iter(),
-            );
+            if !failed {
+                match call_user_code(self.defensive, self.node_id, || {
+                    self.graph[self.node_id].user_data.bottom_ui(
+                        ui,
+                        self.node_id,
+                        self.graph,
+                        user_state,
+                    )
+                }) {
+                    Some(node_responses) => responses.extend(node_responses),
+                    None => failed = true,
+                }
+            }
+
+            if failed {
+                ui.colored_label(Color32::RED, self.localization.widget_error.as_ref());
+            }
         });
 
+        if failed {
+            self.failed_nodes.insert(self.node_id);
+        }
+
         // Second pass, iterate again to draw the ports. This happens outside
         // the child_ui because we want ports to overflow the node background.
 
@@ -655,6 +3751,38 @@ where
         let port_left = outer_rect.left();
         let port_right = outer_rect.right();
 
+        // The ports of a collapsed node are spread along the edges of its title
+        // bar, which is all there is of it.
+        if collapsed {
+            let spread = |count: usize| -> Vec<f32> {
+                (1..=count)
+                    .map(|i| outer_rect.top() + outer_rect.height() * i as f32 / (count + 1) as f32)
+                    .collect()
+            };
+            let node = &self.graph[self.node_id];
+            let connectable: Vec<bool> = node
+                .inputs
+                .iter()
+                .map(|(_, input)| {
+                    !matches!(self.graph[*input].kind(), InputParamKind::ConstantOnly)
+                })
+                .collect();
+            let mut stubs = spread(connectable.iter().filter(|c| **c).count()).into_iter();
+            input_port_heights = connectable
+                .iter()
+                .map(|connectable| match connectable {
+                    true => stubs.next().unwrap_or_default(),
+                    false => outer_rect.center().y,
+                })
+                .collect();
+            output_port_heights = spread(node.outputs.len());
+        }
+        let port_size = if collapsed {
+            STUB_SIZE
+        } else {
+            self.style.port_radius * 2.0
+        };
+
         // Save expanded rect to memory.
         ui.ctx().memory_mut(|mem| {
             mem.data
@@ -673,22 +3801,47 @@ where
             port_locations: &mut PortLocations,
             ongoing_drag: Option<(NodeId, AnyParameterId)>,
             is_connected_input: bool,
+            interaction_priority: InteractionPriority,
+            zoom: f32,
+            port_size: f32,
+            cycle_nodes: &HashSet<NodeId>,
+            panning: bool,
         ) where
             DataType: DataTypeTrait<UserState>,
             UserResponse: UserResponseTrait,
-            NodeData: NodeDataTrait,
+            NodeData: NodeDataTrait<
+                Response = UserResponse,
+                UserState = UserState,
+                DataType = DataType,
+                ValueType = ValueType,
+            >,
         {
-            let port_type = graph.any_param_type(param_id).unwrap();
+            let port_type = graph.resolved_type(param_id);
 
-            let port_rect = Rect::from_center_size(port_pos, egui::vec2(10.0, 10.0));
+            // While dragging a connection, the ports it can be attached to are
+            // enlarged, and the other ones grayed out
+            let verdict = ongoing_drag
+                .and_then(|origin| can_attach(graph, origin, param_id, cycle_nodes, user_state));
+            let port_size = match verdict {
+                Some(true) => port_size * VALID_TARGET_SCALE,
+                _ => port_size,
+            };
 
-            let sense = if ongoing_drag.is_some() {
-                Sense::hover()
-            } else {
-                Sense::click_and_drag()
+            let port_rect = Rect::from_center_size(port_pos, Vec2::splat(port_size * zoom));
+
+            // Ports are drawn after the node body, so their interactive area
+            // is on top of it and wins the drags starting there.
+            let (interact_rect, sense) = match interaction_priority {
+                _ if ongoing_drag.is_some() || panning => (port_rect, Sense::hover()),
+                InteractionPriority::Auto => (port_rect, Sense::click_and_drag()),
+                InteractionPriority::PreferConnect => (
+                    Rect::from_center_size(port_pos, Vec2::splat(2.0 * DISTANCE_TO_CONNECT)),
+                    Sense::click_and_drag(),
+                ),
+                InteractionPriority::PreferMove => (port_rect, Sense::hover()),
             };
 
-            let resp = ui.allocate_rect(port_rect, sense);
+            let resp = ui.allocate_rect(interact_rect, sense);
 
             // Check if the distance between the port and the mouse is the distance to connect
             let close_enough = if let Some(pointer_pos) = ui.ctx().pointer_hover_pos() {
@@ -697,13 +3850,33 @@ where
                 false
             };
 
-            let port_color = if close_enough {
+            let port_color = if verdict == Some(false) {
+                port_type.data_type_color(user_state).lighten(0.3)
+            } else if close_enough {
                 Color32::WHITE
             } else {
                 port_type.data_type_color(user_state)
             };
-            ui.painter()
-                .circle(port_rect.center(), 5.0, port_color, Stroke::NONE);
+            ui.painter().add(port_type.port_shape(user_state).shape(
+                port_rect.center(),
+                (port_size / 2.0 * zoom).max(MIN_PORT_RADIUS),
+                port_color,
+            ));
+            if let AnyParameterId::Input(input) = param_id {
+                if graph[input].required && !is_connected_input {
+                    ui.painter().circle_stroke(
+                        port_rect.center(),
+                        (port_size / 2.0 * zoom).max(MIN_PORT_RADIUS) + 2.0 * zoom,
+                        Stroke::new(1.5 * zoom, REQUIRED_INPUT_COLOR),
+                    );
+                }
+            }
+
+            responses.extend(
+                graph[node_id]
+                    .user_data
+                    .port_response(&resp, node_id, param_id, graph, user_state),
+            );
 
             if resp.drag_started() {
                 if is_connected_input {
@@ -723,14 +3896,26 @@ where
             if let Some((origin_node, origin_param)) = ongoing_drag {
                 if origin_node != node_id {
                     // Don't allow self-loops
-                    if graph.any_param_type(origin_param).unwrap() == port_type
-                        && close_enough
-                        && ui.input(|i| i.pointer.any_released())
-                    {
+                    if close_enough && ui.input(|i| i.pointer.any_released()) {
                         match (param_id, origin_param) {
                             (AnyParameterId::Input(input), AnyParameterId::Output(output))
                             | (AnyParameterId::Output(output), AnyParameterId::Input(input)) => {
-                                responses.push(NodeResponse::ConnectEventEnded { input, output });
+                                let input_node = graph.get_input(input).node;
+                                let verdict = graph[input_node]
+                                    .user_data
+                                    .can_connect(input_node, output, input, graph, user_state);
+                                responses.push(match verdict {
+                                    Ok(()) => NodeResponse::ConnectEventEnded { input, output },
+                                    // Mismatching types may still be bridged by a converter
+                                    Err(_) if !graph.types_accept_connection(output, input) => {
+                                        NodeResponse::IncompatibleConnection { input, output }
+                                    }
+                                    Err(reason) => NodeResponse::RejectedConnection {
+                                        output,
+                                        input,
+                                        reason,
+                                    },
+                                });
                             }
                             _ => { /* Ignore in-in or out-out connections */ }
                         }
@@ -741,11 +3926,19 @@ where
             port_locations.insert(param_id, port_rect.center());
         }
 
+        // Ports of a locked editor don't start connections, drags on them
+        // move the node instead
+        let port_priority = if self.interaction_mode.can_edit() {
+            self.interaction_priority
+        } else {
+            InteractionPriority::PreferMove
+        };
+
         // Input ports
         for ((_, param), port_height) in self.graph[self.node_id]
             .inputs
             .iter()
-            .zip(input_port_heights.into_iter())
+            .zip(input_port_heights)
         {
             let should_draw = match self.graph[*param].kind() {
                 InputParamKind::ConnectionOnly => true,
@@ -765,7 +3958,12 @@ where
                     AnyParameterId::Input(*param),
                     self.port_locations,
                     self.ongoing_drag,
-                    self.graph.connection(*param).is_some(),
+                    self.graph.is_input_connected(*param),
+                    port_priority,
+                    zoom,
+                    port_size,
+                    self.cycle_nodes,
+                    self.panning,
                 );
             }
         }
@@ -774,7 +3972,7 @@ where
         for ((_, param), port_height) in self.graph[self.node_id]
             .outputs
             .iter()
-            .zip(output_port_heights.into_iter())
+            .zip(output_port_heights)
         {
             let pos_right = pos2(port_right, port_height);
             draw_port(
@@ -788,6 +3986,11 @@ where
                 self.port_locations,
                 self.ongoing_drag,
                 false,
+                port_priority,
+                zoom,
+                port_size,
+                self.cycle_nodes,
+                self.panning,
             );
         }
 
@@ -795,20 +3998,26 @@ where
         // NOTE: This code is a bit more involved than it needs to be because egui
         // does not support drawing rectangles with asymmetrical round corners.
 
+        // A collapsed node is only a title bar
+        let titlebar_height = if collapsed {
+            outer_rect.height()
+        } else {
+            title_height + margin.y
+        };
+        let titlebar_rect =
+            Rect::from_min_size(outer_rect.min, vec2(outer_rect.width(), titlebar_height));
+
         let (shape, outline) = {
-            let rounding_radius = 4.0;
+            let rounding_radius = self.style.node_rounding * zoom;
             let rounding = Rounding::same(rounding_radius);
 
-            let titlebar_height = title_height + margin.y;
-            let titlebar_rect =
-                Rect::from_min_size(outer_rect.min, vec2(outer_rect.width(), titlebar_height));
             let titlebar = Shape::Rect(RectShape {
                 rect: titlebar_rect,
                 rounding,
                 fill: self.graph[self.node_id]
                     .user_data
                     .titlebar_color(ui, self.node_id, self.graph, user_state)
-                    .unwrap_or_else(|| background_color.lighten(0.8)),
+                    .unwrap_or(self.style.title_bar_color),
                 stroke: Stroke::NONE,
             });
 
@@ -834,12 +4043,16 @@ where
                 stroke: Stroke::NONE,
             });
 
-            let node_rect = titlebar_rect.union(body_rect).union(bottom_body_rect);
+            let node_rect = if collapsed {
+                titlebar_rect
+            } else {
+                titlebar_rect.union(body_rect).union(bottom_body_rect)
+            };
             let outline = if self.selected {
                 Shape::Rect(RectShape {
-                    rect: node_rect.expand(1.0),
+                    rect: node_rect.expand(zoom),
                     rounding,
-                    fill: Color32::WHITE.lighten(0.8),
+                    fill: self.style.selection_color,
                     stroke: Stroke::NONE,
                 })
             } else {
@@ -849,7 +4062,12 @@ where
             // Take note of the node rect, so the editor can use it later to compute intersections.
             self.node_rects.insert(self.node_id, node_rect);
 
-            (Shape::Vec(vec![titlebar, body, bottom_body]), outline)
+            let shapes = if collapsed {
+                vec![titlebar]
+            } else {
+                vec![titlebar, body, bottom_body]
+            };
+            (Shape::Vec(shapes), outline)
         };
 
         ui.painter().set(background_shape, shape);
@@ -858,43 +4076,309 @@ where
         // --- Interaction ---
 
         // Titlebar buttons
-        let can_delete = self.graph.nodes[self.node_id].user_data.can_delete(
-            self.node_id,
-            self.graph,
-            user_state,
-        );
+        let can_delete = can_edit
+            && self.graph.nodes[self.node_id].user_data.can_delete(
+                self.node_id,
+                self.graph,
+                user_state,
+            );
+
+        if can_delete {
+            let mut close_response = Self::close_button(ui, outer_rect, zoom);
+            if close_response.hovered() {
+                self.pending_deletion.insert(self.node_id);
+                let node = &self.graph[self.node_id];
+                let connection_count = node
+                    .input_ids()
+                    .map(|input| self.graph.connections(input).len())
+                    .sum::<usize>()
+                    + node
+                        .output_ids()
+                        .map(|output| self.graph.output_connection_count(output))
+                        .sum::<usize>();
+                close_response = if connection_count > 0 {
+                    close_response.on_hover_text(format!(
+                        "{}\n{} {}",
+                        self.localization.close_node,
+                        self.localization.connections_to_remove,
+                        connection_count
+                    ))
+                } else {
+                    close_response.on_hover_text(self.localization.close_node.as_ref())
+                };
+            }
+            if close_response.clicked() {
+                responses.push(NodeResponse::DeleteNodeUi(self.node_id));
+            }
+        }
+
+        // Renaming ends with Enter or by clicking elsewhere, and is canceled
+        // with Escape. Empty labels are ignored.
+        if let Some(keep) = renamed {
+            let (_, label) = self.renaming_node.take().unwrap();
+            let new_label = label.trim();
+            let old_label = &self.graph[self.node_id].label;
+            if keep && !new_label.is_empty() && new_label != old_label {
+                responses.push(NodeResponse::RenamedNode {
+                    node: self.node_id,
+                    old_label: old_label.clone(),
+                    new_label: new_label.to_owned(),
+                });
+            }
+        }
 
-        if can_delete && Self::close_button(ui, outer_rect).clicked() {
-            responses.push(NodeResponse::DeleteNodeUi(self.node_id));
+        // Double-clicking the title text renames the node, double-clicking
+        // the rest of the title bar collapses it
+        let double_click_pos = if window_response.double_clicked() {
+            window_response.interact_pointer_pos()
+        } else {
+            None
         };
+        let rename_clicked = can_edit
+            && matches!(
+                (double_click_pos, title_text_rect),
+                (Some(pos), Some(rect)) if rect.contains(pos)
+            );
+
+        // Collapsing, with the toggle or by double-clicking the title bar
+        let mut collapsed_by_double_click = false;
+        if let Some(toggle) = collapse_toggle {
+            let toggle = toggle.on_hover_text(if collapsed {
+                self.localization.expand_node.as_ref()
+            } else {
+                self.localization.collapse_node.as_ref()
+            });
+            let title_double_clicked = !rename_clicked
+                && matches!(double_click_pos, Some(pos) if titlebar_rect.contains(pos));
+            if toggle.clicked() || title_double_clicked {
+                responses.push(NodeResponse::CollapseNode {
+                    node: self.node_id,
+                    collapsed: !collapsed,
+                });
+                collapsed_by_double_click = title_double_clicked;
+            }
+        }
+
+        // Context menu, with the entries of user code first
+        let node_id = self.node_id;
+        let graph = &*self.graph;
+        let defensive = self.defensive;
+        let failed_nodes = &mut *self.failed_nodes;
+        let localization = self.localization;
+        let mut rename_picked = false;
+        let window_response = window_response.context_menu(|ui| {
+            if !failed_nodes.contains(&node_id) {
+                let before = ui.min_rect();
+                match call_user_code(defensive, node_id, || {
+                    graph[node_id]
+                        .user_data
+                        .context_menu_ui(ui, node_id, graph, user_state)
+                }) {
+                    Some(node_responses) => {
+                        if !node_responses.is_empty() {
+                            ui.close_menu();
+                        }
+                        responses.extend(node_responses);
+                    }
+                    None => {
+                        failed_nodes.insert(node_id);
+                    }
+                }
+                if ui.min_rect() != before {
+                    ui.separator();
+                }
+            }
+
+            let has_upstream = graph[node_id]
+                .input_ids()
+                .any(|input| graph.is_input_connected(input));
+            let has_downstream = graph[node_id]
+                .output_ids()
+                .any(|output| graph.output_connection_count(output) > 0);
+            let connected = has_upstream || has_downstream;
+            let entries = [
+                (
+                    can_delete,
+                    &localization.delete_node,
+                    NodeResponse::DeleteNodeUi(node_id),
+                ),
+                (
+                    can_edit,
+                    &localization.duplicate_node,
+                    NodeResponse::DuplicateNode(node_id),
+                ),
+                (
+                    can_edit && connected,
+                    &localization.disconnect_node,
+                    NodeResponse::DisconnectNode(node_id),
+                ),
+                (
+                    can_edit && graph.extraction_bridge(node_id).is_some(),
+                    &localization.extract_node,
+                    NodeResponse::ExtractNode(node_id),
+                ),
+                (
+                    true,
+                    &localization.bring_node_to_front,
+                    NodeResponse::RaiseNode(node_id),
+                ),
+                (
+                    connected,
+                    &localization.select_connected,
+                    NodeResponse::SelectRelated(node_id, NodeRelation::Connected),
+                ),
+                (
+                    has_upstream,
+                    &localization.select_upstream,
+                    NodeResponse::SelectRelated(node_id, NodeRelation::Upstream),
+                ),
+                (
+                    has_downstream,
+                    &localization.select_downstream,
+                    NodeResponse::SelectRelated(node_id, NodeRelation::Downstream),
+                ),
+            ];
+            for (enabled, label, response) in entries {
+                if ui
+                    .add_enabled(enabled, Button::new(label.as_ref()))
+                    .clicked()
+                {
+                    responses.push(response);
+                    ui.close_menu();
+                }
+            }
+            if ui
+                .add_enabled(can_edit, Button::new(localization.rename_node.as_ref()))
+                .clicked()
+            {
+                rename_picked = true;
+                ui.close_menu();
+            }
+        });
 
-        // Movement
-        let drag_delta = window_response.drag_delta();
-        if drag_delta.length_sq() > 0.0 {
+        if rename_clicked || rename_picked {
+            *self.renaming_node = Some((self.node_id, self.graph[self.node_id].label.clone()));
+        }
+
+        // Movement. Dragging a selected node moves the whole selection, see
+        // `NodeResponse::MoveNode`, while dragging another node selects it
+        // alone first.
+        let can_move = self.interaction_mode.can_move() && !self.panning;
+        if window_response.drag_started() && !self.selected && can_move {
+            responses.push(NodeResponse::SelectNode(self.node_id));
+        }
+        let drag_delta = window_response.drag_delta() / zoom;
+        if drag_delta.length_sq() > 0.0 && can_move {
             responses.push(NodeResponse::MoveNode {
                 node: self.node_id,
                 drag_delta,
             });
-            responses.push(NodeResponse::RaiseNode(self.node_id));
+            if self.raise_on_click {
+                responses.push(NodeResponse::RaiseNode(self.node_id));
+            }
         }
 
         // Node selection
         //
         // HACK: Only set the select response when no other response is active.
         // This prevents some issues.
-        if responses.is_empty() && window_response.clicked_by(PointerButton::Primary) {
+        let clicked = window_response.clicked_by(PointerButton::Primary);
+        if responses.is_empty() && clicked {
             responses.push(NodeResponse::SelectNode(self.node_id));
+        }
+        // Raised even when the click did something else, like collapsing the
+        // node, so a node clicked in a stack always ends up on top
+        if clicked && self.raise_on_click {
             responses.push(NodeResponse::RaiseNode(self.node_id));
         }
+        if window_response.double_clicked() && !collapsed_by_double_click && !rename_clicked {
+            responses.push(NodeResponse::NodeDoubleClicked(self.node_id));
+        }
 
         responses
     }
 
-    fn close_button(ui: &mut Ui, node_rect: Rect) -> Response {
+    /// Draws the header of the group of `param` when it starts a new one, and
+    /// keeps track of it in `group`: its name, the height of its center, and
+    /// whether it's collapsed. Returns that height if the group is collapsed,
+    /// in which case `param` isn't drawn and its port goes on the header.
+    fn group_header(
+        &self,
+        ui: &mut Ui,
+        param: AnyParameterId,
+        group: &mut Option<(String, f32, bool)>,
+        responses: &mut Vec<NodeResponse<UserResponse, NodeData>>,
+    ) -> Option<f32> {
+        let name = match self.graph.param_group(param) {
+            Some(name) => name,
+            None => {
+                *group = None;
+                return None;
+            }
+        };
+        if !matches!(group, Some((current, ..)) if current == name) {
+            let key = (self.node_id, name.to_owned());
+            let collapsed = self.collapsed_port_groups.contains(&key);
+            let height_before = ui.min_rect().bottom();
+            let clicked = ui
+                .horizontal(|ui| {
+                    let (_, toggle) = ui
+                        .allocate_exact_size(Vec2::splat(ui.spacing().icon_width), Sense::click());
+                    let openness = if collapsed { 0.0 } else { 1.0 };
+                    collapsing_header::paint_default_icon(ui, openness, &toggle);
+                    let label =
+                        ui.add(Label::new(RichText::new(name).strong()).sense(Sense::click()));
+                    toggle.clicked() || label.clicked()
+                })
+                .inner;
+            if clicked {
+                responses.push(NodeResponse::CollapsePortGroup {
+                    node: self.node_id,
+                    group: key.1.clone(),
+                    collapsed: !collapsed,
+                });
+            }
+            let height = (height_before + ui.min_rect().bottom()) / 2.0;
+            *group = Some((key.1, height, collapsed));
+        }
+        match group {
+            Some((_, height, true)) => Some(*height),
+            _ => None,
+        }
+    }
+
+    /// Shows the description of a param when hovering its row, spanning
+    /// `rows` vertically. Returns false if the user code panicked.
+    fn describe_row(
+        &self,
+        ui: &mut Ui,
+        param_id: AnyParameterId,
+        rows: std::ops::RangeInclusive<f32>,
+        user_state: &mut UserState,
+    ) -> bool {
+        let description = call_user_code(self.defensive, self.node_id, || {
+            self.graph[self.node_id]
+                .user_data
+                .param_description(self.node_id, param_id, self.graph, user_state)
+                .map(|description| description.into_owned())
+        });
+        match description {
+            Some(Some(description)) => {
+                let rect = Rect::from_x_y_ranges(ui.min_rect().x_range(), rows);
+                ui.interact(rect, Id::new((param_id, "description")), Sense::hover())
+                    .on_hover_text(description);
+                true
+            }
+            Some(None) => true,
+            None => false,
+        }
+    }
+
+    fn close_button(ui: &mut Ui, node_rect: Rect, zoom: f32) -> Response {
         // Measurements
-        let margin = 8.0;
-        let size = 10.0;
-        let stroke_width = 2.0;
+        let margin = 8.0 * zoom;
+        let size = 10.0 * zoom;
+        let stroke_width = 2.0 * zoom;
         let offs = margin + size / 2.0;
 
         let position = pos2(node_rect.right() - offs, node_rect.top() + offs);
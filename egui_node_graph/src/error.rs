@@ -7,4 +7,39 @@ pub enum EguiGraphError {
 
     #[error("Parameter {0:?} was not found in the graph.")]
     InvalidParameterId(AnyParameterId),
+
+    #[error("Node {0:?} already has a parameter named {1}")]
+    DuplicateParameterName(NodeId, String),
+
+    #[error("Output {0:?} and input {1:?} have incompatible data types.")]
+    IncompatibleTypes(OutputId, InputId),
+
+    #[error("Node {0:?} already has the external id {1}")]
+    DuplicateExternalId(NodeId, String),
+}
+
+/// The errors of [`GraphEditorState::serialize_versioned`] and
+/// [`GraphEditorState::deserialize_with_migrations`].
+#[cfg(feature = "persistence")]
+#[derive(Debug, thiserror::Error)]
+pub enum PersistenceError {
+    #[error("Could not write the editor state: {0}")]
+    Serialize(String),
+
+    #[error("Could not read the editor state: {0}")]
+    Deserialize(String),
+
+    #[error(
+        "The editor state has version {found}, but only versions up to {supported} are supported"
+    )]
+    NewerVersion { found: u32, supported: u32 },
+
+    #[error("No migration from version {0} of the editor state")]
+    MissingMigration(u32),
+
+    #[error("Could not migrate the editor state from version {from}: {message}")]
+    Migration { from: u32, message: String },
+
+    #[error("The editor state is inconsistent: {0}")]
+    Inconsistent(String),
 }
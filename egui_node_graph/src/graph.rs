@@ -1,7 +1,8 @@
 use super::*;
+use std::num::NonZeroU32;
 
 #[cfg(feature = "persistence")]
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
 /// A node inside the [`Graph`]. Nodes have input and output parameters, stored
 /// as ids. They also contain a custom `NodeData` struct with whatever data the
@@ -11,8 +12,27 @@ use serde::{Deserialize, Serialize};
 pub struct Node<NodeData> {
     pub id: NodeId,
     pub label: String,
+    /// An id that stays the same across sessions, unlike [`NodeId`], to
+    /// match the node with entities outside of the graph. Unique in the
+    /// graph. Set it with [`Graph::set_external_id`], and find the node back
+    /// with [`Graph::node_by_external_id`].
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub external_id: Option<String>,
     pub inputs: Vec<(String, InputId)>,
     pub outputs: Vec<(String, OutputId)>,
+    /// Written as a nested string in versioned blobs, see
+    /// [`GraphEditorState::serialize_versioned`].
+    #[cfg_attr(
+        feature = "persistence",
+        serde(
+            serialize_with = "crate::persistence::serialize_node_data",
+            deserialize_with = "crate::persistence::deserialize_node_data",
+            bound(
+                serialize = "NodeData: Serialize",
+                deserialize = "NodeData: serde::de::DeserializeOwned + 'static"
+            )
+        )
+    )]
     pub user_data: NodeData,
 }
 
@@ -35,6 +55,11 @@ fn shown_inline_default() -> bool {
     true
 }
 
+#[cfg(feature = "persistence")]
+fn max_connections_default() -> Option<NonZeroU32> {
+    NonZeroU32::new(1)
+}
+
 /// An input parameter. Input parameters are inside a node, and represent data
 /// that this node receives. Unlike their [`OutputParam`] counterparts, input
 /// parameters also display an inline widget which allows setting its "value".
@@ -58,6 +83,21 @@ pub struct InputParam<DataType, ValueType> {
     /// When true, the node is shown inline inside the node graph.
     #[cfg_attr(feature = "persistence", serde(default = "shown_inline_default"))]
     pub shown_inline: bool,
+    /// The maximum number of incoming connections, `None` meaning unlimited.
+    /// Inputs created with [`Graph::add_input_param`] accept a single one, see
+    /// [`Graph::add_wide_input_param`].
+    #[cfg_attr(feature = "persistence", serde(default = "max_connections_default"))]
+    pub max_connections: Option<NonZeroU32>,
+    /// The group of params this one is drawn in, under a collapsible header.
+    /// The params of a group should follow each other in their node. See
+    /// [`GraphEditorState::collapsed_port_groups`].
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub group: Option<String>,
+    /// Whether the input is meaningless without a connection. The editor
+    /// highlights the required inputs that aren't connected. See
+    /// [`Graph::set_input_required`].
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub required: bool,
 }
 
 /// An output parameter. Output parameters are inside a node, and represent the
@@ -71,13 +111,19 @@ pub struct OutputParam<DataType> {
     /// Back-reference to the node containing this parameter.
     pub node: NodeId,
     pub typ: DataType,
+    /// The group of params this one is drawn in, see [`InputParam::group`].
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub group: Option<String>,
 }
 
 /// The graph, containing nodes, input parameters and output parameters. Because
 /// graphs are full of self-referential structures, this type uses the `slotmap`
 /// crate to represent all the inner references in the data.
+///
+/// Only the nodes, params and connections are persisted. The indexes derived
+/// from them are rebuilt when the graph is read back.
 #[derive(Debug, Clone)]
-#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "persistence", derive(Serialize))]
 pub struct Graph<NodeData, DataType, ValueType> {
     /// The [`Node`]s of the graph
     pub nodes: SlotMap<NodeId, Node<NodeData>>,
@@ -85,7 +131,125 @@ pub struct Graph<NodeData, DataType, ValueType> {
     pub inputs: SlotMap<InputId, InputParam<DataType, ValueType>>,
     /// The [`OutputParam`]s of the graph
     pub outputs: SlotMap<OutputId, OutputParam<DataType>>,
-    // Connects the input of a node, to the output of its predecessor that
-    // produces it
-    pub connections: SecondaryMap<InputId, OutputId>,
+    /// Connects the input of a node, to the outputs of its predecessors that
+    /// produce it, in the order they were connected. Most inputs have at most
+    /// one, see [`InputParam::max_connections`]. Inputs without connections
+    /// have no entry.
+    pub connections: SecondaryMap<InputId, SVec<OutputId>>,
+    /// The reverse of `connections`: the inputs each output is connected to.
+    /// Kept up to date by the [`Graph`] methods. Call
+    /// [`Graph::rebuild_reverse_connections`] after modifying `connections`
+    /// directly.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub reverse_connections: SecondaryMap<OutputId, SVec<InputId>>,
+    /// The nodes by [`Node::external_id`]. Kept up to date by the [`Graph`]
+    /// methods. Call [`Graph::rebuild_external_ids`] after modifying the ids
+    /// of the nodes directly.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub external_ids: std::collections::HashMap<String, NodeId>,
+    /// The changes recorded since they were last drained, or `None` when
+    /// they aren't recorded. See [`Graph::record_events`].
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) events: Option<Vec<GraphEvent>>,
+}
+
+/// A change made to a [`Graph`], recorded when [`Graph::record_events`] is
+/// on. Replaying the events in order on a model mirroring the graph keeps it
+/// in sync, reading the added nodes, params and values from the graph.
+///
+/// The [`Graph`] methods record them, and so does the editor when it changes
+/// the graph, including the values edited by inline widgets and undo / redo.
+/// Changes made through the fields of the graph directly are not recorded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub enum GraphEvent {
+    /// A node was added. The params added along with it follow as
+    /// `ParamAdded`.
+    NodeAdded(NodeId),
+    /// A node was removed. Its connections and params were removed first,
+    /// each with its own event.
+    NodeRemoved(NodeId),
+    ParamAdded {
+        node: NodeId,
+        param: AnyParameterId,
+    },
+    ParamRemoved {
+        node: NodeId,
+        param: AnyParameterId,
+    },
+    ConnectionAdded {
+        input: InputId,
+        output: OutputId,
+    },
+    ConnectionRemoved {
+        input: InputId,
+        output: OutputId,
+    },
+    /// The value of an input changed.
+    ValueChanged {
+        node: NodeId,
+        input: InputId,
+    },
+}
+
+/// The persisted fields of a [`Graph`], read before rebuilding its indexes.
+#[cfg(feature = "persistence")]
+#[derive(Deserialize)]
+#[serde(rename = "Graph")]
+struct GraphFields<NodeData, DataType, ValueType> {
+    #[serde(bound(deserialize = "NodeData: serde::de::DeserializeOwned + 'static"))]
+    nodes: SlotMap<NodeId, Node<NodeData>>,
+    inputs: SlotMap<InputId, InputParam<DataType, ValueType>>,
+    outputs: SlotMap<OutputId, OutputParam<DataType>>,
+    #[serde(deserialize_with = "deserialize_connections")]
+    connections: SecondaryMap<InputId, SVec<OutputId>>,
+}
+
+#[cfg(feature = "persistence")]
+impl<'de, NodeData, DataType, ValueType> Deserialize<'de> for Graph<NodeData, DataType, ValueType>
+where
+    NodeData: serde::de::DeserializeOwned + 'static,
+    DataType: Deserialize<'de>,
+    ValueType: Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fields = GraphFields::deserialize(deserializer)?;
+        let mut graph = Self {
+            nodes: fields.nodes,
+            inputs: fields.inputs,
+            outputs: fields.outputs,
+            connections: fields.connections,
+            reverse_connections: SecondaryMap::default(),
+            external_ids: std::collections::HashMap::new(),
+            events: None,
+        };
+        graph.rebuild_reverse_connections();
+        graph.rebuild_external_ids();
+        Ok(graph)
+    }
+}
+
+/// Reads the connections of a graph, including the ones saved before inputs
+/// could have several connections. Empty lists are dropped, as inputs without
+/// connections have no entry.
+#[cfg(feature = "persistence")]
+fn deserialize_connections<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<SecondaryMap<InputId, SVec<OutputId>>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Connections {
+        Single(OutputId),
+        Multiple(SVec<OutputId>),
+    }
+
+    let connections = SecondaryMap::<InputId, Connections>::deserialize(deserializer)?;
+    Ok(connections
+        .into_iter()
+        .filter_map(|(input, outputs)| match outputs {
+            Connections::Single(output) => Some((input, smallvec::smallvec![output])),
+            Connections::Multiple(outputs) if outputs.is_empty() => None,
+            Connections::Multiple(outputs) => Some((input, outputs)),
+        })
+        .collect())
 }
@@ -1,4 +1,19 @@
 use super::*;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::num::NonZeroU32;
+
+/// What [`Graph::insert_connection`] did.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Insertion {
+    Added,
+    /// The last connection of the input, from this output, was replaced.
+    Replaced(OutputId),
+    /// The connection was there already, nothing changed.
+    AlreadyConnected,
+    /// The input doesn't exist, nothing changed.
+    Invalid,
+}
 
 impl<NodeData, DataType, ValueType> Graph<NodeData, DataType, ValueType> {
     pub fn new() -> Self {
@@ -7,6 +22,33 @@ impl<NodeData, DataType, ValueType> Graph<NodeData, DataType, ValueType> {
             inputs: SlotMap::default(),
             outputs: SlotMap::default(),
             connections: SecondaryMap::default(),
+            reverse_connections: SecondaryMap::default(),
+            external_ids: HashMap::new(),
+            events: None,
+        }
+    }
+
+    /// Starts or stops recording the changes made to the graph as
+    /// [`GraphEvent`]s, to be taken with [`Graph::drain_events`], e.g. once
+    /// per frame. Stopping drops the events not drained yet.
+    pub fn record_events(&mut self, record: bool) {
+        match (record, &self.events) {
+            (true, None) => self.events = Some(Vec::new()),
+            (false, _) => self.events = None,
+            (true, Some(_)) => {}
+        }
+    }
+
+    /// Takes the changes recorded since the last call, oldest first. Empty
+    /// unless [`Graph::record_events`] is on.
+    pub fn drain_events(&mut self) -> Vec<GraphEvent> {
+        self.events.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    /// Records `event`, if events are recorded.
+    pub(crate) fn push_event(&mut self, event: GraphEvent) {
+        if let Some(events) = &mut self.events {
+            events.push(event);
         }
     }
 
@@ -20,12 +62,14 @@ impl<NodeData, DataType, ValueType> Graph<NodeData, DataType, ValueType> {
             Node {
                 id: node_id,
                 label,
+                external_id: None,
                 // These get filled in later by the user function
                 inputs: Vec::default(),
                 outputs: Vec::default(),
                 user_data,
             }
         });
+        self.push_event(GraphEvent::NodeAdded(node_id));
 
         f(self, node_id);
 
@@ -40,6 +84,31 @@ impl<NodeData, DataType, ValueType> Graph<NodeData, DataType, ValueType> {
         value: ValueType,
         kind: InputParamKind,
         shown_inline: bool,
+    ) -> InputId {
+        self.add_wide_input_param(
+            node_id,
+            name,
+            typ,
+            value,
+            kind,
+            NonZeroU32::new(1),
+            shown_inline,
+        )
+    }
+
+    /// Like [`Graph::add_input_param`], for an input accepting up to
+    /// `max_connections` incoming connections, or any number of them if
+    /// `None`. See [`Graph::connections()`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_wide_input_param(
+        &mut self,
+        node_id: NodeId,
+        name: String,
+        typ: DataType,
+        value: ValueType,
+        kind: InputParamKind,
+        max_connections: Option<NonZeroU32>,
+        shown_inline: bool,
     ) -> InputId {
         let input_id = self.inputs.insert_with_key(|input_id| InputParam {
             id: input_id,
@@ -48,23 +117,48 @@ impl<NodeData, DataType, ValueType> Graph<NodeData, DataType, ValueType> {
             kind,
             node: node_id,
             shown_inline,
+            max_connections,
+            group: None,
+            required: false,
         });
         self.nodes[node_id].inputs.push((name, input_id));
+        self.push_event(GraphEvent::ParamAdded {
+            node: node_id,
+            param: AnyParameterId::Input(input_id),
+        });
         input_id
     }
 
-    pub fn remove_input_param(&mut self, param: InputId) {
+    /// Removes an input from its node, along with its connections, if any. The
+    /// other parameters of the node and their connections are kept.
+    ///
+    /// Returns the removed parameter.
+    pub fn remove_input_param(&mut self, param: InputId) -> InputParam<DataType, ValueType> {
         let node = self[param].node;
         self[node].inputs.retain(|(_, id)| *id != param);
-        self.inputs.remove(param);
-        self.connections.retain(|i, _| i != param);
+        self.remove_connection(param);
+        self.push_event(GraphEvent::ParamRemoved {
+            node,
+            param: AnyParameterId::Input(param),
+        });
+        self.inputs.remove(param).expect("Input should exist")
     }
 
-    pub fn remove_output_param(&mut self, param: OutputId) {
+    /// Removes an output from its node, along with all of its connections. The
+    /// other parameters of the node and their connections are kept.
+    ///
+    /// Returns the removed parameter.
+    pub fn remove_output_param(&mut self, param: OutputId) -> OutputParam<DataType> {
         let node = self[param].node;
         self[node].outputs.retain(|(_, id)| *id != param);
-        self.outputs.remove(param);
-        self.connections.retain(|_, o| *o != param);
+        for input in self.output_connections(param).collect::<SVec<_>>() {
+            self.remove_connection_between(param, input);
+        }
+        self.push_event(GraphEvent::ParamRemoved {
+            node,
+            param: AnyParameterId::Output(param),
+        });
+        self.outputs.remove(param).expect("Output should exist")
     }
 
     pub fn add_output_param(&mut self, node_id: NodeId, name: String, typ: DataType) -> OutputId {
@@ -72,8 +166,13 @@ impl<NodeData, DataType, ValueType> Graph<NodeData, DataType, ValueType> {
             id: output_id,
             node: node_id,
             typ,
+            group: None,
         });
         self.nodes[node_id].outputs.push((name, output_id));
+        self.push_event(GraphEvent::ParamAdded {
+            node: node_id,
+            param: AnyParameterId::Output(output_id),
+        });
         output_id
     }
 
@@ -85,32 +184,250 @@ impl<NodeData, DataType, ValueType> Graph<NodeData, DataType, ValueType> {
     /// ids in the pair (the one on `node_id`'s end) will be invalid after
     /// calling this function.
     pub fn remove_node(&mut self, node_id: NodeId) -> (Node<NodeData>, Vec<(InputId, OutputId)>) {
-        let mut disconnect_events = vec![];
-
-        self.connections.retain(|i, o| {
-            if self.outputs[*o].node == node_id || self.inputs[i].node == node_id {
-                disconnect_events.push((i, *o));
-                false
-            } else {
-                true
-            }
-        });
+        let disconnect_events = self.disconnect_node(node_id);
 
         // NOTE: Collect is needed because we can't borrow the input ids while
         // we remove them inside the loop.
         for input in self[node_id].input_ids().collect::<SVec<_>>() {
             self.inputs.remove(input);
+            self.push_event(GraphEvent::ParamRemoved {
+                node: node_id,
+                param: AnyParameterId::Input(input),
+            });
         }
         for output in self[node_id].output_ids().collect::<SVec<_>>() {
             self.outputs.remove(output);
+            self.push_event(GraphEvent::ParamRemoved {
+                node: node_id,
+                param: AnyParameterId::Output(output),
+            });
         }
         let removed_node = self.nodes.remove(node_id).expect("Node should exist");
+        if let Some(external_id) = &removed_node.external_id {
+            self.external_ids.remove(external_id);
+        }
+        self.push_event(GraphEvent::NodeRemoved(node_id));
 
         (removed_node, disconnect_events)
     }
 
+    /// Removes all the incoming and outgoing connections of a node. Returns the
+    /// removed connections as input-output pairs.
+    pub fn disconnect_node(&mut self, node_id: NodeId) -> Vec<(InputId, OutputId)> {
+        let disconnect_events: Vec<(InputId, OutputId)> = self
+            .iter_connections()
+            .filter(|(i, o)| self.outputs[*o].node == node_id || self.inputs[*i].node == node_id)
+            .collect();
+        for (input, output) in &disconnect_events {
+            self.remove_connection_between(*output, *input);
+        }
+        disconnect_events
+    }
+
+    /// The connections [`Graph::extract_node`] makes to bridge the gap left
+    /// by a node: from the output upstream of its only connected input, to
+    /// the inputs downstream of its only connected output.
+    ///
+    /// Returns `None` when the bridge would be ambiguous, because several
+    /// inputs, outputs or connections of the node are involved, or when
+    /// the upstream output and a downstream input have different types.
+    pub fn extraction_bridge(&self, node_id: NodeId) -> Option<(OutputId, Vec<InputId>)>
+    where
+        DataType: PartialEq,
+    {
+        let mut inputs = self[node_id]
+            .input_ids()
+            .filter(|input| self.is_input_connected(*input));
+        let mut outputs = self[node_id]
+            .output_ids()
+            .filter(|output| self.output_connection_count(*output) > 0);
+        let (input, output) = match (inputs.next(), inputs.next(), outputs.next(), outputs.next()) {
+            (Some(input), None, Some(output), None) => (input, output),
+            _ => return None,
+        };
+        let upstream = match self.connections(input) {
+            [upstream] if self[*upstream].node != node_id => *upstream,
+            _ => return None,
+        };
+        let downstream: Vec<InputId> = self.output_connections(output).collect();
+        let compatible = downstream
+            .iter()
+            .all(|input| self[*input].node != node_id && self[*input].typ == self[upstream].typ);
+        compatible.then_some((upstream, downstream))
+    }
+
+    /// Removes all the connections of a node, like [`Graph::disconnect_node`],
+    /// and connects the output that fed it to the inputs it fed, so a
+    /// pipeline keeps working without it. See [`Graph::extraction_bridge`].
+    ///
+    /// Returns the removed and the added connections as input-output pairs,
+    /// or `None`, leaving the graph untouched, if there is no bridge.
+    #[allow(clippy::type_complexity)]
+    pub fn extract_node(
+        &mut self,
+        node_id: NodeId,
+    ) -> Option<(Vec<(InputId, OutputId)>, Vec<(InputId, OutputId)>)>
+    where
+        DataType: PartialEq,
+    {
+        let (upstream, downstream) = self.extraction_bridge(node_id)?;
+        let removed = self.disconnect_node(node_id);
+        let added = downstream
+            .into_iter()
+            .map(|input| {
+                self.insert_connection(upstream, input);
+                (input, upstream)
+            })
+            .collect();
+        Some((removed, added))
+    }
+
+    /// Changes whether `input` accepts connections, a constant, or both.
+    /// Making it [`InputParamKind::ConstantOnly`] removes its connections,
+    /// which are returned like in [`Graph::disconnect_node`].
+    ///
+    /// Push [`NodeResponse::SetInputKind`] instead to get a `DisconnectEvent`
+    /// for each removed connection from the editor.
+    pub fn set_input_kind(
+        &mut self,
+        input: InputId,
+        kind: InputParamKind,
+    ) -> Vec<(InputId, OutputId)> {
+        self[input].kind = kind;
+        if !matches!(kind, InputParamKind::ConstantOnly) {
+            return Vec::new();
+        }
+        let outputs = self.connections.remove(input).unwrap_or_default();
+        for output in &outputs {
+            self.unlink_reverse_connection(*output, input);
+        }
+        outputs.into_iter().map(|output| (input, output)).collect()
+    }
+
+    /// Marks `input` as needing a connection, see [`InputParam::required`].
+    pub fn set_input_required(&mut self, input: InputId, required: bool) {
+        self[input].required = required;
+    }
+
+    /// Whether `input` needs a connection, see [`InputParam::required`].
+    /// Unknown inputs aren't required.
+    pub fn is_input_required(&self, input: InputId) -> bool {
+        self.inputs.get(input).is_some_and(|param| param.required)
+    }
+
+    /// The required inputs of `node_id` without any connection.
+    pub fn missing_required_inputs(&self, node_id: NodeId) -> impl Iterator<Item = InputId> + '_ {
+        self[node_id]
+            .input_ids()
+            .filter(move |input| self[*input].required && !self.is_input_connected(*input))
+    }
+
+    /// Changes the label of a node, returning the previous one.
+    pub fn rename_node(&mut self, node_id: NodeId, label: String) -> String {
+        std::mem::replace(&mut self[node_id].label, label)
+    }
+
+    /// Changes the external id of a node, or clears it with `None`, returning
+    /// the previous one. See [`Node::external_id`].
+    ///
+    /// Fails if another node already has that id.
+    pub fn set_external_id(
+        &mut self,
+        node_id: NodeId,
+        external_id: Option<String>,
+    ) -> Result<Option<String>, EguiGraphError> {
+        if let Some(id) = &external_id {
+            match self.external_ids.get(id) {
+                Some(owner) if *owner != node_id => {
+                    return Err(EguiGraphError::DuplicateExternalId(*owner, id.clone()));
+                }
+                _ => {}
+            }
+        }
+        let previous = std::mem::replace(&mut self[node_id].external_id, external_id.clone());
+        if let Some(previous) = &previous {
+            self.external_ids.remove(previous);
+        }
+        if let Some(id) = external_id {
+            self.external_ids.insert(id, node_id);
+        }
+        Ok(previous)
+    }
+
+    /// The node with the given [`Node::external_id`], if any.
+    pub fn node_by_external_id(&self, external_id: &str) -> Option<NodeId> {
+        self.external_ids.get(external_id).copied()
+    }
+
+    /// Recomputes [`Graph::external_ids`] from the nodes. Only needed after
+    /// modifying [`Node::external_id`] directly.
+    pub fn rebuild_external_ids(&mut self) {
+        self.external_ids = self
+            .nodes
+            .iter()
+            .filter_map(|(id, node)| Some((node.external_id.clone()?, id)))
+            .collect();
+    }
+
+    /// Changes the name of an input, returning the previous one. Connections
+    /// are kept, and [`Node::get_input`] finds the input by its new name.
+    ///
+    /// Fails if the node already has another input with that name.
+    pub fn rename_input_param(
+        &mut self,
+        param: InputId,
+        name: String,
+    ) -> Result<String, EguiGraphError> {
+        let node = self
+            .try_get_input(param)
+            .ok_or(EguiGraphError::InvalidParameterId(param.into()))?
+            .node;
+        rename_param(node, &mut self.nodes[node].inputs, param, name)
+    }
+
+    /// Changes the name of an output, returning the previous one. Connections
+    /// are kept, and [`Node::get_output`] finds the output by its new name.
+    ///
+    /// Fails if the node already has another output with that name.
+    pub fn rename_output_param(
+        &mut self,
+        param: OutputId,
+        name: String,
+    ) -> Result<String, EguiGraphError> {
+        let node = self
+            .try_get_output(param)
+            .ok_or(EguiGraphError::InvalidParameterId(param.into()))?
+            .node;
+        rename_param(node, &mut self.nodes[node].outputs, param, name)
+    }
+
+    /// Removes the incoming connections of `input_id`. Returns the output it
+    /// was connected to, or the first one for inputs with several connections.
     pub fn remove_connection(&mut self, input_id: InputId) -> Option<OutputId> {
-        self.connections.remove(input_id)
+        let outputs = self.connections.remove(input_id)?;
+        for output in &outputs {
+            self.unlink_reverse_connection(*output, input_id);
+        }
+        outputs.first().copied()
+    }
+
+    /// Removes the connection from `output` to `input`, keeping the other
+    /// connections of `input`. Returns whether they were connected.
+    pub fn remove_connection_between(&mut self, output: OutputId, input: InputId) -> bool {
+        let outputs = match self.connections.get_mut(input) {
+            Some(outputs) => outputs,
+            None => return false,
+        };
+        let len_before = outputs.len();
+        outputs.retain(|o| *o != output);
+        let removed = outputs.len() != len_before;
+        if outputs.is_empty() {
+            self.connections.remove(input);
+        }
+        if removed {
+            self.unlink_reverse_connection(output, input);
+        }
+        removed
     }
 
     pub fn iter_nodes(&self) -> impl Iterator<Item = NodeId> + '_ {
@@ -118,15 +435,238 @@ impl<NodeData, DataType, ValueType> Graph<NodeData, DataType, ValueType> {
     }
 
     pub fn add_connection(&mut self, output: OutputId, input: InputId) {
-        self.connections.insert(input, output);
+        self.insert_connection(output, input);
+    }
+
+    /// Writes a connection, updating the reverse index. When `input` already
+    /// has as many connections as it accepts, its last connection is
+    /// replaced. Connecting the same output twice leaves the graph untouched.
+    pub(crate) fn insert_connection(&mut self, output: OutputId, input: InputId) -> Insertion {
+        let max = self
+            .inputs
+            .get(input)
+            .and_then(|param| param.max_connections)
+            .map_or(usize::MAX, |max| max.get() as usize);
+        let outputs = match self.connections.entry(input) {
+            Some(entry) => entry.or_default(),
+            None => return Insertion::Invalid,
+        };
+        if outputs.contains(&output) {
+            return Insertion::AlreadyConnected;
+        }
+        let previous = if outputs.len() >= max {
+            outputs.pop()
+        } else {
+            None
+        };
+        outputs.push(output);
+        if let Some(previous) = previous {
+            self.unlink_reverse_connection(previous, input);
+        }
+        if let Some(entry) = self.reverse_connections.entry(output) {
+            entry.or_default().push(input);
+        }
+        self.push_event(GraphEvent::ConnectionAdded { input, output });
+        match previous {
+            Some(previous) => Insertion::Replaced(previous),
+            None => Insertion::Added,
+        }
+    }
+
+    /// Removes `input` from the reverse index of `output`, once their
+    /// connection has been removed from [`Graph::connections`].
+    fn unlink_reverse_connection(&mut self, output: OutputId, input: InputId) {
+        self.push_event(GraphEvent::ConnectionRemoved { input, output });
+        if let Some(inputs) = self.reverse_connections.get_mut(output) {
+            inputs.retain(|i| *i != input);
+            if inputs.is_empty() {
+                self.reverse_connections.remove(output);
+            }
+        }
+    }
+
+    /// Recomputes [`Graph::reverse_connections`] from scratch. Only needed
+    /// after modifying the `connections` field directly.
+    pub fn rebuild_reverse_connections(&mut self) {
+        self.reverse_connections.clear();
+        for (input, outputs) in self.connections.iter() {
+            for output in outputs {
+                if let Some(entry) = self.reverse_connections.entry(*output) {
+                    entry.or_default().push(input);
+                }
+            }
+        }
+    }
+
+    /// Returns whether `input` has an incoming connection. Prefer this (and
+    /// [`Graph::output_connection_count`]) to query the connectivity of a
+    /// parameter.
+    pub fn is_input_connected(&self, input: InputId) -> bool {
+        self.connections.contains_key(input)
+    }
+
+    /// Returns whether `input` has as many incoming connections as it accepts,
+    /// see [`InputParam::max_connections`]. A new connection to it would
+    /// replace an existing one.
+    pub fn is_input_full(&self, input: InputId) -> bool {
+        match self
+            .inputs
+            .get(input)
+            .and_then(|param| param.max_connections)
+        {
+            Some(max) => self.connections(input).len() >= max.get() as usize,
+            None => false,
+        }
+    }
+
+    /// Returns the number of inputs `output` is connected to. This does not
+    /// need to scan the connections.
+    pub fn output_connection_count(&self, output: OutputId) -> usize {
+        self.reverse_connections
+            .get(output)
+            .map_or(0, |inputs| inputs.len())
+    }
+
+    /// Iterates the inputs `output` is connected to.
+    pub fn output_connections(&self, output: OutputId) -> impl Iterator<Item = InputId> + '_ {
+        self.reverse_connections
+            .get(output)
+            .into_iter()
+            .flat_map(|inputs| inputs.iter().copied())
+    }
+
+    /// The group `param` is drawn in, see [`InputParam::group`]. `None` for
+    /// ungrouped params and params that don't exist.
+    pub fn param_group(&self, param: AnyParameterId) -> Option<&str> {
+        match param {
+            AnyParameterId::Input(input) => self.inputs.get(input)?.group.as_deref(),
+            AnyParameterId::Output(output) => self.outputs.get(output)?.group.as_deref(),
+        }
+    }
+
+    /// The port a wildcard `param` takes its type from: the first port it's
+    /// connected to that isn't a wildcard itself. `None` for ports that aren't
+    /// wildcards, and wildcards that aren't bound. See
+    /// [`DataTypeTrait::is_wildcard`].
+    pub fn wildcard_binding<UserState>(&self, param: AnyParameterId) -> Option<AnyParameterId>
+    where
+        DataType: DataTypeTrait<UserState>,
+    {
+        match param {
+            AnyParameterId::Input(input) => {
+                if !self[input].typ.is_wildcard() {
+                    return None;
+                }
+                self.connections(input)
+                    .iter()
+                    .find(|output| !self[**output].typ.is_wildcard())
+                    .map(|output| AnyParameterId::Output(*output))
+            }
+            AnyParameterId::Output(output) => {
+                if !self[output].typ.is_wildcard() {
+                    return None;
+                }
+                self.output_connections(output)
+                    .find(|input| !self[*input].typ.is_wildcard())
+                    .map(AnyParameterId::Input)
+            }
+        }
+    }
+
+    /// The data type of `param`, where a bound wildcard takes the type of the
+    /// port it's bound to, see [`Graph::wildcard_binding`].
+    pub fn resolved_type<UserState>(&self, param: AnyParameterId) -> &DataType
+    where
+        DataType: DataTypeTrait<UserState>,
+    {
+        let param = self.wildcard_binding(param).unwrap_or(param);
+        self.any_param_type(param).expect("Param should exist")
+    }
+
+    /// Whether the data types of `output` and `input` allow connecting them,
+    /// see [`DataTypeTrait::superset_of`]. Wildcards are resolved with
+    /// [`Graph::resolved_type`], except for an `input` whose connection would
+    /// be replaced by the new one.
+    pub fn types_accept_connection<UserState>(&self, output: OutputId, input: InputId) -> bool
+    where
+        DataType: DataTypeTrait<UserState>,
+    {
+        let from = self.resolved_type(AnyParameterId::Output(output));
+        let to = if self.is_input_full(input) {
+            &self[input].typ
+        } else {
+            self.resolved_type(AnyParameterId::Input(input))
+        };
+        to.superset_of(from) || from.is_wildcard()
+    }
+
+    /// Checked version of [`Graph::add_connection`]. The connection is only
+    /// written if both parameters exist and their data types accept it, see
+    /// [`Graph::types_accept_connection`]. A rejected connection leaves the
+    /// graph untouched.
+    ///
+    /// Connecting an input that already has as many connections as it accepts
+    /// (a single one, for most inputs) atomically replaces its last
+    /// connection. In that case, the previously connected output is returned.
+    pub fn try_add_connection<UserState>(
+        &mut self,
+        output: OutputId,
+        input: InputId,
+    ) -> Result<Option<OutputId>, EguiGraphError>
+    where
+        DataType: DataTypeTrait<UserState>,
+    {
+        self.any_param_type(AnyParameterId::Output(output))?;
+        self.any_param_type(AnyParameterId::Input(input))?;
+        if !self.types_accept_connection(output, input) {
+            return Err(EguiGraphError::IncompatibleTypes(output, input));
+        }
+        Ok(match self.insert_connection(output, input) {
+            Insertion::Replaced(previous) => Some(previous),
+            Insertion::AlreadyConnected => Some(output),
+            Insertion::Added | Insertion::Invalid => None,
+        })
     }
 
     pub fn iter_connections(&self) -> impl Iterator<Item = (InputId, OutputId)> + '_ {
-        self.connections.iter().map(|(o, i)| (o, *i))
+        self.connections
+            .iter()
+            .flat_map(|(input, outputs)| outputs.iter().map(move |output| (input, *output)))
     }
 
+    /// Returns the output connected to `input`. For inputs with several
+    /// connections, this is the first one: see [`Graph::connections()`].
     pub fn connection(&self, input: InputId) -> Option<OutputId> {
-        self.connections.get(input).copied()
+        self.connections(input).first().copied()
+    }
+
+    /// Returns the outputs connected to `input`, in the order they were
+    /// connected.
+    pub fn connections(&self, input: InputId) -> &[OutputId] {
+        self.connections
+            .get(input)
+            .map_or(&[], |outputs| outputs.as_slice())
+    }
+
+    /// The name of a param, as listed by its node. `None` if the param
+    /// doesn't exist.
+    pub fn param_name(&self, param: AnyParameterId) -> Option<&str> {
+        fn find<Id: PartialEq>(params: &[(String, Id)], param: Id) -> Option<&str> {
+            params
+                .iter()
+                .find(|(_, id)| *id == param)
+                .map(|(name, _)| name.as_str())
+        }
+        match param {
+            AnyParameterId::Input(input) => find(
+                &self.nodes.get(self.try_get_input(input)?.node)?.inputs,
+                input,
+            ),
+            AnyParameterId::Output(output) => find(
+                &self.nodes.get(self.try_get_output(output)?.node)?.outputs,
+                output,
+            ),
+        }
     }
 
     pub fn any_param_type(&self, param: AnyParameterId) -> Result<&DataType, EguiGraphError> {
@@ -152,6 +692,243 @@ impl<NodeData, DataType, ValueType> Graph<NodeData, DataType, ValueType> {
     pub fn get_output(&self, output: OutputId) -> &OutputParam<DataType> {
         &self.outputs[output]
     }
+
+    /// Counts the nodes in the graph, grouped by the key that `f` computes from
+    /// their user data.
+    pub fn histogram_by<K: Hash + Eq>(&self, f: impl Fn(&NodeData) -> K) -> HashMap<K, usize> {
+        self.nodes
+            .values()
+            .fold(HashMap::new(), |mut counts, node| {
+                *counts.entry(f(&node.user_data)).or_default() += 1;
+                counts
+            })
+    }
+
+    /// Counts the connections in the graph, grouped by the keys that `f`
+    /// computes from the user data of their nodes. Keys are `(source,
+    /// destination)` pairs, i.e. the node with the output comes first.
+    pub fn connection_matrix_by<K: Hash + Eq>(
+        &self,
+        f: impl Fn(&NodeData) -> K,
+    ) -> HashMap<(K, K), usize> {
+        self.iter_connections()
+            .map(|(input, output)| {
+                let source = &self[self[output].node].user_data;
+                let destination = &self[self[input].node].user_data;
+                (f(source), f(destination))
+            })
+            .fold(HashMap::new(), |mut counts, key| {
+                *counts.entry(key).or_default() += 1;
+                counts
+            })
+    }
+
+    /// Splits the graph into groups of nodes linked by connections, regardless
+    /// of their direction. Nodes without connections are alone in their
+    /// component. Components, and the nodes inside them, follow the node
+    /// iteration order of the graph.
+    ///
+    /// This runs in linear time over the nodes and connections of the graph.
+    pub fn connected_components(&self) -> Vec<Vec<NodeId>> {
+        // Union-find over the nodes, with path halving.
+        fn find(parents: &mut SecondaryMap<NodeId, NodeId>, mut node: NodeId) -> NodeId {
+            while parents[node] != node {
+                parents[node] = parents[parents[node]];
+                node = parents[node];
+            }
+            node
+        }
+
+        let mut parents: SecondaryMap<NodeId, NodeId> =
+            self.nodes.keys().map(|node| (node, node)).collect();
+        for (input, output) in self.iter_connections() {
+            let a = find(&mut parents, self[input].node);
+            let b = find(&mut parents, self[output].node);
+            if a != b {
+                parents[a] = b;
+            }
+        }
+
+        let mut component_index = SecondaryMap::new();
+        let mut components: Vec<Vec<NodeId>> = Vec::new();
+        for node in self.nodes.keys() {
+            let root = find(&mut parents, node);
+            let index = *component_index.entry(root).unwrap().or_insert_with(|| {
+                components.push(Vec::new());
+                components.len() - 1
+            });
+            components[index].push(node);
+        }
+        components
+    }
+
+    /// Whether connecting `output` to `input` would create a cycle, i.e. the
+    /// node of `output` already depends on the node of `input` through
+    /// connections. Connecting a node to itself is also a cycle.
+    ///
+    /// This is a depth-first search upstream of `output` that stops as soon
+    /// as the node of `input` is found.
+    pub fn would_create_cycle(&self, output: OutputId, input: InputId) -> bool {
+        let target = self[input].node;
+        self.walk_connections(self[output].node, true, |node| node == target)
+    }
+
+    /// The nodes `node` depends on, i.e. the ones that can be reached by
+    /// following connections from inputs to outputs, including `node` itself.
+    pub fn upstream_nodes(&self, node: NodeId) -> HashSet<NodeId> {
+        let mut nodes = HashSet::new();
+        self.walk_connections(node, true, |node| {
+            nodes.insert(node);
+            false
+        });
+        nodes
+    }
+
+    /// The nodes depending on `node`, i.e. the ones that can be reached by
+    /// following connections from outputs to inputs, including `node` itself.
+    /// Relies on [`Graph::reverse_connections`] being up to date.
+    pub fn downstream_nodes(&self, node: NodeId) -> HashSet<NodeId> {
+        let mut nodes = HashSet::new();
+        self.walk_connections(node, false, |node| {
+            nodes.insert(node);
+            false
+        });
+        nodes
+    }
+
+    /// The nodes directly connected to `node`, through its inputs or its
+    /// outputs, not including `node` itself. Relies on
+    /// [`Graph::reverse_connections`] being up to date.
+    pub fn neighbors(&self, node: NodeId) -> HashSet<NodeId> {
+        let upstream = self[node].input_ids().flat_map(|input| {
+            self.connections(input)
+                .iter()
+                .map(|output| self[*output].node)
+        });
+        let downstream = self[node].output_ids().flat_map(|output| {
+            self.output_connections(output)
+                .map(|input| self[input].node)
+        });
+        upstream
+            .chain(downstream)
+            .filter(|neighbor| *neighbor != node)
+            .collect()
+    }
+
+    /// Visits each node reachable from `start` once, depth-first, following
+    /// connections upstream or downstream. Stops as soon as `visit` returns
+    /// true, and returns whether it did.
+    fn walk_connections(
+        &self,
+        start: NodeId,
+        upstream: bool,
+        mut visit: impl FnMut(NodeId) -> bool,
+    ) -> bool {
+        let mut visited = HashSet::new();
+        let mut stack = vec![start];
+        while let Some(node) = stack.pop() {
+            if !visited.insert(node) {
+                continue;
+            }
+            if visit(node) {
+                return true;
+            }
+            if upstream {
+                for input in self[node].input_ids() {
+                    stack.extend(
+                        self.connections(input)
+                            .iter()
+                            .map(|output| self[*output].node),
+                    );
+                }
+            } else {
+                for output in self[node].output_ids() {
+                    stack.extend(
+                        self.output_connections(output)
+                            .map(|input| self[input].node),
+                    );
+                }
+            }
+        }
+        false
+    }
+}
+
+impl<NodeData, DataType, ValueType> Graph<NodeData, DataType, ValueType>
+where
+    NodeData: Clone,
+    DataType: Clone,
+{
+    /// Adds a copy of `node`, with its label, user data and params. The
+    /// values of the copied inputs are made by `clone_value` from the
+    /// original ones. The params of the copy are in the same order as the
+    /// originals, and have no connections.
+    pub fn duplicate_node(
+        &mut self,
+        node: NodeId,
+        clone_value: &mut impl FnMut(&ValueType) -> ValueType,
+    ) -> NodeId {
+        let original = &self[node];
+        let label = original.label.clone();
+        let user_data = original.user_data.clone();
+        let inputs: Vec<(String, InputId)> = original.inputs.clone();
+        let outputs: Vec<(String, OutputId)> = original.outputs.clone();
+        self.add_node(label, user_data, |graph, copy| {
+            for (name, input) in inputs {
+                let param = &graph[input];
+                let mut copied = InputParam {
+                    id: input,
+                    typ: param.typ.clone(),
+                    value: clone_value(&param.value),
+                    kind: param.kind,
+                    node: copy,
+                    shown_inline: param.shown_inline,
+                    max_connections: param.max_connections,
+                    group: param.group.clone(),
+                    required: param.required,
+                };
+                let new_input = graph.inputs.insert_with_key(|id| {
+                    copied.id = id;
+                    copied
+                });
+                graph.nodes[copy].inputs.push((name, new_input));
+                graph.push_event(GraphEvent::ParamAdded {
+                    node: copy,
+                    param: AnyParameterId::Input(new_input),
+                });
+            }
+            for (name, output) in outputs {
+                let mut copied = graph[output].clone();
+                copied.node = copy;
+                let new_output = graph.outputs.insert_with_key(|id| {
+                    copied.id = id;
+                    copied
+                });
+                graph.nodes[copy].outputs.push((name, new_output));
+                graph.push_event(GraphEvent::ParamAdded {
+                    node: copy,
+                    param: AnyParameterId::Output(new_output),
+                });
+            }
+        })
+    }
+}
+
+/// Renames `param` in the list of named params of `node`.
+fn rename_param<Id: Copy + Eq>(
+    node: NodeId,
+    params: &mut [(String, Id)],
+    param: Id,
+    name: String,
+) -> Result<String, EguiGraphError> {
+    if params.iter().any(|(n, id)| *n == name && *id != param) {
+        return Err(EguiGraphError::DuplicateParameterName(node, name));
+    }
+    let (old_name, _) = params
+        .iter_mut()
+        .find(|(_, id)| *id == param)
+        .expect("The node should list its params");
+    Ok(std::mem::replace(old_name, name))
 }
 
 impl<NodeData, DataType, ValueType> Default for Graph<NodeData, DataType, ValueType> {
@@ -164,14 +941,14 @@ impl<NodeData> Node<NodeData> {
     pub fn inputs<'a, DataType, DataValue>(
         &'a self,
         graph: &'a Graph<NodeData, DataType, DataValue>,
-    ) -> impl Iterator<Item = &InputParam<DataType, DataValue>> + 'a {
+    ) -> impl Iterator<Item = &'a InputParam<DataType, DataValue>> + 'a {
         self.input_ids().map(|id| graph.get_input(id))
     }
 
     pub fn outputs<'a, DataType, DataValue>(
         &'a self,
         graph: &'a Graph<NodeData, DataType, DataValue>,
-    ) -> impl Iterator<Item = &OutputParam<DataType>> + 'a {
+    ) -> impl Iterator<Item = &'a OutputParam<DataType>> + 'a {
         self.output_ids().map(|id| graph.get_output(id))
     }
 
@@ -198,6 +975,19 @@ impl<NodeData> Node<NodeData> {
             .map(|x| x.1)
             .ok_or_else(|| EguiGraphError::NoParameterNamed(self.id, name.into()))
     }
+
+    /// `name` if the node has no input called so, otherwise `name` followed by
+    /// the first number that makes it unique, e.g. `in 2`.
+    pub fn unique_input_name(&self, name: &str) -> String {
+        let taken = |candidate: &str| self.inputs.iter().any(|(other, _)| other == candidate);
+        if !taken(name) {
+            return name.to_owned();
+        }
+        (2..)
+            .map(|n| format!("{} {}", name, n))
+            .find(|candidate| !taken(candidate))
+            .expect("A node has finitely many inputs")
+    }
 }
 
 impl<DataType, ValueType> InputParam<DataType, ValueType> {
@@ -213,3 +1003,528 @@ impl<DataType, ValueType> InputParam<DataType, ValueType> {
         self.node
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    fn add_test_node(graph: &mut TestGraph, template: TestTemplate) -> NodeId {
+        graph.add_node(
+            format!("{:?}", template),
+            TestNodeData(template),
+            |graph, id| template.build_node(graph, &mut (), id),
+        )
+    }
+
+    #[test]
+    pub fn test_connection_counts() {
+        let mut graph = TestGraph::new();
+        let [a, b, c] = [(); 3].map(|_| add_test_node(&mut graph, TestTemplate::Scalar));
+        let out_a = graph[a].get_output("out").unwrap();
+        let out_b = graph[b].get_output("out").unwrap();
+        let in_b = graph[b].get_input("in").unwrap();
+        let in_c = graph[c].get_input("in").unwrap();
+
+        graph.add_connection(out_a, in_b);
+        graph.add_connection(out_a, in_c);
+        assert!(graph.is_input_connected(in_b));
+        assert_eq!(graph.output_connection_count(out_a), 2);
+
+        // Replacing a connection moves it to the new output
+        graph.try_add_connection(out_b, in_c).unwrap();
+        assert_eq!(graph.output_connection_count(out_a), 1);
+        assert_eq!(graph.output_connections(out_b).collect::<Vec<_>>(), [in_c]);
+
+        graph.remove_connection(in_b);
+        assert!(!graph.is_input_connected(in_b));
+        assert_eq!(graph.output_connection_count(out_a), 0);
+
+        graph.add_connection(out_a, in_b);
+        graph.add_connection(out_b, in_c);
+        assert_eq!(graph.disconnect_node(b), [(in_b, out_a), (in_c, out_b)]);
+        assert_eq!(graph.iter_connections().count(), 0);
+
+        graph.add_connection(out_a, in_b);
+        graph.remove_node(b);
+        assert_eq!(graph.output_connection_count(out_a), 0);
+        assert!(!graph.is_input_connected(in_c));
+        assert!(graph.reverse_connections.is_empty());
+
+        // Modifying the connections directly requires rebuilding the index
+        graph.connections.insert(in_c, smallvec::smallvec![out_a]);
+        assert_eq!(graph.output_connection_count(out_a), 0);
+        graph.rebuild_reverse_connections();
+        assert_eq!(graph.output_connection_count(out_a), 1);
+    }
+
+    #[test]
+    pub fn test_node_statistics() {
+        let mut graph = TestGraph::new();
+        let a = add_test_node(&mut graph, TestTemplate::Scalar);
+        let b = add_test_node(&mut graph, TestTemplate::Scalar);
+        let c = add_test_node(&mut graph, TestTemplate::Scalar);
+        let d = add_test_node(&mut graph, TestTemplate::Vector);
+        let connect = |graph: &mut TestGraph, from: NodeId, to: NodeId| {
+            let output = graph[from].get_output("out").unwrap();
+            let input = graph[to].get_input("in").unwrap();
+            graph.add_connection(output, input);
+        };
+        connect(&mut graph, a, b);
+        connect(&mut graph, a, c);
+
+        let histogram = graph.histogram_by(|data| data.0);
+        assert_eq!(histogram.len(), 2);
+        assert_eq!(histogram[&TestTemplate::Scalar], 3);
+        assert_eq!(histogram[&TestTemplate::Vector], 1);
+
+        let matrix = graph.connection_matrix_by(|data| data.0);
+        assert_eq!(matrix.len(), 1);
+        assert_eq!(matrix[&(TestTemplate::Scalar, TestTemplate::Scalar)], 2);
+
+        graph.remove_node(d);
+        assert_eq!(graph.histogram_by(|_| ()).get(&()), Some(&3));
+    }
+
+    #[test]
+    pub fn test_connected_components() {
+        let mut graph = TestGraph::new();
+        let [a, b, c, d] = [(); 4].map(|_| add_test_node(&mut graph, TestTemplate::Scalar));
+        assert_eq!(graph.connected_components().len(), 4);
+
+        // Direction doesn't matter: d feeds c, c feeds b
+        for (from, to) in [(d, c), (c, b)] {
+            let output = graph[from].get_output("out").unwrap();
+            let input = graph[to].get_input("in").unwrap();
+            graph.add_connection(output, input);
+        }
+        assert_eq!(graph.connected_components(), vec![vec![a], vec![b, c, d]]);
+        assert_eq!(graph.neighbors(c), [b, d].into_iter().collect());
+        assert!(graph.neighbors(a).is_empty());
+
+        graph.remove_node(c);
+        assert_eq!(
+            graph.connected_components(),
+            vec![vec![a], vec![b], vec![d]]
+        );
+    }
+
+    #[test]
+    pub fn test_would_create_cycle() {
+        let mut graph = TestGraph::new();
+        let [a, b, c, d] = [(); 4].map(|_| add_test_node(&mut graph, TestTemplate::Scalar));
+        // `b` has a second output, so cycles must be found through any output
+        let extra = graph.add_output_param(b, "extra".into(), TestDataType::Scalar);
+        let port = |graph: &TestGraph, node: NodeId| {
+            let output = graph[node].get_output("out").unwrap();
+            let input = graph[node].get_input("in").unwrap();
+            (output, input)
+        };
+        // a -> b -(extra)-> c, d is on its own
+        graph.add_connection(port(&graph, a).0, port(&graph, b).1);
+        graph.add_connection(extra, port(&graph, c).1);
+
+        assert!(graph.would_create_cycle(port(&graph, c).0, port(&graph, a).1));
+        assert!(graph.would_create_cycle(port(&graph, b).0, port(&graph, b).1));
+        assert!(!graph.would_create_cycle(port(&graph, a).0, port(&graph, c).1));
+        assert!(!graph.would_create_cycle(port(&graph, d).0, port(&graph, a).1));
+        assert!(!graph.would_create_cycle(port(&graph, c).0, port(&graph, d).1));
+
+        assert_eq!(graph.upstream_nodes(c), HashSet::from([a, b, c]));
+        assert_eq!(graph.downstream_nodes(a), HashSet::from([a, b, c]));
+        assert_eq!(graph.downstream_nodes(d), HashSet::from([d]));
+        assert_eq!(graph.neighbors(b), HashSet::from([a, c]));
+        assert!(graph.neighbors(d).is_empty());
+
+        // Cycles are walked once
+        graph.add_connection(port(&graph, c).0, port(&graph, a).1);
+        assert_eq!(graph.upstream_nodes(a), HashSet::from([a, b, c]));
+        assert_eq!(graph.downstream_nodes(c), HashSet::from([a, b, c]));
+    }
+
+    #[test]
+    pub fn test_remove_params() {
+        let mut graph = TestGraph::new();
+        let [a, b] = [(); 2].map(|_| add_test_node(&mut graph, TestTemplate::Scalar));
+        let out_a = graph[a].get_output("out").unwrap();
+        let in_b = graph[b].get_input("in").unwrap();
+        let extra = graph.add_input_param(
+            b,
+            "extra".into(),
+            TestDataType::Scalar,
+            TestValue::default(),
+            InputParamKind::ConnectionOrConstant,
+            true,
+        );
+        graph.add_connection(out_a, in_b);
+        graph.add_connection(out_a, extra);
+
+        let removed = graph.remove_input_param(in_b);
+        assert_eq!(removed.node, b);
+        assert!(graph.try_get_input(in_b).is_none());
+        assert!(graph[b].get_input("in").is_err());
+        // The other connections of the node are kept
+        assert_eq!(graph.connection(extra), Some(out_a));
+        assert_eq!(graph.output_connections(out_a).collect::<Vec<_>>(), [extra]);
+
+        let removed = graph.remove_output_param(out_a);
+        assert_eq!(removed.typ, TestDataType::Scalar);
+        assert!(graph[a].outputs.is_empty());
+        assert!(graph.connections.is_empty());
+        assert!(graph.reverse_connections.is_empty());
+    }
+
+    #[test]
+    pub fn test_rename_params() {
+        let mut graph = TestGraph::new();
+        let [a, b] = [(); 2].map(|_| add_test_node(&mut graph, TestTemplate::Scalar));
+        let out_a = graph[a].get_output("out").unwrap();
+        let in_b = graph[b].get_input("in").unwrap();
+        graph.add_connection(out_a, in_b);
+
+        assert_eq!(
+            graph.rename_input_param(in_b, "value".into()).unwrap(),
+            "in"
+        );
+        assert_eq!(graph[b].get_input("value").unwrap(), in_b);
+        assert!(graph[b].get_input("in").is_err());
+        assert_eq!(
+            graph.rename_output_param(out_a, "result".into()).unwrap(),
+            "out"
+        );
+        assert_eq!(graph[a].get_output("result").unwrap(), out_a);
+        assert_eq!(graph.connection(in_b), Some(out_a));
+        assert_eq!(graph.param_name(AnyParameterId::Input(in_b)), Some("value"));
+        assert_eq!(
+            graph.param_name(AnyParameterId::Output(out_a)),
+            Some("result")
+        );
+
+        // Inputs and outputs have separate names
+        let extra = graph.add_input_param(
+            b,
+            "extra".into(),
+            TestDataType::Scalar,
+            TestValue::default(),
+            InputParamKind::ConnectionOrConstant,
+            true,
+        );
+        assert!(matches!(
+            graph.rename_input_param(extra, "value".into()),
+            Err(EguiGraphError::DuplicateParameterName(node, _)) if node == b
+        ));
+        assert!(graph.rename_input_param(extra, "out".into()).is_ok());
+        assert!(graph.rename_input_param(extra, "out".into()).is_ok());
+
+        graph.remove_node(b);
+        assert!(graph.rename_input_param(in_b, "gone".into()).is_err());
+        assert_eq!(graph.param_name(AnyParameterId::Input(in_b)), None);
+    }
+
+    #[test]
+    pub fn test_wide_inputs() {
+        let mut graph = TestGraph::new();
+        let [a, b, c, sink] = [(); 4].map(|_| add_test_node(&mut graph, TestTemplate::Scalar));
+        let [out_a, out_b, out_c] = [a, b, c].map(|node| graph[node].get_output("out").unwrap());
+        let wide = graph.add_wide_input_param(
+            sink,
+            "wide".into(),
+            TestDataType::Scalar,
+            TestValue::default(),
+            InputParamKind::ConnectionOnly,
+            NonZeroU32::new(2),
+            true,
+        );
+
+        assert_eq!(graph.try_add_connection(out_a, wide).unwrap(), None);
+        assert!(!graph.is_input_full(wide));
+        assert_eq!(graph.try_add_connection(out_b, wide).unwrap(), None);
+        assert!(graph.is_input_full(wide));
+        assert_eq!(graph.connections(wide), [out_a, out_b]);
+        assert_eq!(graph.connection(wide), Some(out_a));
+
+        // Once full, the last connection is replaced
+        assert_eq!(graph.try_add_connection(out_c, wide).unwrap(), Some(out_b));
+        assert_eq!(graph.connections(wide), [out_a, out_c]);
+        assert_eq!(graph.output_connection_count(out_b), 0);
+        assert_eq!(graph.try_add_connection(out_c, wide).unwrap(), Some(out_c));
+        assert_eq!(graph.connections(wide), [out_a, out_c]);
+        assert_eq!(graph.iter_connections().count(), 2);
+
+        assert!(graph.remove_connection_between(out_a, wide));
+        assert!(!graph.remove_connection_between(out_a, wide));
+        assert_eq!(graph.connections(wide), [out_c]);
+
+        // Without a limit, inputs are never full
+        graph[wide].max_connections = None;
+        graph.add_connection(out_a, wide);
+        graph.add_connection(out_b, wide);
+        assert!(!graph.is_input_full(wide));
+        assert_eq!(graph.connections(wide), [out_c, out_a, out_b]);
+
+        graph.remove_output_param(out_a);
+        let (_, removed) = graph.remove_node(c);
+        assert_eq!(removed, [(wide, out_c)]);
+        assert_eq!(graph.connections(wide), [out_b]);
+        assert_eq!(graph.remove_connection(wide), Some(out_b));
+        assert!(graph.connections(wide).is_empty());
+        assert!(graph.reverse_connections.is_empty());
+    }
+
+    #[test]
+    pub fn test_wildcard_resolution() {
+        let mut graph = TestGraph::new();
+        let [scalar, vector, sink] = [
+            TestTemplate::Scalar,
+            TestTemplate::Vector,
+            TestTemplate::Scalar,
+        ]
+        .map(|template| add_test_node(&mut graph, template));
+        let out_scalar = graph[scalar].get_output("out").unwrap();
+        let out_vector = graph[vector].get_output("out").unwrap();
+        let in_scalar = graph[sink].get_input("in").unwrap();
+        let any_in = graph.add_input_param(
+            sink,
+            "any".into(),
+            TestDataType::Any,
+            TestValue::default(),
+            InputParamKind::ConnectionOnly,
+            true,
+        );
+        let any_out = graph.add_output_param(scalar, "any".into(), TestDataType::Any);
+        let any = AnyParameterId::Input(any_in);
+
+        // Wildcards accept any type, and take the one of their connection
+        assert!(graph.types_accept_connection(out_vector, any_in));
+        assert!(graph.types_accept_connection(any_out, in_scalar));
+        assert!(!graph.types_accept_connection(out_vector, in_scalar));
+        assert_eq!(graph.wildcard_binding(any), None);
+        assert_eq!(*graph.resolved_type(any), TestDataType::Any);
+        assert!(matches!(
+            graph.try_add_connection(out_vector, in_scalar),
+            Err(EguiGraphError::IncompatibleTypes(..))
+        ));
+        assert_eq!(graph.try_add_connection(out_vector, any_in).unwrap(), None);
+        assert_eq!(
+            graph.wildcard_binding(any),
+            Some(AnyParameterId::Output(out_vector))
+        );
+        assert_eq!(*graph.resolved_type(any), TestDataType::Vector);
+
+        // The connection of a full input is replaced, so it isn't bound by it
+        assert!(graph.types_accept_connection(out_scalar, any_in));
+        assert_eq!(graph.try_add_connection(any_out, in_scalar).unwrap(), None);
+        assert_eq!(
+            *graph.resolved_type(AnyParameterId::Output(any_out)),
+            TestDataType::Scalar
+        );
+        let in_vector = graph[vector].get_input("in").unwrap();
+        assert!(!graph.types_accept_connection(any_out, in_vector));
+
+        graph.remove_connection(any_in);
+        assert_eq!(*graph.resolved_type(any), TestDataType::Any);
+        assert!(graph.types_accept_connection(any_out, any_in));
+    }
+
+    #[test]
+    pub fn test_duplicate_node() {
+        let mut graph = TestGraph::new();
+        let [a, b] = [(); 2].map(|_| add_test_node(&mut graph, TestTemplate::Scalar));
+        let out_a = graph[a].get_output("out").unwrap();
+        let in_b = graph[b].get_input("in").unwrap();
+        graph.add_connection(out_a, in_b);
+        graph[in_b].value = TestValue(7);
+        graph[in_b].group = Some("io".into());
+        graph.rename_node(b, "renamed".into());
+
+        let copy = graph.duplicate_node(b, &mut |value| TestValue(value.0 + 1));
+        assert_ne!(copy, b);
+        assert_eq!(graph[copy].label, "renamed");
+        assert_eq!(graph[copy].user_data.0, TestTemplate::Scalar);
+        let in_copy = graph[copy].get_input("in").unwrap();
+        assert_ne!(in_copy, in_b);
+        assert_eq!(graph[in_copy].node, copy);
+        assert_eq!(graph[in_copy].value, TestValue(8));
+        assert_eq!(graph[in_copy].group.as_deref(), Some("io"));
+        let out_copy = graph[copy].get_output("out").unwrap();
+        assert_eq!(graph[out_copy].node, copy);
+        // The original is untouched, and the copy unconnected
+        assert_eq!(graph[in_b].value, TestValue(7));
+        assert_eq!(graph.connection(in_b), Some(out_a));
+        assert!(!graph.is_input_connected(in_copy));
+        assert_eq!(graph.output_connection_count(out_copy), 0);
+    }
+
+    #[test]
+    pub fn test_set_input_kind() {
+        let mut graph = TestGraph::new();
+        let a = add_test_node(&mut graph, TestTemplate::Scalar);
+        let b = add_test_node(&mut graph, TestTemplate::Scalar);
+        let output = graph[a].get_output("out").unwrap();
+        let input = graph[b].get_input("in").unwrap();
+        graph.add_connection(output, input);
+
+        assert!(graph
+            .set_input_kind(input, InputParamKind::ConnectionOnly)
+            .is_empty());
+        assert!(graph.is_input_connected(input));
+        assert_eq!(
+            graph.set_input_kind(input, InputParamKind::ConstantOnly),
+            vec![(input, output)]
+        );
+        assert!(!graph.is_input_connected(input));
+        assert_eq!(graph.output_connection_count(output), 0);
+    }
+
+    #[test]
+    pub fn test_extract_node() {
+        let mut graph = TestGraph::new();
+        let [a, b, c, d] = [(); 4].map(|_| add_test_node(&mut graph, TestTemplate::Scalar));
+        let out = |graph: &TestGraph, node: NodeId| graph[node].get_output("out").unwrap();
+        let inp = |graph: &TestGraph, node: NodeId| graph[node].get_input("in").unwrap();
+        let (out_a, out_b) = (out(&graph, a), out(&graph, b));
+        let (in_b, in_c, in_d) = (inp(&graph, b), inp(&graph, c), inp(&graph, d));
+        graph.add_connection(out_a, in_b);
+        graph.add_connection(out_b, in_c);
+        graph.add_connection(out_b, in_d);
+
+        assert_eq!(graph.extraction_bridge(b), Some((out_a, vec![in_c, in_d])));
+        // Nothing to bridge at the ends of the pipeline
+        assert_eq!(graph.extraction_bridge(a), None);
+        assert_eq!(graph.extraction_bridge(c), None);
+
+        // Incompatible types
+        graph[in_d].typ = TestDataType::Vector;
+        assert_eq!(graph.extraction_bridge(b), None);
+        assert!(graph.extract_node(b).is_none());
+        assert_eq!(graph.iter_connections().count(), 3);
+        graph[in_d].typ = TestDataType::Scalar;
+
+        let (removed, added) = graph.extract_node(b).unwrap();
+        assert_eq!(removed.len(), 3);
+        assert_eq!(added, [(in_c, out_a), (in_d, out_a)]);
+        assert_eq!(graph.output_connection_count(out_a), 2);
+        assert_eq!(graph.output_connection_count(out_b), 0);
+        assert!(!graph.is_input_connected(in_b));
+    }
+
+    #[test]
+    pub fn test_graph_events() {
+        let mut graph = TestGraph::new();
+        let a = add_test_node(&mut graph, TestTemplate::Scalar);
+        // Nothing is recorded until asked
+        assert!(graph.drain_events().is_empty());
+
+        graph.record_events(true);
+        let b = add_test_node(&mut graph, TestTemplate::Scalar);
+        let (a_in, a_out) = (
+            graph[a].get_input("in").unwrap(),
+            graph[a].get_output("out").unwrap(),
+        );
+        let (b_in, b_out) = (
+            graph[b].get_input("in").unwrap(),
+            graph[b].get_output("out").unwrap(),
+        );
+        graph.add_connection(a_out, b_in);
+        // Replaces the connection from `a`
+        graph.add_connection(b_out, b_in);
+        assert_eq!(
+            graph.drain_events(),
+            [
+                GraphEvent::NodeAdded(b),
+                GraphEvent::ParamAdded {
+                    node: b,
+                    param: AnyParameterId::Input(b_in)
+                },
+                GraphEvent::ParamAdded {
+                    node: b,
+                    param: AnyParameterId::Output(b_out)
+                },
+                GraphEvent::ConnectionAdded {
+                    input: b_in,
+                    output: a_out
+                },
+                GraphEvent::ConnectionRemoved {
+                    input: b_in,
+                    output: a_out
+                },
+                GraphEvent::ConnectionAdded {
+                    input: b_in,
+                    output: b_out
+                },
+            ]
+        );
+
+        graph.add_connection(b_out, a_in);
+        graph.drain_events();
+        graph.remove_node(b);
+        let mut events = graph.drain_events();
+        // The connections go first, in no particular order
+        events[..2].sort_by_key(|event| format!("{:?}", event));
+        let mut expected = [
+            GraphEvent::ConnectionRemoved {
+                input: a_in,
+                output: b_out,
+            },
+            GraphEvent::ConnectionRemoved {
+                input: b_in,
+                output: b_out,
+            },
+        ];
+        expected.sort_by_key(|event| format!("{:?}", event));
+        assert_eq!(events[..2], expected);
+        assert_eq!(
+            events[2..],
+            [
+                GraphEvent::ParamRemoved {
+                    node: b,
+                    param: AnyParameterId::Input(b_in)
+                },
+                GraphEvent::ParamRemoved {
+                    node: b,
+                    param: AnyParameterId::Output(b_out)
+                },
+                GraphEvent::NodeRemoved(b),
+            ]
+        );
+
+        graph.record_events(false);
+        graph.remove_node(a);
+        assert!(graph.drain_events().is_empty());
+    }
+
+    #[test]
+    pub fn test_external_ids() {
+        let mut graph = TestGraph::new();
+        let [a, b] = [(); 2].map(|_| add_test_node(&mut graph, TestTemplate::Scalar));
+        assert_eq!(
+            graph.set_external_id(a, Some("Camera@A".into())).unwrap(),
+            None
+        );
+        assert_eq!(graph.node_by_external_id("Camera@A"), Some(a));
+        assert!(matches!(
+            graph.set_external_id(b, Some("Camera@A".into())),
+            Err(EguiGraphError::DuplicateExternalId(owner, _)) if owner == a
+        ));
+        assert_eq!(graph[b].external_id, None);
+
+        // Changing the id frees the old one
+        let previous = graph.set_external_id(a, Some("Camera@B".into())).unwrap();
+        assert_eq!(previous.as_deref(), Some("Camera@A"));
+        assert_eq!(graph.node_by_external_id("Camera@A"), None);
+        graph.set_external_id(b, Some("Camera@A".into())).unwrap();
+        assert_eq!(graph.node_by_external_id("Camera@A"), Some(b));
+
+        // Copies don't take the id
+        let copy = graph.duplicate_node(b, &mut |value| *value);
+        assert_eq!(graph[copy].external_id, None);
+
+        graph.remove_node(b);
+        assert_eq!(graph.node_by_external_id("Camera@A"), None);
+
+        graph.external_ids.clear();
+        assert_eq!(graph.node_by_external_id("Camera@B"), None);
+        graph.rebuild_external_ids();
+        assert_eq!(graph.node_by_external_id("Camera@B"), Some(a));
+    }
+}
@@ -0,0 +1,241 @@
+use std::collections::{HashMap, HashSet};
+
+use super::*;
+
+#[cfg(feature = "persistence")]
+use serde::{Deserialize, Serialize};
+
+/// The space between a group frame and its nodes, and the height of the title
+/// bar of the frame, in graph units.
+pub(crate) const GROUP_PADDING: f32 = 20.0;
+pub(crate) const GROUP_TITLE_HEIGHT: f32 = 28.0;
+
+/// A titled frame drawn around some nodes, its members. The frame fits its
+/// members, and dragging its title bar moves them all. A node dragged out of
+/// the frame leaves the group. See [`GraphEditorState::groups`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub struct Group {
+    pub title: String,
+}
+
+impl<NodeData, DataType, ValueType, NodeTemplate, UserState, ConnectionData>
+    GraphEditorState<NodeData, DataType, ValueType, NodeTemplate, UserState, ConnectionData>
+{
+    /// Wraps `nodes` in a new group frame. Nodes already in a group leave it,
+    /// and ids of nodes that don't exist are ignored.
+    ///
+    /// Returns the new group, or `None` if none of the nodes exist.
+    pub fn group_nodes(
+        &mut self,
+        nodes: impl IntoIterator<Item = NodeId>,
+        title: String,
+    ) -> Option<GroupId> {
+        let nodes: Vec<NodeId> = nodes
+            .into_iter()
+            .filter(|node| self.graph.nodes.contains_key(*node))
+            .collect();
+        if nodes.is_empty() {
+            return None;
+        }
+        let group = self.groups.insert(Group { title });
+        for node in nodes {
+            self.node_groups.insert(node, group);
+        }
+        self.prune_groups();
+        Some(group)
+    }
+
+    /// Removes a group frame, leaving its members where they are. Returns the
+    /// former members.
+    pub fn remove_group(&mut self, group: GroupId) -> Vec<NodeId> {
+        let members = self.group_members(group);
+        for node in &members {
+            self.node_groups.remove(*node);
+        }
+        self.groups.remove(group);
+        members
+    }
+
+    /// The members of a group, in drawing order.
+    pub fn group_members(&self, group: GroupId) -> Vec<NodeId> {
+        self.node_order
+            .iter()
+            .copied()
+            .filter(|node| self.node_groups.get(*node) == Some(&group))
+            .collect()
+    }
+
+    /// The frame of a group in graph space, title bar included. Members that
+    /// were never drawn have their size estimated. `None` for groups without
+    /// members.
+    pub fn group_rect(&self, group: GroupId) -> Option<egui::Rect> {
+        self.frame_around(
+            self.group_members(group)
+                .into_iter()
+                .filter_map(|node| self.node_graph_rect(node)),
+        )
+    }
+
+    /// The frame of a group whose members have the given rects.
+    fn frame_around(&self, rects: impl Iterator<Item = egui::Rect>) -> Option<egui::Rect> {
+        let bounds = rects.reduce(|bounds, rect| bounds.union(rect))?;
+        let mut frame = bounds.expand(GROUP_PADDING);
+        frame.min.y -= GROUP_TITLE_HEIGHT;
+        Some(frame)
+    }
+
+    /// Removes the nodes in `moved` from their group if they were dragged out
+    /// of its frame, i.e. no longer overlap the frame as it was before the
+    /// drag. `moved` maps the nodes to their position before the drag. Nodes
+    /// moved along with all the other members stay in their group.
+    pub(crate) fn leave_groups(&mut self, moved: &HashMap<NodeId, egui::Pos2>) {
+        let mut leaving = Vec::new();
+        for node in moved.keys() {
+            let group = match self.node_groups.get(*node) {
+                Some(group) => *group,
+                None => continue,
+            };
+            let members = self.group_members(group);
+            if members.iter().all(|member| moved.contains_key(member)) {
+                continue;
+            }
+            let before_drag = members.iter().filter_map(|member| {
+                let rect = self.node_graph_rect(*member)?;
+                Some(match moved.get(member) {
+                    Some(start) => rect.translate(*start - rect.min),
+                    None => rect,
+                })
+            });
+            let frame = self.frame_around(before_drag);
+            let rect = self.node_graph_rect(*node);
+            if let (Some(frame), Some(rect)) = (frame, rect) {
+                if !frame.intersects(rect) {
+                    leaving.push(*node);
+                }
+            }
+        }
+        for node in leaving {
+            self.node_groups.remove(node);
+        }
+        self.prune_groups();
+    }
+
+    /// Drops the memberships of removed nodes, and the groups left without
+    /// members.
+    pub(crate) fn prune_groups(&mut self) {
+        if self.groups.is_empty() && self.node_groups.is_empty() {
+            return;
+        }
+        let (graph, groups) = (&self.graph, &self.groups);
+        self.node_groups
+            .retain(|node, group| graph.nodes.contains_key(node) && groups.contains_key(*group));
+        let used: HashSet<GroupId> = self.node_groups.values().copied().collect();
+        self.groups.retain(|group, _| used.contains(&group));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    fn drag(state: &mut TestEditorState, ctx: &egui::Context, from: egui::Pos2, delta: egui::Vec2) {
+        let button = |pos, pressed| egui::Event::PointerButton {
+            pos,
+            button: egui::PointerButton::Primary,
+            pressed,
+            modifiers: egui::Modifiers::NONE,
+        };
+        for events in [
+            vec![egui::Event::PointerMoved(from)],
+            vec![button(from, true)],
+            vec![egui::Event::PointerMoved(from + delta)],
+            vec![button(from + delta, false)],
+        ] {
+            let input = egui::RawInput {
+                events,
+                ..Default::default()
+            };
+            draw_frame_with_input(state, ctx, input);
+        }
+    }
+
+    #[test]
+    pub fn test_group_membership() {
+        let mut state = TestEditorState::new(1.0);
+        let [a, b, c] = [0.0, 300.0, 600.0]
+            .map(|x| state.add_node(&TestTemplate::Scalar, egui::pos2(x, 50.0), &mut ()));
+        let first = state.group_nodes([a, b], "First".into()).unwrap();
+        assert_eq!(state.group_members(first), vec![a, b]);
+        let frame = state.group_rect(first).unwrap();
+        assert!(frame.min.y < 50.0 - GROUP_TITLE_HEIGHT);
+        assert!(frame.max.x > 300.0);
+
+        // Nodes move from group to group, emptied groups are dropped
+        let second = state.group_nodes([b, c], "Second".into()).unwrap();
+        assert_eq!(state.group_members(first), vec![a]);
+        state.group_nodes([a], "Third".into());
+        assert!(!state.groups.contains_key(first));
+        assert!(state.group_rect(first).is_none());
+
+        state.remove_node(c);
+        assert_eq!(state.group_members(second), vec![b]);
+        assert_eq!(state.remove_group(second), vec![b]);
+        assert!(state.node_groups.get(b).is_none());
+        assert_eq!(state.groups.len(), 1);
+    }
+
+    #[test]
+    pub fn test_drag_group_and_members() {
+        let ctx = egui::Context::default();
+        let mut state = TestEditorState::new(1.0);
+        let [a, b, c] = [0.0, 300.0, 600.0]
+            .map(|x| state.add_node(&TestTemplate::Scalar, egui::pos2(x, 50.0), &mut ()));
+        // Ctrl + G groups the selected nodes
+        hover_editor(&mut state, &ctx);
+        state.selected_nodes = vec![a, b];
+        let input = egui::RawInput {
+            events: vec![egui::Event::Key {
+                key: egui::Key::G,
+                pressed: true,
+                repeat: false,
+                modifiers: egui::Modifiers::COMMAND,
+            }],
+            modifiers: egui::Modifiers::COMMAND,
+            ..Default::default()
+        };
+        draw_frame_with_input(&mut state, &ctx, input);
+        let group = state.node_groups[a];
+        assert_eq!(state.groups[group].title, "Group");
+        assert_eq!(state.group_members(group), vec![a, b]);
+        state.selected_nodes.clear();
+        let title = state.group_rect(group).unwrap().left_top() + egui::vec2(100.0, 10.0);
+
+        // Dragging the title bar moves the members only
+        drag(&mut state, &ctx, title, egui::vec2(40.0, 60.0));
+        assert_eq!(state.node_positions[a], egui::pos2(40.0, 110.0));
+        assert_eq!(state.node_positions[b], egui::pos2(340.0, 110.0));
+        assert_eq!(state.node_positions[c], egui::pos2(600.0, 50.0));
+
+        // A member dragged within the frame stays, out of it leaves
+        let body =
+            |state: &TestEditorState, node| state.node_positions[node] + egui::vec2(60.0, 12.0);
+        let from = body(&state, b);
+        drag(&mut state, &ctx, from, egui::vec2(0.0, 40.0));
+        assert_eq!(state.group_members(group), vec![a, b]);
+        let from = body(&state, b);
+        drag(&mut state, &ctx, from, egui::vec2(0.0, 400.0));
+        assert_eq!(state.group_members(group), vec![a]);
+
+        // Members dragged together stay together
+        state.group_nodes([a, b], "Group".into());
+        state.selected_nodes = vec![a, b];
+        let group = state.node_groups[a];
+        let from = body(&state, a);
+        drag(&mut state, &ctx, from, egui::vec2(0.0, 600.0));
+        assert_eq!(state.node_positions[b].y, 1150.0);
+        // The dragged node was brought to the front
+        assert_eq!(state.group_members(group), vec![b, a]);
+    }
+}
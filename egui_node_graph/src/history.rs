@@ -0,0 +1,529 @@
+use super::*;
+use std::collections::{HashMap, HashSet};
+
+/// The default value of [`History::depth`].
+pub const DEFAULT_HISTORY_DEPTH: usize = 100;
+
+/// The contents of the editor that undo and redo restore.
+#[derive(Clone)]
+pub struct Snapshot<NodeData, DataType, ValueType, ConnectionData = ()> {
+    pub graph: Graph<NodeData, DataType, ValueType>,
+    pub node_positions: SecondaryMap<NodeId, egui::Pos2>,
+    pub node_order: Vec<NodeId>,
+    pub connection_data: HashMap<(InputId, OutputId), ConnectionData>,
+    pub reroutes: HashMap<(InputId, OutputId), Vec<egui::Pos2>>,
+    pub groups: SlotMap<GroupId, Group>,
+    pub node_groups: SecondaryMap<NodeId, GroupId>,
+    pub collapsed_nodes: HashSet<NodeId>,
+    pub collapsed_port_groups: HashSet<(NodeId, String)>,
+}
+
+/// The undo / redo history of a [`GraphEditorState`]. Filled by
+/// [`GraphEditorState::record_history`].
+#[derive(Clone)]
+pub struct History<NodeData, DataType, ValueType, ConnectionData = ()> {
+    /// The maximum number of changes that can be undone.
+    pub depth: usize,
+    /// The state of the editor when the last change was recorded.
+    current: Option<Snapshot<NodeData, DataType, ValueType, ConnectionData>>,
+    undo_stack: Vec<Snapshot<NodeData, DataType, ValueType, ConnectionData>>,
+    redo_stack: Vec<Snapshot<NodeData, DataType, ValueType, ConnectionData>>,
+}
+
+impl<NodeData, DataType, ValueType, ConnectionData> Default
+    for History<NodeData, DataType, ValueType, ConnectionData>
+{
+    fn default() -> Self {
+        Self {
+            depth: DEFAULT_HISTORY_DEPTH,
+            current: None,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+        }
+    }
+}
+
+impl<NodeData, DataType, ValueType, ConnectionData>
+    History<NodeData, DataType, ValueType, ConnectionData>
+{
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Forgets all the recorded changes.
+    pub fn clear(&mut self) {
+        self.current = None;
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+}
+
+/// Whether two snapshots differ in something the user can edit through the
+/// editor: nodes, labels, params, connections and their data, input values,
+/// positions, reroute points, groups and collapsed nodes. The user data of
+/// the nodes is not compared.
+fn same_contents<NodeData, DataType, ValueType: PartialEq, ConnectionData: PartialEq>(
+    a: &Snapshot<NodeData, DataType, ValueType, ConnectionData>,
+    b: &Snapshot<NodeData, DataType, ValueType, ConnectionData>,
+) -> bool {
+    let same_nodes = a.graph.nodes.len() == b.graph.nodes.len()
+        && a.graph.nodes.iter().all(|(id, node)| {
+            matches!(b.graph.nodes.get(id), Some(other)
+                if node.label == other.label
+                    && node.inputs == other.inputs
+                    && node.outputs == other.outputs)
+        });
+    let same_values = a.graph.inputs.len() == b.graph.inputs.len()
+        && a.graph.inputs.iter().all(|(id, input)| {
+            matches!(b.graph.inputs.get(id), Some(other) if input.value == other.value)
+        });
+    let same_connections = a.graph.connections.len() == b.graph.connections.len()
+        && a.graph
+            .connections
+            .iter()
+            .all(|(input, outputs)| b.graph.connections(input) == outputs.as_slice());
+    let same_positions = a.node_positions.len() == b.node_positions.len()
+        && a.node_positions
+            .iter()
+            .all(|(id, pos)| b.node_positions.get(id) == Some(pos));
+    let same_node_groups = a.node_groups.len() == b.node_groups.len()
+        && a.node_groups
+            .iter()
+            .all(|(id, group)| b.node_groups.get(id) == Some(group));
+    let same_groups = a.groups.len() == b.groups.len()
+        && a.groups
+            .iter()
+            .all(|(id, group)| b.groups.get(id) == Some(group));
+    let same_layout = a.reroutes == b.reroutes
+        && same_node_groups
+        && same_groups
+        && a.collapsed_nodes == b.collapsed_nodes
+        && a.collapsed_port_groups == b.collapsed_port_groups;
+    same_nodes
+        && same_values
+        && same_connections
+        && same_positions
+        && a.connection_data == b.connection_data
+        && same_layout
+}
+
+/// The events turning `from` into `to`, recorded when undo or redo replace
+/// the graph, see [`Graph::record_events`]. The removals come first.
+fn restore_events<NodeData, DataType, ValueType: PartialEq>(
+    from: &Graph<NodeData, DataType, ValueType>,
+    to: &Graph<NodeData, DataType, ValueType>,
+) -> Vec<GraphEvent> {
+    let mut events = Vec::new();
+    for (input, output) in from.iter_connections() {
+        if !to.connections(input).contains(&output) {
+            events.push(GraphEvent::ConnectionRemoved { input, output });
+        }
+    }
+    for (input, param) in from.inputs.iter() {
+        if !to.inputs.contains_key(input) {
+            events.push(GraphEvent::ParamRemoved {
+                node: param.node,
+                param: AnyParameterId::Input(input),
+            });
+        }
+    }
+    for (output, param) in from.outputs.iter() {
+        if !to.outputs.contains_key(output) {
+            events.push(GraphEvent::ParamRemoved {
+                node: param.node,
+                param: AnyParameterId::Output(output),
+            });
+        }
+    }
+    events.extend(
+        from.iter_nodes()
+            .filter(|node| !to.nodes.contains_key(*node))
+            .map(GraphEvent::NodeRemoved),
+    );
+    events.extend(
+        to.iter_nodes()
+            .filter(|node| !from.nodes.contains_key(*node))
+            .map(GraphEvent::NodeAdded),
+    );
+    for (input, param) in to.inputs.iter() {
+        match from.inputs.get(input) {
+            None => events.push(GraphEvent::ParamAdded {
+                node: param.node,
+                param: AnyParameterId::Input(input),
+            }),
+            Some(before) if before.value != param.value => events.push(GraphEvent::ValueChanged {
+                node: param.node,
+                input,
+            }),
+            Some(_) => {}
+        }
+    }
+    for (output, param) in to.outputs.iter() {
+        if !from.outputs.contains_key(output) {
+            events.push(GraphEvent::ParamAdded {
+                node: param.node,
+                param: AnyParameterId::Output(output),
+            });
+        }
+    }
+    for (input, output) in to.iter_connections() {
+        if !from.connections(input).contains(&output) {
+            events.push(GraphEvent::ConnectionAdded { input, output });
+        }
+    }
+    events
+}
+
+impl<NodeData, DataType, ValueType, NodeTemplate, UserState, ConnectionData>
+    GraphEditorState<NodeData, DataType, ValueType, NodeTemplate, UserState, ConnectionData>
+where
+    NodeData: Clone,
+    DataType: Clone,
+    ValueType: Clone + PartialEq,
+    ConnectionData: Clone + PartialEq,
+{
+    fn snapshot(&self) -> Snapshot<NodeData, DataType, ValueType, ConnectionData> {
+        let mut graph = self.graph.clone();
+        // The recorded events belong to the live graph only
+        graph.events = None;
+        Snapshot {
+            graph,
+            node_positions: self.node_positions.clone(),
+            node_order: self.node_order.clone(),
+            connection_data: self.connection_data.clone(),
+            reroutes: self.reroutes.clone(),
+            groups: self.groups.clone(),
+            node_groups: self.node_groups.clone(),
+            collapsed_nodes: self.collapsed_nodes.clone(),
+            collapsed_port_groups: self.collapsed_port_groups.clone(),
+        }
+    }
+
+    /// Records the changes done since the last call in the undo history. Call
+    /// this once per frame, after [`GraphEditorState::draw_graph_editor`].
+    ///
+    /// Nothing is recorded while the pointer is held down or a text field has
+    /// the keyboard focus, so a whole gesture (dragging a node, a slider,
+    /// typing a value...) is undone at once.
+    pub fn record_history(&mut self, ctx: &egui::Context) {
+        if ctx.input(|i| i.pointer.any_down()) || ctx.wants_keyboard_input() {
+            return;
+        }
+        let snapshot = self.snapshot();
+        match self.history.current.take() {
+            Some(current) if !same_contents(&current, &snapshot) => {
+                self.history.undo_stack.push(current);
+                if self.history.undo_stack.len() > self.history.depth {
+                    let excess = self.history.undo_stack.len() - self.history.depth;
+                    self.history.undo_stack.drain(..excess);
+                }
+                self.history.redo_stack.clear();
+            }
+            _ => {}
+        }
+        self.history.current = Some(snapshot);
+    }
+
+    /// Reverts the last recorded change. Returns a [`NodeResponse::Undone`]
+    /// listing the nodes that came back or disappeared, or `None` if there was
+    /// nothing to undo. The response can be passed to the `prepend_responses`
+    /// of the next [`GraphEditorState::draw_graph_editor`] call.
+    pub fn undo<UserResponse>(&mut self) -> Option<NodeResponse<UserResponse, NodeData>>
+    where
+        UserResponse: UserResponseTrait,
+        NodeData: NodeDataTrait,
+    {
+        let previous = self.history.undo_stack.pop()?;
+        let current = self
+            .history
+            .current
+            .take()
+            .unwrap_or_else(|| self.snapshot());
+        self.history.redo_stack.push(current);
+        let (restored_nodes, removed_nodes) = self.restore(previous);
+        Some(NodeResponse::Undone {
+            restored_nodes,
+            removed_nodes,
+        })
+    }
+
+    /// Applies the last undone change again. Returns a
+    /// [`NodeResponse::Redone`], or `None` if there was nothing to redo. See
+    /// [`GraphEditorState::undo`].
+    pub fn redo<UserResponse>(&mut self) -> Option<NodeResponse<UserResponse, NodeData>>
+    where
+        UserResponse: UserResponseTrait,
+        NodeData: NodeDataTrait,
+    {
+        let next = self.history.redo_stack.pop()?;
+        let current = self
+            .history
+            .current
+            .take()
+            .unwrap_or_else(|| self.snapshot());
+        self.history.undo_stack.push(current);
+        let (restored_nodes, removed_nodes) = self.restore(next);
+        Some(NodeResponse::Redone {
+            restored_nodes,
+            removed_nodes,
+        })
+    }
+
+    /// Replaces the contents of the editor with `snapshot`. Returns the nodes
+    /// that were added and removed by doing so.
+    fn restore(
+        &mut self,
+        snapshot: Snapshot<NodeData, DataType, ValueType, ConnectionData>,
+    ) -> (Vec<NodeId>, Vec<NodeId>) {
+        let restored_nodes = snapshot
+            .graph
+            .iter_nodes()
+            .filter(|id| !self.graph.nodes.contains_key(*id))
+            .collect();
+        let removed_nodes = self
+            .graph
+            .iter_nodes()
+            .filter(|id| !snapshot.graph.nodes.contains_key(*id))
+            .collect();
+
+        let mut events = self.graph.events.take();
+        if let Some(events) = &mut events {
+            events.extend(restore_events(&self.graph, &snapshot.graph));
+        }
+        self.graph = snapshot.graph.clone();
+        self.graph.events = events;
+        self.node_positions = snapshot.node_positions.clone();
+        self.node_order = snapshot.node_order.clone();
+        self.connection_data = snapshot.connection_data.clone();
+        self.reroutes = snapshot.reroutes.clone();
+        self.groups = snapshot.groups.clone();
+        self.node_groups = snapshot.node_groups.clone();
+        self.collapsed_nodes = snapshot.collapsed_nodes.clone();
+        self.collapsed_port_groups = snapshot.collapsed_port_groups.clone();
+        self.history.current = Some(snapshot);
+        self.connection_in_progress = None;
+        // The selection, the node being renamed and the highlighted
+        // connections aren't part of the snapshot, and may refer to nodes and
+        // connections that the restored graph doesn't have.
+        self.sync_with_graph();
+
+        (restored_nodes, removed_nodes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    fn record(state: &mut TestEditorState) {
+        state.record_history(&egui::Context::default());
+    }
+
+    #[test]
+    pub fn test_undo_redo_nodes_and_connections() {
+        let mut state = TestEditorState::new(1.0);
+        record(&mut state);
+        let a = state.add_node(&TestTemplate::Scalar, egui::Pos2::ZERO, &mut ());
+        let b = state.add_node(&TestTemplate::Scalar, egui::Pos2::ZERO, &mut ());
+        record(&mut state);
+        let output = state.graph[a].get_output("out").unwrap();
+        let input = state.graph[b].get_input("in").unwrap();
+        state.graph.add_connection(output, input);
+        record(&mut state);
+        state.remove_node(b);
+        record(&mut state);
+        assert!(state.history.can_undo());
+
+        // Node deletion comes back with its connection, and the same id
+        let response = state.undo::<TestResponse>().unwrap();
+        assert!(matches!(
+            response,
+            NodeResponse::Undone { restored_nodes, removed_nodes }
+                if restored_nodes == [b] && removed_nodes.is_empty()
+        ));
+        assert_eq!(state.graph.connection(input), Some(output));
+        assert_eq!(state.graph.output_connection_count(output), 1);
+
+        state.undo::<TestResponse>().unwrap();
+        assert!(state.graph.iter_connections().next().is_none());
+        let response = state.undo::<TestResponse>().unwrap();
+        assert!(matches!(
+            response,
+            NodeResponse::Undone { removed_nodes, .. } if removed_nodes.len() == 2
+        ));
+        assert!(state.graph.nodes.is_empty());
+        assert!(state.undo::<TestResponse>().is_none());
+
+        let response = state.redo::<TestResponse>().unwrap();
+        assert!(matches!(
+            response,
+            NodeResponse::Redone { restored_nodes, .. } if restored_nodes.len() == 2
+        ));
+        assert_eq!(state.node_order, [a, b]);
+
+        // A new change drops the redo history
+        state.add_node(&TestTemplate::Vector, egui::Pos2::ZERO, &mut ());
+        record(&mut state);
+        assert!(!state.history.can_redo());
+    }
+
+    #[test]
+    pub fn test_history_depth() {
+        let mut state = TestEditorState::new(1.0);
+        state.history.depth = 2;
+        record(&mut state);
+        for _ in 0..5 {
+            state.add_node(&TestTemplate::Scalar, egui::Pos2::ZERO, &mut ());
+            record(&mut state);
+        }
+        // Recording without changes doesn't add entries
+        record(&mut state);
+
+        assert!(state.undo::<TestResponse>().is_some());
+        assert!(state.undo::<TestResponse>().is_some());
+        assert!(state.undo::<TestResponse>().is_none());
+        assert_eq!(state.graph.nodes.len(), 3);
+    }
+
+    #[test]
+    pub fn test_node_drag_is_undone_at_once() {
+        let ctx = egui::Context::default();
+        let mut state = TestEditorState::new(1.0);
+        let node = state.add_node(&TestTemplate::Scalar, egui::pos2(100.0, 100.0), &mut ());
+        let input = state.graph[node].get_input("in").unwrap();
+        let ports = draw_frame(&mut state, &ctx).port_locations;
+        state.record_history(&ctx);
+
+        // Press on the node body, then move the pointer over several frames
+        let start = ports[&AnyParameterId::Input(input)] + egui::vec2(20.0, 0.0);
+        let mut events = vec![
+            vec![egui::Event::PointerMoved(start)],
+            vec![egui::Event::PointerButton {
+                pos: start,
+                button: egui::PointerButton::Primary,
+                pressed: true,
+                modifiers: egui::Modifiers::NONE,
+            }],
+        ];
+        for step in 1..=5 {
+            let pos = start + egui::vec2(10.0 * step as f32, 0.0);
+            events.push(vec![egui::Event::PointerMoved(pos)]);
+        }
+        events.push(vec![egui::Event::PointerButton {
+            pos: start + egui::vec2(50.0, 0.0),
+            button: egui::PointerButton::Primary,
+            pressed: false,
+            modifiers: egui::Modifiers::NONE,
+        }]);
+        for events in events {
+            let input = egui::RawInput {
+                events,
+                ..Default::default()
+            };
+            draw_frame_with_input(&mut state, &ctx, input);
+            state.record_history(&ctx);
+        }
+        assert_eq!(state.node_positions[node], egui::pos2(150.0, 100.0));
+
+        state.undo::<TestResponse>().unwrap();
+        assert_eq!(state.node_positions[node], egui::pos2(100.0, 100.0));
+        assert!(!state.history.can_undo());
+    }
+
+    #[test]
+    pub fn test_undo_records_graph_events() {
+        let mut state = TestEditorState::new(1.0);
+        let a = state.add_node(&TestTemplate::Scalar, egui::Pos2::ZERO, &mut ());
+        let a_in = state.graph[a].get_input("in").unwrap();
+        record(&mut state);
+        let b = state.add_node(&TestTemplate::Scalar, egui::Pos2::ZERO, &mut ());
+        let output = state.graph[a].get_output("out").unwrap();
+        let input = state.graph[b].get_input("in").unwrap();
+        let b_out = state.graph[b].get_output("out").unwrap();
+        state.graph.add_connection(output, input);
+        state.graph[a_in].value = TestValue(3);
+        record(&mut state);
+
+        // Undo removes `b` and reverts the value
+        state.graph.record_events(true);
+        state.undo::<TestResponse>().unwrap();
+        assert_eq!(
+            state.graph.drain_events(),
+            [
+                GraphEvent::ConnectionRemoved { input, output },
+                GraphEvent::ParamRemoved {
+                    node: b,
+                    param: AnyParameterId::Input(input)
+                },
+                GraphEvent::ParamRemoved {
+                    node: b,
+                    param: AnyParameterId::Output(b_out)
+                },
+                GraphEvent::NodeRemoved(b),
+                GraphEvent::ValueChanged {
+                    node: a,
+                    input: a_in
+                },
+            ]
+        );
+
+        state.redo::<TestResponse>().unwrap();
+        let events = state.graph.drain_events();
+        assert_eq!(events[0], GraphEvent::NodeAdded(b));
+        assert_eq!(
+            events.last(),
+            Some(&GraphEvent::ConnectionAdded { input, output })
+        );
+        assert_eq!(events.len(), 5);
+    }
+
+    #[test]
+    pub fn test_undo_restores_editor_layout() {
+        let mut state = TestEditorState::new(1.0);
+        let a = state.add_node(&TestTemplate::Scalar, egui::Pos2::ZERO, &mut ());
+        let b = state.add_node(&TestTemplate::Scalar, egui::pos2(300.0, 0.0), &mut ());
+        let output = state.graph[a].get_output("out").unwrap();
+        let input = state.graph[b].get_input("in").unwrap();
+        state.graph.add_connection(output, input);
+        record(&mut state);
+
+        // Editing the layout alone is a change of its own
+        let group = state.group_nodes([b], "Sink".into()).unwrap();
+        state.collapsed_nodes.insert(b);
+        let reroute = vec![egui::pos2(150.0, 100.0)];
+        state.reroutes.insert((input, output), reroute.clone());
+        state
+            .connection_data
+            .insert((input, output), TestLink("rgb".into()));
+        record(&mut state);
+        assert!(state.history.can_undo());
+
+        // The only member of its group, which goes away with it
+        state.remove_node(b);
+        state.sync_with_graph();
+        assert!(state.groups.is_empty());
+        record(&mut state);
+
+        state.undo::<TestResponse>().unwrap();
+        assert_eq!(state.node_groups.get(b), Some(&group));
+        assert_eq!(state.groups[group].title, "Sink");
+        assert!(state.collapsed_nodes.contains(&b));
+        assert_eq!(state.reroutes[&(input, output)], reroute);
+        assert_eq!(
+            state.connection_data[&(input, output)],
+            TestLink("rgb".into())
+        );
+
+        state.undo::<TestResponse>().unwrap();
+        assert!(state.groups.is_empty());
+        assert!(state.collapsed_nodes.is_empty());
+        assert!(state.reroutes.is_empty());
+        assert!(state.connection_data.is_empty());
+        assert_eq!(state.graph.connection(input), Some(output));
+    }
+}
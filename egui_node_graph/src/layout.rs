@@ -0,0 +1,429 @@
+use std::collections::HashMap;
+
+use super::*;
+
+/// Settings of [`GraphEditorState::auto_layout`]. Node sizes are not known
+/// outside of drawing, so they are estimated from their number of params.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LayoutSettings {
+    /// The horizontal distance between the left sides of two consecutive
+    /// layers.
+    pub layer_spacing: f32,
+    /// The vertical gap between two nodes of the same layer.
+    pub node_spacing: f32,
+    /// The estimated height of a node without params, or of a collapsed
+    /// node.
+    pub title_height: f32,
+    /// The estimated height added by each input and output of a node.
+    pub row_height: f32,
+    /// How many times the layers are swept, up and down, to reduce the
+    /// number of crossing connections.
+    pub sweeps: usize,
+}
+
+impl Default for LayoutSettings {
+    fn default() -> Self {
+        Self {
+            layer_spacing: 280.0,
+            node_spacing: 30.0,
+            title_height: 40.0,
+            row_height: 28.0,
+            sweeps: 4,
+        }
+    }
+}
+
+/// A vertex of the layered layout: either a node, or a dummy vertex standing
+/// for a connection that crosses a layer.
+struct Vertex {
+    node: Option<NodeId>,
+    layer: usize,
+    height: f32,
+    /// The connected vertices in the previous and the next layer.
+    preds: Vec<usize>,
+    succs: Vec<usize>,
+}
+
+impl<NodeData, DataType, ValueType, NodeTemplate, UserState, ConnectionData>
+    GraphEditorState<NodeData, DataType, ValueType, NodeTemplate, UserState, ConnectionData>
+{
+    /// Moves all the nodes so that connections flow from left to right, in a
+    /// layered ("Sugiyama-style") layout:
+    ///
+    /// - Each node is placed in a layer given by the longest path of
+    ///   connections leading to it. Connections closing a cycle are ignored.
+    /// - Connections spanning several layers are routed through dummy
+    ///   vertices, which keep some room for them in the layers they cross.
+    /// - Nodes are reordered inside their layer, towards the average position
+    ///   of their neighbours, to reduce crossings.
+    /// - Nodes are then stacked in each layer, aligned with their inputs
+    ///   when there is room, using their estimated heights.
+    ///
+    /// The top-left corner of the nodes stays where it was.
+    pub fn auto_layout(&mut self, settings: &LayoutSettings) {
+        let nodes: Vec<NodeId> = self
+            .node_order
+            .iter()
+            .copied()
+            .filter(|node| self.graph.nodes.contains_key(*node))
+            .collect();
+        if nodes.is_empty() {
+            return;
+        }
+        let origin = nodes
+            .iter()
+            .filter_map(|node| self.node_positions.get(*node))
+            .fold(egui::pos2(f32::INFINITY, f32::INFINITY), |a, b| a.min(*b));
+        let origin = if origin.x.is_finite() && origin.y.is_finite() {
+            origin
+        } else {
+            egui::Pos2::ZERO
+        };
+
+        let vertices = self.layered_vertices(&nodes, settings);
+        let layers = order_layers(&vertices, settings.sweeps);
+        let tops = assign_tops(&vertices, &layers, settings.node_spacing);
+
+        for (index, vertex) in vertices.iter().enumerate() {
+            if let Some(node) = vertex.node {
+                let x = origin.x + vertex.layer as f32 * settings.layer_spacing;
+                self.node_positions
+                    .insert(node, egui::pos2(x, origin.y + tops[index]));
+            }
+        }
+    }
+
+    /// The height of `node` estimated from its number of params, as it isn't
+    /// known outside of drawing. The params of a collapsed group only take
+    /// the row of its header.
+    pub(crate) fn estimated_height(&self, node: NodeId, settings: &LayoutSettings) -> f32 {
+        let rows = if self.collapsed_nodes.contains(&node) {
+            0
+        } else {
+            let node = &self.graph[node];
+            let params = node
+                .input_ids()
+                .map(AnyParameterId::Input)
+                .chain(node.output_ids().map(AnyParameterId::Output));
+            let mut rows = 0;
+            let mut previous_group = None;
+            for param in params {
+                let group = self.graph.param_group(param);
+                let collapsed = group.is_some_and(|group| {
+                    self.collapsed_port_groups
+                        .contains(&(node.id, group.to_owned()))
+                });
+                if group.is_some() && group != previous_group {
+                    rows += 1;
+                }
+                if !collapsed {
+                    rows += 1;
+                }
+                previous_group = group;
+            }
+            rows
+        };
+        settings.title_height + rows as f32 * settings.row_height
+    }
+
+    /// Builds the vertices of `nodes` in their layers, plus the dummy
+    /// vertices of the connections spanning several layers.
+    fn layered_vertices(&self, nodes: &[NodeId], settings: &LayoutSettings) -> Vec<Vertex> {
+        let index: HashMap<NodeId, usize> =
+            nodes.iter().enumerate().map(|(i, n)| (*n, i)).collect();
+        let mut edges: Vec<(usize, usize)> = self
+            .graph
+            .iter_connections()
+            .filter_map(|(input, output)| {
+                let from = *index.get(&self.graph[output].node)?;
+                let to = *index.get(&self.graph[input].node)?;
+                (from != to).then_some((from, to))
+            })
+            .collect();
+        edges.sort_unstable();
+        edges.dedup();
+        let edges = acyclic_edges(nodes.len(), &edges);
+
+        // Longest path from the nodes without inputs, in topological order
+        let mut indegree = vec![0; nodes.len()];
+        let mut succs = vec![Vec::new(); nodes.len()];
+        for (from, to) in &edges {
+            indegree[*to] += 1;
+            succs[*from].push(*to);
+        }
+        let mut ranks = vec![0; nodes.len()];
+        let mut ready: Vec<usize> = (0..nodes.len()).filter(|i| indegree[*i] == 0).collect();
+        while let Some(vertex) = ready.pop() {
+            for succ in &succs[vertex] {
+                ranks[*succ] = ranks[*succ].max(ranks[vertex] + 1);
+                indegree[*succ] -= 1;
+                if indegree[*succ] == 0 {
+                    ready.push(*succ);
+                }
+            }
+        }
+
+        let mut vertices: Vec<Vertex> = nodes
+            .iter()
+            .zip(&ranks)
+            .map(|(node, rank)| Vertex {
+                node: Some(*node),
+                layer: *rank,
+                height: self.estimated_height(*node, settings),
+                preds: Vec::new(),
+                succs: Vec::new(),
+            })
+            .collect();
+        for (from, to) in edges {
+            let mut previous = from;
+            for layer in ranks[from] + 1..ranks[to] {
+                vertices.push(Vertex {
+                    node: None,
+                    layer,
+                    height: 0.0,
+                    preds: vec![previous],
+                    succs: Vec::new(),
+                });
+                let dummy = vertices.len() - 1;
+                vertices[previous].succs.push(dummy);
+                previous = dummy;
+            }
+            vertices[previous].succs.push(to);
+            vertices[to].preds.push(previous);
+        }
+        vertices
+    }
+}
+
+/// Drops the edges closing a cycle, found as the back edges of a depth-first
+/// search.
+fn acyclic_edges(count: usize, edges: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    let mut succs = vec![Vec::new(); count];
+    for (from, to) in edges {
+        succs[*from].push(*to);
+    }
+    // 0: not visited, 1: on the stack, 2: done
+    let mut state = vec![0u8; count];
+    let mut kept = Vec::with_capacity(edges.len());
+    for start in 0..count {
+        if state[start] != 0 {
+            continue;
+        }
+        state[start] = 1;
+        let mut stack = vec![(start, 0)];
+        while let Some((vertex, next)) = stack.last_mut() {
+            let vertex = *vertex;
+            match succs[vertex].get(*next) {
+                Some(succ) => {
+                    *next += 1;
+                    let succ = *succ;
+                    match state[succ] {
+                        0 => {
+                            kept.push((vertex, succ));
+                            state[succ] = 1;
+                            stack.push((succ, 0));
+                        }
+                        2 => kept.push((vertex, succ)),
+                        _ => { /* Back edge */ }
+                    }
+                }
+                None => {
+                    state[vertex] = 2;
+                    stack.pop();
+                }
+            }
+        }
+    }
+    kept
+}
+
+/// Splits the vertices in layers and orders each layer to reduce crossings,
+/// with barycenter sweeps. Returns the best ordering found.
+fn order_layers(vertices: &[Vertex], sweeps: usize) -> Vec<Vec<usize>> {
+    let layer_count = vertices.iter().map(|v| v.layer + 1).max().unwrap_or(0);
+    let mut layers = vec![Vec::new(); layer_count];
+    for (index, vertex) in vertices.iter().enumerate() {
+        layers[vertex.layer].push(index);
+    }
+
+    let mut position = vec![0.0; vertices.len()];
+    let mut best = layers.clone();
+    let mut best_crossings = crossings(vertices, &layers);
+    for sweep in 0..sweeps * 2 {
+        let down = sweep % 2 == 0;
+        let order: Vec<usize> = if down {
+            (1..layer_count).collect()
+        } else {
+            (0..layer_count.saturating_sub(1)).rev().collect()
+        };
+        for layer in order {
+            let fixed = if down { layer - 1 } else { layer + 1 };
+            for (i, vertex) in layers[fixed].iter().enumerate() {
+                position[*vertex] = i as f32;
+            }
+            // Vertices without neighbours in the fixed layer keep their place
+            let barycenters: HashMap<usize, f32> = layers[layer]
+                .iter()
+                .enumerate()
+                .map(|(i, vertex)| {
+                    let vertex = &vertices[*vertex];
+                    let neighbours = if down { &vertex.preds } else { &vertex.succs };
+                    let barycenter = if neighbours.is_empty() {
+                        i as f32
+                    } else {
+                        neighbours.iter().map(|n| position[*n]).sum::<f32>()
+                            / neighbours.len() as f32
+                    };
+                    (layers[layer][i], barycenter)
+                })
+                .collect();
+            layers[layer].sort_by(|a, b| {
+                barycenters[a]
+                    .partial_cmp(&barycenters[b])
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+        }
+        let count = crossings(vertices, &layers);
+        if count < best_crossings {
+            best_crossings = count;
+            best = layers.clone();
+        }
+    }
+    best
+}
+
+/// The number of crossing connections between consecutive layers.
+fn crossings(vertices: &[Vertex], layers: &[Vec<usize>]) -> usize {
+    let mut position = vec![0; vertices.len()];
+    for layer in layers {
+        for (i, vertex) in layer.iter().enumerate() {
+            position[*vertex] = i;
+        }
+    }
+    let mut count = 0;
+    for layer in layers {
+        let edges: Vec<(usize, usize)> = layer
+            .iter()
+            .flat_map(|from| {
+                let position = &position;
+                vertices[*from]
+                    .succs
+                    .iter()
+                    .map(move |to| (position[*from], position[*to]))
+            })
+            .collect();
+        for (i, (a_from, a_to)) in edges.iter().enumerate() {
+            for (b_from, b_to) in &edges[i + 1..] {
+                if (a_from < b_from && a_to > b_to) || (a_from > b_from && a_to < b_to) {
+                    count += 1;
+                }
+            }
+        }
+    }
+    count
+}
+
+/// The top of each vertex, relative to the topmost one. Vertices are stacked
+/// in the order of their layer, and aligned with the average center of their
+/// predecessors when that doesn't overlap the previous vertex.
+fn assign_tops(vertices: &[Vertex], layers: &[Vec<usize>], spacing: f32) -> Vec<f32> {
+    let mut tops = vec![0.0; vertices.len()];
+    for layer in layers {
+        let mut next_free = f32::NEG_INFINITY;
+        for vertex in layer {
+            let v = &vertices[*vertex];
+            let desired = if v.preds.is_empty() {
+                next_free.max(0.0)
+            } else {
+                let centers: f32 = v
+                    .preds
+                    .iter()
+                    .map(|p| tops[*p] + vertices[*p].height / 2.0)
+                    .sum();
+                centers / v.preds.len() as f32 - v.height / 2.0
+            };
+            let top = desired.max(next_free);
+            tops[*vertex] = top;
+            next_free = top + v.height + spacing;
+        }
+    }
+    let min = tops.iter().copied().fold(f32::INFINITY, f32::min);
+    tops.iter().map(|top| top - min).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    fn connect(state: &mut TestEditorState, from: NodeId, to: NodeId) {
+        let output = state.graph[from].get_output("out").unwrap();
+        let input = state.graph[to].get_input("in").unwrap();
+        state.graph.add_connection(output, input);
+    }
+
+    #[test]
+    pub fn test_layers_follow_connections() {
+        let mut state = TestEditorState::new(1.0);
+        let [a, b, c, d] =
+            [(); 4].map(|_| state.add_node(&TestTemplate::Scalar, egui::pos2(50.0, 50.0), &mut ()));
+        // `d` is alone, `a` reaches `c` directly and through `b`
+        connect(&mut state, a, b);
+        connect(&mut state, b, c);
+        state.graph.add_input_param(
+            c,
+            "extra".into(),
+            TestDataType::Scalar,
+            TestValue::default(),
+            InputParamKind::ConnectionOrConstant,
+            true,
+        );
+        let extra = state.graph[c].get_input("extra").unwrap();
+        state
+            .graph
+            .add_connection(state.graph[a].get_output("out").unwrap(), extra);
+
+        let settings = LayoutSettings::default();
+        state.auto_layout(&settings);
+
+        let pos = |node| state.node_positions[node];
+        assert_eq!(pos(a).x, 50.0);
+        assert_eq!(pos(b).x, 50.0 + settings.layer_spacing);
+        assert_eq!(pos(c).x, 50.0 + 2.0 * settings.layer_spacing);
+        assert_eq!(pos(d).x, 50.0);
+        // Nodes of the same layer don't overlap
+        assert!((pos(a).y - pos(d).y).abs() >= settings.title_height + 2.0 * settings.row_height);
+        let top = [a, b, c, d]
+            .iter()
+            .map(|n| pos(*n).y)
+            .fold(f32::INFINITY, f32::min);
+        assert_eq!(top, 50.0);
+    }
+
+    #[test]
+    pub fn test_crossings_are_reduced() {
+        let mut state = TestEditorState::new(1.0);
+        let [s1, s2, t1, t2] =
+            [(); 4].map(|_| state.add_node(&TestTemplate::Scalar, egui::Pos2::ZERO, &mut ()));
+        connect(&mut state, s1, t2);
+        connect(&mut state, s2, t1);
+
+        state.auto_layout(&LayoutSettings::default());
+
+        let y = |node| state.node_positions[node].y;
+        assert_eq!(y(s1) < y(s2), y(t2) < y(t1));
+    }
+
+    #[test]
+    pub fn test_cycles_are_laid_out() {
+        let mut state = TestEditorState::new(1.0);
+        let [a, b] =
+            [(); 2].map(|_| state.add_node(&TestTemplate::Scalar, egui::Pos2::ZERO, &mut ()));
+        connect(&mut state, a, b);
+        connect(&mut state, b, a);
+
+        state.auto_layout(&LayoutSettings::default());
+
+        assert_ne!(state.node_positions[a], state.node_positions[b]);
+    }
+}
@@ -32,6 +32,52 @@ pub use ui_state::*;
 pub mod node_finder;
 pub use node_finder::*;
 
+/// The find-node palette, searching the nodes of the graph
+pub mod node_search;
+pub use node_search::*;
+
+/// The user-visible strings rendered by the editor, which can be translated.
+pub mod localization;
+pub use localization::*;
+
+/// The colors and sizes the editor is drawn with.
+pub mod style;
+pub use style::*;
+
+/// The undo / redo history of the editor.
+pub mod history;
+pub use history::*;
+
+/// Copying and pasting groups of nodes.
+pub mod clipboard;
+pub use clipboard::*;
+
+/// Automatic placement of the nodes.
+pub mod layout;
+pub use layout::*;
+
+/// Group frames wrapping several nodes.
+pub mod groups;
+pub use groups::*;
+
+/// Merging an external description of the graph into the editor.
+pub mod sync;
+pub use sync::*;
+
+/// Exporting the graph as GraphViz and Mermaid diagrams.
+pub mod diagram;
+pub use diagram::*;
+
+/// Versioned serialization of the editor state, with migrations.
+#[cfg(feature = "persistence")]
+pub mod persistence;
+#[cfg(feature = "persistence")]
+pub use persistence::*;
+
+/// Optional measurements of the time spent drawing the editor.
+pub mod timings;
+pub use timings::*;
+
 /// The inner details of the egui implementation. Most egui code lives here.
 pub mod editor_ui;
 pub use editor_ui::*;
@@ -43,4 +89,11 @@ pub use traits::*;
 
 mod utils;
 
+/// Tessellating the connections into a single mesh, reused across frames.
+mod connection_mesh;
+pub(crate) use connection_mesh::*;
+
 mod color_hex_utils;
+
+#[cfg(test)]
+mod test_utils;
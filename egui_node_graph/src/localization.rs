@@ -0,0 +1,134 @@
+use std::borrow::Cow;
+
+/// The user-visible strings rendered by the editor itself. Replace the
+/// [`GraphEditorState::localization`] field to translate the editor.
+///
+/// Strings coming from the user's trait implementations (template labels, data
+/// type names...) are not covered here, since those are already under the
+/// control of user code.
+#[derive(Clone, Debug)]
+pub struct Localization {
+    /// Tooltip shown when hovering the button that deletes a node.
+    pub close_node: Cow<'static, str>,
+    /// Tooltip shown when hovering the toggle that collapses a node to its
+    /// title bar.
+    pub collapse_node: Cow<'static, str>,
+    /// Tooltip shown when hovering the toggle of a collapsed node.
+    pub expand_node: Cow<'static, str>,
+    /// Entries of the context menu of nodes.
+    pub delete_node: Cow<'static, str>,
+    pub duplicate_node: Cow<'static, str>,
+    pub rename_node: Cow<'static, str>,
+    pub disconnect_node: Cow<'static, str>,
+    pub extract_node: Cow<'static, str>,
+    pub bring_node_to_front: Cow<'static, str>,
+    pub select_connected: Cow<'static, str>,
+    pub select_upstream: Cow<'static, str>,
+    pub select_downstream: Cow<'static, str>,
+    /// Entries of the context menu of connections.
+    pub remove_connection: Cow<'static, str>,
+    pub insert_node: Cow<'static, str>,
+    /// Tooltip of the button adding an input to a node, see
+    /// [`NodeDataTrait::can_add_input`].
+    pub add_input: Cow<'static, str>,
+    /// Tooltip of the button removing an input from a node, see
+    /// [`NodeDataTrait::can_remove_input`].
+    pub remove_input: Cow<'static, str>,
+    /// Title of the groups created with <kbd>Ctrl</kbd> + <kbd>G</kbd>.
+    pub new_group: Cow<'static, str>,
+    /// Entries of the context menu of group frames.
+    pub remove_group: Cow<'static, str>,
+    pub delete_group_and_nodes: Cow<'static, str>,
+    /// Shown in the tooltip of ports, before the list of the params they are
+    /// connected to.
+    pub connected_to: Cow<'static, str>,
+    /// Shown in the tooltip of ports without connections.
+    pub not_connected: Cow<'static, str>,
+    /// Shown in the tooltip of inputs, for each [`InputParamKind`].
+    pub connection_only: Cow<'static, str>,
+    pub constant_only: Cow<'static, str>,
+    pub connection_or_constant: Cow<'static, str>,
+    /// Shown after [`Localization::close_node`], followed by the number of
+    /// connections deleting the node would remove.
+    pub connections_to_remove: Cow<'static, str>,
+    /// Question shown before deleting selected nodes with the keyboard, when
+    /// that would remove connections.
+    pub delete_nodes_prompt: Cow<'static, str>,
+    /// Placeholder text shown in the empty search bar of the node finder.
+    pub node_finder_placeholder: Cow<'static, str>,
+    /// Placeholder text of the search bar of the find-node palette, see
+    /// [`NodeSearch`], and the text shown when no node matches.
+    pub find_node_placeholder: Cow<'static, str>,
+    pub no_matching_nodes: Cow<'static, str>,
+    /// Titles of the sections at the top of the node finder, see
+    /// [`FinderFavorites`].
+    pub favorite_templates: Cow<'static, str>,
+    pub recent_templates: Cow<'static, str>,
+    /// Tooltip of the star next to the templates of the node finder.
+    pub pin_template: Cow<'static, str>,
+    /// Question shown when offering to insert a converter node between two
+    /// ports of incompatible types.
+    pub insert_converter_prompt: Cow<'static, str>,
+    /// Label of the button accepting the insertion of a converter node.
+    pub insert_converter_confirm: Cow<'static, str>,
+    /// Title of the list of inputs offered when a connection is dropped on
+    /// the body of a node.
+    pub choose_input: Cow<'static, str>,
+    /// Shown when a connection is dropped on the body of a node that has no
+    /// compatible input.
+    pub no_compatible_input: Cow<'static, str>,
+    /// Shown when a connection is refused because it would create a cycle.
+    /// See [`GraphEditorState::enforce_acyclic`].
+    pub would_create_cycle: Cow<'static, str>,
+    /// Label of the buttons dismissing a confirmation.
+    pub cancel: Cow<'static, str>,
+    /// Shown instead of the body of a node whose UI panicked, in defensive
+    /// mode. See [`GraphEditorState::defensive`].
+    pub widget_error: Cow<'static, str>,
+}
+
+impl Default for Localization {
+    fn default() -> Self {
+        Self {
+            close_node: Cow::Borrowed("Delete node"),
+            collapse_node: Cow::Borrowed("Collapse node"),
+            expand_node: Cow::Borrowed("Expand node"),
+            delete_node: Cow::Borrowed("Delete"),
+            duplicate_node: Cow::Borrowed("Duplicate"),
+            rename_node: Cow::Borrowed("Rename"),
+            disconnect_node: Cow::Borrowed("Disconnect all"),
+            extract_node: Cow::Borrowed("Extract"),
+            bring_node_to_front: Cow::Borrowed("Bring to front"),
+            select_connected: Cow::Borrowed("Select connected"),
+            select_upstream: Cow::Borrowed("Select upstream"),
+            select_downstream: Cow::Borrowed("Select downstream"),
+            remove_connection: Cow::Borrowed("Remove connection"),
+            insert_node: Cow::Borrowed("Insert node…"),
+            add_input: Cow::Borrowed("Add input"),
+            remove_input: Cow::Borrowed("Remove input"),
+            new_group: Cow::Borrowed("Group"),
+            remove_group: Cow::Borrowed("Remove frame only"),
+            delete_group_and_nodes: Cow::Borrowed("Delete frame and nodes"),
+            connected_to: Cow::Borrowed("Connected to:"),
+            not_connected: Cow::Borrowed("Not connected"),
+            connection_only: Cow::Borrowed("Connection only"),
+            constant_only: Cow::Borrowed("Constant only"),
+            connection_or_constant: Cow::Borrowed("Connection or constant"),
+            connections_to_remove: Cow::Borrowed("Connections that will be removed:"),
+            delete_nodes_prompt: Cow::Borrowed("Delete the selected nodes?"),
+            node_finder_placeholder: Cow::Borrowed("Search nodes…"),
+            find_node_placeholder: Cow::Borrowed("Find node…"),
+            no_matching_nodes: Cow::Borrowed("No matching nodes"),
+            favorite_templates: Cow::Borrowed("Favorites"),
+            recent_templates: Cow::Borrowed("Recent"),
+            pin_template: Cow::Borrowed("Pin to favorites"),
+            insert_converter_prompt: Cow::Borrowed("Insert a converter node?"),
+            insert_converter_confirm: Cow::Borrowed("Insert"),
+            choose_input: Cow::Borrowed("Connect to:"),
+            no_compatible_input: Cow::Borrowed("No compatible input"),
+            would_create_cycle: Cow::Borrowed("This connection would create a cycle"),
+            cancel: Cow::Borrowed("Cancel"),
+            widget_error: Cow::Borrowed("Widget error — see log"),
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use std::{collections::BTreeMap, marker::PhantomData};
 
-use crate::{color_hex_utils::*, CategoryTrait, NodeTemplateIter, NodeTemplateTrait};
+use crate::{CategoryTrait, GraphStyle, Localization, NodeTemplateIter, NodeTemplateTrait};
 
 use egui::*;
 
@@ -8,12 +8,65 @@ use egui::*;
 #[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
 pub struct NodeFinder<NodeTemplate> {
     pub query: String,
-    /// Reset every frame. When set, the node finder will be moved at that position
+    /// Where the node finder was opened. It's drawn there, and the picked
+    /// node is created there.
     pub position: Option<Pos2>,
     pub just_spawned: bool,
+    /// The index of the listed template picked by <kbd>Enter</kbd>. Moved
+    /// with the arrow keys, and reset when the query changes.
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub highlighted: usize,
+    /// Whether the keyboard drives the list rather than the search box.
+    /// Toggled with <kbd>Tab</kbd>.
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub list_focused: bool,
     _phantom: PhantomData<NodeTemplate>,
 }
 
+/// The templates pinned by the user, and the ones picked last, listed at the
+/// top of the node finder. Kept in [`GraphEditorState::finder_favorites`].
+///
+/// Templates are identified by their [`NodeTemplateTrait::node_finder_label`],
+/// so the lists can be saved, and pre-seeded with [`FinderFavorites::pin`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub struct FinderFavorites {
+    /// The pinned templates, in the order they were pinned.
+    pub favorites: Vec<String>,
+    /// The templates picked last, most recent first. At most
+    /// [`FinderFavorites::MAX_RECENT`] are kept.
+    pub recent: Vec<String>,
+}
+
+impl FinderFavorites {
+    pub const MAX_RECENT: usize = 5;
+
+    pub fn is_pinned(&self, label: &str) -> bool {
+        self.favorites.iter().any(|favorite| favorite == label)
+    }
+
+    /// Adds a template to the favorites, unless it's already one of them.
+    pub fn pin(&mut self, label: impl Into<String>) {
+        let label = label.into();
+        if !self.is_pinned(&label) {
+            self.favorites.push(label);
+        }
+    }
+
+    pub fn unpin(&mut self, label: &str) {
+        self.favorites.retain(|favorite| favorite != label);
+    }
+
+    /// Moves a template to the top of the recent ones, done when it's picked
+    /// in the node finder.
+    pub fn record_use(&mut self, label: impl Into<String>) {
+        let label = label.into();
+        self.recent.retain(|recent| *recent != label);
+        self.recent.insert(0, label);
+        self.recent.truncate(Self::MAX_RECENT);
+    }
+}
+
 impl<NodeTemplate, NodeData, UserState, CategoryType> NodeFinder<NodeTemplate>
 where
     NodeTemplate:
@@ -25,6 +78,8 @@ where
             query: "".into(),
             position: Some(pos),
             just_spawned: true,
+            highlighted: 0,
+            list_focused: false,
             _phantom: Default::default(),
         }
     }
@@ -32,46 +87,87 @@ where
     /// Shows the node selector panel with a search bar. Returns whether a node
     /// archetype was selected and, in that case, the finder should be hidden on
     /// the next frame.
+    ///
+    /// With an empty query, the templates are listed by category. Otherwise,
+    /// the templates fuzzily matching the query are listed from best to worst
+    /// match, regardless of their categories. Either way, the `favorites` and
+    /// recent templates among them come first. The star next to each template
+    /// pins it, and the picked template is recorded as recent.
+    ///
+    /// The arrow keys move a highlight through the listed templates, opening
+    /// the category it moves into, and <kbd>Enter</kbd> picks the highlighted
+    /// one. <kbd>Tab</kbd> moves the keyboard between the search box and the
+    /// list.
+    ///
+    /// The finder is drawn with the colors of `style`, see
+    /// [`GraphStyle::finder_color`].
     pub fn show(
         &mut self,
         ui: &mut Ui,
         all_kinds: impl NodeTemplateIter<Item = NodeTemplate>,
         user_state: &mut UserState,
+        localization: &Localization,
+        style: &GraphStyle,
+        favorites: &mut FinderFavorites,
     ) -> Option<NodeTemplate> {
-        let background_color;
-        let text_color;
-
-        if ui.visuals().dark_mode {
-            background_color = color_from_hex("#3f3f3f").unwrap();
-            text_color = color_from_hex("#fefefe").unwrap();
-        } else {
-            background_color = color_from_hex("#fefefe").unwrap();
-            text_color = color_from_hex("#3f3f3f").unwrap();
-        }
-
-        ui.visuals_mut().widgets.noninteractive.fg_stroke = Stroke::new(2.0, text_color);
+        ui.visuals_mut().widgets.noninteractive.fg_stroke =
+            Stroke::new(2.0, style.finder_text_color);
 
         let frame = Frame::dark_canvas(ui.style())
-            .fill(background_color)
+            .fill(style.finder_color)
             .inner_margin(vec2(5.0, 5.0));
 
         // The archetype that will be returned.
         let mut submitted_archetype = None;
         frame.show(ui, |ui| {
             ui.vertical(|ui| {
-                let resp = ui.text_edit_singleline(&mut self.query);
+                // Tab is handled below, instead of moving the focus around
+                let resp = ui.add(
+                    TextEdit::singleline(&mut self.query)
+                        .hint_text(localization.node_finder_placeholder.as_ref())
+                        .lock_focus(true),
+                );
                 if self.just_spawned {
                     resp.request_focus();
                     self.just_spawned = false;
                 }
+                if ui.input(|i| i.key_pressed(Key::Tab)) {
+                    self.list_focused = !self.list_focused;
+                    if self.list_focused {
+                        resp.surrender_focus();
+                    } else {
+                        resp.request_focus();
+                    }
+                } else if resp.gained_focus() || resp.clicked() {
+                    self.list_focused = false;
+                }
                 let update_open = resp.changed();
 
-                let mut query_submit = resp.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+                let enter_pressed = ui.input(|i| i.key_pressed(Key::Enter));
+                let query_submit = enter_pressed && (resp.lost_focus() || self.list_focused);
 
                 let max_height = ui.input(|i| i.screen_rect.height() * 0.5);
                 let scroll_area_width = resp.rect.width() - 30.0;
 
                 let all_kinds = all_kinds.all_kinds();
+
+                // Rank the templates matching a non-empty query, best first.
+                // Ties are broken by preferring shorter labels, then by
+                // template order.
+                let mut ranked: Vec<((bool, i32), &NodeTemplate, String)> = all_kinds
+                    .iter()
+                    .filter(|_| !self.query.is_empty())
+                    .filter_map(|kind| {
+                        let kind_name = kind.node_finder_label(user_state).into_owned();
+                        let description = kind.node_finder_description(user_state);
+                        let rank = match_rank(&self.query, &kind_name, description.as_deref())?;
+                        Some((rank, kind, kind_name))
+                    })
+                    .collect();
+                ranked.sort_by(|(a, _, a_name), (b, _, b_name)| {
+                    b.cmp(a).then(a_name.len().cmp(&b_name.len()))
+                });
+
                 let mut categories: BTreeMap<String, Vec<&NodeTemplate>> = Default::default();
                 let mut orphan_kinds = Vec::new();
 
@@ -87,6 +183,65 @@ where
                     }
                 }
 
+                // The favorite and recent templates matching the query
+                let shortcut = |labels: &[String], user_state: &mut UserState| {
+                    let mut kinds = Vec::new();
+                    for label in labels {
+                        let kind = all_kinds
+                            .iter()
+                            .find(|kind| kind.node_finder_label(user_state) == label.as_str());
+                        if let Some(kind) = kind {
+                            let description = kind.node_finder_description(user_state);
+                            if match_rank(&self.query, label, description.as_deref()).is_some() {
+                                kinds.push(kind);
+                            }
+                        }
+                    }
+                    kinds
+                };
+                let sections = [
+                    (
+                        &localization.favorite_templates,
+                        shortcut(&favorites.favorites, user_state),
+                    ),
+                    (
+                        &localization.recent_templates,
+                        shortcut(&favorites.recent, user_state),
+                    ),
+                ];
+
+                // The listed templates, in order. The arrow keys move the
+                // highlight through them, across sections and categories.
+                let mut entries: Vec<&NodeTemplate> = sections
+                    .iter()
+                    .flat_map(|(_, kinds)| kinds.iter().copied())
+                    .collect();
+                if self.query.is_empty() {
+                    entries.extend(categories.values().flatten().copied());
+                    entries.extend(orphan_kinds.iter().copied());
+                } else {
+                    entries.extend(ranked.iter().map(|(_, kind, _)| *kind));
+                }
+
+                if update_open {
+                    self.highlighted = 0;
+                }
+                let mut highlight_moved = false;
+                if !entries.is_empty() {
+                    if ui.input(|i| i.key_pressed(Key::ArrowDown)) {
+                        self.highlighted += 1;
+                        highlight_moved = true;
+                    }
+                    if ui.input(|i| i.key_pressed(Key::ArrowUp)) {
+                        self.highlighted = self.highlighted.saturating_sub(1);
+                        highlight_moved = true;
+                    }
+                    self.highlighted = self.highlighted.min(entries.len() - 1);
+                }
+                if query_submit {
+                    submitted_archetype = entries.get(self.highlighted).map(|kind| (*kind).clone());
+                }
+
                 Frame::default()
                     .inner_margin(vec2(10.0, 10.0))
                     .show(ui, |ui| {
@@ -94,58 +249,458 @@ where
                             .max_height(max_height)
                             .show(ui, |ui| {
                                 ui.set_width(scroll_area_width);
-                                for (category, kinds) in categories {
-                                    let filtered_kinds: Vec<_> = kinds
-                                        .into_iter()
-                                        .map(|kind| {
-                                            let kind_name =
-                                                kind.node_finder_label(user_state).to_string();
-                                            (kind, kind_name)
-                                        })
-                                        .filter(|(_kind, kind_name)| {
-                                            kind_name
-                                                .to_lowercase()
-                                                .contains(self.query.to_lowercase().as_str())
-                                        })
-                                        .collect();
-
-                                    if !filtered_kinds.is_empty() {
-                                        let default_open = !self.query.is_empty();
-
-                                        CollapsingHeader::new(&category)
-                                            .default_open(default_open)
-                                            .open(update_open.then_some(default_open))
-                                            .show(ui, |ui| {
-                                                for (kind, kind_name) in filtered_kinds {
-                                                    if ui
-                                                        .selectable_label(false, kind_name)
-                                                        .clicked()
-                                                    {
-                                                        submitted_archetype = Some(kind.clone());
-                                                    } else if query_submit {
-                                                        submitted_archetype = Some(kind.clone());
-                                                        query_submit = false;
-                                                    }
-                                                }
-                                            });
+                                let mut index = 0;
+                                let mut entry = |ui: &mut Ui, kind: &NodeTemplate, index: usize| {
+                                    let highlighted = index == self.highlighted;
+                                    let resp = finder_entry(
+                                        ui,
+                                        kind,
+                                        highlighted,
+                                        favorites,
+                                        localization,
+                                        user_state,
+                                    );
+                                    if highlighted && highlight_moved {
+                                        resp.scroll_to_me(None);
                                     }
-                                }
+                                    if resp.clicked() {
+                                        submitted_archetype = Some(kind.clone());
+                                    }
+                                };
 
-                                for kind in orphan_kinds {
-                                    let kind_name = kind.node_finder_label(user_state).to_string();
+                                for (title, kinds) in &sections {
+                                    if kinds.is_empty() {
+                                        continue;
+                                    }
+                                    ui.weak(title.as_ref());
+                                    for kind in kinds {
+                                        entry(ui, kind, index);
+                                        index += 1;
+                                    }
+                                    ui.separator();
+                                }
 
-                                    if ui.selectable_label(false, kind_name).clicked() {
-                                        submitted_archetype = Some(kind.clone());
-                                    } else if query_submit {
-                                        submitted_archetype = Some(kind.clone());
-                                        query_submit = false;
+                                if !self.query.is_empty() {
+                                    for (_, kind, _) in &ranked {
+                                        entry(ui, kind, index);
+                                        index += 1;
                                     }
+                                    return;
+                                }
+
+                                for (category, kinds) in categories {
+                                    // Collapse the categories when the query is
+                                    // cleared, and open the one the highlight
+                                    // moves into
+                                    let first = index;
+                                    index += kinds.len();
+                                    let highlighted = (first..index).contains(&self.highlighted);
+                                    let open = if update_open {
+                                        Some(false)
+                                    } else {
+                                        (highlight_moved && highlighted).then_some(true)
+                                    };
+                                    CollapsingHeader::new(&category)
+                                        .default_open(false)
+                                        .open(open)
+                                        .show(ui, |ui| {
+                                            for (offset, kind) in kinds.into_iter().enumerate() {
+                                                entry(ui, kind, first + offset);
+                                            }
+                                        });
+                                }
+
+                                for kind in orphan_kinds {
+                                    entry(ui, kind, index);
+                                    index += 1;
                                 }
                             });
                     });
             });
         });
 
+        if let Some(kind) = &submitted_archetype {
+            favorites.record_use(kind.node_finder_label(user_state));
+        }
         submitted_archetype
     }
 }
+
+/// A template listed by the node finder, after its icon and with its
+/// description below, if any. The star before it pins it to the favorites.
+/// Returns the response of its label.
+fn finder_entry<NodeTemplate: NodeTemplateTrait>(
+    ui: &mut Ui,
+    kind: &NodeTemplate,
+    highlighted: bool,
+    favorites: &mut FinderFavorites,
+    localization: &Localization,
+    user_state: &mut NodeTemplate::UserState,
+) -> Response {
+    let label = kind.node_finder_label(user_state).into_owned();
+    let text = match kind.node_finder_icon(user_state) {
+        Some(icon) => format!("{} {}", icon, label),
+        None => label.clone(),
+    };
+    let description = kind.node_finder_description(user_state);
+    ui.horizontal(|ui| {
+        let pinned = favorites.is_pinned(&label);
+        let star = Button::new(if pinned { "★" } else { "☆" }).frame(false);
+        if ui
+            .add(star)
+            .on_hover_text(localization.pin_template.as_ref())
+            .clicked()
+        {
+            if pinned {
+                favorites.unpin(&label);
+            } else {
+                favorites.pin(label);
+            }
+        }
+        // The description wraps, making the row taller
+        ui.vertical(|ui| {
+            let resp = ui.selectable_label(highlighted, text);
+            if let Some(description) = description {
+                ui.label(RichText::new(description).small().weak());
+            }
+            resp
+        })
+        .inner
+    })
+    .inner
+}
+
+/// Ranks how well a template matches `query`, see [`fuzzy_score`]. The
+/// templates whose label matches come first, then the ones whose description
+/// contains the query. Returns `None` when neither match.
+fn match_rank(query: &str, label: &str, description: Option<&str>) -> Option<(bool, i32)> {
+    if let Some(score) = fuzzy_score(query, label) {
+        return Some((true, score));
+    }
+    let query = query.trim().to_lowercase();
+    let found = description?.to_lowercase().contains(&query);
+    found.then_some((false, 0))
+}
+
+/// Scores how well `query` matches `label`, ignoring case and the whitespace
+/// of the query. All the characters of the query must appear in the label, in
+/// order, but not necessarily next to each other: `"ynet"` matches `"Create
+/// Yolo Detection Network"`. Returns `None` when the label doesn't match.
+///
+/// Higher scores are better matches. Characters matched at the start of a word
+/// and runs of consecutive characters are favored, gaps are penalized.
+pub(crate) fn fuzzy_score(query: &str, label: &str) -> Option<i32> {
+    const MATCH: i32 = 1;
+    const WORD_START: i32 = 8;
+    const CONSECUTIVE: i32 = 6;
+
+    let query: Vec<char> = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    let label: Vec<char> = label.chars().collect();
+    let lower: Vec<char> = label
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+    if query.is_empty() {
+        return Some(0);
+    }
+
+    let bonus = |j: usize| {
+        let word_start = match j.checked_sub(1).map(|i| label[i]) {
+            None => true,
+            Some(prev) => {
+                !prev.is_alphanumeric()
+                    || (prev.is_lowercase() && label[j].is_uppercase())
+                    || (!prev.is_numeric() && label[j].is_numeric())
+            }
+        };
+        MATCH + if word_start { WORD_START } else { 0 }
+    };
+
+    // `best[j]` is the best score of the query so far, with its last
+    // character matched at `j` in the label.
+    let mut best: Vec<Option<i32>> = (0..label.len())
+        .map(|j| (lower[j] == query[0]).then(|| bonus(j)))
+        .collect();
+    for c in &query[1..] {
+        let mut next = vec![None; label.len()];
+        // The best `best[k] + k` for `k < j - 1`, i.e. with a gap before `j`
+        let mut gap_best: Option<i32> = None;
+        for j in 1..label.len() {
+            if j >= 2 {
+                let candidate = best[j - 2].map(|score| score + j as i32 - 2);
+                gap_best = gap_best.max(candidate);
+            }
+            if lower[j] != *c {
+                continue;
+            }
+            let consecutive = best[j - 1].map(|score| score + CONSECUTIVE);
+            let gapped = gap_best.map(|score| score - (j as i32 - 1));
+            next[j] = consecutive.max(gapped).map(|score| score + bonus(j));
+        }
+        best = next;
+    }
+    best.into_iter().flatten().max()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Graph, NodeId};
+    use std::borrow::Cow;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Template(&'static str);
+
+    impl NodeTemplateTrait for Template {
+        type NodeData = ();
+        type DataType = ();
+        type ValueType = ();
+        type UserState = ();
+        type CategoryType = &'static str;
+
+        fn node_finder_label(&self, _user_state: &mut ()) -> std::borrow::Cow<'_, str> {
+            self.0.into()
+        }
+        fn node_finder_categories(&self, _user_state: &mut ()) -> Vec<&'static str> {
+            match self.0 {
+                "Subnet mask" | "Neural tensor" => vec!["Network"],
+                _ => Vec::new(),
+            }
+        }
+        fn node_finder_description(&self, _user_state: &mut ()) -> Option<Cow<'_, str>> {
+            (self.0 == "Add scalar").then_some("Sums two numbers".into())
+        }
+        fn node_finder_icon(&self, _user_state: &mut ()) -> Option<char> {
+            (self.0 == "Add scalar").then_some('+')
+        }
+        fn node_graph_label(&self, _user_state: &mut ()) -> String {
+            self.0.into()
+        }
+        fn user_data(&self, _user_state: &mut ()) {}
+        fn build_node(&self, _graph: &mut Graph<(), (), ()>, _user_state: &mut (), _id: NodeId) {}
+    }
+
+    struct AllTemplates;
+    impl NodeTemplateIter for AllTemplates {
+        type Item = Template;
+        fn all_kinds(&self) -> Vec<Template> {
+            vec![
+                Template("Subnet mask"),
+                Template("Create Yolo Detection Network"),
+                Template("Neural tensor"),
+                Template("Add scalar"),
+            ]
+        }
+    }
+
+    /// Draws a node finder for a single frame and returns all painted text.
+    fn painted_text(
+        localization: &Localization,
+        query: &str,
+        favorites: &mut FinderFavorites,
+    ) -> Vec<String> {
+        let ctx = Context::default();
+        let mut finder = NodeFinder::<Template>::new_at(Pos2::ZERO);
+        finder.query = query.into();
+        let output = ctx.run(RawInput::default(), |ctx| {
+            CentralPanel::default().show(ctx, |ui| {
+                finder.show(
+                    ui,
+                    AllTemplates,
+                    &mut (),
+                    localization,
+                    &GraphStyle::default(),
+                    favorites,
+                );
+            });
+        });
+        output
+            .shapes
+            .into_iter()
+            .filter_map(|epaint::ClippedShape(_, shape)| match shape {
+                Shape::Text(text) => Some(text.galley.text().to_owned()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    pub fn test_localized_placeholder() {
+        let default_text = painted_text(&Localization::default(), "", &mut Default::default());
+        assert!(default_text.iter().any(|t| t == "Search nodes…"));
+
+        let localization = Localization {
+            node_finder_placeholder: "ノードを検索…".into(),
+            ..Default::default()
+        };
+        let localized_text = painted_text(&localization, "", &mut Default::default());
+        assert!(localized_text.iter().any(|t| t == "ノードを検索…"));
+        assert!(!localized_text.iter().any(|t| t == "Search nodes…"));
+    }
+
+    #[test]
+    pub fn test_fuzzy_score() {
+        assert!(fuzzy_score("ynet", "Create Yolo Detection Network").is_some());
+        assert!(fuzzy_score("YOLO net", "Create Yolo Detection Network").is_some());
+        assert!(fuzzy_score("ten", "Network").is_none());
+        assert_eq!(fuzzy_score("", "Anything"), Some(0));
+
+        // Word starts and consecutive characters win
+        let score = |label| fuzzy_score("net", label).unwrap();
+        assert!(score("Network") > score("Neural tensor"));
+        assert!(score("Neural tensor") > score("Subnet mask"));
+        assert!(fuzzy_score("ds", "DetectionSpatial") > fuzzy_score("ds", "Add scalars"));
+    }
+
+    #[test]
+    pub fn test_keyboard_picks_ranked_result() {
+        let ctx = Context::default();
+        let mut finder = NodeFinder::<Template>::new_at(Pos2::ZERO);
+        finder.query = "net".into();
+        let key = |key| Event::Key {
+            key,
+            pressed: true,
+            repeat: false,
+            modifiers: Modifiers::NONE,
+        };
+        let mut submitted = None;
+        for events in [vec![], vec![key(Key::ArrowDown)], vec![key(Key::Enter)]] {
+            let input = RawInput {
+                events,
+                ..Default::default()
+            };
+            let _ = ctx.run(input, |ctx| {
+                CentralPanel::default().show(ctx, |ui| {
+                    submitted = finder.show(
+                        ui,
+                        AllTemplates,
+                        &mut (),
+                        &Localization::default(),
+                        &GraphStyle::default(),
+                        &mut Default::default(),
+                    );
+                });
+            });
+        }
+        // "Network" is the best match, "Neural tensor" the second best
+        assert_eq!(finder.highlighted, 1);
+        assert_eq!(submitted, Some(Template("Neural tensor")));
+    }
+
+    #[test]
+    pub fn test_favorites() {
+        let mut favorites = FinderFavorites::default();
+        for label in ["a", "b", "c", "d", "e", "f", "b"] {
+            favorites.record_use(label);
+        }
+        assert_eq!(favorites.recent, ["b", "f", "e", "d", "c"]);
+        favorites.pin("x");
+        favorites.pin("x");
+        assert_eq!(favorites.favorites, ["x"]);
+        favorites.unpin("x");
+        assert!(!favorites.is_pinned("x"));
+
+        // Listed at the top, when matching the query
+        let mut favorites = FinderFavorites::default();
+        favorites.pin("Add scalar");
+        favorites.pin("Not a template");
+        favorites.record_use("Network");
+        let count = |text: &[String], label: &str| text.iter().filter(|t| *t == label).count();
+        let text = painted_text(&Localization::default(), "", &mut favorites);
+        assert_eq!(count(&text, "Favorites"), 1);
+        assert_eq!(count(&text, "+ Add scalar"), 2);
+        assert_eq!(count(&text, "Not a template"), 0);
+        // Not offered by `AllTemplates`
+        assert_eq!(count(&text, "Recent"), 0);
+        assert_eq!(count(&text, "★"), 2);
+
+        let text = painted_text(&Localization::default(), "add", &mut favorites);
+        assert_eq!(count(&text, "Favorites"), 1);
+        let text = painted_text(&Localization::default(), "net", &mut favorites);
+        assert_eq!(count(&text, "Favorites"), 0);
+    }
+
+    #[test]
+    pub fn test_descriptions_and_icons() {
+        let text = painted_text(&Localization::default(), "", &mut Default::default());
+        assert!(text.iter().any(|t| t == "+ Add scalar"));
+        assert!(text.iter().any(|t| t == "Sums two numbers"));
+
+        // The description is searched too, after the labels
+        let text = painted_text(&Localization::default(), "numbers", &mut Default::default());
+        assert!(text.iter().any(|t| t == "+ Add scalar"));
+        assert!(
+            match_rank("sub", "Subnet mask", None) > match_rank("sub", "Add", Some("Subtracts"))
+        );
+        assert_eq!(match_rank("mul", "Add", Some("Sums")), None);
+    }
+
+    #[test]
+    pub fn test_keyboard_navigation() {
+        let key = |key| Event::Key {
+            key,
+            pressed: true,
+            repeat: false,
+            modifiers: Modifiers::NONE,
+        };
+        // Feeds the keys to a new finder, one per frame, and returns the
+        // picked template and the text of the last frame, once the
+        // categories are done opening
+        let type_keys = |keys: &[Key]| {
+            let ctx = Context::default();
+            let mut finder = NodeFinder::<Template>::new_at(Pos2::ZERO);
+            let mut submitted = None;
+            let mut text = Vec::new();
+            let frames = std::iter::once(vec![])
+                .chain(keys.iter().map(|k| vec![key(*k)]))
+                .chain(std::iter::repeat_n(vec![], 10));
+            for events in frames {
+                let input = RawInput {
+                    events,
+                    ..Default::default()
+                };
+                let output = ctx.run(input, |ctx| {
+                    CentralPanel::default().show(ctx, |ui| {
+                        submitted = submitted.take().or(finder.show(
+                            ui,
+                            AllTemplates,
+                            &mut (),
+                            &Localization::default(),
+                            &GraphStyle::default(),
+                            &mut Default::default(),
+                        ));
+                    });
+                });
+                text = output
+                    .shapes
+                    .into_iter()
+                    .filter_map(|epaint::ClippedShape(_, shape)| match shape {
+                        Shape::Text(text) => Some(text.galley.text().to_owned()),
+                        _ => None,
+                    })
+                    .collect();
+            }
+            (finder, submitted, text)
+        };
+
+        // The highlight opens the collapsed category it moves into
+        let (finder, _, text) = type_keys(&[Key::ArrowDown]);
+        assert_eq!(finder.highlighted, 1);
+        assert!(text.iter().any(|t| t == "Neural tensor"));
+
+        // And moves on past the end of the category
+        let (_, submitted, _) = type_keys(&[Key::ArrowDown, Key::ArrowDown, Key::Enter]);
+        assert_eq!(submitted, Some(Template("Create Yolo Detection Network")));
+
+        // Tab moves the keyboard to the list and back
+        let (finder, submitted, _) = type_keys(&[Key::Tab, Key::ArrowDown, Key::Enter]);
+        assert!(finder.list_focused);
+        assert_eq!(submitted, Some(Template("Neural tensor")));
+        let (finder, _, _) = type_keys(&[Key::Tab, Key::Tab]);
+        assert!(!finder.list_focused);
+    }
+}
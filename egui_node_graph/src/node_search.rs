@@ -0,0 +1,191 @@
+use crate::node_finder::fuzzy_score;
+use crate::{Graph, Localization, NodeDataTrait, NodeId};
+
+use egui::*;
+
+/// The find-node palette, listing the nodes of the graph whose label or
+/// [`NodeDataTrait::template_name`] fuzzily matches a query. Opened with
+/// <kbd>Ctrl</kbd> + <kbd>F</kbd> when [`GraphEditorState::find_nodes`] is
+/// set.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodeSearch {
+    pub query: String,
+    /// The index of the listed node picked by <kbd>Enter</kbd>. Moved with
+    /// the arrow keys, and reset when the query changes.
+    pub highlighted: usize,
+    pub just_spawned: bool,
+}
+
+/// A node listed by the find-node palette.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NodeSearchMatch {
+    pub node: NodeId,
+    pub label: String,
+    /// See [`NodeDataTrait::template_name`].
+    pub template_name: Option<String>,
+}
+
+impl Default for NodeSearch {
+    fn default() -> Self {
+        Self {
+            query: String::new(),
+            highlighted: 0,
+            just_spawned: true,
+        }
+    }
+}
+
+impl NodeSearch {
+    /// The nodes among `nodes` matching the query, best match first. A node
+    /// matches when its label or its template name does, see
+    /// [`NodeSearch`]. All of them match an empty query, in their order.
+    pub fn matches<NodeData, DataType, ValueType, UserState>(
+        &self,
+        graph: &Graph<NodeData, DataType, ValueType>,
+        nodes: &[NodeId],
+        user_state: &mut UserState,
+    ) -> Vec<NodeSearchMatch>
+    where
+        NodeData: NodeDataTrait<DataType = DataType, ValueType = ValueType, UserState = UserState>,
+    {
+        let mut ranked: Vec<(i32, NodeSearchMatch)> = nodes
+            .iter()
+            .filter_map(|node| {
+                let label = graph.nodes.get(*node)?.label.clone();
+                let template_name = graph[*node]
+                    .user_data
+                    .template_name(*node, graph, user_state)
+                    .map(|name| name.into_owned());
+                let score = fuzzy_score(&self.query, &label).max(
+                    template_name
+                        .as_deref()
+                        .and_then(|name| fuzzy_score(&self.query, name)),
+                )?;
+                let found = NodeSearchMatch {
+                    node: *node,
+                    label,
+                    template_name,
+                };
+                Some((score, found))
+            })
+            .collect();
+        // Stable, so ties keep the order of `nodes`
+        ranked.sort_by(|(a, _), (b, _)| b.cmp(a));
+        ranked.into_iter().map(|(_, found)| found).collect()
+    }
+
+    /// Shows the search box and the nodes matching it. Returns the node
+    /// picked with <kbd>Enter</kbd> or by clicking it, after which the
+    /// palette should be closed.
+    ///
+    /// The arrow keys cycle through the matches.
+    pub fn show<NodeData, DataType, ValueType, UserState>(
+        &mut self,
+        ui: &mut Ui,
+        graph: &Graph<NodeData, DataType, ValueType>,
+        nodes: &[NodeId],
+        user_state: &mut UserState,
+        localization: &Localization,
+    ) -> Option<NodeId>
+    where
+        NodeData: NodeDataTrait<DataType = DataType, ValueType = ValueType, UserState = UserState>,
+    {
+        let mut picked = None;
+        Frame::popup(ui.style()).show(ui, |ui| {
+            let resp = ui.add(
+                TextEdit::singleline(&mut self.query)
+                    .hint_text(localization.find_node_placeholder.as_ref()),
+            );
+            if self.just_spawned {
+                resp.request_focus();
+                self.just_spawned = false;
+            }
+            if resp.changed() {
+                self.highlighted = 0;
+            }
+
+            let matches = self.matches(graph, nodes, user_state);
+            let mut highlight_moved = false;
+            if !matches.is_empty() {
+                ui.input(|i| {
+                    if i.key_pressed(Key::ArrowDown) {
+                        self.highlighted += 1;
+                        highlight_moved = true;
+                    }
+                    if i.key_pressed(Key::ArrowUp) {
+                        self.highlighted += matches.len() - 1;
+                        highlight_moved = true;
+                    }
+                });
+                self.highlighted %= matches.len();
+            }
+            if resp.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter)) {
+                match matches.get(self.highlighted) {
+                    Some(found) => picked = Some(found.node),
+                    None => resp.request_focus(),
+                }
+            }
+
+            if matches.is_empty() {
+                ui.weak(localization.no_matching_nodes.as_ref());
+            }
+            ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                for (index, found) in matches.iter().enumerate() {
+                    let highlighted = index == self.highlighted;
+                    ui.horizontal(|ui| {
+                        let resp = ui.selectable_label(highlighted, &found.label);
+                        if let Some(name) = &found.template_name {
+                            if *name != found.label {
+                                ui.weak(name);
+                            }
+                        }
+                        if highlighted && highlight_moved {
+                            resp.scroll_to_me(None);
+                        }
+                        if resp.clicked() {
+                            picked = Some(found.node);
+                        }
+                    });
+                }
+            });
+        });
+        picked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    pub fn test_matches() {
+        let mut graph = TestGraph::new();
+        let nodes: Vec<NodeId> = ["XLinkIn", "Camera", "XLinkOut", "Renamed"]
+            .iter()
+            .map(|label| {
+                let data = TestNodeData(TestTemplate::Scalar);
+                graph.add_node(label.to_string(), data, |_, _| {})
+            })
+            .collect();
+        let search = |query: &str| {
+            let search = NodeSearch {
+                query: query.into(),
+                ..Default::default()
+            };
+            let found = search.matches(&graph, &nodes, &mut ());
+            found
+                .into_iter()
+                .map(|found| found.node)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(search(""), nodes);
+        assert_eq!(search("xlink"), [nodes[0], nodes[2]]);
+        assert_eq!(search("xlout"), [nodes[2]]);
+        // By template name, whatever the label
+        assert_eq!(search("scalar"), nodes);
+        assert!(search("vector").is_empty());
+    }
+}
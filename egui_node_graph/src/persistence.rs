@@ -0,0 +1,399 @@
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
+
+use super::*;
+
+use serde::de::{DeserializeOwned, Error as _};
+use serde::ser::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Converts a serialized editor state from version `from` to version
+/// `from + 1`, typically by reading it with the types of the old version and
+/// writing it with the new ones. See
+/// [`GraphEditorState::deserialize_with_migrations`].
+///
+/// Migrations get and return the RON of the editor state only, without the
+/// version. They run in the same mode as the loading itself, so the library
+/// types can be read and written as usual.
+#[derive(Clone, Copy)]
+pub struct Migration {
+    pub from: u32,
+    pub migrate: fn(&str) -> Result<String, String>,
+}
+
+/// The envelope of a blob written by [`GraphEditorState::serialize_versioned`].
+#[derive(Serialize, Deserialize)]
+struct VersionedState {
+    version: u32,
+    state: String,
+}
+
+thread_local! {
+    /// Set while reading or writing a versioned blob. The user data of each
+    /// node is then stored as a nested RON string, so that the data of a node
+    /// that can't be read doesn't prevent reading the rest of the blob.
+    static NESTED_NODE_DATA: Cell<bool> = const { Cell::new(false) };
+    /// Creates the data of the nodes whose data can't be read, while loading
+    /// leniently. Holds a `fn(&str) -> NodeData`.
+    static PLACEHOLDER: RefCell<Option<Box<dyn Any>>> = const { RefCell::new(None) };
+}
+
+/// Enables the versioned mode until dropped, even if loading panics.
+struct VersionedMode {
+    nested: bool,
+    placeholder: Option<Box<dyn Any>>,
+}
+
+impl VersionedMode {
+    fn enable(placeholder: Option<Box<dyn Any>>) -> Self {
+        Self {
+            nested: NESTED_NODE_DATA.with(|nested| nested.replace(true)),
+            placeholder: PLACEHOLDER.with(|current| current.replace(placeholder)),
+        }
+    }
+}
+
+impl Drop for VersionedMode {
+    fn drop(&mut self) {
+        NESTED_NODE_DATA.with(|nested| nested.set(self.nested));
+        let placeholder = self.placeholder.take();
+        PLACEHOLDER.with(|current| *current.borrow_mut() = placeholder);
+    }
+}
+
+pub(crate) fn serialize_node_data<NodeData: Serialize, S: Serializer>(
+    data: &NodeData,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    if NESTED_NODE_DATA.with(Cell::get) {
+        let nested = ron::to_string(data).map_err(S::Error::custom)?;
+        serializer.serialize_str(&nested)
+    } else {
+        data.serialize(serializer)
+    }
+}
+
+pub(crate) fn deserialize_node_data<'de, NodeData, D>(deserializer: D) -> Result<NodeData, D::Error>
+where
+    NodeData: DeserializeOwned + 'static,
+    D: Deserializer<'de>,
+{
+    if !NESTED_NODE_DATA.with(Cell::get) {
+        return NodeData::deserialize(deserializer);
+    }
+    let nested = String::deserialize(deserializer)?;
+    ron::from_str(&nested).or_else(|err| {
+        let placeholder = PLACEHOLDER.with(|placeholder| {
+            placeholder
+                .borrow()
+                .as_ref()
+                .and_then(|placeholder| placeholder.downcast_ref::<fn(&str) -> NodeData>())
+                .copied()
+        });
+        match placeholder {
+            Some(placeholder) => Ok(placeholder(&nested)),
+            None => Err(D::Error::custom(format!(
+                "Invalid node data {}: {}",
+                nested, err
+            ))),
+        }
+    })
+}
+
+impl<NodeData, DataType, ValueType, NodeTemplate, UserState, ConnectionData>
+    GraphEditorState<NodeData, DataType, ValueType, NodeTemplate, UserState, ConnectionData>
+where
+    NodeData: 'static,
+    Self: Serialize + DeserializeOwned,
+{
+    /// Serializes the editor state to RON, tagged with `version`: the version
+    /// of the layout of the state, including the user types, to bump whenever
+    /// older blobs can no longer be read. Read the blob back with
+    /// [`GraphEditorState::deserialize_with_migrations`].
+    pub fn serialize_versioned(&self, version: u32) -> Result<String, PersistenceError> {
+        let _mode = VersionedMode::enable(None);
+        let state =
+            ron::to_string(self).map_err(|err| PersistenceError::Serialize(err.to_string()))?;
+        ron::to_string(&VersionedState { version, state })
+            .map_err(|err| PersistenceError::Serialize(err.to_string()))
+    }
+
+    /// Reads a blob written by [`GraphEditorState::serialize_versioned`].
+    /// Blobs of an older version are brought up to `version` by running the
+    /// `migrations` in turn. Blobs without a version, e.g. written by
+    /// `eframe::set_value`, are read as they are.
+    ///
+    /// When `placeholder` is given, nodes whose user data can't be read, e.g.
+    /// because their template no longer exists, get the data it returns for
+    /// the RON of the old data. They keep their label, params, connections
+    /// and position. Otherwise, such nodes fail the whole load.
+    ///
+    /// Corrupted or inconsistent blobs are reported as errors.
+    pub fn deserialize_with_migrations(
+        blob: &str,
+        version: u32,
+        migrations: &[Migration],
+        placeholder: Option<fn(&str) -> NodeData>,
+    ) -> Result<Self, PersistenceError> {
+        let versioned = match ron::from_str::<VersionedState>(blob) {
+            Ok(versioned) => versioned,
+            Err(_) => {
+                let state: Self = ron::from_str(blob)
+                    .map_err(|err| PersistenceError::Deserialize(err.to_string()))?;
+                state.check_consistency()?;
+                return Ok(state);
+            }
+        };
+        if versioned.version > version {
+            return Err(PersistenceError::NewerVersion {
+                found: versioned.version,
+                supported: version,
+            });
+        }
+
+        let _mode = VersionedMode::enable(placeholder.map(|p| Box::new(p) as Box<dyn Any>));
+        let mut state = versioned.state;
+        for from in versioned.version..version {
+            let migration = migrations
+                .iter()
+                .find(|migration| migration.from == from)
+                .ok_or(PersistenceError::MissingMigration(from))?;
+            state = (migration.migrate)(&state)
+                .map_err(|message| PersistenceError::Migration { from, message })?;
+        }
+        // The graph rebuilds its indexes as it is read
+        let state: Self =
+            ron::from_str(&state).map_err(|err| PersistenceError::Deserialize(err.to_string()))?;
+        state.check_consistency()?;
+        Ok(state)
+    }
+}
+
+impl<NodeData, DataType, ValueType, NodeTemplate, UserState, ConnectionData>
+    GraphEditorState<NodeData, DataType, ValueType, NodeTemplate, UserState, ConnectionData>
+{
+    /// Checks that the ids stored in the graph refer to each other, so that
+    /// indexing the graph can't panic, e.g. after reading it from a file. The
+    /// rest of the editor state is repaired by
+    /// [`GraphEditorState::sync_with_graph`].
+    pub fn check_consistency(&self) -> Result<(), PersistenceError> {
+        let graph = &self.graph;
+        let inconsistent = |message: String| Err(PersistenceError::Inconsistent(message));
+        let mut external_ids = HashSet::new();
+        for (id, node) in &graph.nodes {
+            if node.id != id {
+                return inconsistent(format!("Node {:?} is stored as {:?}", node.id, id));
+            }
+            if let Some(external_id) = &node.external_id {
+                if !external_ids.insert(external_id) {
+                    return inconsistent(format!("External id '{}' is not unique", external_id));
+                }
+            }
+            for (name, input) in &node.inputs {
+                if graph.inputs.get(*input).map(|param| param.node) != Some(id) {
+                    return inconsistent(format!("Input '{}' of node {:?} is missing", name, id));
+                }
+            }
+            for (name, output) in &node.outputs {
+                if graph.outputs.get(*output).map(|param| param.node) != Some(id) {
+                    return inconsistent(format!("Output '{}' of node {:?} is missing", name, id));
+                }
+            }
+        }
+        for (id, input) in &graph.inputs {
+            if !graph.nodes.contains_key(input.node) {
+                return inconsistent(format!("The node of input {:?} is missing", id));
+            }
+        }
+        for (id, output) in &graph.outputs {
+            if !graph.nodes.contains_key(output.node) {
+                return inconsistent(format!("The node of output {:?} is missing", id));
+            }
+        }
+        for (input, outputs) in &graph.connections {
+            if !graph.inputs.contains_key(input) {
+                return inconsistent(format!("Connected input {:?} is missing", input));
+            }
+            if let Some(output) = outputs.iter().find(|o| !graph.outputs.contains_key(**o)) {
+                return inconsistent(format!("Connected output {:?} is missing", output));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    type Load = fn(&str) -> Result<TestEditorState, PersistenceError>;
+
+    fn saved_state() -> (TestEditorState, [NodeId; 2]) {
+        let mut state = TestEditorState::new(1.0);
+        let a = state.add_node(&TestTemplate::Scalar, egui::pos2(10.0, 20.0), &mut ());
+        let b = state.add_node(&TestTemplate::Panicking, egui::pos2(300.0, 20.0), &mut ());
+        state.graph[a].label = "Alpha".into();
+        state
+            .graph
+            .set_external_id(a, Some("ColorCamera@CAM_A".into()))
+            .unwrap();
+        state.raise_node(a);
+        let output = state.graph[a].get_output("out").unwrap();
+        let input = state.graph[b].get_input("in").unwrap();
+        state.graph.add_connection(output, input);
+        (state, [a, b])
+    }
+
+    #[test]
+    pub fn test_versions_and_migrations() {
+        let (state, [a, b]) = saved_state();
+        let output = state.graph[a].get_output("out").unwrap();
+        let assert_indexed = |loaded: &TestEditorState| {
+            assert_eq!(loaded.graph.output_connection_count(output), 1);
+            assert_eq!(
+                loaded.graph.node_by_external_id("ColorCamera@CAM_A"),
+                Some(a)
+            );
+        };
+        let blob = state.serialize_versioned(1).unwrap();
+        let load: Load = |blob| TestEditorState::deserialize_with_migrations(blob, 1, &[], None);
+        let loaded = load(&blob).unwrap();
+        assert_eq!(loaded.graph[a].label, "Alpha");
+        assert_eq!(loaded.graph[b].user_data.0, TestTemplate::Panicking);
+        assert_eq!(loaded.node_positions[b], egui::pos2(300.0, 20.0));
+        assert_eq!(loaded.graph.connections.len(), 1);
+        assert_eq!(loaded.node_order, [b, a]);
+        assert_indexed(&loaded);
+
+        // Blobs without a version are read as they are
+        let unversioned = load(&ron::to_string(&state).unwrap()).unwrap();
+        assert_eq!(unversioned.graph[a].label, "Alpha");
+        assert_indexed(&unversioned);
+
+        // Older versions are migrated one version at a time
+        let migrations = [
+            Migration {
+                from: 2,
+                migrate: |state| Ok(state.replace("Beta", "Gamma")),
+            },
+            Migration {
+                from: 1,
+                migrate: |state| Ok(state.replace("Alpha", "Beta")),
+            },
+        ];
+        let migrated =
+            TestEditorState::deserialize_with_migrations(&blob, 3, &migrations, None).unwrap();
+        assert_eq!(migrated.graph[a].label, "Gamma");
+        assert_indexed(&migrated);
+        assert!(matches!(
+            TestEditorState::deserialize_with_migrations(&blob, 4, &migrations, None),
+            Err(PersistenceError::MissingMigration(3))
+        ));
+        let failing = [Migration {
+            from: 1,
+            migrate: |_| Err("Unsupported".into()),
+        }];
+        assert!(matches!(
+            TestEditorState::deserialize_with_migrations(&blob, 2, &failing, None),
+            Err(PersistenceError::Migration { from: 1, .. })
+        ));
+        let newer = state.serialize_versioned(2).unwrap();
+        assert!(matches!(
+            load(&newer),
+            Err(PersistenceError::NewerVersion {
+                found: 2,
+                supported: 1
+            })
+        ));
+    }
+
+    #[test]
+    pub fn test_indexes_are_rebuilt_on_load() {
+        let (state, [a, _]) = saved_state();
+        let output = state.graph[a].get_output("out").unwrap();
+        let loaded: TestEditorState = ron::from_str(&ron::to_string(&state).unwrap()).unwrap();
+        // Without drawing the editor first
+        assert_eq!(loaded.graph.output_connection_count(output), 1);
+        assert_eq!(
+            loaded.graph.node_by_external_id("ColorCamera@CAM_A"),
+            Some(a)
+        );
+    }
+
+    #[test]
+    pub fn test_legacy_connections() {
+        let (state, [a, b]) = saved_state();
+        let output = state.graph[a].get_output("out").unwrap();
+        let input = state.graph[b].get_input("in").unwrap();
+        let blob = ron::to_string(&state.graph).unwrap();
+        let connected = format!("Some([{}])", ron::to_string(&output).unwrap());
+        assert!(blob.contains(&connected));
+
+        // Saved when inputs had a single connection
+        let legacy = blob.replace(
+            &connected,
+            &format!("Some({})", ron::to_string(&output).unwrap()),
+        );
+        let graph: TestGraph = ron::from_str(&legacy).unwrap();
+        assert_eq!(graph.connections(input), [output]);
+        assert_eq!(graph.output_connection_count(output), 1);
+        assert_eq!(ron::to_string(&graph).unwrap(), blob);
+
+        // An empty list is no connection
+        let empty = blob.replace(&connected, "Some([])");
+        let graph: TestGraph = ron::from_str(&empty).unwrap();
+        assert!(!graph.is_input_connected(input));
+        assert_eq!(graph.connection(input), None);
+    }
+
+    #[test]
+    pub fn test_unknown_node_data_is_replaced() {
+        let (state, [a, b]) = saved_state();
+        // A template that no longer exists
+        let blob = state
+            .serialize_versioned(1)
+            .unwrap()
+            .replace("Panicking", "Removed");
+        assert!(matches!(
+            TestEditorState::deserialize_with_migrations(&blob, 1, &[], None),
+            Err(PersistenceError::Deserialize(_))
+        ));
+
+        let placeholder = |data: &str| {
+            assert!(data.contains("Removed"));
+            TestNodeData(TestTemplate::Vector)
+        };
+        let loaded =
+            TestEditorState::deserialize_with_migrations(&blob, 1, &[], Some(placeholder)).unwrap();
+        assert_eq!(loaded.graph[a].user_data.0, TestTemplate::Scalar);
+        assert_eq!(loaded.graph[b].user_data.0, TestTemplate::Vector);
+        assert_eq!(loaded.node_positions[b], egui::pos2(300.0, 20.0));
+        let input = loaded.graph[b].get_input("in").unwrap();
+        assert!(loaded.graph.is_input_connected(input));
+    }
+
+    #[test]
+    pub fn test_corrupted_blobs_are_errors() {
+        let (mut state, [_, b]) = saved_state();
+        let load: Load = |blob| TestEditorState::deserialize_with_migrations(blob, 1, &[], None);
+        let blob = state.serialize_versioned(1).unwrap();
+        for corrupted in [
+            &blob[..blob.len() / 2],
+            "",
+            "(version: 1, state: \"(\")",
+            "🦀",
+        ] {
+            assert!(load(corrupted).is_err(), "{}", corrupted);
+        }
+
+        let input = state.graph[b].get_input("in").unwrap();
+        state.graph.inputs.remove(input);
+        let blob = state.serialize_versioned(1).unwrap();
+        assert!(matches!(
+            load(&blob),
+            Err(PersistenceError::Inconsistent(_))
+        ));
+    }
+}
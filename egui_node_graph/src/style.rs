@@ -0,0 +1,187 @@
+use egui::{pos2, vec2, Color32, Pos2, Rect, Shape, Stroke, Visuals};
+
+use crate::color_hex_utils::color_from_hex;
+use crate::utils::ColorUtils;
+
+/// The colors and sizes the editor is drawn with. Set
+/// [`GraphEditorState::style`] to match the editor with the rest of the
+/// application. Sizes are in points at 100% zoom, and scale with the zoom.
+///
+/// Start from [`GraphStyle::from_egui_visuals`] to only change a few fields.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GraphStyle {
+    /// Painted over the whole editor, below the grid. Transparent by default,
+    /// showing the panel the editor is in.
+    pub background_color: Color32,
+    /// The color of the lines or dots of [`GraphEditorState::grid`].
+    pub grid_color: Color32,
+    /// The body of the nodes.
+    pub node_color: Color32,
+    /// The title bar of the nodes, unless [`NodeDataTrait::titlebar_color`]
+    /// picks another one.
+    pub title_bar_color: Color32,
+    /// The title of the nodes, and the labels of their parameters.
+    pub node_text_color: Color32,
+    /// The outline around the selected nodes.
+    pub selection_color: Color32,
+    /// The radius of the corners of the nodes.
+    pub node_rounding: f32,
+    /// The minimum height of the title bar. It grows to fit the title.
+    pub title_bar_height: f32,
+    /// The radius of the ports of expanded nodes. The stubs of collapsed
+    /// nodes are smaller.
+    pub port_radius: f32,
+    /// The width of the connections.
+    pub wire_width: f32,
+    /// Draw an arrowhead at the input end of the connections, pointing
+    /// towards the input.
+    pub arrowheads: bool,
+    /// Keep drawing the inline widget of connected inputs, disabled, instead
+    /// of [`WidgetValueTrait::value_widget_connected`]. Shows the constant
+    /// that is used again once the input is disconnected. Doesn't apply to
+    /// inputs of kind [`InputParamKind::ConnectionOnly`], which have none.
+    pub show_widget_when_connected: bool,
+    /// The background of the node finder.
+    pub finder_color: Color32,
+    /// The text of the node finder.
+    pub finder_text_color: Color32,
+}
+
+impl GraphStyle {
+    /// A style fitting the egui theme `visuals`, the one the editor uses
+    /// when [`GraphEditorState::style`] isn't set.
+    pub fn from_egui_visuals(visuals: &Visuals) -> Self {
+        let hex = |hex: &str| color_from_hex(hex).unwrap();
+        let (node_color, node_text_color, finder_color, finder_text_color) = if visuals.dark_mode {
+            (
+                hex("#3f3f3f"),
+                hex("#fefefe"),
+                hex("#3f3f3f"),
+                hex("#fefefe"),
+            )
+        } else {
+            (
+                hex("#ffffff"),
+                hex("#505050"),
+                hex("#fefefe"),
+                hex("#3f3f3f"),
+            )
+        };
+        Self {
+            background_color: Color32::TRANSPARENT,
+            grid_color: visuals
+                .widgets
+                .noninteractive
+                .bg_stroke
+                .color
+                .linear_multiply(0.5),
+            node_color,
+            title_bar_color: node_color.lighten(0.8),
+            node_text_color,
+            selection_color: Color32::WHITE.lighten(0.8),
+            node_rounding: 4.0,
+            title_bar_height: 0.0,
+            port_radius: 5.0,
+            wire_width: 5.0,
+            arrowheads: false,
+            show_widget_when_connected: false,
+            finder_color,
+            finder_text_color,
+        }
+    }
+}
+
+impl Default for GraphStyle {
+    fn default() -> Self {
+        Self::from_egui_visuals(&Visuals::dark())
+    }
+}
+
+/// The shape of the ports of a data type, see [`DataTypeTrait::port_shape`].
+/// Tells types apart without relying on their colors only.
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PortShape {
+    #[default]
+    Circle,
+    Square,
+    Diamond,
+    /// Pointing right, in the direction data flows.
+    Triangle,
+}
+
+impl PortShape {
+    /// The port centered on `center`, `radius` away from it. The corners of
+    /// the polygons reach a bit further, so they look as large as the circle.
+    /// The center stays where the connections are attached.
+    pub fn shape(self, center: Pos2, radius: f32, color: Color32) -> Shape {
+        match self {
+            PortShape::Circle => Shape::circle_filled(center, radius, color),
+            PortShape::Square => Shape::rect_filled(
+                Rect::from_center_size(center, vec2(1.8, 1.8) * radius),
+                0.0,
+                color,
+            ),
+            PortShape::Diamond => {
+                let r = 1.25 * radius;
+                Shape::convex_polygon(
+                    vec![
+                        center + vec2(r, 0.0),
+                        center + vec2(0.0, r),
+                        center - vec2(r, 0.0),
+                        center - vec2(0.0, r),
+                    ],
+                    color,
+                    Stroke::NONE,
+                )
+            }
+            PortShape::Triangle => {
+                // Centered on its centroid
+                let r = 1.3 * radius;
+                let half_side = r * 3f32.sqrt() / 2.0;
+                Shape::convex_polygon(
+                    vec![
+                        pos2(center.x + r, center.y),
+                        pos2(center.x - r / 2.0, center.y + half_side),
+                        pos2(center.x - r / 2.0, center.y - half_side),
+                    ],
+                    color,
+                    Stroke::NONE,
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::Vec2;
+
+    #[test]
+    pub fn test_port_shapes() {
+        let center = pos2(10.0, 20.0);
+        for port_shape in [
+            PortShape::Circle,
+            PortShape::Square,
+            PortShape::Diamond,
+            PortShape::Triangle,
+        ] {
+            let small = port_shape.shape(center, 5.0, Color32::RED);
+            let large = port_shape.shape(center, 10.0, Color32::RED);
+            // Connections are attached to the center of the polygons
+            if let Shape::Path(path) = &small {
+                let sum = path
+                    .points
+                    .iter()
+                    .fold(Vec2::ZERO, |sum, point| sum + point.to_vec2());
+                let centroid = (sum / path.points.len() as f32).to_pos2();
+                assert!(centroid.distance(center) < 0.01, "{:?}", port_shape);
+            }
+            // They scale with the radius
+            let (small, large) = (small.visual_bounding_rect(), large.visual_bounding_rect());
+            assert!((large.width() - 2.0 * small.width()).abs() < 0.01);
+            assert!((large.height() - 2.0 * small.height()).abs() < 0.01);
+            assert!(small.width() >= 9.0 && small.width() <= 13.0);
+        }
+    }
+}
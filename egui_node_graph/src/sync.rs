@@ -0,0 +1,391 @@
+use std::collections::{HashMap, HashSet};
+
+use super::*;
+use crate::ui_state::GRID_LAYOUT_SPACING;
+
+/// A node of an external description of the graph, merged into the editor by
+/// [`GraphEditorState::sync_from`].
+#[derive(Clone, Debug)]
+pub struct SyncNode<NodeTemplate> {
+    /// Matched against [`Node::external_id`]. Nodes without one, or whose id
+    /// isn't in the graph yet, are matched by label instead.
+    pub external_id: Option<String>,
+    pub label: String,
+    /// Builds the node when the graph doesn't have it yet.
+    pub template: NodeTemplate,
+}
+
+/// A connection of an external description of the graph, as indices into its
+/// [`SyncNode`]s and the names of their params.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyncConnection {
+    pub output_node: usize,
+    pub output: String,
+    pub input_node: usize,
+    pub input: String,
+}
+
+/// What [`GraphEditorState::sync_from`] changed, or what it would change for
+/// [`GraphEditorState::preview_sync`]. Nodes are listed by label.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub kept: Vec<String>,
+    /// Connections added between the nodes of the description.
+    pub connections_added: usize,
+    /// Connections removed between kept nodes. The connections of removed
+    /// nodes aren't counted.
+    pub connections_removed: usize,
+}
+
+impl<NodeData, DataType, ValueType, NodeTemplate, UserState, ConnectionData>
+    GraphEditorState<NodeData, DataType, ValueType, NodeTemplate, UserState, ConnectionData>
+{
+    /// The graph node each of `nodes` stands for, if any. Nodes are matched by
+    /// external id first, then the n-th node with a label is matched to the
+    /// n-th graph node without an external id that has that label.
+    fn match_sync_nodes<T>(&self, nodes: &[SyncNode<T>]) -> Vec<Option<NodeId>> {
+        let mut matched: Vec<Option<NodeId>> = nodes
+            .iter()
+            .map(|node| {
+                node.external_id
+                    .as_deref()
+                    .and_then(|id| self.graph.node_by_external_id(id))
+            })
+            .collect();
+        let mut taken: HashSet<NodeId> = matched.iter().flatten().copied().collect();
+        for (node, matched) in nodes.iter().zip(&mut matched) {
+            if matched.is_some() {
+                continue;
+            }
+            *matched = self
+                .graph
+                .nodes
+                .iter()
+                .find(|(id, candidate)| {
+                    !taken.contains(id)
+                        && candidate.external_id.is_none()
+                        && candidate.label == node.label
+                })
+                .map(|(id, _)| id);
+            taken.extend(*matched);
+        }
+        matched
+    }
+
+    /// The nodes [`Self::sync_from`] would add, remove and keep, to ask for
+    /// confirmation before merging. Connections aren't counted.
+    pub fn preview_sync<T>(&self, nodes: &[SyncNode<T>]) -> SyncReport {
+        let matched = self.match_sync_nodes(nodes);
+        let kept: HashSet<NodeId> = matched.iter().flatten().copied().collect();
+        SyncReport {
+            added: nodes
+                .iter()
+                .zip(&matched)
+                .filter(|(_, matched)| matched.is_none())
+                .map(|(node, _)| node.label.clone())
+                .collect(),
+            removed: self
+                .graph
+                .nodes
+                .iter()
+                .filter(|(id, _)| !kept.contains(id))
+                .map(|(_, node)| node.label.clone())
+                .collect(),
+            kept: matched
+                .iter()
+                .flatten()
+                .map(|id| self.graph[*id].label.clone())
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+impl<NodeData, DataType, ValueType, NodeTemplate, UserState, ConnectionData>
+    GraphEditorState<NodeData, DataType, ValueType, NodeTemplate, UserState, ConnectionData>
+where
+    NodeTemplate: NodeTemplateTrait<
+        NodeData = NodeData,
+        DataType = DataType,
+        ValueType = ValueType,
+        UserState = UserState,
+    >,
+{
+    /// Merges an external description of the graph into the editor, instead
+    /// of replacing it. Nodes are matched as for [`Self::preview_sync`]:
+    ///
+    /// - Graph nodes without a counterpart in `nodes` are removed.
+    /// - Missing nodes are built from their template, and placed next to the
+    ///   nodes they are connected to, or below the graph.
+    /// - Connections between the nodes are added or removed to match
+    ///   `connections`. The ones naming params that don't exist are ignored.
+    ///
+    /// The positions of the kept nodes are left untouched.
+    pub fn sync_from(
+        &mut self,
+        nodes: &[SyncNode<NodeTemplate>],
+        connections: &[SyncConnection],
+        user_state: &mut UserState,
+    ) -> SyncReport {
+        let mut report = self.preview_sync(nodes);
+        let mut matched = self.match_sync_nodes(nodes);
+
+        let kept: HashSet<NodeId> = matched.iter().flatten().copied().collect();
+        let stale: Vec<NodeId> = self
+            .graph
+            .iter_nodes()
+            .filter(|id| !kept.contains(id))
+            .collect();
+        self.remove_nodes(stale);
+
+        for (node, matched) in nodes.iter().zip(&mut matched) {
+            if let Some(id) = *matched {
+                // Nodes matched by label are matched by id from now on
+                if self.graph[id].external_id.is_none() && node.external_id.is_some() {
+                    // Only fails if another node has the id, which would have
+                    // matched it instead
+                    let _ = self.graph.set_external_id(id, node.external_id.clone());
+                }
+                continue;
+            }
+            let id = self.add_node(&node.template, egui::Pos2::ZERO, user_state);
+            self.graph.rename_node(id, node.label.clone());
+            let _ = self.graph.set_external_id(id, node.external_id.clone());
+            *matched = Some(id);
+        }
+        self.place_synced_nodes(&matched, &kept, connections);
+
+        // Only the connections between nodes of the description remain, in
+        // its order for inputs taking several connections
+        let mut wanted: Vec<(InputId, OutputId)> = connections
+            .iter()
+            .filter_map(|connection| {
+                let output_node = (*matched.get(connection.output_node)?)?;
+                let input_node = (*matched.get(connection.input_node)?)?;
+                let output = self.graph[output_node].get_output(&connection.output);
+                let input = self.graph[input_node].get_input(&connection.input);
+                Some((input.ok()?, output.ok()?))
+            })
+            .collect();
+        let mut seen = HashSet::new();
+        wanted.retain(|connection| seen.insert(*connection));
+        let unwanted: Vec<(InputId, OutputId)> = self
+            .graph
+            .iter_connections()
+            .filter(|connection| !seen.contains(connection))
+            .collect();
+        for (input, output) in unwanted {
+            if self.graph.remove_connection_between(output, input) {
+                self.reroutes.remove(&(input, output));
+                self.connection_data.remove(&(input, output));
+                report.connections_removed += 1;
+            }
+        }
+        for (input, output) in wanted {
+            if !self.graph.connections(input).contains(&output) {
+                self.graph.add_connection(output, input);
+                report.connections_added += 1;
+            }
+        }
+        report
+    }
+
+    /// Places the nodes added by [`Self::sync_from`] to the right of the
+    /// nodes they take an input from, or to the left of the nodes they feed,
+    /// stacking the nodes sharing a neighbor. The nodes not connected to the
+    /// kept ones start chains in a row below the graph.
+    fn place_synced_nodes(
+        &mut self,
+        matched: &[Option<NodeId>],
+        kept: &HashSet<NodeId>,
+        connections: &[SyncConnection],
+    ) {
+        let mut pending: Vec<NodeId> = matched
+            .iter()
+            .flatten()
+            .copied()
+            .filter(|id| !kept.contains(id))
+            .collect();
+        let mut placed: HashSet<NodeId> = kept.clone();
+        // How many nodes were stacked on each side of each neighbor
+        let mut stacked: HashMap<(NodeId, bool), usize> = HashMap::new();
+        let neighbor = |node: NodeId, placed: &HashSet<NodeId>| {
+            connections.iter().find_map(|connection| {
+                let output_node = (*matched.get(connection.output_node)?)?;
+                let input_node = (*matched.get(connection.input_node)?)?;
+                if input_node == node && placed.contains(&output_node) {
+                    Some((output_node, true))
+                } else if output_node == node && placed.contains(&input_node) {
+                    Some((input_node, false))
+                } else {
+                    None
+                }
+            })
+        };
+        let bottom = placed
+            .iter()
+            .filter_map(|id| self.node_positions.get(*id))
+            .map(|pos| pos.y + GRID_LAYOUT_SPACING.y)
+            .fold(None, |bottom: Option<f32>, y| {
+                Some(bottom.map_or(y, |bottom| bottom.max(y)))
+            })
+            .unwrap_or(0.0);
+        let mut row_x: f32 = 0.0;
+        loop {
+            let before = pending.len();
+            pending.retain(|node| match neighbor(*node, &placed) {
+                Some((anchor, right)) => {
+                    let count = stacked.entry((anchor, right)).or_default();
+                    let side = if right { 1.0 } else { -1.0 };
+                    let offset = egui::vec2(
+                        side * GRID_LAYOUT_SPACING.x,
+                        *count as f32 * GRID_LAYOUT_SPACING.y,
+                    );
+                    *count += 1;
+                    let position = self.node_positions[anchor] + offset;
+                    self.node_positions.insert(*node, position);
+                    row_x = row_x.max(position.x + GRID_LAYOUT_SPACING.x);
+                    placed.insert(*node);
+                    false
+                }
+                None => true,
+            });
+            if pending.len() != before {
+                continue;
+            }
+            if pending.is_empty() {
+                break;
+            }
+            // Starts a new chain in the row below the graph
+            let node = pending.remove(0);
+            self.node_positions.insert(node, egui::pos2(row_x, bottom));
+            row_x += GRID_LAYOUT_SPACING.x;
+            placed.insert(node);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    fn sync_node(external_id: Option<&str>, label: &str) -> SyncNode<TestTemplate> {
+        SyncNode {
+            external_id: external_id.map(Into::into),
+            label: label.into(),
+            template: TestTemplate::Scalar,
+        }
+    }
+
+    fn sync_connection(output_node: usize, input_node: usize) -> SyncConnection {
+        SyncConnection {
+            output_node,
+            output: "out".into(),
+            input_node,
+            input: "in".into(),
+        }
+    }
+
+    fn strings(labels: &[&str]) -> Vec<String> {
+        labels.iter().map(|label| label.to_string()).collect()
+    }
+
+    #[test]
+    pub fn test_sync_from() {
+        let mut state = TestEditorState::default();
+        let camera = sync_node(Some("Camera@A"), "Camera");
+        let encoder = sync_node(Some("Encoder@1"), "Encoder");
+        let report = state.sync_from(
+            &[camera.clone(), encoder.clone()],
+            &[sync_connection(0, 1)],
+            &mut (),
+        );
+        assert_eq!(report.added, strings(&["Camera", "Encoder"]));
+        assert_eq!(report.connections_added, 1);
+        let a = state.graph.node_by_external_id("Camera@A").unwrap();
+        let b = state.graph.node_by_external_id("Encoder@1").unwrap();
+        assert_eq!(
+            state.node_positions[b] - state.node_positions[a],
+            egui::vec2(250.0, 0.0)
+        );
+
+        // The user moves the camera around and renames it
+        state.node_positions[a] = egui::pos2(-40.0, 70.0);
+        state.graph.rename_node(a, "Main camera".into());
+
+        // The encoder is replaced by a detector and an output, fed by the
+        // camera
+        let nodes = [
+            camera,
+            sync_node(Some("Detector@2"), "Detector"),
+            sync_node(Some("XLinkOut@3"), "XLinkOut"),
+        ];
+        let connections = [sync_connection(0, 1), sync_connection(1, 2)];
+        let preview = state.preview_sync(&nodes);
+        let report = state.sync_from(&nodes, &connections, &mut ());
+        assert_eq!(
+            report,
+            SyncReport {
+                added: strings(&["Detector", "XLinkOut"]),
+                removed: strings(&["Encoder"]),
+                kept: strings(&["Main camera"]),
+                connections_added: 2,
+                connections_removed: 0,
+            }
+        );
+        assert_eq!(preview.added, report.added);
+        assert_eq!(preview.removed, report.removed);
+        assert_eq!(preview.kept, report.kept);
+
+        assert_eq!(state.node_positions[a], egui::pos2(-40.0, 70.0));
+        assert!(!state.graph.nodes.contains_key(b));
+        let detector = state.graph.node_by_external_id("Detector@2").unwrap();
+        let output = state.graph.node_by_external_id("XLinkOut@3").unwrap();
+        assert_eq!(state.node_positions[detector], egui::pos2(210.0, 70.0));
+        assert_eq!(state.node_positions[output], egui::pos2(460.0, 70.0));
+        assert_eq!(state.graph.iter_connections().count(), 2);
+
+        // Syncing the same description again changes nothing
+        let report = state.sync_from(&nodes, &connections, &mut ());
+        assert!(report.added.is_empty() && report.removed.is_empty());
+        assert_eq!(report.connections_added + report.connections_removed, 0);
+
+        // Only the delta of the connections is applied
+        let connections = [sync_connection(0, 2)];
+        let report = state.sync_from(&nodes, &connections, &mut ());
+        assert_eq!(report.connections_added, 1);
+        assert_eq!(report.connections_removed, 2);
+        let output_in = state.graph[output].get_input("in").unwrap();
+        let camera_out = state.graph[a].get_output("out").unwrap();
+        assert_eq!(state.graph.connection(output_in), Some(camera_out));
+    }
+
+    #[test]
+    pub fn test_sync_matches_by_label() {
+        let mut state = TestEditorState::default();
+        let nodes = [
+            sync_node(None, "Camera"),
+            sync_node(None, "Camera"),
+            sync_node(None, "Encoder"),
+        ];
+        state.sync_from(&nodes, &[], &mut ());
+        let ids: Vec<NodeId> = state.graph.iter_nodes().collect();
+        // Unconnected nodes go in a row
+        assert_eq!(state.node_positions[ids[1]], egui::pos2(250.0, 0.0));
+
+        // The second camera is matched to the second node labelled so, and
+        // takes the new id
+        let nodes = [
+            sync_node(None, "Camera"),
+            sync_node(Some("Camera@B"), "Camera"),
+        ];
+        let report = state.sync_from(&nodes, &[], &mut ());
+        assert_eq!(report.kept, strings(&["Camera", "Camera"]));
+        assert_eq!(report.removed, strings(&["Encoder"]));
+        assert!(report.added.is_empty());
+        assert_eq!(state.graph.node_by_external_id("Camera@B"), Some(ids[1]));
+    }
+}
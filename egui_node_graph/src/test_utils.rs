@@ -0,0 +1,379 @@
+//! Minimal implementations of the user traits, used by the unit tests.
+
+use super::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub enum TestDataType {
+    Scalar,
+    Vector,
+    /// A wildcard, drawn in gray until it's bound
+    Any,
+}
+
+/// Pressing <kbd>+</kbd> while hovering the label of an inline input
+/// increments its value.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub struct TestValue(pub u32);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TestResponse;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub enum TestTemplate {
+    /// A scalar input and a scalar output
+    Scalar,
+    /// A vector input and a vector output
+    Vector,
+    /// Like `Scalar`, but its `bottom_ui` panics
+    Panicking,
+    /// A scalar input and a vector output. The converter offered when a scalar
+    /// is dropped on a vector input, missing from the node finder.
+    ScalarToVector,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub struct TestNodeData(pub TestTemplate);
+
+/// Connection data labelled with its contents.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub struct TestLink(pub String);
+
+pub type TestGraph = Graph<TestNodeData, TestDataType, TestValue>;
+pub type TestEditorState =
+    GraphEditorState<TestNodeData, TestDataType, TestValue, TestTemplate, (), TestLink>;
+
+impl UserResponseTrait for TestResponse {}
+
+impl ConnectionDataTrait for TestLink {
+    fn label(&self) -> Option<std::borrow::Cow<'_, str>> {
+        Some(self.0.as_str().into())
+    }
+}
+
+impl DataTypeTrait<()> for TestDataType {
+    fn data_type_color(&self, _user_state: &mut ()) -> egui::Color32 {
+        match self {
+            TestDataType::Any => egui::Color32::GRAY,
+            _ => egui::Color32::WHITE,
+        }
+    }
+
+    fn name(&self) -> std::borrow::Cow<'_, str> {
+        format!("{:?}", self).into()
+    }
+
+    fn is_wildcard(&self) -> bool {
+        *self == TestDataType::Any
+    }
+}
+
+impl WidgetValueTrait for TestValue {
+    type Response = TestResponse;
+    type UserState = ();
+    type NodeData = TestNodeData;
+
+    fn value_widget(
+        &mut self,
+        param_name: &str,
+        _node_id: NodeId,
+        ui: &mut egui::Ui,
+        _user_state: &mut (),
+        _node_data: &TestNodeData,
+    ) -> Vec<TestResponse> {
+        let hovered = ui.label(param_name).hovered();
+        if hovered && ui.input(|i| i.key_pressed(egui::Key::PlusEquals)) {
+            self.0 += 1;
+        }
+        Vec::new()
+    }
+
+    fn value_widget_connected(
+        &mut self,
+        param_name: &str,
+        _node_id: NodeId,
+        ui: &mut egui::Ui,
+        _user_state: &mut (),
+        _node_data: &TestNodeData,
+    ) -> Vec<TestResponse> {
+        ui.label(format!("{} (connected)", param_name));
+        Vec::new()
+    }
+}
+
+impl NodeDataTrait for TestNodeData {
+    type Response = TestResponse;
+    type UserState = ();
+    type DataType = TestDataType;
+    type ValueType = TestValue;
+
+    fn bottom_ui(
+        &self,
+        _ui: &mut egui::Ui,
+        _node_id: NodeId,
+        _graph: &TestGraph,
+        _user_state: &mut (),
+    ) -> Vec<NodeResponse<TestResponse, TestNodeData>> {
+        if self.0 == TestTemplate::Panicking {
+            panic!("bottom_ui of a panicking test node");
+        }
+        Vec::new()
+    }
+
+    fn template_name(
+        &self,
+        _node_id: NodeId,
+        _graph: &TestGraph,
+        _user_state: &mut (),
+    ) -> Option<std::borrow::Cow<'_, str>> {
+        Some(format!("{:?}", self.0).into())
+    }
+
+    /// Nodes labelled `protected` can't be deleted.
+    fn can_delete(&self, node_id: NodeId, graph: &TestGraph, _user_state: &mut ()) -> bool {
+        graph[node_id].label != "protected"
+    }
+
+    /// Nodes labelled `status` show a dot next to their title.
+    fn top_bar_ui(
+        &self,
+        ui: &mut egui::Ui,
+        node_id: NodeId,
+        graph: &TestGraph,
+        _user_state: &mut (),
+    ) -> Vec<NodeResponse<TestResponse, TestNodeData>> {
+        if graph[node_id].label == "status" {
+            ui.label("●");
+        }
+        Vec::new()
+    }
+
+    /// The `in` inputs are described.
+    fn param_description(
+        &self,
+        node_id: NodeId,
+        param_id: AnyParameterId,
+        graph: &TestGraph,
+        _user_state: &mut (),
+    ) -> Option<std::borrow::Cow<'_, str>> {
+        let input = graph[node_id].get_input("in").ok()?;
+        (param_id == AnyParameterId::Input(input)).then(|| "The value to forward".into())
+    }
+
+    /// Nodes labelled `detailed` are always drawn in full.
+    fn always_detailed(&self, node_id: NodeId, graph: &TestGraph, _user_state: &mut ()) -> bool {
+        graph[node_id].label == "detailed"
+    }
+
+    /// Nodes labelled `expanded` can't be collapsed.
+    fn can_collapse(&self, node_id: NodeId, graph: &TestGraph, _user_state: &mut ()) -> bool {
+        graph[node_id].label != "expanded"
+    }
+
+    /// Nodes labelled `variadic` can be given more `in` inputs.
+    fn can_add_input(
+        &self,
+        node_id: NodeId,
+        graph: &TestGraph,
+        _user_state: &mut (),
+    ) -> Option<(String, TestDataType, TestValue, InputParamKind)> {
+        (graph[node_id].label == "variadic").then(|| {
+            (
+                "in".into(),
+                TestDataType::Scalar,
+                TestValue::default(),
+                InputParamKind::ConnectionOrConstant,
+            )
+        })
+    }
+
+    /// All the inputs of `variadic` nodes but the first can be removed.
+    fn can_remove_input(
+        &self,
+        node_id: NodeId,
+        input: InputId,
+        graph: &TestGraph,
+        _user_state: &mut (),
+    ) -> bool {
+        graph[node_id].label == "variadic" && graph[node_id].get_input("in").ok() != Some(input)
+    }
+
+    /// Inputs named `locked` refuse all connections.
+    fn can_connect(
+        &self,
+        node_id: NodeId,
+        output: OutputId,
+        input: InputId,
+        graph: &TestGraph,
+        _user_state: &mut (),
+    ) -> Result<(), String> {
+        if graph[node_id].get_input("locked").ok() == Some(input) {
+            Err("This input is locked".into())
+        } else if !graph.types_accept_connection(output, input) {
+            Err("Mismatching types".into())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl NodeTemplateTrait for TestTemplate {
+    type NodeData = TestNodeData;
+    type DataType = TestDataType;
+    type ValueType = TestValue;
+    type UserState = ();
+    type CategoryType = ();
+
+    fn node_finder_label(&self, _user_state: &mut ()) -> std::borrow::Cow<'_, str> {
+        format!("{:?}", self).into()
+    }
+
+    fn node_graph_label(&self, _user_state: &mut ()) -> String {
+        format!("{:?}", self)
+    }
+
+    fn user_data(&self, _user_state: &mut ()) -> TestNodeData {
+        TestNodeData(*self)
+    }
+
+    fn build_node(&self, graph: &mut TestGraph, _user_state: &mut (), node_id: NodeId) {
+        let (input, output) = match self {
+            TestTemplate::Scalar | TestTemplate::Panicking => {
+                (TestDataType::Scalar, TestDataType::Scalar)
+            }
+            TestTemplate::Vector => (TestDataType::Vector, TestDataType::Vector),
+            TestTemplate::ScalarToVector => (TestDataType::Scalar, TestDataType::Vector),
+        };
+        graph.add_input_param(
+            node_id,
+            "in".into(),
+            input,
+            TestValue::default(),
+            InputParamKind::ConnectionOrConstant,
+            true,
+        );
+        graph.add_output_param(node_id, "out".into(), output);
+    }
+
+    /// Scalars are converted to vectors, never the other way around.
+    fn converter(from: &TestDataType, to: &TestDataType, _user_state: &mut ()) -> Option<Self> {
+        (*from == TestDataType::Scalar && *to == TestDataType::Vector)
+            .then_some(TestTemplate::ScalarToVector)
+    }
+}
+
+pub struct AllTestTemplates;
+impl NodeTemplateIter for AllTestTemplates {
+    type Item = TestTemplate;
+
+    fn all_kinds(&self) -> Vec<TestTemplate> {
+        vec![
+            TestTemplate::Scalar,
+            TestTemplate::Vector,
+            TestTemplate::Panicking,
+        ]
+    }
+}
+
+/// Draws the editor in a headless egui context for a single frame.
+pub fn draw_frame(
+    state: &mut TestEditorState,
+    ctx: &egui::Context,
+) -> GraphResponse<TestResponse, TestNodeData> {
+    draw_frame_with_input(state, ctx, egui::RawInput::default())
+}
+
+/// Like [`draw_frame`], feeding the given input to the frame.
+pub fn draw_frame_with_input(
+    state: &mut TestEditorState,
+    ctx: &egui::Context,
+    input: egui::RawInput,
+) -> GraphResponse<TestResponse, TestNodeData> {
+    draw_frame_with_responses(state, ctx, input, Vec::new())
+}
+
+/// Draws a frame with the pointer over the editor, where it stays for the
+/// next frames. The keyboard shortcuts of the editor require it.
+pub fn hover_editor(state: &mut TestEditorState, ctx: &egui::Context) {
+    let input = egui::RawInput {
+        events: vec![egui::Event::PointerMoved(egui::pos2(1.0, 1.0))],
+        ..Default::default()
+    };
+    draw_frame_with_input(state, ctx, input);
+}
+
+/// Like [`draw_frame_with_input`], passing `prepend_responses` to the editor.
+pub fn draw_frame_with_responses(
+    state: &mut TestEditorState,
+    ctx: &egui::Context,
+    input: egui::RawInput,
+    prepend_responses: Vec<NodeResponse<TestResponse, TestNodeData>>,
+) -> GraphResponse<TestResponse, TestNodeData> {
+    run_frame(state, ctx, input, prepend_responses).0
+}
+
+/// Like [`draw_frame_with_input`], returning all the shapes painted during the
+/// frame, by the editor or by egui, with the nested ones flattened.
+pub fn draw_frame_shapes(
+    state: &mut TestEditorState,
+    ctx: &egui::Context,
+    input: egui::RawInput,
+) -> Vec<egui::Shape> {
+    fn collect(shape: egui::Shape, shapes: &mut Vec<egui::Shape>) {
+        match shape {
+            egui::Shape::Vec(nested) => nested.into_iter().for_each(|shape| collect(shape, shapes)),
+            shape => shapes.push(shape),
+        }
+    }
+    let output = run_frame(state, ctx, input, Vec::new()).1;
+    let mut shapes = Vec::new();
+    for clipped in output.shapes {
+        collect(clipped.1, &mut shapes);
+    }
+    shapes
+}
+
+/// Like [`draw_frame_with_input`], returning all the text painted during the
+/// frame, by the editor or by egui.
+pub fn draw_frame_texts(
+    state: &mut TestEditorState,
+    ctx: &egui::Context,
+    input: egui::RawInput,
+) -> Vec<String> {
+    draw_frame_shapes(state, ctx, input)
+        .into_iter()
+        .filter_map(|shape| match shape {
+            egui::Shape::Text(text) => Some(text.galley.text().to_owned()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn run_frame(
+    state: &mut TestEditorState,
+    ctx: &egui::Context,
+    input: egui::RawInput,
+    prepend_responses: Vec<NodeResponse<TestResponse, TestNodeData>>,
+) -> (GraphResponse<TestResponse, TestNodeData>, egui::FullOutput) {
+    let mut response = None;
+    let mut prepend_responses = Some(prepend_responses);
+    let output = ctx.run(input, |ctx| {
+        // Without a frame, the editor starts at the top-left corner of the
+        // screen, so graph and screen positions are easy to relate.
+        egui::CentralPanel::default()
+            .frame(egui::Frame::none())
+            .show(ctx, |ui| {
+                response = Some(state.draw_graph_editor(
+                    ui,
+                    AllTestTemplates,
+                    &mut (),
+                    prepend_responses.take().unwrap_or_default(),
+                ));
+            });
+    });
+    (response.expect("The editor should have been drawn"), output)
+}
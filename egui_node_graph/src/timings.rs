@@ -0,0 +1,83 @@
+use std::time::{Duration, Instant};
+
+/// The number of frames kept in [`crate::GraphEditorState::timings`].
+pub const TIMINGS_HISTORY: usize = 120;
+
+/// The time spent in each phase of drawing the graph editor, for one frame.
+#[derive(Default, Copy, Clone, Debug, PartialEq)]
+pub struct FrameTimings {
+    /// Keeping the editor state in sync with the graph, and setting up the
+    /// background.
+    pub background: Duration,
+    /// Laying out and drawing the nodes and their widgets, along with the
+    /// node finder and popups.
+    pub nodes: Duration,
+    /// Drawing the connections.
+    pub connections: Duration,
+    /// Handling the node responses and the mouse input.
+    pub interaction: Duration,
+}
+
+impl FrameTimings {
+    pub fn total(&self) -> Duration {
+        self.background + self.nodes + self.connections + self.interaction
+    }
+}
+
+/// Measures the phases of a frame. When disabled, the clock is never read.
+pub(crate) struct FrameTimer {
+    last: Option<Instant>,
+    timings: FrameTimings,
+}
+
+impl FrameTimer {
+    /// `Instant` is not available on the web, timings are never collected
+    /// there.
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            last: (enabled && !cfg!(target_arch = "wasm32")).then(Instant::now),
+            timings: FrameTimings::default(),
+        }
+    }
+
+    /// Adds the time elapsed since the previous lap to the given phase.
+    pub fn lap(&mut self, phase: impl FnOnce(&mut FrameTimings) -> &mut Duration) {
+        if let Some(last) = self.last {
+            let now = Instant::now();
+            *phase(&mut self.timings) += now - last;
+            self.last = Some(now);
+        }
+    }
+
+    /// Returns the collected timings, if enabled.
+    pub fn finish(self) -> Option<FrameTimings> {
+        self.last.map(|_| self.timings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    pub fn test_timings_are_collected_when_enabled() {
+        let ctx = egui::Context::default();
+        let mut state = TestEditorState::new(1.0);
+        state.add_node(&TestTemplate::Scalar, egui::Pos2::ZERO, &mut ());
+
+        draw_frame(&mut state, &ctx);
+        assert!(state.timings().is_empty());
+
+        state.collect_timings = true;
+        for _ in 0..TIMINGS_HISTORY + 5 {
+            draw_frame(&mut state, &ctx);
+        }
+        assert_eq!(state.timings().len(), TIMINGS_HISTORY);
+        assert!(state.timings().iter().all(|t| t.total() > Duration::ZERO));
+
+        state.collect_timings = false;
+        draw_frame(&mut state, &ctx);
+        assert!(state.timings().is_empty());
+    }
+}
@@ -19,6 +19,10 @@ pub trait WidgetValueTrait: Default {
     /// The return value is a vector of custom response objects which can be used
     /// to implement handling of side effects. If unsure, the response Vec can
     /// be empty.
+    ///
+    /// When the editor is locked, see [`GraphEditorState::interaction_mode`],
+    /// the `ui` is disabled: `ui.is_enabled()` is false and the egui widgets
+    /// added to it can't be changed.
     fn value_widget(
         &mut self,
         param_name: &str,
@@ -56,6 +60,14 @@ pub trait DataTypeTrait<UserState>: PartialEq + Eq {
     /// The associated port color of this datatype
     fn data_type_color(&self, user_state: &mut UserState) -> egui::Color32;
 
+    /// The shape of the ports of this datatype, so types can be told apart
+    /// without relying on their colors only.
+    ///
+    /// Default implementation draws circles.
+    fn port_shape(&self, _user_state: &mut UserState) -> PortShape {
+        PortShape::Circle
+    }
+
     /// The name of this datatype. Return type is specified as Cow<str> because
     /// some implementations will need to allocate a new string to provide an
     /// answer while others won't.
@@ -84,7 +96,27 @@ pub trait DataTypeTrait<UserState>: PartialEq + Eq {
     ///     }
     /// }
     /// ```
-    fn name(&self) -> std::borrow::Cow<str>;
+    fn name(&self) -> std::borrow::Cow<'_, str>;
+
+    /// Whether this is a wildcard type, for the ports of generic nodes. A
+    /// wildcard port accepts connections of any type, and takes the type of
+    /// the port it gets connected to, see [`Graph::resolved_type`]. The editor
+    /// reports it with [`NodeResponse::WildcardBound`].
+    ///
+    /// Default implementation has no wildcards.
+    fn is_wildcard(&self) -> bool {
+        false
+    }
+
+    /// Whether an input of this type accepts connections from outputs of type
+    /// `other`. This is what the editor consults when connecting ports,
+    /// instead of comparing their types.
+    ///
+    /// Default implementation accepts the same type, and a wildcard accepts
+    /// any type.
+    fn superset_of(&self, other: &Self) -> bool {
+        self.is_wildcard() || self == other
+    }
 }
 
 /// This trait must be implemented for the `NodeData` generic parameter of the
@@ -113,7 +145,13 @@ where
     where
         Self::Response: UserResponseTrait;
 
-    /// UI to draw on the top bar of the node.
+    /// UI to draw on the top bar of the node, after the title and before the
+    /// close button, e.g. a status indicator or a button opening a popup. The
+    /// responses are handled like the ones of [`Self::bottom_ui`].
+    ///
+    /// The `ui` lays out horizontally, centered on the title, and its text
+    /// color is the one of the title. Content taller than the title makes
+    /// the title bar grow, see [`GraphStyle::title_bar_height`].
     fn top_bar_ui(
         &self,
         _ui: &mut egui::Ui,
@@ -176,6 +214,65 @@ where
     ) {
     }
 
+    /// Invoked every frame with the response of each port of the node. This
+    /// can be used to react to interactions that the library doesn't handle by
+    /// itself, e.g. showing a context menu with `response.clone().context_menu`
+    /// or checking for modifier clicks.
+    ///
+    /// Right-clicking a port opens the node finder, unless
+    /// [`InputBindings::port_context_menus`] is set to leave the secondary
+    /// button to the ports.
+    ///
+    /// Default implementation does nothing.
+    fn port_response(
+        &self,
+        _response: &egui::Response,
+        _node_id: NodeId,
+        _param_id: AnyParameterId,
+        _graph: &Graph<Self, Self::DataType, Self::ValueType>,
+        _user_state: &mut Self::UserState,
+    ) -> Vec<NodeResponse<Self::Response, Self>>
+    where
+        Self::Response: UserResponseTrait,
+    {
+        Default::default()
+    }
+
+    /// A short help text for a param of the node, shown in the tooltip of its
+    /// port and when hovering its row in the node.
+    ///
+    /// Default implementation describes nothing.
+    fn param_description(
+        &self,
+        _node_id: NodeId,
+        _param_id: AnyParameterId,
+        _graph: &Graph<Self, Self::DataType, Self::ValueType>,
+        _user_state: &mut Self::UserState,
+    ) -> Option<std::borrow::Cow<'_, str>> {
+        None
+    }
+
+    /// Additional UI shown at the bottom of the tooltip of a port, after the
+    /// name, data type and connections of its param.
+    ///
+    /// Default implementation adds nothing.
+    fn port_tooltip_ui(
+        &self,
+        _ui: &mut egui::Ui,
+        _node_id: NodeId,
+        _param_id: AnyParameterId,
+        _graph: &Graph<Self, Self::DataType, Self::ValueType>,
+        _user_state: &mut Self::UserState,
+    ) {
+    }
+
+    /// Whether the user can delete the node. Protected nodes have no close
+    /// button and their Delete context menu entry is disabled. Deleting them
+    /// with <kbd>Delete</kbd>, along with their group, or with a
+    /// [`NodeResponse::DeleteNodeUi`] emits [`NodeResponse::DeleteDenied`]
+    /// instead. [`GraphEditorState::remove_node`] ignores this.
+    ///
+    /// Default implementation allows deleting every node.
     fn can_delete(
         &self,
         _node_id: NodeId,
@@ -184,6 +281,132 @@ where
     ) -> bool {
         true
     }
+
+    /// Additional entries of the context menu of the node, shown when it's
+    /// right-clicked. They come before the entries of the editor: Delete,
+    /// Duplicate, Disconnect all and Bring to front. The menu is closed when
+    /// responses are returned.
+    ///
+    /// Default implementation adds no entries.
+    fn context_menu_ui(
+        &self,
+        _ui: &mut egui::Ui,
+        _node_id: NodeId,
+        _graph: &Graph<Self, Self::DataType, Self::ValueType>,
+        _user_state: &mut Self::UserState,
+    ) -> Vec<NodeResponse<Self::Response, Self>>
+    where
+        Self::Response: UserResponseTrait,
+    {
+        Default::default()
+    }
+
+    /// Whether the node is always drawn in full, even when the editor is
+    /// zoomed out past [`GraphEditorState::low_detail_width`]. Useful for
+    /// nodes showing a live preview.
+    ///
+    /// Default implementation draws every node in low detail.
+    fn always_detailed(
+        &self,
+        _node_id: NodeId,
+        _graph: &Graph<Self, Self::DataType, Self::ValueType>,
+        _user_state: &mut Self::UserState,
+    ) -> bool {
+        false
+    }
+
+    /// Whether the node can be collapsed to its title bar. Nodes for which
+    /// this returns false don't show the collapse toggle, and are always drawn
+    /// expanded, even if they are listed in
+    /// [`GraphEditorState::collapsed_nodes`].
+    ///
+    /// Default implementation allows collapsing every node.
+    fn can_collapse(
+        &self,
+        _node_id: NodeId,
+        _graph: &Graph<Self, Self::DataType, Self::ValueType>,
+        _user_state: &mut Self::UserState,
+    ) -> bool {
+        true
+    }
+
+    /// The input added to the node when the `+` button shown after its inputs
+    /// is clicked: its name, data type, initial value and kind. The name is
+    /// made unique by appending a number to it if needed, see
+    /// [`Node::unique_input_name`]. The input is shown inline.
+    ///
+    /// Default implementation shows no `+` button.
+    fn can_add_input(
+        &self,
+        _node_id: NodeId,
+        _graph: &Graph<Self, Self::DataType, Self::ValueType>,
+        _user_state: &mut Self::UserState,
+    ) -> Option<(String, Self::DataType, Self::ValueType, InputParamKind)> {
+        None
+    }
+
+    /// Whether `input`, one of the inputs of this node, shows a button
+    /// removing it along with its connections, see
+    /// [`NodeResponse::RemoveInput`]. Only inputs shown inline have a row to
+    /// put the button in.
+    ///
+    /// Default implementation removes no input.
+    fn can_remove_input(
+        &self,
+        _node_id: NodeId,
+        _input: InputId,
+        _graph: &Graph<Self, Self::DataType, Self::ValueType>,
+        _user_state: &mut Self::UserState,
+    ) -> bool {
+        false
+    }
+
+    /// Consulted before connecting `output` to `input`, one of the inputs of
+    /// this node. When an error is returned, the connection is not made, a
+    /// [`NodeResponse::RejectedConnection`] is emitted and the message is shown
+    /// next to the input for a moment.
+    ///
+    /// This can be called several times for the same connection, so it should
+    /// be cheap and have no side effects.
+    ///
+    /// Default implementation accepts connections between ports of the same
+    /// data type, see [`Graph::types_accept_connection`]. Connections dropped
+    /// on a port of a different data type are offered a converter node, see
+    /// [`NodeTemplateTrait::converter`].
+    fn can_connect(
+        &self,
+        _node_id: NodeId,
+        output: OutputId,
+        input: InputId,
+        graph: &Graph<Self, Self::DataType, Self::ValueType>,
+        _user_state: &mut Self::UserState,
+    ) -> Result<(), String>
+    where
+        Self::DataType: DataTypeTrait<Self::UserState>,
+    {
+        if graph.types_accept_connection(output, input) {
+            Ok(())
+        } else {
+            let from = graph.resolved_type(AnyParameterId::Output(output));
+            let to = graph.resolved_type(AnyParameterId::Input(input));
+            Err(format!("Cannot connect {} to {}", from.name(), to.name()))
+        }
+    }
+
+    /// The name of the kind of node, e.g. the label of its template in the
+    /// node finder. The find-node palette matches it along with the label of
+    /// the node, see [`GraphEditorState::find_nodes`], so renamed nodes can
+    /// still be found by kind.
+    ///
+    /// Default implementation has no name, only labels are matched.
+    fn template_name(
+        &self,
+        _node_id: NodeId,
+        _graph: &Graph<Self, Self::DataType, Self::ValueType>,
+        _user_state: &mut Self::UserState,
+    ) -> Option<std::borrow::Cow<'_, str>> {
+        None
+    }
 }
 
 /// This trait can be implemented by any user type. The trait tells the library
@@ -214,7 +437,7 @@ impl CategoryTrait for () {
     }
 }
 
-impl<'a> CategoryTrait for &'a str {
+impl CategoryTrait for &str {
     fn name(&self) -> String {
         self.to_string()
     }
@@ -251,7 +474,7 @@ pub trait NodeTemplateTrait: Clone {
     /// The return type is Cow<str> to allow returning owned or borrowed values
     /// more flexibly. Refer to the documentation for `DataTypeTrait::name` for
     /// more information
-    fn node_finder_label(&self, user_state: &mut Self::UserState) -> std::borrow::Cow<str>;
+    fn node_finder_label(&self, user_state: &mut Self::UserState) -> std::borrow::Cow<'_, str>;
 
     /// Vec of categories to which the node belongs.
     ///
@@ -262,6 +485,22 @@ pub trait NodeTemplateTrait: Clone {
         Vec::default()
     }
 
+    /// A short explanation of what the node does, shown in a muted line under
+    /// its label in the node finder. The query of the finder is also looked
+    /// up in it. None by default.
+    fn node_finder_description(
+        &self,
+        _user_state: &mut Self::UserState,
+    ) -> Option<std::borrow::Cow<'_, str>> {
+        None
+    }
+
+    /// A character shown before the label of the node in the node finder,
+    /// e.g. an emoji. None by default.
+    fn node_finder_icon(&self, _user_state: &mut Self::UserState) -> Option<char> {
+        None
+    }
+
     /// Returns a descriptive name for the node kind, used in the graph.
     fn node_graph_label(&self, user_state: &mut Self::UserState) -> String;
 
@@ -271,14 +510,49 @@ pub trait NodeTemplateTrait: Clone {
     /// This function is run when this node kind gets added to the graph. The
     /// node will be empty by default, and this function can be used to fill its
     /// parameters.
+    ///
+    /// The editor may also build nodes in a scratch graph to find out about
+    /// their parameters, see [`GraphEditorState::drop_on_background`], so this
+    /// shouldn't have side effects on `user_state`.
     fn build_node(
         &self,
         graph: &mut Graph<Self::NodeData, Self::DataType, Self::ValueType>,
         user_state: &mut Self::UserState,
         node_id: NodeId,
     );
+
+    /// Returns the template of a node that converts values of type `from` into
+    /// values of type `to`, if any. When a connection is dropped on a port of
+    /// an incompatible data type, the editor offers to insert a converter node
+    /// in between.
+    ///
+    /// The node built from the returned template must have an input of type
+    /// `from` and an output of type `to`. The first ones found are connected.
+    ///
+    /// Default implementation provides no converters.
+    fn converter(
+        _from: &Self::DataType,
+        _to: &Self::DataType,
+        _user_state: &mut Self::UserState,
+    ) -> Option<Self> {
+        None
+    }
 }
 
 /// The custom user response types when drawing nodes in the graph must
 /// implement this trait.
+/// This trait must be implemented by the `ConnectionData` generic parameter
+/// of the [`GraphEditorState`], the user data attached to connections. It's
+/// implemented for `()`, the default, which attaches nothing.
+pub trait ConnectionDataTrait {
+    /// A short text, or an icon, drawn at the middle of the connection.
+    ///
+    /// Default implementation draws nothing.
+    fn label(&self) -> Option<std::borrow::Cow<'_, str>> {
+        None
+    }
+}
+
+impl ConnectionDataTrait for () {}
+
 pub trait UserResponseTrait: Clone + std::fmt::Debug {}
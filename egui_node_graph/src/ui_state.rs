@@ -1,22 +1,399 @@
 use super::*;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::marker::PhantomData;
 
 #[cfg(feature = "persistence")]
 use serde::{Deserialize, Serialize};
 
-#[derive(Default, Copy, Clone)]
+/// The smallest zoom factor of the graph editor.
+pub const MIN_ZOOM: f32 = 0.2;
+/// The largest zoom factor of the graph editor.
+pub const MAX_ZOOM: f32 = 2.5;
+
+/// The margin left around the nodes by [`GraphEditorState::fit_to_screen`],
+/// in screen units.
+const FIT_MARGIN: f32 = 40.0;
+
+/// The estimated width of the nodes that haven't been drawn yet.
+const ESTIMATED_NODE_WIDTH: f32 = 200.0;
+
+/// The default [`GraphEditorState::low_detail_width`].
+pub const DEFAULT_LOW_DETAIL_WIDTH: f32 = 60.0;
+
+#[cfg(feature = "persistence")]
+fn default_low_detail_width() -> f32 {
+    DEFAULT_LOW_DETAIL_WIDTH
+}
+
+#[cfg(feature = "persistence")]
+fn default_true() -> bool {
+    true
+}
+
+/// Writes the maps keyed by connection, like the reroute points, as a list,
+/// since formats like JSON only allow strings as map keys.
+#[cfg(feature = "persistence")]
+mod connection_map_as_list {
+    use super::*;
+
+    type ConnectionMap<T> = HashMap<(InputId, OutputId), T>;
+
+    pub fn serialize<T: Serialize, S: serde::Serializer>(
+        map: &ConnectionMap<T>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(map)
+    }
+
+    pub fn deserialize<'de, T: Deserialize<'de>, D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<ConnectionMap<T>, D::Error> {
+        let entries = Vec::<((InputId, OutputId), T)>::deserialize(deserializer)?;
+        Ok(entries.into_iter().collect())
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
 pub struct PanZoom {
+    /// The offset of the graph origin from the top-left corner of the editor,
+    /// in screen units.
     pub pan: egui::Vec2,
+    /// How many screen units a graph unit takes. Clamped between
+    /// [`MIN_ZOOM`] and [`MAX_ZOOM`] when drawing the editor.
     pub zoom: f32,
 }
 
+impl Default for PanZoom {
+    fn default() -> Self {
+        Self {
+            pan: egui::Vec2::ZERO,
+            zoom: 1.0,
+        }
+    }
+}
+
+/// A change of the view requested outside of drawing. It's applied the next
+/// time the editor is drawn, once its size is known.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum ViewRequest {
+    FitToScreen,
+    CenterOnNode(NodeId),
+}
+
+/// How the grid behind the graph is drawn.
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub enum GridStyle {
+    #[default]
+    Hidden,
+    Dots,
+    Lines,
+}
+
+/// The grid behind the graph, which dragged nodes can snap to.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub struct Grid {
+    pub style: GridStyle,
+    /// The distance between two grid lines, in graph units. When zoomed out,
+    /// only every other line is drawn until they are far enough apart.
+    pub spacing: f32,
+    /// When set, the position of dragged nodes snaps to the grid, even if it
+    /// isn't drawn. The other selected nodes keep their offset to the dragged
+    /// one. Holding <kbd>Ctrl</kbd> (<kbd>Cmd</kbd> on macOS) while dragging
+    /// disables snapping.
+    pub snap: bool,
+}
+
+impl Default for Grid {
+    fn default() -> Self {
+        Self {
+            style: GridStyle::Hidden,
+            spacing: 20.0,
+            snap: false,
+        }
+    }
+}
+
+/// Highlights the connections of the node or port under the pointer, see
+/// [`GraphEditorState::hover_highlight`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub struct HoverHighlight {
+    /// When set, hovering a node brightens its connections and outlines the
+    /// nodes it's connected to, while the other connections fade. Hovering a
+    /// port only highlights the connections of that port.
+    pub enabled: bool,
+    /// How long the pointer has to stay on a node or port before it's
+    /// highlighted, in seconds.
+    pub delay: f64,
+    /// The opacity of the faded connections, between 0 and 1.
+    pub faded_opacity: f32,
+}
+
+impl Default for HoverHighlight {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            delay: 0.3,
+            faded_opacity: 0.3,
+        }
+    }
+}
+
+/// Guides shown while dragging a node into alignment with another one, see
+/// [`GraphEditorState::alignment_guides`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub struct AlignmentGuides {
+    /// When set, a dragged node whose left edge, horizontal center or top
+    /// edge comes close to the same edge of a visible node snaps to it, and a
+    /// guide line is drawn between the two. This wins over snapping to the
+    /// grid. Holding <kbd>Ctrl</kbd> (<kbd>Cmd</kbd> on macOS) while dragging
+    /// disables both.
+    pub enabled: bool,
+    /// How close the edges have to be for the node to snap, in screen pixels.
+    pub threshold: f32,
+}
+
+impl Default for AlignmentGuides {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            threshold: 6.0,
+        }
+    }
+}
+
+/// Opens the node finder when the secondary button is pressed on the
+/// background, or when it's released, see [`InputBindings::finder_trigger`].
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub enum FinderTrigger {
+    /// Opens it when the button is released. A release ending a pan gesture
+    /// that moved the view doesn't open it, so the secondary button can also
+    /// be the [`InputBindings::pan_button`].
+    #[default]
+    Release,
+    /// Opens it as soon as the button is pressed.
+    Press,
+}
+
+/// The pointer buttons, keys and modifiers panning and zooming the view, and
+/// deciding what drags do, see [`GraphEditorState::input_bindings`]. The
+/// defaults match the behavior of earlier versions.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "persistence", serde(default))]
+pub struct InputBindings {
+    /// Dragging with this button pans the view. The pan wins over the nodes:
+    /// starting the drag on a node doesn't move it.
+    pub pan_button: egui::PointerButton,
+    /// When set, dragging with the primary button while holding
+    /// <kbd>Space</kbd> pans the view too.
+    pub space_drag_pans: bool,
+    /// When set, scrolling zooms the view. Otherwise scrolling pans it, and
+    /// only scrolling while holding [`Self::zoom_modifier`] zooms.
+    pub scroll_zooms: bool,
+    /// The modifiers to hold for scrolling to zoom when
+    /// [`Self::scroll_zooms`] isn't set.
+    pub zoom_modifier: egui::Modifiers,
+    /// When the secondary button opens the node finder, or the menu of the
+    /// connection under the pointer.
+    pub finder_trigger: FinderTrigger,
+    /// When set, the secondary button doesn't open the node finder over
+    /// ports, so they can show their own context menu from
+    /// [`NodeDataTrait::port_response`].
+    pub port_context_menus: bool,
+    /// Whether ports or the node body win drags starting near a port.
+    pub interaction_priority: InteractionPriority,
+    /// Holding these keys temporarily forces
+    /// [`InteractionPriority::PreferConnect`] or
+    /// [`InteractionPriority::PreferMove`], respectively. `None` disables the
+    /// key.
+    pub prefer_connect_key: Option<egui::Key>,
+    pub prefer_move_key: Option<egui::Key>,
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        Self {
+            pan_button: egui::PointerButton::Middle,
+            space_drag_pans: false,
+            scroll_zooms: true,
+            zoom_modifier: egui::Modifiers::COMMAND,
+            finder_trigger: FinderTrigger::Release,
+            port_context_menus: false,
+            interaction_priority: InteractionPriority::Auto,
+            prefer_connect_key: Some(egui::Key::C),
+            prefer_move_key: Some(egui::Key::M),
+        }
+    }
+}
+
+/// The nodes selected along with a node by
+/// [`GraphEditorState::select_related`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NodeRelation {
+    /// The nodes directly connected to it, see [`Graph::neighbors`].
+    Connected,
+    /// The nodes it depends on, see [`Graph::upstream_nodes`].
+    Upstream,
+    /// The nodes depending on it, see [`Graph::downstream_nodes`].
+    Downstream,
+}
+
+/// The distance between the nodes placed by
+/// [`GraphEditorState::from_graph`] and [`GraphEditorState::sync_from`].
+pub(crate) const GRID_LAYOUT_SPACING: egui::Vec2 = egui::vec2(250.0, 200.0);
+
+/// Decides what a drag starting close to a port does, when both the port and
+/// the node body are under the pointer.
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub enum InteractionPriority {
+    /// Drags starting exactly on a port create connections, drags starting
+    /// anywhere else on the node move it.
+    #[default]
+    Auto,
+    /// Drags starting within the connection radius of a port create
+    /// connections, even when they start on the node body. Useful with
+    /// imprecise pointers, e.g. on touch screens.
+    PreferConnect,
+    /// Drags always move the node, even when starting on a port. No new
+    /// connections can be created.
+    PreferMove,
+}
+
+/// What the user is allowed to change in the editor, see
+/// [`GraphEditorState::interaction_mode`]. Panning, zooming, selecting and
+/// tooltips work in every mode.
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub enum InteractionMode {
+    /// Everything can be changed.
+    #[default]
+    Editable,
+    /// Nothing can be changed. Value widgets are shown disabled.
+    ReadOnly,
+    /// Nodes, groups and reroutes can be moved around, but nodes,
+    /// connections and values can't be changed.
+    LayoutOnly,
+}
+
+impl InteractionMode {
+    /// Whether nodes, connections and values can be added, removed or changed.
+    pub fn can_edit(self) -> bool {
+        self == Self::Editable
+    }
+
+    /// Whether nodes, groups and reroutes can be moved.
+    pub fn can_move(self) -> bool {
+        self != Self::ReadOnly
+    }
+}
+
+/// How connections are drawn between their ports.
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
+pub enum ConnectionStyle {
+    /// Smooth curves, leaving and entering the ports horizontally.
+    #[default]
+    Bezier,
+    /// Straight lines from port to port.
+    Straight,
+    /// Horizontal and vertical lines with rounded corners, leaving and
+    /// entering the ports horizontally. Connections going backwards run below
+    /// the nodes they connect.
+    Orthogonal,
+}
+
+/// A connection that was dropped on a port of an incompatible type, for which
+/// user code provided a converter template.
+#[derive(Clone)]
+pub struct PendingConverter<NodeTemplate> {
+    pub output: OutputId,
+    pub input: InputId,
+    /// The template used to build the converter node.
+    pub template: NodeTemplate,
+    /// The screen position where the confirmation popup is shown.
+    pub position: egui::Pos2,
+}
+
+/// Nodes the user asked to delete with the keyboard, whose deletion would
+/// remove connections. The connections are highlighted until the deletion is
+/// confirmed or cancelled.
+#[derive(Clone)]
+pub struct PendingNodeDeletion {
+    pub nodes: Vec<NodeId>,
+    /// The number of connections the deletion would remove.
+    pub connection_count: usize,
+    /// The screen position where the confirmation popup is shown.
+    pub position: egui::Pos2,
+}
+
+/// A connection dropped on the background of the editor, or a connection a
+/// node is being inserted into, waiting for the node it will be attached to
+/// to be picked in the node finder. See
+/// [`GraphEditorState::drop_on_background`].
+#[derive(Clone)]
+pub struct PendingFinderConnection<NodeTemplate> {
+    /// The port the connection was dragged from.
+    pub port: AnyParameterId,
+    /// The templates offered by the node finder: the ones building a node
+    /// with a port the connection can be attached to.
+    pub templates: Vec<NodeTemplate>,
+    /// The screen position where the connection was dropped. The new node is
+    /// placed there.
+    pub position: egui::Pos2,
+    /// The input `port` is connected to, when the node is being inserted into
+    /// that connection from its context menu. The connection is replaced by
+    /// two going through the new node.
+    pub splice: Option<InputId>,
+}
+
+/// A node removed from the graph, along with its removed connections as
+/// input-output pairs.
+pub type RemovedNode<NodeData> = (Node<NodeData>, Vec<(InputId, OutputId)>);
+
+/// A connection dropped on the body of a node with several compatible inputs,
+/// waiting for the user to pick one. See
+/// [`GraphEditorState::drop_on_node_body`].
+#[derive(Clone, Debug)]
+pub struct PendingInputChoice {
+    pub output: OutputId,
+    /// The compatible inputs of the node, along with their names.
+    pub inputs: Vec<(String, InputId)>,
+    /// The entry of `inputs` selected with the keyboard.
+    pub highlighted: usize,
+    /// The screen position where the chooser is shown.
+    pub position: egui::Pos2,
+}
+
 #[derive(Clone)]
 #[cfg_attr(feature = "persistence", derive(Serialize, Deserialize))]
-pub struct GraphEditorState<NodeData, DataType, ValueType, NodeTemplate, UserState> {
+pub struct GraphEditorState<
+    NodeData,
+    DataType,
+    ValueType,
+    NodeTemplate,
+    UserState,
+    ConnectionData = (),
+> {
+    #[cfg_attr(
+        feature = "persistence",
+        serde(
+            bound(deserialize = "NodeData: serde::de::DeserializeOwned + 'static, \
+                DataType: Deserialize<'de>, ValueType: Deserialize<'de>")
+        )
+    )]
     pub graph: Graph<NodeData, DataType, ValueType>,
     /// Nodes are drawn in this order. Draw order is important because nodes
-    /// that are drawn last are on top.
+    /// that are drawn last are on top. Every node of the graph appears exactly
+    /// once, which [`Self::raise_node`] and [`Self::send_to_back`] preserve.
+    /// An order changed by hand is repaired on the next frame, see
+    /// [`Self::sync_with_graph`].
     pub node_order: Vec<NodeId>,
     /// An ongoing connection interaction: The mouse has dragged away from a
     /// port and the user is holding the click
@@ -24,19 +401,280 @@ pub struct GraphEditorState<NodeData, DataType, ValueType, NodeTemplate, UserSta
     /// The currently selected node. Some interface actions depend on the
     /// currently selected node.
     pub selected_nodes: Vec<NodeId>,
+    /// The selected connection, as an input-output pair. Connections are
+    /// selected by clicking them, and removed with <kbd>Delete</kbd>. Selecting
+    /// a connection clears the node selection, and the other way around.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub selected_connection: Option<(InputId, OutputId)>,
+    /// The connection whose context menu is open, and the screen position of
+    /// the menu.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub connection_menu: Option<(InputId, OutputId, egui::Pos2)>,
+    /// The reroute points of the connections, keyed by input-output pair, in
+    /// graph space. A connection is drawn through its reroute points, in
+    /// order. Double-clicking or alt-clicking a connection adds one, which
+    /// can then be dragged around, and double-clicking or alt-clicking it
+    /// removes it. The points of removed connections are dropped.
+    #[cfg_attr(
+        feature = "persistence",
+        serde(default, with = "connection_map_as_list")
+    )]
+    pub reroutes: HashMap<(InputId, OutputId), Vec<egui::Pos2>>,
+    /// User data attached to the connections, keyed by input-output pair, e.g.
+    /// whether a link is blocking. Set with
+    /// [`Self::add_connection_with_data`], and shown at the middle of the
+    /// connections by [`ConnectionDataTrait::label`]. Connections without an
+    /// entry have no data. The data of removed connections is dropped.
+    #[cfg_attr(
+        feature = "persistence",
+        serde(
+            default = "HashMap::new",
+            with = "connection_map_as_list",
+            bound(
+                serialize = "ConnectionData: Serialize",
+                deserialize = "ConnectionData: Deserialize<'de>"
+            )
+        )
+    )]
+    pub connection_data: HashMap<(InputId, OutputId), ConnectionData>,
+    /// Connections drawn with dashes flowing from the output to the input,
+    /// e.g. to show the ones data is currently going through. Filled by the
+    /// application.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub active_connections: HashSet<(InputId, OutputId)>,
+    /// The group frames. Selected nodes are grouped with <kbd>Ctrl</kbd> +
+    /// <kbd>G</kbd>, see [`Self::group_nodes`]. Groups left without members
+    /// are dropped.
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub groups: SlotMap<GroupId, Group>,
+    /// The group of each node that belongs to one.
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub node_groups: SecondaryMap<NodeId, GroupId>,
+    /// The nodes moved by the ongoing drag, and their position before it.
+    /// They may leave their group when it ends.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) moved_nodes: HashMap<NodeId, egui::Pos2>,
+    /// The grid drawn behind the graph, and whether nodes snap to it.
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub grid: Grid,
+    /// How the connections of the hovered node or port are highlighted.
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub hover_highlight: HoverHighlight,
+    /// How dragged nodes align with the other nodes.
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub alignment_guides: AlignmentGuides,
+    /// How the pointer and the keyboard pan and zoom the view.
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub input_bindings: InputBindings,
+    /// Where the ongoing pan gesture started, in screen space.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) pan_origin: Option<egui::Pos2>,
+    /// The position the dragged node would have without snapping to the grid
+    /// or to the alignment guides.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) unsnapped_drag: Option<(NodeId, egui::Pos2)>,
+    /// The guide lines the dragged node is aligned to, in graph space.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) active_guides: Vec<[egui::Pos2; 2]>,
+    /// How connections are drawn. Connections are hit by clicks along their
+    /// drawn path, whatever the style.
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub connection_style: ConnectionStyle,
     /// The mouse drag start position for an ongoing box selection.
     pub ongoing_box_selection: Option<egui::Pos2>,
     /// The position of each node.
     pub node_positions: SecondaryMap<NodeId, egui::Pos2>,
+    /// The nodes collapsed to their title bar. Their parameters are hidden,
+    /// and their ports are drawn as small stubs on the edges of the title bar.
+    /// See [`NodeDataTrait::can_collapse`].
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub collapsed_nodes: HashSet<NodeId>,
+    /// The groups of params collapsed to their header, by node and group
+    /// name. The ports of their params are all drawn on the header. See
+    /// [`InputParam::group`].
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub collapsed_port_groups: HashSet<(NodeId, String)>,
+    /// Nodes that are about to be deleted, e.g. because the pointer is over
+    /// their close button. The connections that would be removed along with
+    /// them are highlighted. Recomputed every frame.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub pending_deletion: HashSet<NodeId>,
+    /// A deletion of the selected nodes with <kbd>Delete</kbd> that would
+    /// remove connections, waiting for the user to confirm it.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub deletion_to_confirm: Option<PendingNodeDeletion>,
     /// The node finder is used to create new nodes.
     pub node_finder: Option<NodeFinder<NodeTemplate>>,
-    /// The panning of the graph viewport.
+    /// The templates listed first by the node finder. Set favorites with
+    /// [`FinderFavorites::pin`] to offer some templates from the start.
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub finder_favorites: FinderFavorites,
+    /// When set, <kbd>Ctrl</kbd> + <kbd>F</kbd> opens the find-node palette,
+    /// see [`NodeSearch`]. Picking a node in it selects the node and centers
+    /// the view on it. Off by default, for applications with their own search.
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub find_nodes: bool,
+    /// The find-node palette, if open.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub node_search: Option<NodeSearch>,
+    /// A converter node the user is being offered to insert between two
+    /// ports of incompatible types. See [`NodeTemplateTrait::converter`].
+    #[cfg_attr(feature = "persistence", serde(skip, default = "Option::default"))]
+    pub pending_converter: Option<PendingConverter<NodeTemplate>>,
+    /// When set, a connection dragged from an output can be dropped anywhere on
+    /// the body of a node, not only on its ports. If the node has a single
+    /// unconnected input of the right type, it's connected right away. If it
+    /// has several, the user is asked to pick one. If it has none, the drop is
+    /// rejected with a [`NodeResponse::RejectedDrop`].
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub drop_on_node_body: bool,
+    /// The input chooser of a connection dropped on a node body.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub pending_input_choice: Option<PendingInputChoice>,
+    /// When set, a connection dropped on the background of the editor opens
+    /// the node finder, offering only the templates with a compatible port.
+    /// The picked node is created where the connection was dropped, already
+    /// connected. Closing the finder drops the connection.
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub drop_on_background: bool,
+    /// The connection dropped on the background while the node finder is
+    /// open, if any.
+    #[cfg_attr(feature = "persistence", serde(skip, default = "Option::default"))]
+    pub pending_finder_connection: Option<PendingFinderConnection<NodeTemplate>>,
+    /// When set, a single node without connections dragged onto a connection
+    /// is inserted into it when dropped: the output of the connection is
+    /// connected to the first compatible input of the node, and the first
+    /// compatible output of the node to the input of the connection. The
+    /// connection is highlighted while the node hovers it, if the node has
+    /// both ports.
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub drop_node_on_connection: bool,
+    /// The node dragged onto a connection, and the connection it would be
+    /// inserted into, see [`GraphEditorState::drop_node_on_connection`].
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) splice_target: Option<(NodeId, InputId, OutputId)>,
+    /// Where and when (in egui time) the last drop on a node body was
+    /// rejected, to give visual feedback.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub rejected_drop: Option<(egui::Pos2, f64)>,
+    /// The input of the last connection refused by
+    /// [`NodeDataTrait::can_connect`], the reason, and when (in egui time) it
+    /// happened, to give visual feedback.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub rejected_connection: Option<(InputId, String, f64)>,
+    /// An input with several connections a drag has started from. One of its
+    /// connections is detached once the direction of the drag is known.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) detaching_input: Option<InputId>,
+    /// The port under the pointer, and since when (in egui time) it's there.
+    /// Its tooltip is shown after a short delay.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) hovered_port: Option<(AnyParameterId, f64)>,
+    /// The node under the pointer, and since when.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) hovered_node: Option<(NodeId, f64)>,
+    /// The node whose title is being edited, and the edited label. Started
+    /// by double-clicking the title or with Rename in the context menu of
+    /// the node.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub renaming_node: Option<(NodeId, String)>,
+    /// The view change to apply the next time the editor is drawn, see
+    /// [`Self::fit_to_screen`] and [`Self::center_on_node`].
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) view_request: Option<ViewRequest>,
+    /// Where the nodes and their ports were last drawn. Nodes outside of the
+    /// viewport are placed from it instead of being drawn.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) node_layouts: SecondaryMap<NodeId, NodeLayout>,
+    /// The connections tessellated during the last frame, reused by the next
+    /// one for the segments whose shape hasn't changed.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) connection_meshes: ConnectionMeshes,
+    /// The wildcard ports that were bound at the end of the last frame, and
+    /// the ports they were bound to. `None` until the first frame, which
+    /// reports no change.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) wildcard_bindings: Option<HashMap<AnyParameterId, AnyParameterId>>,
+    /// The panning and zoom of the graph viewport. The scroll wheel, or
+    /// <kbd>Ctrl</kbd> + scroll on touchpads, zooms around the cursor.
     pub pan_zoom: PanZoom,
+    /// When set, connections that would create a cycle are refused with a
+    /// [`NodeResponse::RejectedConnection`], and the ports they would attach
+    /// to are grayed out while dragging. See [`Graph::would_create_cycle`].
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub enforce_acyclic: bool,
+    /// When set, replacing the connection of an already connected input is
+    /// reported as a [`NodeResponse::DisconnectEvent`] followed by a
+    /// [`NodeResponse::ConnectEventEnded`] instead of a single
+    /// [`NodeResponse::ReplacedConnection`]. Kept for compatibility with code
+    /// written against the older event sequence.
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub split_replaced_connections: bool,
+    /// Whether [`Self::duplicate_selection`] also connects the inputs of
+    /// the copies to the outputs outside of the selection feeding the
+    /// originals. Connections between the duplicated nodes are always
+    /// copied.
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub duplicate_incoming_connections: bool,
+    /// Whether clicking or dragging a node draws it on top of the others.
+    /// Otherwise the order only changes through [`Self::raise_node`],
+    /// [`Self::send_to_back`] and the node context menu.
+    #[cfg_attr(feature = "persistence", serde(default = "default_true"))]
+    pub raise_on_click: bool,
+    /// What the user is allowed to change, e.g. to show a graph without
+    /// letting it be edited. Can be switched at any time.
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub interaction_mode: InteractionMode,
+    /// Catch panics in the user trait callbacks drawing the nodes
+    /// (`top_bar_ui`, `value_widget`, `output_ui`, `separator`, `bottom_ui`,
+    /// `context_menu_ui`, `port_tooltip_ui`).
+    /// The UI of a node that panicked is replaced by an error placeholder for
+    /// as long as the editor lives, its ports and connections still work.
+    /// The responses of the failed call are dropped.
+    ///
+    /// The callbacks are called as if they were unwind safe: a panic may leave
+    /// the user state or the node data half updated. Panics are still
+    /// reported by the panic hook, and require `panic = "unwind"`.
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub defensive: bool,
+    /// The nodes whose UI panicked, in defensive mode.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub failed_nodes: HashSet<NodeId>,
+    /// The changes that can be undone. See
+    /// [`GraphEditorState::record_history`].
+    #[cfg_attr(feature = "persistence", serde(skip, default = "History::default"))]
+    pub history: History<NodeData, DataType, ValueType, ConnectionData>,
+    /// Measure the time spent in each phase of drawing the editor, see
+    /// [`Self::timings`]. When disabled, the clock is never read. Timings are
+    /// not collected on the web.
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub collect_timings: bool,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub(crate) frame_timings: VecDeque<FrameTimings>,
+    /// Show an overview of the whole graph in the bottom-right corner of the
+    /// editor. Clicking or dragging in it pans the view.
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub minimap: bool,
+    /// Nodes are drawn in low detail once the editor is zoomed out so far that
+    /// a node of the default width, 200 graph units, would be narrower than
+    /// this on screen. Only their title bar and port stubs are drawn, like
+    /// collapsed nodes, and their parameter widgets are skipped. Their ports
+    /// can still be connected. Set to zero to always draw the nodes in full.
+    /// See [`NodeDataTrait::always_detailed`].
+    #[cfg_attr(feature = "persistence", serde(default = "default_low_detail_width"))]
+    pub low_detail_width: f32,
+    /// The strings rendered by the editor. See [`Localization`].
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub localization: Localization,
+    /// The colors and sizes the editor is drawn with. When unset, they follow
+    /// the egui theme, see [`GraphStyle::from_egui_visuals`].
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub style: Option<GraphStyle>,
     pub _user_state: PhantomData<fn() -> UserState>,
 }
 
-impl<NodeData, DataType, ValueType, NodeKind, UserState>
-    GraphEditorState<NodeData, DataType, ValueType, NodeKind, UserState>
+impl<NodeData, DataType, ValueType, NodeKind, UserState, ConnectionData>
+    GraphEditorState<NodeData, DataType, ValueType, NodeKind, UserState, ConnectionData>
 {
     pub fn new(default_zoom: f32) -> Self {
         Self {
@@ -47,9 +685,355 @@ impl<NodeData, DataType, ValueType, NodeKind, UserState>
             ..Default::default()
         }
     }
+
+    /// Builds an editor for an already populated `graph`. The nodes are
+    /// placed on a grid, in the order they are iterated, and are all given a
+    /// position and a place in `node_order`. See
+    /// [`Self::from_graph_with_positions`] to place them yourself, or
+    /// [`Self::auto_layout`] to arrange them afterwards.
+    pub fn from_graph(graph: Graph<NodeData, DataType, ValueType>) -> Self {
+        let columns = (graph.nodes.len() as f32).sqrt().ceil().max(1.0) as usize;
+        let mut index = 0;
+        Self::from_graph_with_positions(graph, |_, _| {
+            let position = egui::pos2(
+                (index % columns) as f32 * GRID_LAYOUT_SPACING.x,
+                (index / columns) as f32 * GRID_LAYOUT_SPACING.y,
+            );
+            index += 1;
+            position
+        })
+    }
+
+    /// Builds an editor for an already populated `graph`, placing each node
+    /// where `position` says, in graph space. The nodes are drawn in the
+    /// order they are iterated.
+    pub fn from_graph_with_positions(
+        graph: Graph<NodeData, DataType, ValueType>,
+        mut position: impl FnMut(NodeId, &Graph<NodeData, DataType, ValueType>) -> egui::Pos2,
+    ) -> Self {
+        let mut state = Self {
+            graph,
+            ..Default::default()
+        };
+        for node in state.graph.iter_nodes() {
+            state
+                .node_positions
+                .insert(node, position(node, &state.graph));
+            state.node_order.push(node);
+        }
+        // The graph may have been filled through its fields directly
+        state.graph.rebuild_reverse_connections();
+        state.graph.rebuild_external_ids();
+        state
+    }
+}
+impl<NodeData, DataType, ValueType, NodeKind, UserState, ConnectionData>
+    GraphEditorState<NodeData, DataType, ValueType, NodeKind, UserState, ConnectionData>
+{
+    /// Removes a node from the graph, along with all the editor state that
+    /// refers to it. This is the preferred way to delete nodes, since removing
+    /// them from the [`Graph`] directly leaves the editor state out of sync.
+    ///
+    /// Returns the removed node and its removed connections as input-output
+    /// pairs, like [`Graph::remove_node`].
+    pub fn remove_node(&mut self, node_id: NodeId) -> (Node<NodeData>, Vec<(InputId, OutputId)>) {
+        let removed = self.graph.remove_node(node_id);
+        for connection in &removed.1 {
+            self.reroutes.remove(connection);
+            self.connection_data.remove(connection);
+        }
+        self.node_positions.remove(node_id);
+        // Make sure to not leave references to old nodes hanging
+        self.selected_nodes.retain(|id| *id != node_id);
+        self.node_order.retain(|id| *id != node_id);
+        self.failed_nodes.remove(&node_id);
+        self.collapsed_nodes.remove(&node_id);
+        self.collapsed_port_groups
+            .retain(|(node, _)| *node != node_id);
+        self.prune_groups();
+        if matches!(self.connection_in_progress, Some((node, _)) if node == node_id) {
+            self.connection_in_progress = None;
+        }
+        if matches!(self.renaming_node, Some((node, _)) if node == node_id) {
+            self.renaming_node = None;
+        }
+        removed
+    }
+
+    /// Draws `node` on top of all the other nodes, see [`Self::node_order`].
+    /// Ids of nodes that don't exist are ignored.
+    pub fn raise_node(&mut self, node: NodeId) {
+        if self.graph.nodes.contains_key(node) {
+            self.node_order.retain(|id| *id != node);
+            self.node_order.push(node);
+        }
+    }
+
+    /// Draws `node` below all the other nodes, see [`Self::node_order`]. Ids
+    /// of nodes that don't exist are ignored.
+    pub fn send_to_back(&mut self, node: NodeId) {
+        if self.graph.nodes.contains_key(node) {
+            self.node_order.retain(|id| *id != node);
+            self.node_order.insert(0, node);
+        }
+    }
+
+    /// Removes several nodes at once, like calling [`Self::remove_node`] for
+    /// each of them. Ids of nodes that don't exist are ignored.
+    ///
+    /// Returns the removed nodes, and the connections removed along with them.
+    /// A connection between two removed nodes is only reported once.
+    pub fn remove_nodes(
+        &mut self,
+        nodes: impl IntoIterator<Item = NodeId>,
+    ) -> Vec<RemovedNode<NodeData>> {
+        let mut removed = Vec::new();
+        for node_id in nodes {
+            if self.graph.nodes.contains_key(node_id) {
+                removed.push(self.remove_node(node_id));
+            }
+        }
+        removed
+    }
+
+    /// Selects `nodes` and the nodes related to any of them by `relation`,
+    /// replacing the selection. Ids of nodes that don't exist are ignored.
+    pub fn select_related(&mut self, nodes: &[NodeId], relation: NodeRelation) {
+        let mut related = HashSet::new();
+        for node in nodes.iter().copied() {
+            if !self.graph.nodes.contains_key(node) {
+                continue;
+            }
+            related.insert(node);
+            related.extend(match relation {
+                NodeRelation::Connected => self.graph.neighbors(node),
+                NodeRelation::Upstream => self.graph.upstream_nodes(node),
+                NodeRelation::Downstream => self.graph.downstream_nodes(node),
+            });
+        }
+        self.selected_nodes = self
+            .node_order
+            .iter()
+            .copied()
+            .filter(|node| related.contains(node))
+            .collect();
+        self.selected_connection = None;
+    }
+
+    /// Connects `output` to `input`, like [`Graph::add_connection`], and
+    /// attaches `data` to the connection. See [`Self::connection_data`].
+    pub fn add_connection_with_data(
+        &mut self,
+        output: OutputId,
+        input: InputId,
+        data: ConnectionData,
+    ) {
+        self.graph.add_connection(output, input);
+        self.connection_data.insert((input, output), data);
+    }
+
+    /// The connections of the graph as input-output pairs, like
+    /// [`Graph::iter_connections`], along with their data, if any.
+    pub fn iter_connections_with_data(
+        &self,
+    ) -> impl Iterator<Item = (InputId, OutputId, Option<&ConnectionData>)> + '_ {
+        self.graph
+            .iter_connections()
+            .map(|(input, output)| (input, output, self.connection_data.get(&(input, output))))
+    }
+
+    /// Pans and zooms the view so all the nodes are visible, with some
+    /// margin. The view zooms out as needed, but never zooms in past 100%.
+    ///
+    /// This takes effect the next time the editor is drawn. Nodes that were
+    /// never drawn, e.g. right after an import, have their size estimated.
+    pub fn fit_to_screen(&mut self) {
+        self.view_request = Some(ViewRequest::FitToScreen);
+    }
+
+    /// Pans the view so `node` is in its center, keeping the zoom. Useful to
+    /// reveal a node picked outside of the editor, e.g. in a list.
+    ///
+    /// This takes effect the next time the editor is drawn, like
+    /// [`Self::fit_to_screen`].
+    pub fn center_on_node(&mut self, node: NodeId) {
+        self.view_request = Some(ViewRequest::CenterOnNode(node));
+    }
+
+    /// The rect of a node in graph space, as last drawn. Nodes that were never
+    /// drawn have their size estimated. `None` for nodes without a position.
+    pub(crate) fn node_graph_rect(&self, node: NodeId) -> Option<egui::Rect> {
+        // Checked first, nodes removed from the graph have no position
+        let pos = self.node_positions.get(node)?;
+        if let Some(layout) = self.node_layouts.get(node) {
+            return Some(layout.rect.translate(pos.to_vec2()));
+        }
+        let height = self.estimated_height(node, &LayoutSettings::default());
+        Some(egui::Rect::from_min_size(
+            *pos,
+            egui::vec2(ESTIMATED_NODE_WIDTH, height),
+        ))
+    }
+
+    /// Applies the view change requested since the last frame, if any, to the
+    /// editor drawn in `editor_rect`.
+    pub(crate) fn apply_view_request(&mut self, editor_rect: egui::Rect) {
+        let size = editor_rect.size();
+        match self.view_request.take() {
+            Some(ViewRequest::FitToScreen) => {
+                let bounds = self
+                    .node_order
+                    .iter()
+                    .filter_map(|node| self.node_graph_rect(*node))
+                    .reduce(|bounds, rect| bounds.union(rect));
+                if let Some(bounds) = bounds {
+                    let available =
+                        (size - egui::Vec2::splat(2.0 * FIT_MARGIN)).max(egui::Vec2::splat(1.0));
+                    let zoom = (available.x / bounds.width())
+                        .min(available.y / bounds.height())
+                        .min(1.0)
+                        .clamp(MIN_ZOOM, MAX_ZOOM);
+                    self.pan_zoom.zoom = zoom;
+                    self.pan_zoom.pan = size / 2.0 - bounds.center().to_vec2() * zoom;
+                }
+            }
+            Some(ViewRequest::CenterOnNode(node)) => {
+                if let Some(rect) = self.node_graph_rect(node) {
+                    self.pan_zoom.pan = size / 2.0 - rect.center().to_vec2() * self.pan_zoom.zoom;
+                }
+            }
+            None => {}
+        }
+    }
+
+    /// The timings of the last frames drawn with
+    /// [`GraphEditorState::collect_timings`] enabled, oldest first. At most
+    /// [`TIMINGS_HISTORY`] frames are kept.
+    pub fn timings(&self) -> &VecDeque<FrameTimings> {
+        &self.frame_timings
+    }
+
+    /// Stores the timings of a frame, or forgets the previous ones when
+    /// timings are no longer collected.
+    pub(crate) fn record_timings(&mut self, timings: Option<FrameTimings>) {
+        match timings {
+            Some(timings) => {
+                if self.frame_timings.len() == TIMINGS_HISTORY {
+                    self.frame_timings.pop_front();
+                }
+                self.frame_timings.push_back(timings);
+            }
+            None => self.frame_timings.clear(),
+        }
+    }
+
+    /// The interaction priority for this frame: the one forced by the held
+    /// keys, if any, or the one configured in
+    /// [`InputBindings::interaction_priority`]. Keys are ignored while typing
+    /// in a text field.
+    pub fn effective_interaction_priority(&self, ctx: &egui::Context) -> InteractionPriority {
+        let bindings = self.input_bindings;
+        if ctx.wants_keyboard_input() {
+            return bindings.interaction_priority;
+        }
+        ctx.input(|i| {
+            let held = |key: Option<egui::Key>| key.is_some_and(|key| i.key_down(key));
+            if held(bindings.prefer_connect_key) {
+                InteractionPriority::PreferConnect
+            } else if held(bindings.prefer_move_key) {
+                InteractionPriority::PreferMove
+            } else {
+                bindings.interaction_priority
+            }
+        })
+    }
+
+    /// Brings the per-node editor state back in sync with the graph: entries
+    /// for nodes that no longer exist are pruned, and nodes missing from
+    /// `node_order` or `node_positions` are added back. This happens when nodes
+    /// are added to or removed from the [`Graph`] directly.
+    ///
+    /// The reroute points of removed connections are also dropped. Removing
+    /// connections is expected, so this doesn't count as a fix. The indexes
+    /// of the graph aren't checked, see [`Graph::rebuild_reverse_connections`]
+    /// and [`Graph::rebuild_external_ids`].
+    ///
+    /// Returns whether anything had to be fixed. This is called automatically
+    /// by the editor before drawing.
+    pub fn sync_with_graph(&mut self) -> bool {
+        let graph = &self.graph;
+        let old_order_len = self.node_order.len();
+        let old_positions_len = self.node_positions.len();
+        self.node_positions
+            .retain(|id, _| graph.nodes.contains_key(id));
+        self.selected_nodes
+            .retain(|id| graph.nodes.contains_key(*id));
+        self.collapsed_nodes
+            .retain(|id| graph.nodes.contains_key(*id));
+        self.collapsed_port_groups
+            .retain(|(id, _)| graph.nodes.contains_key(*id));
+        if matches!(&self.renaming_node, Some((id, _)) if !graph.nodes.contains_key(*id)) {
+            self.renaming_node = None;
+        }
+        self.reroutes
+            .retain(|(input, output), _| graph.connections(*input).contains(output));
+        self.connection_data
+            .retain(|(input, output), _| graph.connections(*input).contains(output));
+        self.active_connections
+            .retain(|(input, output)| graph.connections(*input).contains(output));
+        // Duplicated entries would draw the same node twice
+        let mut ordered = HashSet::new();
+        self.node_order
+            .retain(|id| graph.nodes.contains_key(*id) && ordered.insert(*id));
+        let mut fixed = old_order_len != self.node_order.len()
+            || old_positions_len != self.node_positions.len();
+
+        for node_id in self.graph.iter_nodes() {
+            if !ordered.contains(&node_id) {
+                self.node_order.push(node_id);
+                fixed = true;
+            }
+            if !self.node_positions.contains_key(node_id) {
+                self.node_positions.insert(node_id, egui::Pos2::ZERO);
+                fixed = true;
+            }
+        }
+        self.prune_groups();
+        fixed
+    }
+}
+
+impl<NodeData, DataType, ValueType, NodeTemplate, UserState, ConnectionData>
+    GraphEditorState<NodeData, DataType, ValueType, NodeTemplate, UserState, ConnectionData>
+where
+    NodeTemplate: NodeTemplateTrait<
+        NodeData = NodeData,
+        DataType = DataType,
+        ValueType = ValueType,
+        UserState = UserState,
+    >,
+{
+    /// Builds a new node from `node_kind` and places it at `position`, in graph
+    /// space. The new node is drawn on top of all the other nodes.
+    pub fn add_node(
+        &mut self,
+        node_kind: &NodeTemplate,
+        position: egui::Pos2,
+        user_state: &mut UserState,
+    ) -> NodeId {
+        let new_node = self.graph.add_node(
+            node_kind.node_graph_label(user_state),
+            node_kind.user_data(user_state),
+            |graph, node_id| node_kind.build_node(graph, user_state, node_id),
+        );
+        self.node_positions.insert(new_node, position);
+        self.node_order.push(new_node);
+        new_node
+    }
 }
-impl<NodeData, DataType, ValueType, NodeKind, UserState> Default
-    for GraphEditorState<NodeData, DataType, ValueType, NodeKind, UserState>
+
+impl<NodeData, DataType, ValueType, NodeKind, UserState, ConnectionData> Default
+    for GraphEditorState<NodeData, DataType, ValueType, NodeKind, UserState, ConnectionData>
 {
     fn default() -> Self {
         Self {
@@ -57,16 +1041,73 @@ impl<NodeData, DataType, ValueType, NodeKind, UserState> Default
             node_order: Default::default(),
             connection_in_progress: Default::default(),
             selected_nodes: Default::default(),
+            selected_connection: None,
+            connection_menu: None,
+            reroutes: Default::default(),
+            connection_data: Default::default(),
+            active_connections: Default::default(),
+            connection_style: Default::default(),
+            groups: Default::default(),
+            node_groups: Default::default(),
+            moved_nodes: Default::default(),
+            grid: Default::default(),
+            hover_highlight: Default::default(),
+            alignment_guides: Default::default(),
+            input_bindings: Default::default(),
+            pan_origin: None,
+            unsnapped_drag: None,
+            active_guides: Vec::new(),
+            view_request: None,
+            node_layouts: Default::default(),
+            connection_meshes: Default::default(),
+            wildcard_bindings: None,
             ongoing_box_selection: Default::default(),
             node_positions: Default::default(),
+            collapsed_nodes: Default::default(),
+            collapsed_port_groups: Default::default(),
+            pending_deletion: Default::default(),
+            deletion_to_confirm: None,
             node_finder: Default::default(),
+            finder_favorites: Default::default(),
+            find_nodes: false,
+            node_search: None,
+            pending_converter: Default::default(),
+            drop_on_node_body: false,
+            pending_input_choice: None,
+            drop_on_background: false,
+            pending_finder_connection: None,
+            drop_node_on_connection: false,
+            splice_target: None,
+            rejected_drop: None,
+            rejected_connection: None,
+            detaching_input: None,
+            hovered_port: None,
+            hovered_node: None,
+            renaming_node: None,
             pan_zoom: Default::default(),
+            enforce_acyclic: false,
+            split_replaced_connections: false,
+            interaction_mode: Default::default(),
+            raise_on_click: true,
+            duplicate_incoming_connections: false,
+            defensive: false,
+            failed_nodes: Default::default(),
+            history: Default::default(),
+            collect_timings: false,
+            frame_timings: Default::default(),
+            minimap: false,
+            low_detail_width: DEFAULT_LOW_DETAIL_WIDTH,
+            localization: Default::default(),
+            style: None,
             _user_state: Default::default(),
         }
     }
 }
 
 impl PanZoom {
+    /// Adds `zoom_delta` to the zoom, within `zoom_min..=zoom_max`. The graph
+    /// position under `point`, relative to the top-left corner of the editor,
+    /// stays where it is.
     pub fn adjust_zoom(
         &mut self,
         zoom_delta: f32,
@@ -75,9 +1116,3181 @@ impl PanZoom {
         zoom_max: f32,
     ) {
         let zoom_clamped = (self.zoom + zoom_delta).clamp(zoom_min, zoom_max);
-        let zoom_delta = zoom_clamped - self.zoom;
+        self.pan = point - (point - self.pan) * (zoom_clamped / self.zoom);
+        self.zoom = zoom_clamped;
+    }
+
+    /// Converts a position in graph space to a screen position, for an editor
+    /// drawn in `editor_rect`.
+    pub fn graph_to_screen(&self, pos: egui::Pos2, editor_rect: egui::Rect) -> egui::Pos2 {
+        editor_rect.min + self.pan + pos.to_vec2() * self.zoom
+    }
+
+    /// Converts a screen position to a position in graph space, for an editor
+    /// drawn in `editor_rect`.
+    pub fn screen_to_graph(&self, pos: egui::Pos2, editor_rect: egui::Rect) -> egui::Pos2 {
+        ((pos - editor_rect.min - self.pan) / self.zoom).to_pos2()
+    }
+
+    /// Brings back the zoom within [`MIN_ZOOM`] and [`MAX_ZOOM`]. Invalid
+    /// zooms, e.g. from a state saved before zooming was supported, are reset.
+    pub(crate) fn sanitize(&mut self) {
+        if !self.zoom.is_finite() || self.zoom <= 0.0 {
+            self.zoom = 1.0;
+        }
+        self.zoom = self.zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+}
+
+impl Grid {
+    /// The grid point closest to `pos`, in graph space.
+    pub fn snap(&self, pos: egui::Pos2) -> egui::Pos2 {
+        let spacing = self.spacing;
+        egui::pos2(
+            (pos.x / spacing).round() * spacing,
+            (pos.y / spacing).round() * spacing,
+        )
+    }
+
+    /// Resets invalid spacings, which would draw an infinite number of lines.
+    pub(crate) fn sanitize(&mut self) {
+        if !self.spacing.is_finite() || self.spacing <= 0.0 {
+            self.spacing = Grid::default().spacing;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    fn state_with_nodes(count: usize) -> (TestEditorState, Vec<NodeId>) {
+        let mut state = TestEditorState::new(1.0);
+        let nodes = (0..count)
+            .map(|i| {
+                state.add_node(
+                    &TestTemplate::Scalar,
+                    egui::pos2(i as f32 * 100.0, 0.0),
+                    &mut (),
+                )
+            })
+            .collect();
+        (state, nodes)
+    }
+
+    #[test]
+    pub fn test_remove_node_cleans_up_editor_state() {
+        let (mut state, nodes) = state_with_nodes(2);
+        let output = state.graph[nodes[0]].get_output("out").unwrap();
+        let input = state.graph[nodes[1]].get_input("in").unwrap();
+        state.graph.add_connection(output, input);
+        state.selected_nodes = nodes.clone();
+
+        let (node, connections) = state.remove_node(nodes[0]);
+
+        assert_eq!(node.id, nodes[0]);
+        assert_eq!(connections, vec![(input, output)]);
+        assert_eq!(state.node_order, vec![nodes[1]]);
+        assert_eq!(state.selected_nodes, vec![nodes[1]]);
+        assert!(!state.node_positions.contains_key(nodes[0]));
+        assert!(!state.sync_with_graph());
+    }
+
+    #[test]
+    pub fn test_from_graph() {
+        let (state, nodes) = state_with_nodes(5);
+        let graph = state.graph;
+
+        let mut state = TestEditorState::from_graph(graph.clone());
+        assert_eq!(state.node_order, nodes);
+        let positions: Vec<egui::Pos2> = nodes.iter().map(|n| state.node_positions[*n]).collect();
+        assert_eq!(positions[0], egui::Pos2::ZERO);
+        // Three columns, and no two nodes on the same spot
+        assert_eq!(positions[3].x, positions[0].x);
+        assert!(positions[3].y > positions[0].y);
+        for (i, a) in positions.iter().enumerate() {
+            assert!(positions[i + 1..].iter().all(|b| a != b));
+        }
+        assert!(!state.sync_with_graph());
+        draw_frame(&mut state, &egui::Context::default());
+
+        let state = TestEditorState::from_graph_with_positions(graph, |node, _| {
+            egui::pos2(if node == nodes[2] { 42.0 } else { 0.0 }, 0.0)
+        });
+        assert_eq!(state.node_positions[nodes[2]], egui::pos2(42.0, 0.0));
+        assert_eq!(state.node_positions.len(), 5);
+    }
+
+    #[test]
+    pub fn test_editor_heals_nodes_removed_from_graph() {
+        let (mut state, nodes) = state_with_nodes(3);
+        state.selected_nodes = vec![nodes[1]];
+        // Bypass the editor state on purpose
+        state.graph.remove_node(nodes[1]);
+
+        let ctx = egui::Context::default();
+        draw_frame(&mut state, &ctx);
+
+        assert_eq!(state.node_order, vec![nodes[0], nodes[2]]);
+        assert!(!state.node_positions.contains_key(nodes[1]));
+        assert!(state.selected_nodes.is_empty());
+        assert!(!state.sync_with_graph());
+    }
+
+    #[test]
+    pub fn test_editor_heals_nodes_added_to_graph() {
+        let (mut state, nodes) = state_with_nodes(1);
+        let added = state.graph.add_node(
+            "added".into(),
+            TestNodeData(TestTemplate::Vector),
+            |_, _| {},
+        );
+        // Duplicated and stale entries are also pruned
+        state.node_order.push(nodes[0]);
+
+        let ctx = egui::Context::default();
+        draw_frame(&mut state, &ctx);
+
+        assert_eq!(state.node_order, vec![nodes[0], added]);
+        assert!(state.node_positions.contains_key(added));
+    }
+
+    #[test]
+    pub fn test_remove_nodes() {
+        let (mut state, nodes) = state_with_nodes(3);
+        let output = state.graph[nodes[0]].get_output("out").unwrap();
+        let input = state.graph[nodes[1]].get_input("in").unwrap();
+        state.graph.add_connection(output, input);
+
+        let removed = state.remove_nodes([nodes[0], nodes[1], nodes[0]]);
+
+        assert_eq!(removed.len(), 2);
+        let connections: usize = removed.iter().map(|(_, c)| c.len()).sum();
+        assert_eq!(connections, 1);
+        assert_eq!(state.node_order, vec![nodes[2]]);
+        assert!(!state.sync_with_graph());
+    }
+
+    #[test]
+    pub fn test_defensive_mode_disables_panicking_nodes() {
+        let (mut state, nodes) = state_with_nodes(1);
+        let panicking = state.add_node(&TestTemplate::Panicking, egui::Pos2::ZERO, &mut ());
+        let output = state.graph[nodes[0]].get_output("out").unwrap();
+        let input = state.graph[panicking].get_input("in").unwrap();
+        state.graph.add_connection(output, input);
+        state.defensive = true;
+
+        let ctx = egui::Context::default();
+        draw_frame(&mut state, &ctx);
+        draw_frame(&mut state, &ctx);
+
+        assert_eq!(state.failed_nodes, [panicking].into_iter().collect());
+        assert_eq!(state.graph.connection(input), Some(output));
+
+        state.remove_node(panicking);
+        assert!(state.failed_nodes.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "panicking test node")]
+    pub fn test_panics_propagate_by_default() {
+        let mut state = TestEditorState::new(1.0);
+        state.add_node(&TestTemplate::Panicking, egui::Pos2::ZERO, &mut ());
+        draw_frame(&mut state, &egui::Context::default());
+    }
+
+    /// Drags the pointer from `from` by `delta`, returning the responses of
+    /// all the frames involved.
+    fn drag(
+        state: &mut TestEditorState,
+        ctx: &egui::Context,
+        from: egui::Pos2,
+        delta: egui::Vec2,
+        keys: &[egui::Key],
+    ) -> Vec<NodeResponse<TestResponse, TestNodeData>> {
+        let key_events = |pressed| {
+            keys.iter().map(move |key| egui::Event::Key {
+                key: *key,
+                pressed,
+                repeat: false,
+                modifiers: egui::Modifiers::NONE,
+            })
+        };
+        let button = |pos, pressed| egui::Event::PointerButton {
+            pos,
+            button: egui::PointerButton::Primary,
+            pressed,
+            modifiers: egui::Modifiers::NONE,
+        };
+        let frames = [
+            key_events(true)
+                .chain([egui::Event::PointerMoved(from)])
+                .collect(),
+            vec![button(from, true)],
+            vec![egui::Event::PointerMoved(from + delta)],
+            key_events(false)
+                .chain([button(from + delta, false)])
+                .collect(),
+        ];
+        frames
+            .into_iter()
+            .flat_map(|events| {
+                let input = egui::RawInput {
+                    events,
+                    ..Default::default()
+                };
+                draw_frame_with_input(state, ctx, input).node_responses
+            })
+            .collect()
+    }
+
+    fn starts_connection(responses: &[NodeResponse<TestResponse, TestNodeData>]) -> bool {
+        responses
+            .iter()
+            .any(|r| matches!(r, NodeResponse::ConnectEventStarted(..)))
+    }
+
+    fn moves_node(responses: &[NodeResponse<TestResponse, TestNodeData>]) -> bool {
+        responses
+            .iter()
+            .any(|r| matches!(r, NodeResponse::MoveNode { .. }))
+    }
+
+    /// Returns a fresh editor with a single node, and the screen position of
+    /// its output port.
+    fn single_node_editor(ctx: &egui::Context) -> (TestEditorState, egui::Pos2) {
+        let (mut state, nodes) = state_with_nodes(1);
+        *state.node_positions.get_mut(nodes[0]).unwrap() = egui::pos2(100.0, 100.0);
+        let output = state.graph[nodes[0]].get_output("out").unwrap();
+        let port_locations = draw_frame(&mut state, ctx).port_locations;
+        (state, port_locations[&AnyParameterId::Output(output)])
+    }
+
+    #[test]
+    pub fn test_prefer_move_drags_node_from_port() {
+        let ctx = egui::Context::default();
+        let (mut state, port) = single_node_editor(&ctx);
+        let delta = egui::vec2(30.0, 0.0);
+        assert!(starts_connection(&drag(&mut state, &ctx, port, delta, &[])));
+
+        let (mut state, port) = single_node_editor(&ctx);
+        state.input_bindings.interaction_priority = InteractionPriority::PreferMove;
+        let responses = drag(&mut state, &ctx, port, delta, &[]);
+        assert!(moves_node(&responses));
+        assert!(!starts_connection(&responses));
+
+        // Holding M forces the same behavior
+        let (mut state, port) = single_node_editor(&ctx);
+        let responses = drag(&mut state, &ctx, port, delta, &[egui::Key::M]);
+        assert!(moves_node(&responses));
+        assert!(!starts_connection(&responses));
+
+        // Unless the key is bound to another one
+        let (mut state, port) = single_node_editor(&ctx);
+        state.input_bindings.prefer_move_key = Some(egui::Key::N);
+        let responses = drag(&mut state, &ctx, port, delta, &[egui::Key::M]);
+        assert!(starts_connection(&responses));
+        let (mut state, port) = single_node_editor(&ctx);
+        state.input_bindings.prefer_move_key = Some(egui::Key::N);
+        let responses = drag(&mut state, &ctx, port, delta, &[egui::Key::N]);
+        assert!(moves_node(&responses));
+    }
+
+    #[test]
+    pub fn test_prefer_connect_grabs_port_from_node_body() {
+        let ctx = egui::Context::default();
+        // Inside the node body, close to the port without touching it
+        let offset = egui::vec2(-3.0, 8.0);
+        let delta = egui::vec2(30.0, 0.0);
+
+        let (mut state, port) = single_node_editor(&ctx);
+        let responses = drag(&mut state, &ctx, port + offset, delta, &[]);
+        assert!(moves_node(&responses));
+        assert!(!starts_connection(&responses));
+
+        let (mut state, port) = single_node_editor(&ctx);
+        state.input_bindings.interaction_priority = InteractionPriority::PreferConnect;
+        let responses = drag(&mut state, &ctx, port + offset, delta, &[]);
+        assert!(starts_connection(&responses));
+        assert!(!moves_node(&responses));
+
+        // Holding C forces the same behavior
+        let (mut state, port) = single_node_editor(&ctx);
+        let responses = drag(&mut state, &ctx, port + offset, delta, &[egui::Key::C]);
+        assert!(starts_connection(&responses));
+    }
+
+    /// Returns an editor with a source node and a target node of the given
+    /// template, with `extra_inputs` additional scalar inputs on the target.
+    /// Also returns the output of the source and the location of a point on
+    /// the body of the target, away from its ports.
+    fn drop_editor(
+        ctx: &egui::Context,
+        target: TestTemplate,
+        extra_inputs: usize,
+    ) -> (TestEditorState, OutputId, NodeId, egui::Pos2, egui::Pos2) {
+        let mut state = TestEditorState::new(1.0);
+        state.drop_on_node_body = true;
+        let source = state.add_node(&TestTemplate::Scalar, egui::pos2(0.0, 0.0), &mut ());
+        let target = state.add_node(&target, egui::pos2(300.0, 0.0), &mut ());
+        for i in 0..extra_inputs {
+            state.graph.add_input_param(
+                target,
+                format!("extra{}", i),
+                TestDataType::Scalar,
+                TestValue::default(),
+                InputParamKind::ConnectionOrConstant,
+                true,
+            );
+        }
+        let output = state.graph[source].get_output("out").unwrap();
+        let input = state.graph[target].get_input("in").unwrap();
+        let ports = draw_frame(&mut state, ctx).port_locations;
+        let from = ports[&AnyParameterId::Output(output)];
+        let body = ports[&AnyParameterId::Input(input)] + egui::vec2(30.0, 0.0);
+        (state, output, target, from, body)
+    }
+
+    #[test]
+    pub fn test_drop_on_node_body_with_single_input() {
+        let ctx = egui::Context::default();
+        let (mut state, output, target, from, body) = drop_editor(&ctx, TestTemplate::Scalar, 0);
+
+        drag(&mut state, &ctx, from, body - from, &[]);
+
+        let input = state.graph[target].get_input("in").unwrap();
+        assert_eq!(state.graph.connection(input), Some(output));
+        assert!(state.pending_input_choice.is_none());
+    }
+
+    #[test]
+    pub fn test_drop_on_node_body_with_several_inputs() {
+        let ctx = egui::Context::default();
+        let (mut state, output, target, from, body) = drop_editor(&ctx, TestTemplate::Scalar, 1);
+
+        drag(&mut state, &ctx, from, body - from, &[]);
+        let choice = state.pending_input_choice.as_ref().unwrap();
+        assert_eq!(choice.inputs.len(), 2);
+        assert!(state.graph.iter_connections().next().is_none());
+
+        let key = |key| egui::Event::Key {
+            key,
+            pressed: true,
+            repeat: false,
+            modifiers: egui::Modifiers::NONE,
+        };
+        let input = egui::RawInput {
+            events: vec![key(egui::Key::ArrowDown), key(egui::Key::Enter)],
+            ..Default::default()
+        };
+        let responses = draw_frame_with_input(&mut state, &ctx, input).node_responses;
+
+        let extra = state.graph[target].get_input("extra0").unwrap();
+        assert_eq!(state.graph.connection(extra), Some(output));
+        assert!(state.pending_input_choice.is_none());
+        assert!(responses.iter().any(
+            |r| matches!(r, NodeResponse::ConnectEventEnded { input, .. } if *input == extra)
+        ));
+    }
+
+    #[test]
+    pub fn test_drop_on_node_body_without_compatible_input() {
+        let ctx = egui::Context::default();
+        let (mut state, _, target, from, body) = drop_editor(&ctx, TestTemplate::Vector, 0);
+
+        let responses = drag(&mut state, &ctx, from, body - from, &[]);
+
+        assert!(responses
+            .iter()
+            .any(|r| matches!(r, NodeResponse::RejectedDrop { node, .. } if *node == target)));
+        assert!(state.graph.iter_connections().next().is_none());
+        assert!(state.rejected_drop.is_some());
+    }
+
+    #[test]
+    pub fn test_drop_on_background_opens_filtered_finder() {
+        let ctx = egui::Context::default();
+        let mut state = TestEditorState::new(1.0);
+        state.drop_on_background = true;
+        let source = state.add_node(&TestTemplate::Scalar, egui::pos2(300.0, 0.0), &mut ());
+        let output = state.graph[source].get_output("out").unwrap();
+        let input = state.graph[source].get_input("in").unwrap();
+        let ports = draw_frame(&mut state, &ctx).port_locations;
+        let key = |key| egui::Event::Key {
+            key,
+            pressed: true,
+            repeat: false,
+            modifiers: egui::Modifiers::NONE,
+        };
+        let frame_with_key = |state: &mut TestEditorState, key_event| {
+            let input = egui::RawInput {
+                events: vec![key_event],
+                ..Default::default()
+            };
+            draw_frame_with_input(state, &ctx, input).node_responses
+        };
+
+        // Dropped from an input, and cancelled
+        let from = ports[&AnyParameterId::Input(input)];
+        drag(&mut state, &ctx, from, egui::vec2(-200.0, 300.0), &[]);
+        assert!(state.node_finder.is_some());
+        let pending = state.pending_finder_connection.as_ref().unwrap();
+        assert_eq!(pending.port, AnyParameterId::Input(input));
+        frame_with_key(&mut state, key(egui::Key::Escape));
+        assert!(state.node_finder.is_none());
+        assert!(state.pending_finder_connection.is_none());
+
+        // Dropped from an output: only templates with a scalar input are offered
+        let from = ports[&AnyParameterId::Output(output)];
+        let drop_pos = from + egui::vec2(200.0, 300.0);
+        drag(&mut state, &ctx, from, drop_pos - from, &[]);
+        let pending = state.pending_finder_connection.as_ref().unwrap();
+        assert_eq!(
+            pending.templates,
+            vec![TestTemplate::Scalar, TestTemplate::Panicking]
+        );
+        assert_eq!(pending.position, drop_pos);
+
+        // Pick the first template from the keyboard
+        draw_frame(&mut state, &ctx);
+        state.node_finder.as_mut().unwrap().query = "scalar".into();
+        let responses = frame_with_key(&mut state, key(egui::Key::Enter));
+        let new_node = *state.node_order.last().unwrap();
+        assert_ne!(new_node, source);
+        assert_eq!(state.graph[new_node].user_data.0, TestTemplate::Scalar);
+        assert_eq!(state.node_positions[new_node], drop_pos);
+        let new_input = state.graph[new_node].get_input("in").unwrap();
+        assert_eq!(state.graph.connection(new_input), Some(output));
+        assert!(responses
+            .iter()
+            .any(|r| matches!(r, NodeResponse::ConnectEventEnded { .. })));
+        assert!(state.pending_finder_connection.is_none());
+    }
+
+    #[test]
+    pub fn test_enforce_acyclic() {
+        let ctx = egui::Context::default();
+        let mut state = TestEditorState::new(1.0);
+        let a = state.add_node(&TestTemplate::Scalar, egui::pos2(0.0, 0.0), &mut ());
+        let b = state.add_node(&TestTemplate::Scalar, egui::pos2(300.0, 0.0), &mut ());
+        let out_b = state.graph[b].get_output("out").unwrap();
+        let in_a = state.graph[a].get_input("in").unwrap();
+        state.graph.add_connection(
+            state.graph[a].get_output("out").unwrap(),
+            state.graph[b].get_input("in").unwrap(),
+        );
+        let ports = draw_frame(&mut state, &ctx).port_locations;
+        let from = ports[&AnyParameterId::Output(out_b)];
+        let to = ports[&AnyParameterId::Input(in_a)];
+
+        state.enforce_acyclic = true;
+        let responses = drag(&mut state, &ctx, from, to - from, &[]);
+        assert!(responses.iter().any(|r| matches!(
+            r,
+            NodeResponse::RejectedConnection { reason, .. }
+                if *reason == state.localization.would_create_cycle
+        )));
+        assert_eq!(state.graph.connection(in_a), None);
+
+        state.enforce_acyclic = false;
+        drag(&mut state, &ctx, from, to - from, &[]);
+        assert_eq!(state.graph.connection(in_a), Some(out_b));
+    }
+
+    #[test]
+    pub fn test_can_connect_rejects_connection() {
+        let ctx = egui::Context::default();
+        let (mut state, output, target, from, _) = drop_editor(&ctx, TestTemplate::Scalar, 0);
+        let locked = state.graph.add_input_param(
+            target,
+            "locked".into(),
+            TestDataType::Scalar,
+            TestValue::default(),
+            InputParamKind::ConnectionOrConstant,
+            true,
+        );
+        let to = draw_frame(&mut state, &ctx).port_locations[&AnyParameterId::Input(locked)];
+
+        let responses = drag(&mut state, &ctx, from, to - from, &[]);
+
+        assert!(responses.iter().any(|r| matches!(
+            r,
+            NodeResponse::RejectedConnection { output: o, input, reason }
+                if *o == output && *input == locked && reason == "This input is locked"
+        )));
+        assert!(state.graph.iter_connections().next().is_none());
+        assert!(matches!(&state.rejected_connection, Some((input, _, _)) if *input == locked));
+
+        // Connections that don't come from a port are checked as well
+        let prepend = vec![NodeResponse::ConnectEventEnded {
+            output,
+            input: locked,
+        }];
+        let responses =
+            draw_frame_with_responses(&mut state, &ctx, Default::default(), prepend).node_responses;
+        assert!(responses
+            .iter()
+            .any(|r| matches!(r, NodeResponse::RejectedConnection { .. })));
+        assert!(state.graph.iter_connections().next().is_none());
+    }
+
+    /// Drags a connection from the output of a `from` node to the input of a
+    /// `to` node, returning the editor, the two ports and the responses.
+    fn drop_incompatible(
+        ctx: &egui::Context,
+        from: TestTemplate,
+        to: TestTemplate,
+    ) -> (
+        TestEditorState,
+        OutputId,
+        InputId,
+        Vec<NodeResponse<TestResponse, TestNodeData>>,
+    ) {
+        let mut state = TestEditorState::new(1.0);
+        let source = state.add_node(&from, egui::pos2(0.0, 0.0), &mut ());
+        let target = state.add_node(&to, egui::pos2(300.0, 0.0), &mut ());
+        let output = state.graph[source].get_output("out").unwrap();
+        let input = state.graph[target].get_input("in").unwrap();
+        let ports = draw_frame(&mut state, ctx).port_locations;
+        let start = ports[&AnyParameterId::Output(output)];
+        let end = ports[&AnyParameterId::Input(input)];
+        let responses = drag(&mut state, ctx, start, end - start, &[]);
+        (state, output, input, responses)
+    }
+
+    #[test]
+    pub fn test_converter_is_inserted() {
+        let ctx = egui::Context::default();
+        let (mut state, output, input, responses) =
+            drop_incompatible(&ctx, TestTemplate::Scalar, TestTemplate::Vector);
+        assert!(responses.iter().any(|r| matches!(
+            r,
+            NodeResponse::IncompatibleConnection { output: o, input: i } if *o == output && *i == input
+        )));
+        assert!(matches!(
+            &state.pending_converter,
+            Some(pending) if pending.template == TestTemplate::ScalarToVector
+        ));
+        assert!(state.graph.iter_connections().next().is_none());
+        // egui hides new areas for their first frame, while it sizes them
+        draw_frame(&mut state, &ctx);
+
+        // Click the confirmation button of the popup
+        let confirm = draw_frame_shapes(&mut state, &ctx, Default::default())
+            .into_iter()
+            .find_map(|shape| match shape {
+                egui::Shape::Text(text)
+                    if text.galley.text() == state.localization.insert_converter_confirm =>
+                {
+                    Some(text.pos + text.galley.size() / 2.0)
+                }
+                _ => None,
+            })
+            .unwrap();
+        let button = |pressed| egui::Event::PointerButton {
+            pos: confirm,
+            button: egui::PointerButton::Primary,
+            pressed,
+            modifiers: egui::Modifiers::NONE,
+        };
+        let mut responses = Vec::new();
+        for events in [
+            vec![egui::Event::PointerMoved(confirm)],
+            vec![button(true)],
+            vec![button(false)],
+            vec![],
+        ] {
+            let input = egui::RawInput {
+                events,
+                ..Default::default()
+            };
+            responses.extend(draw_frame_with_input(&mut state, &ctx, input).node_responses);
+        }
+
+        assert!(state.pending_converter.is_none());
+        assert_eq!(state.graph.nodes.len(), 3);
+        let converter = state
+            .graph
+            .iter_nodes()
+            .find(|node| state.graph[*node].user_data.0 == TestTemplate::ScalarToVector)
+            .unwrap();
+        let converter_in = state.graph[converter].get_input("in").unwrap();
+        let converter_out = state.graph[converter].get_output("out").unwrap();
+        assert_eq!(state.graph.connection(converter_in), Some(output));
+        assert_eq!(state.graph.connection(input), Some(converter_out));
+        assert!(responses
+            .iter()
+            .any(|r| matches!(r, NodeResponse::CreatedNode(node) if *node == converter)));
+    }
+
+    #[test]
+    pub fn test_no_converter() {
+        let ctx = egui::Context::default();
+        // There is no converter from vectors to scalars
+        let (mut state, output, input, responses) =
+            drop_incompatible(&ctx, TestTemplate::Vector, TestTemplate::Scalar);
+        assert!(responses.iter().any(|r| matches!(
+            r,
+            NodeResponse::IncompatibleConnection { output: o, input: i } if *o == output && *i == input
+        )));
+        draw_frame(&mut state, &ctx);
+
+        assert!(state.pending_converter.is_none());
+        assert_eq!(state.graph.nodes.len(), 2);
+        assert!(state.graph.iter_connections().next().is_none());
+    }
+
+    #[test]
+    pub fn test_keyboard_shortcuts() {
+        let ctx = egui::Context::default();
+        let (mut state, nodes) = state_with_nodes(3);
+        hover_editor(&mut state, &ctx);
+        let frame_with_events = |state: &mut TestEditorState, events| {
+            let input = egui::RawInput {
+                events,
+                ..Default::default()
+            };
+            draw_frame_with_input(state, &ctx, input).node_responses
+        };
+        let key = |key, modifiers| egui::Event::Key {
+            key,
+            pressed: true,
+            repeat: false,
+            modifiers,
+        };
+
+        frame_with_events(
+            &mut state,
+            vec![key(egui::Key::A, egui::Modifiers::COMMAND)],
+        );
+        assert_eq!(state.selected_nodes, nodes);
+
+        // Typing into the node finder doesn't delete the selection
+        state.selected_nodes = vec![nodes[0], nodes[2]];
+        state.node_finder = Some(NodeFinder::new_at(egui::pos2(500.0, 500.0)));
+        draw_frame(&mut state, &ctx);
+        frame_with_events(
+            &mut state,
+            vec![key(egui::Key::Delete, egui::Modifiers::NONE)],
+        );
+        assert_eq!(state.node_order.len(), 3);
+
+        // Escape closes the finder, then Delete removes the selection
+        frame_with_events(
+            &mut state,
+            vec![key(egui::Key::Escape, egui::Modifiers::NONE)],
+        );
+        assert!(state.node_finder.is_none());
+        let responses = frame_with_events(
+            &mut state,
+            vec![key(egui::Key::Delete, egui::Modifiers::NONE)],
+        );
+        assert_eq!(state.node_order, vec![nodes[1]]);
+        let deleted: Vec<NodeId> = responses
+            .iter()
+            .filter_map(|r| match r {
+                NodeResponse::DeleteNodeFull { node_id, .. } => Some(*node_id),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(deleted, vec![nodes[0], nodes[2]]);
+
+        // Escape cancels a connection drag
+        let output = state.graph[nodes[1]].get_output("out").unwrap();
+        let from = draw_frame(&mut state, &ctx).port_locations[&AnyParameterId::Output(output)];
+        let button = |pressed| egui::Event::PointerButton {
+            pos: from,
+            button: egui::PointerButton::Primary,
+            pressed,
+            modifiers: egui::Modifiers::NONE,
+        };
+        frame_with_events(&mut state, vec![egui::Event::PointerMoved(from)]);
+        frame_with_events(&mut state, vec![button(true)]);
+        frame_with_events(
+            &mut state,
+            vec![egui::Event::PointerMoved(from + egui::vec2(50.0, 50.0))],
+        );
+        assert!(state.connection_in_progress.is_some());
+        frame_with_events(
+            &mut state,
+            vec![key(egui::Key::Escape, egui::Modifiers::NONE)],
+        );
+        assert!(state.connection_in_progress.is_none());
+    }
+
+    #[test]
+    pub fn test_deletion_breaking_connections_is_confirmed() {
+        let ctx = egui::Context::default();
+        let (mut state, nodes) = state_with_nodes(3);
+        for pair in nodes.windows(2) {
+            let output = state.graph[pair[0]].get_output("out").unwrap();
+            let input = state.graph[pair[1]].get_input("in").unwrap();
+            state.graph.add_connection(output, input);
+        }
+        hover_editor(&mut state, &ctx);
+        let key = |key| egui::RawInput {
+            events: vec![egui::Event::Key {
+                key,
+                pressed: true,
+                repeat: false,
+                modifiers: egui::Modifiers::NONE,
+            }],
+            ..Default::default()
+        };
+
+        // The deletion waits for a confirmation, its connections highlighted
+        state.selected_nodes = vec![nodes[0], nodes[1]];
+        draw_frame_with_input(&mut state, &ctx, key(egui::Key::Delete));
+        draw_frame(&mut state, &ctx);
+        assert_eq!(state.node_order.len(), 3);
+        let pending = state.deletion_to_confirm.as_ref().unwrap();
+        assert_eq!(pending.nodes, [nodes[0], nodes[1]]);
+        assert_eq!(pending.connection_count, 2);
+        assert!(draw_frame_texts(&mut state, &ctx, Default::default())
+            .contains(&state.localization.delete_nodes_prompt.to_string()));
+        assert_eq!(state.pending_deletion, nodes[..2].iter().copied().collect());
+
+        // Escape cancels it
+        draw_frame_with_input(&mut state, &ctx, key(egui::Key::Escape));
+        draw_frame(&mut state, &ctx);
+        assert!(state.deletion_to_confirm.is_none());
+        assert!(state.pending_deletion.is_empty());
+        assert_eq!(state.node_order.len(), 3);
+
+        // Enter confirms it
+        draw_frame_with_input(&mut state, &ctx, key(egui::Key::Delete));
+        draw_frame_with_input(&mut state, &ctx, key(egui::Key::Enter));
+        assert!(state.deletion_to_confirm.is_none());
+        assert_eq!(state.node_order, [nodes[2]]);
+        assert!(state.graph.iter_connections().next().is_none());
+    }
+
+    #[test]
+    pub fn test_collapse_node() {
+        let ctx = egui::Context::default();
+        let (mut state, nodes) = state_with_nodes(2);
+        *state.node_positions.get_mut(nodes[1]).unwrap() = egui::pos2(300.0, 0.0);
+        let click = |state: &mut TestEditorState, pos| {
+            let button = |pressed| egui::Event::PointerButton {
+                pos,
+                button: egui::PointerButton::Primary,
+                pressed,
+                modifiers: egui::Modifiers::NONE,
+            };
+            [
+                vec![egui::Event::PointerMoved(pos)],
+                vec![button(true)],
+                vec![button(false)],
+            ]
+            .into_iter()
+            .flat_map(|events| {
+                let input = egui::RawInput {
+                    events,
+                    ..Default::default()
+                };
+                draw_frame_with_input(state, &ctx, input).node_responses
+            })
+            .collect::<Vec<_>>()
+        };
+        let port_heights = |state: &mut TestEditorState, node: NodeId| {
+            let ports = draw_frame(state, &ctx).port_locations;
+            let input = state.graph[node].get_input("in").unwrap();
+            let output = state.graph[node].get_output("out").unwrap();
+            (
+                ports[&AnyParameterId::Input(input)].y,
+                ports[&AnyParameterId::Output(output)].y,
+            )
+        };
+        let (input_y, output_y) = port_heights(&mut state, nodes[0]);
+        assert!(input_y < output_y);
+
+        // The toggle sits at the left of the title
+        let toggle = egui::pos2(20.0, 12.0);
+        let responses = click(&mut state, toggle);
+        assert!(responses.iter().any(|r| matches!(
+            r,
+            NodeResponse::CollapseNode { node, collapsed: true } if *node == nodes[0]
+        )));
+        assert!(state.collapsed_nodes.contains(&nodes[0]));
+        // The ports are stubs on the edges of the title bar
+        let (input_y, output_y) = port_heights(&mut state, nodes[0]);
+        assert_eq!(input_y, output_y);
+        assert!(input_y < 30.0);
+
+        // Connections can still be made to and from collapsed nodes
+        state.collapsed_nodes.insert(nodes[1]);
+        let ports = draw_frame(&mut state, &ctx).port_locations;
+        let output = state.graph[nodes[0]].get_output("out").unwrap();
+        let input = state.graph[nodes[1]].get_input("in").unwrap();
+        let from = ports[&AnyParameterId::Output(output)];
+        let to = ports[&AnyParameterId::Input(input)];
+        drag(&mut state, &ctx, from, to - from, &[]);
+        assert_eq!(state.graph.connection(input), Some(output));
+
+        // Double-clicking the title bar, next to the title, expands the node
+        // back
+        let title = egui::pos2(76.0, 12.0);
+        let mut responses = click(&mut state, title);
+        responses.extend(click(&mut state, title));
+        assert!(responses.iter().any(|r| matches!(
+            r,
+            NodeResponse::CollapseNode {
+                collapsed: false,
+                ..
+            }
+        )));
+        assert!(!state.collapsed_nodes.contains(&nodes[0]));
+
+        // User code can veto collapsing
+        state.graph.rename_node(nodes[0], "expanded".into());
+        state.collapsed_nodes.insert(nodes[0]);
+        let (input_y, output_y) = port_heights(&mut state, nodes[0]);
+        assert!(input_y < output_y);
+        let responses = click(&mut state, toggle);
+        assert!(!responses
+            .iter()
+            .any(|r| matches!(r, NodeResponse::CollapseNode { .. })));
+
+        state.remove_node(nodes[1]);
+        assert!(!state.collapsed_nodes.contains(&nodes[1]));
+    }
+
+    #[test]
+    pub fn test_node_context_menu() {
+        let ctx = egui::Context::default();
+        let (mut state, nodes) = state_with_nodes(2);
+        *state.node_positions.get_mut(nodes[1]).unwrap() = egui::pos2(300.0, 0.0);
+        let output = state.graph[nodes[0]].get_output("out").unwrap();
+        let input = state.graph[nodes[1]].get_input("in").unwrap();
+        state.graph.add_connection(output, input);
+        let click = |state: &mut TestEditorState, pos, button| {
+            let event = |pressed| egui::Event::PointerButton {
+                pos,
+                button,
+                pressed,
+                modifiers: egui::Modifiers::NONE,
+            };
+            [
+                vec![egui::Event::PointerMoved(pos)],
+                vec![event(true)],
+                vec![event(false)],
+            ]
+            .into_iter()
+            .flat_map(|events| {
+                let input = egui::RawInput {
+                    events,
+                    ..Default::default()
+                };
+                draw_frame_with_input(state, &ctx, input).node_responses
+            })
+            .collect::<Vec<_>>()
+        };
+        draw_frame(&mut state, &ctx);
+
+        // Right-clicking the background still opens the node finder
+        click(
+            &mut state,
+            egui::pos2(600.0, 600.0),
+            egui::PointerButton::Secondary,
+        );
+        assert!(state.node_finder.is_some());
+        state.node_finder = None;
+
+        // Right-clicking a node opens its context menu instead
+        let body = egui::pos2(40.0, 40.0);
+        click(&mut state, body, egui::PointerButton::Secondary);
+        assert!(state.node_finder.is_none());
+
+        // Delete is the first entry
+        let responses = click(
+            &mut state,
+            body + egui::vec2(20.0, 12.0),
+            egui::PointerButton::Primary,
+        );
+        assert!(responses.iter().any(|r| matches!(
+            r,
+            NodeResponse::DeleteNodeFull { node_id, .. } if *node_id == nodes[0]
+        )));
+        assert!(!state.graph.nodes.contains_key(nodes[0]));
+
+        // Disconnect all
+        let (mut state, nodes) = state_with_nodes(3);
+        let output = state.graph[nodes[1]].get_output("out").unwrap();
+        let inputs = [nodes[0], nodes[2]].map(|node| state.graph[node].get_input("in").unwrap());
+        for input in inputs {
+            state.graph.add_connection(output, input);
+        }
+        let responses = draw_frame_with_responses(
+            &mut state,
+            &ctx,
+            Default::default(),
+            vec![NodeResponse::DisconnectNode(nodes[1])],
+        )
+        .node_responses;
+        let disconnected = responses
+            .iter()
+            .filter(|r| matches!(r, NodeResponse::DisconnectEvent { .. }))
+            .count();
+        assert_eq!(disconnected, 2);
+        assert_eq!(state.graph.iter_connections().count(), 0);
+        assert!(state.connection_in_progress.is_none());
+    }
+
+    #[test]
+    pub fn test_select_and_remove_connection() {
+        let ctx = egui::Context::default();
+        let (mut state, nodes) = state_with_nodes(3);
+        *state.node_positions.get_mut(nodes[1]).unwrap() = egui::pos2(400.0, 0.0);
+        *state.node_positions.get_mut(nodes[2]).unwrap() = egui::pos2(400.0, 300.0);
+        let output = state.graph[nodes[0]].get_output("out").unwrap();
+        let inputs = [nodes[1], nodes[2]].map(|node| state.graph[node].get_input("in").unwrap());
+        for input in inputs {
+            state.graph.add_connection(output, input);
+        }
+        let frame_with_events = |state: &mut TestEditorState, events| {
+            let input = egui::RawInput {
+                events,
+                ..Default::default()
+            };
+            draw_frame_with_input(state, &ctx, input).node_responses
+        };
+        let click = |state: &mut TestEditorState, pos, button| {
+            let event = |pressed| egui::Event::PointerButton {
+                pos,
+                button,
+                pressed,
+                modifiers: egui::Modifiers::NONE,
+            };
+            let mut responses = frame_with_events(state, vec![egui::Event::PointerMoved(pos)]);
+            responses.extend(frame_with_events(state, vec![event(true)]));
+            responses.extend(frame_with_events(state, vec![event(false)]));
+            responses
+        };
+        let ports = draw_frame(&mut state, &ctx).port_locations;
+        let from = ports[&AnyParameterId::Output(output)];
+        let to = inputs.map(|input| ports[&AnyParameterId::Input(input)]);
+        // Symmetric curves go through the middle of their ports
+        let middle = from + (to[1] - from) / 2.0;
+
+        state.selected_nodes = vec![nodes[0]];
+        click(&mut state, middle, egui::PointerButton::Primary);
+        assert_eq!(state.selected_connection, Some((inputs[1], output)));
+        assert!(state.selected_nodes.is_empty());
+
+        // Near the end of the other connection, that one is picked
+        click(
+            &mut state,
+            to[0] - egui::vec2(25.0, 3.0),
+            egui::PointerButton::Primary,
+        );
+        assert_eq!(state.selected_connection, Some((inputs[0], output)));
+
+        // Selecting a node clears the selected connection
+        click(
+            &mut state,
+            egui::pos2(40.0, 40.0),
+            egui::PointerButton::Primary,
+        );
+        assert_eq!(state.selected_nodes, vec![nodes[0]]);
+        assert!(state.selected_connection.is_none());
+
+        // Away from any connection, the selection is cleared
+        click(
+            &mut state,
+            middle + egui::vec2(0.0, 100.0),
+            egui::PointerButton::Primary,
+        );
+        assert!(state.selected_connection.is_none());
+
+        click(&mut state, middle, egui::PointerButton::Primary);
+        let responses = frame_with_events(
+            &mut state,
+            vec![egui::Event::Key {
+                key: egui::Key::Delete,
+                pressed: true,
+                repeat: false,
+                modifiers: egui::Modifiers::NONE,
+            }],
+        );
+        assert!(responses.iter().any(|r| matches!(
+            r,
+            NodeResponse::DisconnectEvent { input, .. } if *input == inputs[1]
+        )));
+        assert_eq!(state.graph.connections(inputs[1]), []);
+        assert_eq!(state.graph.connections(inputs[0]), [output]);
+        assert!(state.selected_connection.is_none());
+        assert!(state.connection_in_progress.is_none());
+
+        // Remove connection is the only entry of the context menu
+        let middle = from + (to[0] - from) / 2.0;
+        click(&mut state, middle, egui::PointerButton::Secondary);
+        assert!(state.node_finder.is_none());
+        assert_eq!(state.connection_menu, Some((inputs[0], output, middle)));
+        let responses = click(
+            &mut state,
+            middle + egui::vec2(20.0, 12.0),
+            egui::PointerButton::Primary,
+        );
+        assert!(responses
+            .iter()
+            .any(|r| matches!(r, NodeResponse::DisconnectEvent { .. })));
+        assert_eq!(state.graph.iter_connections().count(), 0);
+        assert!(state.connection_menu.is_none());
+    }
+
+    #[test]
+    pub fn test_reroute_points() {
+        let ctx = egui::Context::default();
+        let (mut state, nodes) = state_with_nodes(2);
+        *state.node_positions.get_mut(nodes[1]).unwrap() = egui::pos2(400.0, 0.0);
+        let output = state.graph[nodes[0]].get_output("out").unwrap();
+        let input = state.graph[nodes[1]].get_input("in").unwrap();
+        state.graph.add_connection(output, input);
+        // Clicks in quick succession are counted as double or triple clicks,
+        // so they are spaced out unless `count` is more than one
+        let time = std::cell::Cell::new(0.0);
+        let clicks = |state: &mut TestEditorState, pos, modifiers, count| {
+            time.set(time.get() + 1.0);
+            let button = |pressed| egui::Event::PointerButton {
+                pos,
+                button: egui::PointerButton::Primary,
+                pressed,
+                modifiers,
+            };
+            for _ in 0..count {
+                for events in [
+                    vec![egui::Event::PointerMoved(pos)],
+                    vec![button(true)],
+                    vec![button(false)],
+                ] {
+                    time.set(time.get() + 0.02);
+                    let input = egui::RawInput {
+                        time: Some(time.get()),
+                        events,
+                        modifiers,
+                        ..Default::default()
+                    };
+                    draw_frame_with_input(state, &ctx, input);
+                }
+            }
+        };
+        let click = |state: &mut TestEditorState, pos, modifiers| clicks(state, pos, modifiers, 1);
+        let double_click =
+            |state: &mut TestEditorState, pos| clicks(state, pos, egui::Modifiers::NONE, 2);
+        let ports = draw_frame(&mut state, &ctx).port_locations;
+        let from = ports[&AnyParameterId::Output(output)];
+        let to = ports[&AnyParameterId::Input(input)];
+        // Symmetric curves go through the middle of their ends
+        let middle = from + (to - from) / 2.0;
+
+        // Double-clicking the connection adds a reroute point under the cursor
+        double_click(&mut state, middle);
+        assert_eq!(state.reroutes[&(input, output)], vec![middle]);
+
+        // Which can be dragged around. The connection follows it.
+        drag(&mut state, &ctx, middle, egui::vec2(0.0, 200.0), &[]);
+        let point = middle + egui::vec2(0.0, 200.0);
+        assert_eq!(state.reroutes[&(input, output)], vec![point]);
+        click(&mut state, middle, egui::Modifiers::NONE);
+        assert!(state.selected_connection.is_none());
+        let second_half = point + (to - point) / 2.0;
+        click(&mut state, second_half, egui::Modifiers::NONE);
+        assert_eq!(state.selected_connection, Some((input, output)));
+
+        // Alt-clicking a segment splits it, in order
+        click(&mut state, second_half, egui::Modifiers::ALT);
+        assert_eq!(state.reroutes[&(input, output)], vec![point, second_half]);
+
+        // Double-clicking a reroute point removes it
+        double_click(&mut state, point);
+        assert_eq!(state.reroutes[&(input, output)], vec![second_half]);
+        click(&mut state, second_half, egui::Modifiers::ALT);
+        assert!(state.reroutes.is_empty());
+
+        // Reroute points go away along with their connection
+        click(&mut state, middle, egui::Modifiers::ALT);
+        assert_eq!(state.reroutes.len(), 1);
+        state.graph.remove_connection_between(output, input);
+        draw_frame(&mut state, &ctx);
+        assert!(state.reroutes.is_empty());
+    }
+
+    #[test]
+    pub fn test_connection_styles() {
+        let ctx = egui::Context::default();
+        let (mut state, nodes) = state_with_nodes(2);
+        *state.node_positions.get_mut(nodes[1]).unwrap() = egui::pos2(400.0, 150.0);
+        let output = state.graph[nodes[0]].get_output("out").unwrap();
+        let input = state.graph[nodes[1]].get_input("in").unwrap();
+        state.graph.add_connection(output, input);
+        // Spaced out, so they aren't double-clicks adding reroute points
+        let time = std::cell::Cell::new(0.0);
+        let click = |state: &mut TestEditorState, pos| {
+            state.selected_connection = None;
+            time.set(time.get() + 1.0);
+            let button = |pressed| egui::Event::PointerButton {
+                pos,
+                button: egui::PointerButton::Primary,
+                pressed,
+                modifiers: egui::Modifiers::NONE,
+            };
+            for events in [
+                vec![egui::Event::PointerMoved(pos)],
+                vec![button(true)],
+                vec![button(false)],
+            ] {
+                time.set(time.get() + 0.02);
+                let input = egui::RawInput {
+                    time: Some(time.get()),
+                    events,
+                    ..Default::default()
+                };
+                draw_frame_with_input(state, &ctx, input);
+            }
+            state.selected_connection.is_some()
+        };
+        let ports = draw_frame(&mut state, &ctx).port_locations;
+        let from = ports[&AnyParameterId::Output(output)];
+        let to = ports[&AnyParameterId::Input(input)];
+        let middle = from + (to - from) / 2.0;
+        // On the vertical run of an orthogonal connection
+        let vertical = egui::pos2(middle.x, from.y + 30.0);
+
+        assert_eq!(state.connection_style, ConnectionStyle::Bezier);
+        assert!(!click(&mut state, vertical));
+
+        state.connection_style = ConnectionStyle::Orthogonal;
+        assert!(click(&mut state, vertical));
+        assert!(click(&mut state, middle));
+        assert!(!click(&mut state, middle + egui::vec2(50.0, -30.0)));
+
+        state.connection_style = ConnectionStyle::Straight;
+        assert!(!click(&mut state, vertical));
+        assert!(click(&mut state, from + (to - from) * 0.25));
+
+        // Orthogonal connections going backwards run below their nodes
+        state.connection_style = ConnectionStyle::Orthogonal;
+        *state.node_positions.get_mut(nodes[1]).unwrap() = egui::pos2(-300.0, 0.0);
+        let lowest = draw_frame_shapes(&mut state, &ctx, Default::default())
+            .into_iter()
+            .filter_map(|shape| match shape {
+                egui::Shape::Mesh(mesh) => mesh.vertices.iter().map(|v| v.pos.y).reduce(f32::max),
+                _ => None,
+            })
+            .reduce(f32::max)
+            .unwrap();
+        assert!(lowest > from.y + 15.0);
+    }
+
+    #[test]
+    pub fn test_snap_to_grid() {
+        let ctx = egui::Context::default();
+        let (mut state, nodes) = state_with_nodes(2);
+        *state.node_positions.get_mut(nodes[0]).unwrap() = egui::pos2(3.0, 7.0);
+        *state.node_positions.get_mut(nodes[1]).unwrap() = egui::pos2(203.0, 57.0);
+        state.selected_nodes = nodes.clone();
+        state.grid.snap = true;
+        draw_frame(&mut state, &ctx);
+        let title =
+            |state: &TestEditorState| state.node_positions[nodes[0]] + egui::vec2(60.0, 12.0);
+
+        // The dragged node lands on the grid, the others keep their offset
+        let from = title(&state);
+        let responses = drag(&mut state, &ctx, from, egui::vec2(25.0, 12.0), &[]);
+        assert_eq!(state.node_positions[nodes[0]], egui::pos2(20.0, 20.0));
+        assert_eq!(state.node_positions[nodes[1]], egui::pos2(220.0, 70.0));
+        // The reported delta is the one applied
+        let moved = responses
+            .iter()
+            .filter_map(|r| match r {
+                NodeResponse::MoveNode { drag_delta, .. } => Some(*drag_delta),
+                _ => None,
+            })
+            .fold(egui::Vec2::ZERO, |total, delta| total + delta);
+        assert_eq!(moved, egui::vec2(17.0, 13.0));
+
+        // Small drags accumulate until the next grid point is closer
+        let from = title(&state);
+        let button = |pos, pressed| egui::Event::PointerButton {
+            pos,
+            button: egui::PointerButton::Primary,
+            pressed,
+            modifiers: egui::Modifiers::NONE,
+        };
+        let mut frames = vec![
+            vec![egui::Event::PointerMoved(from)],
+            vec![button(from, true)],
+        ];
+        frames.extend((1..=4).map(|i| {
+            vec![egui::Event::PointerMoved(
+                from + egui::vec2(4.0 * i as f32, 0.0),
+            )]
+        }));
+        for events in frames {
+            let input = egui::RawInput {
+                events,
+                ..Default::default()
+            };
+            draw_frame_with_input(&mut state, &ctx, input);
+            assert_eq!(state.node_positions[nodes[0]].y, 20.0);
+        }
+        assert_eq!(state.node_positions[nodes[0]], egui::pos2(40.0, 20.0));
+        let input = egui::RawInput {
+            events: vec![button(from + egui::vec2(16.0, 0.0), false)],
+            ..Default::default()
+        };
+        draw_frame_with_input(&mut state, &ctx, input);
+
+        // Holding Ctrl disables snapping
+        let from = title(&state);
+        for (events, modifiers) in [
+            (
+                vec![egui::Event::PointerMoved(from)],
+                egui::Modifiers::COMMAND,
+            ),
+            (vec![button(from, true)], egui::Modifiers::COMMAND),
+            (
+                vec![egui::Event::PointerMoved(from + egui::vec2(5.0, 3.0))],
+                egui::Modifiers::COMMAND,
+            ),
+            (
+                vec![button(from + egui::vec2(5.0, 3.0), false)],
+                egui::Modifiers::COMMAND,
+            ),
+        ] {
+            let input = egui::RawInput {
+                events,
+                modifiers,
+                ..Default::default()
+            };
+            draw_frame_with_input(&mut state, &ctx, input);
+        }
+        assert_eq!(state.node_positions[nodes[0]], egui::pos2(45.0, 23.0));
+    }
+
+    #[test]
+    pub fn test_fit_to_screen_and_center_on_node() {
+        let ctx = egui::Context::default();
+        let screen = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(800.0, 600.0));
+        let frame = |state: &mut TestEditorState| {
+            let input = egui::RawInput {
+                screen_rect: Some(screen),
+                ..Default::default()
+            };
+            draw_frame_with_input(state, &ctx, input).port_locations
+        };
+        let (mut state, nodes) = state_with_nodes(3);
+        *state.node_positions.get_mut(nodes[1]).unwrap() = egui::pos2(2000.0, 0.0);
+        *state.node_positions.get_mut(nodes[2]).unwrap() = egui::pos2(1000.0, 1500.0);
+
+        // Works before the nodes were ever drawn
+        state.fit_to_screen();
+        let ports = frame(&mut state);
+        assert!(state.pan_zoom.zoom < 1.0);
+        assert_eq!(ports.len(), 6);
+        assert!(ports.values().all(|pos| screen.shrink(20.0).contains(*pos)));
+
+        // A small graph isn't zoomed in
+        let (mut state, nodes) = state_with_nodes(1);
+        frame(&mut state);
+        state.fit_to_screen();
+        frame(&mut state);
+        assert_eq!(state.pan_zoom.zoom, 1.0);
+
+        state.pan_zoom.zoom = 2.0;
+        *state.node_positions.get_mut(nodes[0]).unwrap() = egui::pos2(-500.0, 300.0);
+        state.center_on_node(nodes[0]);
+        let ports = frame(&mut state);
+        let input = state.graph[nodes[0]].get_input("in").unwrap();
+        let output = state.graph[nodes[0]].get_output("out").unwrap();
+        let (input, output) = (
+            ports[&AnyParameterId::Input(input)],
+            ports[&AnyParameterId::Output(output)],
+        );
+        assert_eq!(state.pan_zoom.zoom, 2.0);
+        assert!(input.x < 400.0 && output.x > 400.0);
+        assert!((input.y - 300.0).abs() < 100.0);
+
+        // Nodes removed in the meantime are ignored
+        state.center_on_node(nodes[0]);
+        state.remove_node(nodes[0]);
+        let pan = state.pan_zoom.pan;
+        frame(&mut state);
+        assert_eq!(state.pan_zoom.pan, pan);
+    }
+
+    #[test]
+    pub fn test_port_tooltip_after_delay() {
+        let ctx = egui::Context::default();
+        let (mut state, nodes) = state_with_nodes(2);
+        *state.node_positions.get_mut(nodes[1]).unwrap() = egui::pos2(300.0, 0.0);
+        let output = state.graph[nodes[0]].get_output("out").unwrap();
+        let input = state.graph[nodes[1]].get_input("in").unwrap();
+        state.graph.add_connection(output, input);
+        state.graph.rename_node(nodes[0], "Source".into());
+        let port = draw_frame(&mut state, &ctx).port_locations[&AnyParameterId::Input(input)];
+        let frame_at = |state: &mut TestEditorState, time, events| {
+            let input = egui::RawInput {
+                time: Some(time),
+                events,
+                ..Default::default()
+            };
+            draw_frame_texts(state, &ctx, input)
+        };
+        let button = |pressed| egui::Event::PointerButton {
+            pos: port,
+            button: egui::PointerButton::Primary,
+            pressed,
+            modifiers: egui::Modifiers::NONE,
+        };
+        let tooltip = "Source › out".to_owned();
+
+        frame_at(&mut state, 1.0, vec![egui::Event::PointerMoved(port)]);
+        assert!(!frame_at(&mut state, 1.2, vec![]).contains(&tooltip));
+        // egui hides new tooltips for their first frame
+        frame_at(&mut state, 1.5, vec![]);
+        let texts = frame_at(&mut state, 1.6, vec![]);
+        assert!(texts.contains(&tooltip));
+        assert!(texts.contains(&"Scalar".to_owned()));
+        assert!(texts.contains(&"Connection or constant".to_owned()));
+
+        // Moving away hides it, and coming back restarts the delay
+        let away = port + egui::vec2(100.0, 300.0);
+        frame_at(&mut state, 1.7, vec![egui::Event::PointerMoved(away)]);
+        frame_at(&mut state, 1.8, vec![egui::Event::PointerMoved(port)]);
+        assert!(!frame_at(&mut state, 1.9, vec![]).contains(&tooltip));
+
+        // No tooltip while dragging a connection
+        frame_at(&mut state, 2.0, vec![button(true)]);
+        assert!(!frame_at(&mut state, 3.0, vec![]).contains(&tooltip));
+    }
+
+    #[test]
+    pub fn test_drag_feedback() {
+        let ctx = egui::Context::default();
+        let mut state = TestEditorState::new(1.0);
+        let a = state.add_node(&TestTemplate::Scalar, egui::pos2(0.0, 0.0), &mut ());
+        let b = state.add_node(&TestTemplate::Scalar, egui::pos2(300.0, 0.0), &mut ());
+        let c = state.add_node(&TestTemplate::Vector, egui::pos2(300.0, 200.0), &mut ());
+        let output = state.graph[a].get_output("out").unwrap();
+        let scalar_in = state.graph[b].get_input("in").unwrap();
+        let vector_in = state.graph[c].get_input("in").unwrap();
+        let ports = draw_frame(&mut state, &ctx).port_locations;
+        state.connection_in_progress = Some((a, AnyParameterId::Output(output)));
+
+        let shapes_at = |state: &mut TestEditorState, pos| {
+            let input = egui::RawInput {
+                events: vec![egui::Event::PointerMoved(pos)],
+                ..Default::default()
+            };
+            draw_frame_shapes(state, &ctx, input)
+        };
+        let circle_at = |shapes: &[egui::Shape], pos: egui::Pos2| {
+            shapes.iter().find_map(|shape| match shape {
+                egui::Shape::Circle(circle) if circle.center.distance(pos) < 1.0 => Some(*circle),
+                _ => None,
+            })
+        };
+        let wire_color = |shapes: &[egui::Shape]| {
+            shapes.iter().find_map(|shape| match shape {
+                egui::Shape::CubicBezier(bezier) => Some(bezier.stroke.color),
+                _ => None,
+            })
+        };
+
+        // Compatible ports are enlarged, incompatible ones grayed out
+        let shapes = shapes_at(&mut state, egui::pos2(200.0, 300.0));
+        let scalar_port = circle_at(&shapes, ports[&AnyParameterId::Input(scalar_in)]).unwrap();
+        let vector_port = circle_at(&shapes, ports[&AnyParameterId::Input(vector_in)]).unwrap();
+        assert_eq!(scalar_port.radius, 6.5);
+        assert_eq!(vector_port.radius, 5.0);
+        assert_eq!(vector_port.fill, egui::Color32::from_gray(76));
+
+        // The connection is tinted by whether it can be attached to the
+        // hovered port
+        let shapes = shapes_at(&mut state, ports[&AnyParameterId::Input(scalar_in)]);
+        assert_eq!(
+            wire_color(&shapes),
+            Some(egui::Color32::from_rgb(90, 200, 90))
+        );
+        let shapes = shapes_at(&mut state, ports[&AnyParameterId::Input(vector_in)]);
+        assert_eq!(
+            wire_color(&shapes),
+            Some(egui::Color32::from_rgb(220, 60, 60))
+        );
+        let shapes = shapes_at(&mut state, egui::pos2(200.0, 300.0));
+        assert_eq!(wire_color(&shapes), Some(egui::Color32::WHITE));
+    }
+
+    #[test]
+    pub fn test_removing_params_keeps_editor_consistent() {
+        let (mut state, nodes) = state_with_nodes(2);
+        let output = state.graph[nodes[0]].get_output("out").unwrap();
+        state.connection_in_progress = Some((nodes[0], AnyParameterId::Output(output)));
+
+        // Removing the port being dragged from cancels the drag
+        state.graph.remove_output_param(output);
+        let ctx = egui::Context::default();
+        draw_frame(&mut state, &ctx);
+        assert!(state.connection_in_progress.is_none());
+
+        // Nodes without parameters are still drawn
+        let input = state.graph[nodes[0]].get_input("in").unwrap();
+        state.graph.remove_input_param(input);
+        let responses = draw_frame(&mut state, &ctx);
+        assert_eq!(responses.port_locations.len(), 2);
+        assert!(!state.sync_with_graph());
+    }
+
+    #[test]
+    pub fn test_zoom_scales_nodes_and_ports() {
+        let ctx = egui::Context::default();
+        let (mut state, port) = single_node_editor(&ctx);
+        let node = state.node_order[0];
+        let origin = state
+            .pan_zoom
+            .graph_to_screen(egui::Pos2::ZERO, ctx.available_rect());
+        let at_zoom_1 = port - origin - egui::vec2(100.0, 100.0);
+
+        state.pan_zoom.zoom = 2.0;
+        let output = state.graph[node].get_output("out").unwrap();
+        draw_frame(&mut state, &ctx);
+        let port = draw_frame(&mut state, &ctx).port_locations[&AnyParameterId::Output(output)];
+        let at_zoom_2 = port - origin - egui::vec2(200.0, 200.0);
+        assert!((at_zoom_2 - at_zoom_1 * 2.0).length() < 3.0);
+
+        // Drags are converted back to graph space
+        let responses = drag(
+            &mut state,
+            &ctx,
+            port - egui::vec2(20.0, 0.0),
+            egui::vec2(40.0, 0.0),
+            &[],
+        );
+        assert!(moves_node(&responses));
+        assert_eq!(state.node_positions[node], egui::pos2(120.0, 100.0));
+    }
+
+    #[test]
+    pub fn test_scroll_zooms_around_cursor() {
+        let ctx = egui::Context::default();
+        let (mut state, _) = state_with_nodes(1);
+        draw_frame(&mut state, &ctx);
+        let editor_rect = ctx.available_rect();
+        let cursor = egui::pos2(400.0, 300.0);
+        let under_cursor = state.pan_zoom.screen_to_graph(cursor, editor_rect);
+
+        let scroll = |state: &mut TestEditorState, amount: f32| {
+            let input = egui::RawInput {
+                events: vec![
+                    egui::Event::PointerMoved(cursor),
+                    egui::Event::Scroll(egui::vec2(0.0, amount)),
+                ],
+                ..Default::default()
+            };
+            draw_frame_with_input(state, &ctx, input);
+        };
+        scroll(&mut state, 100.0);
+        let zoom = state.pan_zoom.zoom;
+        assert!((zoom - 0.5f32.exp()).abs() < 1e-4);
+        let after = state.pan_zoom.screen_to_graph(cursor, editor_rect);
+        assert!((after - under_cursor).length() < 1e-3);
+
+        // The zoom is clamped
+        scroll(&mut state, 2000.0);
+        assert_eq!(state.pan_zoom.zoom, MAX_ZOOM);
+        scroll(&mut state, -5000.0);
+        assert_eq!(state.pan_zoom.zoom, MIN_ZOOM);
+        let after = state.pan_zoom.screen_to_graph(cursor, editor_rect);
+        assert!((after - under_cursor).length() < 1e-2);
+    }
+
+    #[test]
+    pub fn test_minimap_pans_view() {
+        let ctx = egui::Context::default();
+        let (mut state, _) = state_with_nodes(2);
+        draw_frame(&mut state, &ctx);
+        // The editor covers the whole default screen
+        let minimap = ctx.available_rect().max - egui::vec2(190.0, 140.0);
+
+        // Without the minimap, this is a click on the background
+        drag(&mut state, &ctx, minimap, egui::Vec2::ZERO, &[]);
+        assert_eq!(state.pan_zoom.pan, egui::Vec2::ZERO);
+
+        state.minimap = true;
+        draw_frame(&mut state, &ctx);
+        drag(&mut state, &ctx, minimap, egui::Vec2::ZERO, &[]);
+        let centered = state.pan_zoom.pan;
+        assert_ne!(centered, egui::Vec2::ZERO);
+
+        // Dragging the viewport to the left pans the graph to the right
+        drag(&mut state, &ctx, minimap, egui::vec2(-10.0, 0.0), &[]);
+        assert!(state.pan_zoom.pan.x > centered.x);
+        assert_eq!(state.pan_zoom.pan.y, centered.y);
+
+        // Right clicks on the minimap don't open the node finder
+        let button = |pressed| egui::Event::PointerButton {
+            pos: minimap,
+            button: egui::PointerButton::Secondary,
+            pressed,
+            modifiers: egui::Modifiers::NONE,
+        };
+        for events in [
+            vec![egui::Event::PointerMoved(minimap)],
+            vec![button(true)],
+            vec![button(false)],
+        ] {
+            let input = egui::RawInput {
+                events,
+                ..Default::default()
+            };
+            draw_frame_with_input(&mut state, &ctx, input);
+        }
+        assert!(state.node_finder.is_none());
+    }
+
+    #[test]
+    pub fn test_drag_detaches_connection_of_wide_input() {
+        let ctx = egui::Context::default();
+        for detached in 0..2 {
+            let (mut state, nodes) = state_with_nodes(3);
+            let positions = [(0.0, 0.0), (0.0, 300.0), (300.0, 150.0)];
+            for (node, (x, y)) in nodes.iter().zip(positions) {
+                state.node_positions[*node] = egui::pos2(x, y);
+            }
+            let wide = state.graph.add_wide_input_param(
+                nodes[2],
+                "wide".into(),
+                TestDataType::Scalar,
+                TestValue::default(),
+                InputParamKind::ConnectionOnly,
+                None,
+                true,
+            );
+            let outputs = [nodes[0], nodes[1]].map(|n| state.graph[n].get_output("out").unwrap());
+            for output in outputs {
+                state.graph.add_connection(output, wide);
+            }
+            let ports = draw_frame(&mut state, &ctx).port_locations;
+
+            // Drag off the input towards the output to detach
+            let from = ports[&AnyParameterId::Input(wide)];
+            let towards = ports[&AnyParameterId::Output(outputs[detached])];
+            let delta = (towards - from).normalized() * 30.0;
+            let responses = drag(&mut state, &ctx, from, delta, &[]);
+
+            assert!(responses.iter().any(|r| matches!(
+                r,
+                NodeResponse::DisconnectEvent { input, output }
+                    if *input == wide && *output == outputs[detached]
+            )));
+            assert_eq!(state.graph.connections(wide), [outputs[1 - detached]]);
+        }
+    }
+
+    #[test]
+    pub fn test_value_changes_are_reported() {
+        let ctx = egui::Context::default();
+        let mut state = TestEditorState::new(1.0);
+        let node = state.add_node(&TestTemplate::Scalar, egui::pos2(100.0, 100.0), &mut ());
+        let input = state.graph[node].get_input("in").unwrap();
+        let ports = draw_frame(&mut state, &ctx).port_locations;
+        let label = ports[&AnyParameterId::Input(input)] + egui::vec2(15.0, 0.0);
+        let is_value_change = |response: &NodeResponse<TestResponse, TestNodeData>| {
+            matches!(response, NodeResponse::ValueChanged { .. })
+        };
+
+        let hover = egui::RawInput {
+            events: vec![egui::Event::PointerMoved(label)],
+            ..Default::default()
+        };
+        let responses = draw_frame_with_input(&mut state, &ctx, hover).node_responses;
+        assert!(!responses.iter().any(is_value_change));
+
+        state.graph.record_events(true);
+        let press = egui::RawInput {
+            events: vec![egui::Event::Key {
+                key: egui::Key::PlusEquals,
+                pressed: true,
+                repeat: false,
+                modifiers: egui::Modifiers::NONE,
+            }],
+            ..Default::default()
+        };
+        let responses = draw_frame_with_input(&mut state, &ctx, press).node_responses;
+        assert_eq!(state.graph[input].value, TestValue(1));
+        assert!(matches!(
+            responses[..],
+            [NodeResponse::ValueChanged { node: n, input: i }] if n == node && i == input
+        ));
+        assert_eq!(
+            state.graph.drain_events(),
+            [GraphEvent::ValueChanged { node, input }]
+        );
+        let responses = draw_frame(&mut state, &ctx).node_responses;
+        assert!(!responses.iter().any(is_value_change));
+        assert!(state.graph.drain_events().is_empty());
+    }
+
+    #[test]
+    pub fn test_offscreen_nodes_are_culled() {
+        let ctx = egui::Context::default();
+        let (mut state, nodes) = state_with_nodes(2);
+        *state.node_positions.get_mut(nodes[1]).unwrap() = egui::pos2(3000.0, 0.0);
+        state.graph.rename_node(nodes[1], "Far".into());
+        let output = state.graph[nodes[0]].get_output("out").unwrap();
+        let input = state.graph[nodes[1]].get_input("in").unwrap();
+        state.graph.add_connection(output, input);
+        let frame = |state: &mut TestEditorState| {
+            let input = egui::RawInput {
+                screen_rect: Some(egui::Rect::from_min_size(
+                    egui::Pos2::ZERO,
+                    egui::vec2(800.0, 600.0),
+                )),
+                ..Default::default()
+            };
+            let texts = draw_frame_texts(state, &ctx, input.clone());
+            let ports = draw_frame_with_input(state, &ctx, input).port_locations;
+            (texts.contains(&"Far".to_owned()), ports)
+        };
+
+        // Nodes are drawn once before they can be culled
+        let (_, ports) = frame(&mut state);
+        let far_port = ports[&AnyParameterId::Input(input)];
+        let (drawn, ports) = frame(&mut state);
+        assert!(!drawn);
+        assert_eq!(ports[&AnyParameterId::Input(input)], far_port);
+
+        // Panning keeps the ports of culled nodes in place, and draws them
+        // again once they come into view
+        state.pan_zoom.pan = egui::vec2(-1000.0, 0.0);
+        let (drawn, ports) = frame(&mut state);
+        assert!(!drawn);
+        assert_eq!(
+            ports[&AnyParameterId::Input(input)],
+            far_port - egui::vec2(1000.0, 0.0)
+        );
+        state.pan_zoom.pan = egui::vec2(-2800.0, 0.0);
+        let (drawn, ports) = frame(&mut state);
+        assert!(drawn);
+        assert_eq!(
+            ports[&AnyParameterId::Input(input)],
+            far_port - egui::vec2(2800.0, 0.0)
+        );
+
+        // Culled nodes with new parameters are drawn to place their ports
+        state.pan_zoom.pan = egui::Vec2::ZERO;
+        frame(&mut state);
+        let extra = state.graph.add_input_param(
+            nodes[1],
+            "extra".into(),
+            TestDataType::Scalar,
+            TestValue::default(),
+            InputParamKind::ConnectionOnly,
+            true,
+        );
+        let (_, ports) = frame(&mut state);
+        assert!(ports.contains_key(&AnyParameterId::Input(extra)));
+    }
+
+    #[test]
+    pub fn test_connections_are_tessellated_once() {
+        let ctx = egui::Context::default();
+        let (mut state, nodes) = state_with_nodes(3);
+        for pair in nodes.windows(2) {
+            let output = state.graph[pair[0]].get_output("out").unwrap();
+            let input = state.graph[pair[1]].get_input("in").unwrap();
+            state.graph.add_connection(output, input);
+        }
+        let connection_meshes = |state: &mut TestEditorState| {
+            let shapes = draw_frame_shapes(state, &ctx, Default::default());
+            let meshes = shapes
+                .iter()
+                .filter(|shape| matches!(shape, egui::Shape::Mesh(_)))
+                .count();
+            (meshes, state.connection_meshes.tessellated)
+        };
+
+        assert_eq!(connection_meshes(&mut state), (1, 2));
+        assert_eq!(connection_meshes(&mut state), (1, 0));
+
+        // Panning moves the connections without changing their shape
+        state.pan_zoom.pan += egui::vec2(30.0, 40.0);
+        assert_eq!(connection_meshes(&mut state), (1, 0));
+
+        // Only the connections of a moved node change
+        *state.node_positions.get_mut(nodes[2]).unwrap() += egui::vec2(0.0, 50.0);
+        assert_eq!(connection_meshes(&mut state), (1, 1));
+        state.pan_zoom.zoom = 2.0;
+        assert_eq!(connection_meshes(&mut state), (1, 2));
+    }
+
+    #[test]
+    pub fn test_low_detail_when_zoomed_out() {
+        let ctx = egui::Context::default();
+        let (mut state, nodes) = state_with_nodes(2);
+        state.graph.rename_node(nodes[1], "detailed".into());
+        let input = state.graph[nodes[0]].get_input("in").unwrap();
+        let frame = |state: &mut TestEditorState| {
+            let texts = draw_frame_texts(state, &ctx, Default::default());
+            let ports = draw_frame(state, &ctx).port_locations;
+            let widgets = texts.iter().filter(|text| *text == "in").count();
+            (widgets, ports.contains_key(&AnyParameterId::Input(input)))
+        };
+
+        assert_eq!(frame(&mut state), (2, true));
+        // Only the title bar and ports of the other node are left
+        state.pan_zoom.zoom = 0.25;
+        assert_eq!(frame(&mut state), (1, true));
+        state.low_detail_width = 0.0;
+        assert_eq!(frame(&mut state), (2, true));
+    }
+
+    #[test]
+    pub fn test_response_maps_graph_to_screen() {
+        let ctx = egui::Context::default();
+        let (mut state, nodes) = state_with_nodes(2);
+        let output = state.graph[nodes[0]].get_output("out").unwrap();
+        state.pan_zoom = PanZoom {
+            pan: egui::vec2(30.0, 40.0),
+            zoom: 1.5,
+        };
+        let response = draw_frame(&mut state, &ctx);
+
+        for node in &nodes {
+            let rect = response.node_screen_rect(*node).unwrap();
+            let position = state.node_positions[*node];
+            assert_eq!(rect.min, response.graph_to_screen(position));
+            assert_eq!(response.screen_to_graph(rect.min), position);
+        }
+        let port = response
+            .port_screen_pos(AnyParameterId::Output(output))
+            .unwrap();
+        assert!((response.node_screen_rect(nodes[0]).unwrap().max.x - port.x).abs() < 1.0);
+
+        // Panning moves everything along
+        state.pan_zoom.pan += egui::vec2(100.0, 0.0);
+        let panned = draw_frame(&mut state, &ctx);
+        assert_eq!(
+            panned.port_screen_pos(AnyParameterId::Output(output)),
+            Some(port + egui::vec2(100.0, 0.0))
+        );
+    }
+
+    #[test]
+    pub fn test_param_descriptions() {
+        let ctx = egui::Context::default();
+        let (mut state, nodes) = state_with_nodes(1);
+        let input = state.graph[nodes[0]].get_input("in").unwrap();
+        let port = draw_frame(&mut state, &ctx).port_locations[&AnyParameterId::Input(input)];
+        let hover = |state: &mut TestEditorState, pos: egui::Pos2| {
+            let mut shown = false;
+            for time in [1.0, 1.5, 1.6] {
+                let input = egui::RawInput {
+                    time: Some(time),
+                    events: vec![egui::Event::PointerMoved(pos)],
+                    ..Default::default()
+                };
+                let texts = draw_frame_texts(state, &ctx, input);
+                shown = texts.contains(&"The value to forward".to_owned());
+            }
+            shown
+        };
+
+        // In the tooltip of the port, and over the row of the param
+        assert!(hover(&mut state, port));
+        assert!(hover(&mut state, port + egui::vec2(40.0, 0.0)));
+        // Not over the title bar
+        let title = state.node_positions[nodes[0]] + egui::vec2(40.0, 8.0);
+        assert!(!hover(&mut state, title));
+    }
+
+    #[test]
+    pub fn test_connection_data() {
+        let ctx = egui::Context::default();
+        let (mut state, nodes) = state_with_nodes(2);
+        *state.node_positions.get_mut(nodes[1]).unwrap() = egui::pos2(300.0, 0.0);
+        let output = state.graph[nodes[0]].get_output("out").unwrap();
+        let input = state.graph[nodes[1]].get_input("in").unwrap();
+        let link = TestLink("NB".into());
+        state.add_connection_with_data(output, input, link.clone());
+
+        // Shown at the middle of the connection
+        let texts = draw_frame_texts(&mut state, &ctx, Default::default());
+        assert!(texts.contains(&"NB".to_owned()));
+        assert_eq!(
+            state.iter_connections_with_data().collect::<Vec<_>>(),
+            vec![(input, output, Some(&link))]
+        );
+
+        // Copied along with the connections
+        state.selected_nodes = nodes.clone();
+        let copies = state.duplicate_selection(egui::vec2(0.0, 200.0));
+        let copied_input = state.graph[copies[1]].get_input("in").unwrap();
+        let copied_output = state.graph[copies[0]].get_output("out").unwrap();
+        assert_eq!(
+            state.connection_data.get(&(copied_input, copied_output)),
+            Some(&link)
+        );
+        state.remove_nodes(copies);
+
+        // Dropped with the connection, and restored by undo
+        state.record_history(&ctx);
+        state.graph.remove_connection(input);
+        draw_frame(&mut state, &ctx);
+        assert!(state.connection_data.is_empty());
+        state.record_history(&ctx);
+        state.undo::<TestResponse>().unwrap();
+        assert_eq!(state.connection_data.get(&(input, output)), Some(&link));
+    }
+
+    #[test]
+    pub fn test_variadic_inputs() {
+        let ctx = egui::Context::default();
+        let (mut state, nodes) = state_with_nodes(2);
+        *state.node_positions.get_mut(nodes[1]).unwrap() = egui::pos2(300.0, 0.0);
+        state.graph[nodes[1]].label = "variadic".into();
+        let button_pos = |state: &mut TestEditorState, label: &str| {
+            draw_frame_shapes(state, &ctx, Default::default())
+                .into_iter()
+                .find_map(|shape| match shape {
+                    egui::Shape::Text(text) if text.galley.text() == label => {
+                        Some(text.galley.rect.translate(text.pos.to_vec2()).center())
+                    }
+                    _ => None,
+                })
+        };
+
+        // Only the variadic node has a `+` button, and its first input can't
+        // be removed
+        let add = button_pos(&mut state, "+").unwrap();
+        assert!(add.x > 300.0);
+        assert!(button_pos(&mut state, "🗙").is_none());
+
+        let mut time = 0.0;
+        let mut click = |state: &mut TestEditorState, pos| {
+            [vec![egui::Event::PointerMoved(pos)], vec![], vec![]]
+                .into_iter()
+                .enumerate()
+                .flat_map(|(i, mut events)| {
+                    if i > 0 {
+                        events.push(egui::Event::PointerButton {
+                            pos,
+                            button: egui::PointerButton::Primary,
+                            pressed: i == 1,
+                            modifiers: egui::Modifiers::NONE,
+                        });
+                    }
+                    time += 0.1;
+                    let input = egui::RawInput {
+                        events,
+                        time: Some(time),
+                        ..Default::default()
+                    };
+                    draw_frame_with_input(state, &ctx, input).node_responses
+                })
+                .collect::<Vec<_>>()
+        };
+        let responses = click(&mut state, add);
+        let added = responses
+            .iter()
+            .find_map(|r| match r {
+                NodeResponse::AddedInput { node, input } if *node == nodes[1] => Some(*input),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(state.graph[nodes[1]].get_input("in 2").unwrap(), added);
+
+        // Added inputs get unique names, and can be removed
+        draw_frame_with_responses(
+            &mut state,
+            &ctx,
+            Default::default(),
+            vec![NodeResponse::AddInput(nodes[1])],
+        );
+        assert!(state.graph[nodes[1]].get_input("in 3").is_ok());
+        let output = state.graph[nodes[0]].get_output("out").unwrap();
+        state.graph.add_connection(output, added);
+        let remove = button_pos(&mut state, "🗙").unwrap();
+        let responses = click(&mut state, remove);
+        let disconnected = responses.iter().position(
+            |r| matches!(r, NodeResponse::DisconnectEvent { input, .. } if *input == added),
+        );
+        let removed = responses.iter().position(|r| {
+            matches!(r, NodeResponse::RemovedInput { input, name, .. }
+                if *input == added && name == "in 2")
+        });
+        assert!(disconnected.unwrap() < removed.unwrap());
+        assert!(state.graph.try_get_input(added).is_none());
+        assert!(state.graph.output_connections(output).next().is_none());
+        assert_eq!(state.graph[nodes[1]].inputs.len(), 2);
+    }
+
+    #[test]
+    pub fn test_wildcard_bindings() {
+        let ctx = egui::Context::default();
+        let (mut state, nodes) = state_with_nodes(2);
+        *state.node_positions.get_mut(nodes[1]).unwrap() = egui::pos2(300.0, 0.0);
+        let output = state.graph[nodes[0]].get_output("out").unwrap();
+        let any = state.graph.add_input_param(
+            nodes[1],
+            "any".into(),
+            TestDataType::Any,
+            TestValue::default(),
+            InputParamKind::ConnectionOnly,
+            true,
+        );
+        let responses = |state: &mut TestEditorState, responses| {
+            draw_frame_with_responses(state, &ctx, Default::default(), responses).node_responses
+        };
+        let port_colors = |state: &mut TestEditorState| {
+            draw_frame_shapes(state, &ctx, Default::default())
+                .into_iter()
+                .filter_map(|shape| match shape {
+                    egui::Shape::Circle(circle) => Some(circle.fill),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        assert!(responses(&mut state, vec![]).is_empty());
+        assert!(port_colors(&mut state).contains(&egui::Color32::GRAY));
+
+        // Binding the wildcard is reported, and it takes the color of its type
+        let bound = responses(
+            &mut state,
+            vec![NodeResponse::ConnectEventEnded { output, input: any }],
+        );
+        assert!(bound.iter().any(|r| matches!(
+            r,
+            NodeResponse::WildcardBound { node, param, bound_to }
+                if *node == nodes[1]
+                    && *param == AnyParameterId::Input(any)
+                    && *bound_to == AnyParameterId::Output(output)
+        )));
+        assert!(!port_colors(&mut state).contains(&egui::Color32::GRAY));
+        assert!(responses(&mut state, vec![]).is_empty());
+
+        // Disconnecting it reverts it to a wildcard
+        let unbound = responses(
+            &mut state,
+            vec![NodeResponse::RemoveConnection { output, input: any }],
+        );
+        assert!(unbound.iter().any(|r| matches!(
+            r,
+            NodeResponse::WildcardUnbound { node, param }
+                if *node == nodes[1] && *param == AnyParameterId::Input(any)
+        )));
+        assert!(port_colors(&mut state).contains(&egui::Color32::GRAY));
+    }
+
+    #[test]
+    pub fn test_port_groups() {
+        let ctx = egui::Context::default();
+        let (mut state, nodes) = state_with_nodes(1);
+        let [a, b] = ["a", "b"].map(|name| {
+            let input = state.graph.add_input_param(
+                nodes[0],
+                name.into(),
+                TestDataType::Scalar,
+                TestValue::default(),
+                InputParamKind::ConnectionOrConstant,
+                true,
+            );
+            state.graph[input].group = Some("io".into());
+            AnyParameterId::Input(input)
+        });
+        let first = AnyParameterId::Input(state.graph[nodes[0]].get_input("in").unwrap());
+        let response = draw_frame(&mut state, &ctx);
+        let expanded = response.node_screen_rect(nodes[0]).unwrap();
+        let port = |response: &GraphResponse<_, _>, param| response.port_screen_pos(param).unwrap();
+        assert!(port(&response, a).y < port(&response, b).y);
+
+        // Clicking the header collapses the group
+        let header = port(&response, a) + egui::vec2(30.0, -20.0);
+        let texts = draw_frame_texts(&mut state, &ctx, Default::default());
+        assert!(texts.contains(&"io".to_owned()));
+        assert!(texts.contains(&"a".to_owned()));
+        let click = |pressed| egui::RawInput {
+            events: vec![
+                egui::Event::PointerMoved(header),
+                egui::Event::PointerButton {
+                    pos: header,
+                    button: egui::PointerButton::Primary,
+                    pressed,
+                    modifiers: egui::Modifiers::NONE,
+                },
+            ],
+            ..Default::default()
+        };
+        draw_frame_with_input(&mut state, &ctx, click(true));
+        let responses = draw_frame_with_input(&mut state, &ctx, click(false)).node_responses;
+        assert!(responses.iter().any(|r| matches!(
+            r,
+            NodeResponse::CollapsePortGroup { node, group, collapsed: true }
+                if *node == nodes[0] && group == "io"
+        )));
+        assert!(state
+            .collapsed_port_groups
+            .contains(&(nodes[0], "io".to_owned())));
+
+        // Its ports share a stub on the header, and the node shrinks
+        let response = draw_frame(&mut state, &ctx);
+        assert_eq!(port(&response, a), port(&response, b));
+        assert!(port(&response, a).y > port(&response, first).y);
+        assert!(response.node_screen_rect(nodes[0]).unwrap().height() < expanded.height());
+        let texts = draw_frame_texts(&mut state, &ctx, Default::default());
+        assert!(texts.contains(&"io".to_owned()));
+        assert!(!texts.contains(&"a".to_owned()));
+
+        state.remove_node(nodes[0]);
+        assert!(state.collapsed_port_groups.is_empty());
+    }
+
+    #[test]
+    pub fn test_interaction_modes() {
+        let ctx = egui::Context::default();
+        let delta = egui::vec2(30.0, 0.0);
+
+        // Read-only: dragging a port neither connects nor moves
+        let (mut state, port) = single_node_editor(&ctx);
+        state.interaction_mode = InteractionMode::ReadOnly;
+        let responses = drag(&mut state, &ctx, port, delta, &[]);
+        assert!(!starts_connection(&responses));
+        assert!(!moves_node(&responses));
+        assert!(state.connection_in_progress.is_none());
+
+        // Layout only: the same drag moves the node
+        let (mut state, port) = single_node_editor(&ctx);
+        state.interaction_mode = InteractionMode::LayoutOnly;
+        let responses = drag(&mut state, &ctx, port, delta, &[]);
+        assert!(moves_node(&responses));
+        assert!(!starts_connection(&responses));
+
+        // Nodes can't be deleted, and right clicks don't open the node finder
+        let (mut state, nodes) = state_with_nodes(1);
+        state.graph[nodes[0]].label = "variadic".into();
+        state.interaction_mode = InteractionMode::ReadOnly;
+        state.selected_nodes = nodes.clone();
+        let delete = egui::Event::Key {
+            key: egui::Key::Delete,
+            pressed: true,
+            repeat: false,
+            modifiers: egui::Modifiers::NONE,
+        };
+        let background = egui::pos2(500.0, 500.0);
+        let right_button = |pressed| egui::Event::PointerButton {
+            pos: background,
+            button: egui::PointerButton::Secondary,
+            pressed,
+            modifiers: egui::Modifiers::NONE,
+        };
+        for events in [
+            vec![delete, egui::Event::PointerMoved(background)],
+            vec![right_button(true)],
+            vec![right_button(false)],
+        ] {
+            let input = egui::RawInput {
+                events,
+                ..Default::default()
+            };
+            draw_frame_with_input(&mut state, &ctx, input);
+        }
+        assert_eq!(state.node_order, nodes);
+        assert!(state.node_finder.is_none());
+
+        // Inputs can't be added, and value widgets are still shown
+        let texts = draw_frame_texts(&mut state, &ctx, Default::default());
+        assert!(!texts.contains(&"+".to_owned()));
+        assert!(texts.contains(&"in".to_owned()));
+
+        // Switching back makes the graph editable again
+        state.interaction_mode = InteractionMode::Editable;
+        let texts = draw_frame_texts(&mut state, &ctx, Default::default());
+        assert!(texts.contains(&"+".to_owned()));
+    }
+
+    #[test]
+    pub fn test_interaction_responses() {
+        let ctx = egui::Context::default();
+        let (mut state, nodes) = state_with_nodes(2);
+        *state.node_positions.get_mut(nodes[1]).unwrap() = egui::pos2(300.0, 0.0);
+        state.selected_nodes = nodes.clone();
+        draw_frame(&mut state, &ctx);
+        let rect = draw_frame(&mut state, &ctx)
+            .node_screen_rect(nodes[0])
+            .unwrap();
+        let body = rect.center();
+
+        // Moving the selection reports the final positions once, at the end
+        let responses = drag(&mut state, &ctx, body, egui::vec2(30.0, 40.0), &[]);
+        let moved: Vec<(NodeId, egui::Pos2)> = responses
+            .iter()
+            .filter_map(|r| match r {
+                NodeResponse::NodeMoved { node, new_pos } => Some((*node, *new_pos)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(moved.len(), 2);
+        for (node, expected) in [(nodes[0], (30.0, 40.0)), (nodes[1], (330.0, 40.0))] {
+            let (_, new_pos) = moved.iter().find(|(n, _)| *n == node).unwrap();
+            assert!(new_pos.distance(egui::pos2(expected.0, expected.1)) < 0.01);
+            assert_eq!(*new_pos, state.node_positions[node]);
+        }
+
+        let mut time = 1.0;
+        let mut clicks = |state: &mut TestEditorState, pos, count| {
+            let mut responses = Vec::new();
+            for _ in 0..count {
+                for pressed in [true, false] {
+                    time += 0.05;
+                    let input = egui::RawInput {
+                        time: Some(time),
+                        events: vec![
+                            egui::Event::PointerMoved(pos),
+                            egui::Event::PointerButton {
+                                pos,
+                                button: egui::PointerButton::Primary,
+                                pressed,
+                                modifiers: egui::Modifiers::NONE,
+                            },
+                        ],
+                        ..Default::default()
+                    };
+                    responses.extend(draw_frame_with_input(state, &ctx, input).node_responses);
+                }
+            }
+            time += 1.0;
+            responses
+        };
+
+        let responses = clicks(&mut state, body + egui::vec2(30.0, 40.0), 2);
+        assert!(responses
+            .iter()
+            .any(|r| matches!(r, NodeResponse::NodeDoubleClicked(node) if *node == nodes[0])));
+
+        let responses = clicks(&mut state, egui::pos2(300.0, 400.0), 1);
+        assert!(responses.iter().any(|r| matches!(
+            r,
+            NodeResponse::BackgroundClicked { graph_pos }
+                if graph_pos.distance(egui::pos2(300.0, 400.0)) < 0.01
+        )));
+        assert!(!responses
+            .iter()
+            .any(|r| matches!(r, NodeResponse::NodeDoubleClicked(_))));
+    }
+
+    #[test]
+    pub fn test_hover_highlight() {
+        let ctx = egui::Context::default();
+        let mut state = TestEditorState::new(1.0);
+        let [a, b, c, d] = [(0.0, 0.0), (300.0, 0.0), (0.0, 200.0), (300.0, 200.0)]
+            .map(|(x, y)| state.add_node(&TestTemplate::Scalar, egui::pos2(x, y), &mut ()));
+        for (from, to) in [(a, b), (c, d)] {
+            let output = state.graph[from].get_output("out").unwrap();
+            let input = state.graph[to].get_input("in").unwrap();
+            state.graph.add_connection(output, input);
+        }
+        let rects = draw_frame(&mut state, &ctx).node_rects;
+        let hover = |time| egui::RawInput {
+            time: Some(time),
+            events: vec![egui::Event::PointerMoved(rects[&a].center())],
+            ..Default::default()
+        };
+        // The outlines of the neighbors, and whether there are faded meshes
+        let outlined = |shapes: &[egui::Shape]| -> Vec<egui::Rect> {
+            shapes
+                .iter()
+                .filter_map(|shape| match shape {
+                    egui::Shape::Rect(rect) if rect.stroke.width == 1.5 => Some(rect.rect),
+                    _ => None,
+                })
+                .collect()
+        };
+        let faded = |shapes: &[egui::Shape]| {
+            shapes.iter().any(|shape| match shape {
+                egui::Shape::Mesh(mesh) => mesh
+                    .vertices
+                    .iter()
+                    .any(|v| (70..=80).contains(&v.color.a())),
+                _ => false,
+            })
+        };
+
+        // Nothing is highlighted before the delay
+        let shapes = draw_frame_shapes(&mut state, &ctx, hover(1.0));
+        assert!(outlined(&shapes).is_empty());
+        assert!(!faded(&shapes));
+
+        let shapes = draw_frame_shapes(&mut state, &ctx, hover(1.5));
+        assert_eq!(outlined(&shapes), [rects[&b].expand(2.0)]);
+        assert!(faded(&shapes));
+
+        // Unless disabled
+        state.hover_highlight.enabled = false;
+        let shapes = draw_frame_shapes(&mut state, &ctx, hover(2.0));
+        assert!(outlined(&shapes).is_empty());
+        assert!(!faded(&shapes));
+    }
+
+    #[test]
+    pub fn test_rename_node() {
+        let ctx = egui::Context::default();
+        let (mut state, nodes) = state_with_nodes(1);
+        let original = state.graph[nodes[0]].label.clone();
+        let mut time = 0.0;
+        let mut frame = |state: &mut TestEditorState, events| {
+            time += 0.1;
+            let input = egui::RawInput {
+                time: Some(time),
+                events,
+                ..Default::default()
+            };
+            draw_frame_with_input(state, &ctx, input).node_responses
+        };
+        let key = |key| egui::Event::Key {
+            key,
+            pressed: true,
+            repeat: false,
+            modifiers: egui::Modifiers::NONE,
+        };
+        let button = |pressed| egui::Event::PointerButton {
+            pos: egui::pos2(50.0, 12.0),
+            button: egui::PointerButton::Primary,
+            pressed,
+            modifiers: egui::Modifiers::NONE,
+        };
+
+        // Double-clicking the title text edits it, instead of collapsing
+        let mut responses = frame(
+            &mut state,
+            vec![egui::Event::PointerMoved(egui::pos2(50.0, 12.0))],
+        );
+        for _ in 0..2 {
+            responses.extend(frame(&mut state, vec![button(true)]));
+            responses.extend(frame(&mut state, vec![button(false)]));
+        }
+        assert_eq!(state.renaming_node, Some((nodes[0], original.clone())));
+        assert!(!responses
+            .iter()
+            .any(|r| matches!(r, NodeResponse::CollapseNode { .. })));
+
+        // Typing goes to the title, and Enter applies it
+        frame(&mut state, vec![]);
+        state.renaming_node.as_mut().unwrap().1 = "Camera".into();
+        frame(&mut state, vec![egui::Event::Text(" B".into())]);
+        let mut responses = frame(&mut state, vec![key(egui::Key::Enter)]);
+        responses.extend(frame(&mut state, vec![]));
+        assert!(responses.iter().any(|r| matches!(
+            r,
+            NodeResponse::RenamedNode { node, old_label, new_label }
+                if *node == nodes[0] && *old_label == original && new_label == "Camera B"
+        )));
+        assert_eq!(state.graph[nodes[0]].label, "Camera B");
+        assert!(state.renaming_node.is_none());
+
+        // Escape cancels, and empty labels are ignored
+        for (label, end) in [("Other", egui::Key::Escape), ("  ", egui::Key::Enter)] {
+            state.renaming_node = Some((nodes[0], label.into()));
+            frame(&mut state, vec![]);
+            frame(&mut state, vec![]);
+            let mut responses = frame(&mut state, vec![key(end)]);
+            responses.extend(frame(&mut state, vec![]));
+            assert!(!responses
+                .iter()
+                .any(|r| matches!(r, NodeResponse::RenamedNode { .. })));
+            assert_eq!(state.graph[nodes[0]].label, "Camera B");
+            assert!(state.renaming_node.is_none());
+        }
+    }
+
+    #[test]
+    pub fn test_drag_selection() {
+        let ctx = egui::Context::default();
+        let (mut state, nodes) = state_with_nodes(3);
+        for (node, x) in nodes.iter().zip([0.0, 300.0, 600.0]) {
+            *state.node_positions.get_mut(*node).unwrap() = egui::pos2(x, 0.0);
+        }
+        draw_frame(&mut state, &ctx);
+        let title = |state: &TestEditorState, node: NodeId| {
+            state.node_positions[node] + egui::vec2(76.0, 12.0)
+        };
+
+        // Dragging a selected node moves the whole selection
+        state.selected_nodes = vec![nodes[0], nodes[1]];
+        let from = title(&state, nodes[1]);
+        drag(&mut state, &ctx, from, egui::vec2(10.0, 20.0), &[]);
+        assert_eq!(state.node_positions[nodes[0]], egui::pos2(10.0, 20.0));
+        assert_eq!(state.node_positions[nodes[1]], egui::pos2(310.0, 20.0));
+        assert_eq!(state.node_positions[nodes[2]], egui::pos2(600.0, 0.0));
+        assert_eq!(state.selected_nodes, [nodes[0], nodes[1]]);
+
+        // Dragging another node selects it alone, and only moves it
+        let from = title(&state, nodes[2]);
+        drag(&mut state, &ctx, from, egui::vec2(10.0, 20.0), &[]);
+        assert_eq!(state.selected_nodes, [nodes[2]]);
+        assert_eq!(state.node_positions[nodes[0]], egui::pos2(10.0, 20.0));
+        assert_eq!(state.node_positions[nodes[2]], egui::pos2(610.0, 20.0));
+    }
+
+    #[test]
+    pub fn test_alignment_guides() {
+        let ctx = egui::Context::default();
+        let (mut state, nodes) = state_with_nodes(2);
+        *state.node_positions.get_mut(nodes[0]).unwrap() = egui::pos2(0.0, 0.0);
+        *state.node_positions.get_mut(nodes[1]).unwrap() = egui::pos2(300.0, 200.0);
+        draw_frame(&mut state, &ctx);
+        let title =
+            |state: &TestEditorState| state.node_positions[nodes[1]] + egui::vec2(76.0, 12.0);
+        let button = |pos, pressed| egui::Event::PointerButton {
+            pos,
+            button: egui::PointerButton::Primary,
+            pressed,
+            modifiers: egui::Modifiers::NONE,
+        };
+
+        // Close to the left edge of the other node, the dragged node snaps to
+        // it and a guide is drawn while the button is down
+        let from = title(&state);
+        let to = from + egui::vec2(-297.0, -50.0);
+        for events in [
+            vec![egui::Event::PointerMoved(from)],
+            vec![button(from, true)],
+            vec![egui::Event::PointerMoved(to)],
+        ] {
+            let input = egui::RawInput {
+                events,
+                ..Default::default()
+            };
+            draw_frame_with_input(&mut state, &ctx, input);
+        }
+        assert_eq!(state.node_positions[nodes[1]], egui::pos2(0.0, 150.0));
+        // The nodes have the same width, so their centers are aligned too
+        assert_eq!(state.active_guides.len(), 2);
+        for [top, bottom] in state.active_guides.iter().copied() {
+            assert_eq!(top.x, bottom.x);
+            assert!(top.y <= 0.0 && bottom.y > 150.0);
+        }
+        assert_eq!(state.active_guides[0][0].x, 0.0);
+        let input = egui::RawInput {
+            events: vec![button(to, false)],
+            ..Default::default()
+        };
+        draw_frame_with_input(&mut state, &ctx, input);
+        assert!(state.active_guides.is_empty());
+
+        // Further away, the node moves freely
+        let from = title(&state);
+        drag(&mut state, &ctx, from, egui::vec2(20.0, 0.0), &[]);
+        assert_eq!(state.node_positions[nodes[1]], egui::pos2(20.0, 150.0));
+
+        // The guides win over the grid
+        state.grid.snap = true;
+        *state.node_positions.get_mut(nodes[0]).unwrap() = egui::pos2(7.0, 0.0);
+        draw_frame(&mut state, &ctx);
+        let from = title(&state);
+        drag(&mut state, &ctx, from, egui::vec2(-11.0, 3.0), &[]);
+        assert_eq!(state.node_positions[nodes[1]], egui::pos2(7.0, 160.0));
+
+        // Disabled, only the grid applies
+        state.alignment_guides.enabled = false;
+        let from = title(&state);
+        drag(&mut state, &ctx, from, egui::vec2(1.0, 0.0), &[]);
+        assert_eq!(state.node_positions[nodes[1]], egui::pos2(0.0, 160.0));
+    }
+
+    #[test]
+    pub fn test_node_order() {
+        let ctx = egui::Context::default();
+        let (mut state, nodes) = state_with_nodes(3);
+        state.raise_node(nodes[0]);
+        assert_eq!(state.node_order, [nodes[1], nodes[2], nodes[0]]);
+        state.send_to_back(nodes[2]);
+        assert_eq!(state.node_order, [nodes[2], nodes[1], nodes[0]]);
+
+        // Removed nodes aren't added back
+        state.remove_node(nodes[2]);
+        state.raise_node(nodes[2]);
+        state.send_to_back(nodes[2]);
+        assert_eq!(state.node_order, [nodes[1], nodes[0]]);
+
+        // An order broken by hand is repaired
+        state.node_order = vec![nodes[0], nodes[0]];
+        assert!(state.sync_with_graph());
+        assert_eq!(state.node_order, [nodes[0], nodes[1]]);
+
+        // Clicking the title of a node raises it, unless disabled
+        *state.node_positions.get_mut(nodes[1]).unwrap() = egui::pos2(300.0, 0.0);
+        let click = |state: &mut TestEditorState, pos| {
+            let button = |pressed| egui::Event::PointerButton {
+                pos,
+                button: egui::PointerButton::Primary,
+                pressed,
+                modifiers: egui::Modifiers::NONE,
+            };
+            for events in [
+                vec![egui::Event::PointerMoved(pos)],
+                vec![button(true)],
+                vec![button(false)],
+            ] {
+                let input = egui::RawInput {
+                    events,
+                    ..Default::default()
+                };
+                draw_frame_with_input(state, &ctx, input);
+            }
+        };
+        click(&mut state, egui::pos2(76.0, 12.0));
+        assert_eq!(state.node_order, [nodes[1], nodes[0]]);
+        assert_eq!(state.selected_nodes, [nodes[0]]);
+        state.raise_on_click = false;
+        click(&mut state, egui::pos2(376.0, 12.0));
+        assert_eq!(state.node_order, [nodes[1], nodes[0]]);
+        assert_eq!(state.selected_nodes, [nodes[1]]);
+    }
+
+    #[test]
+    pub fn test_graph_style() {
+        let ctx = egui::Context::default();
+        let (mut state, nodes) = state_with_nodes(1);
+        assert_eq!(
+            GraphStyle::from_egui_visuals(&egui::Visuals::dark()),
+            GraphStyle::default()
+        );
+        assert_ne!(
+            GraphStyle::from_egui_visuals(&egui::Visuals::light()).node_color,
+            GraphStyle::default().node_color
+        );
+        let port_radii = |shapes: Vec<egui::Shape>| {
+            shapes
+                .into_iter()
+                .filter_map(|shape| match shape {
+                    egui::Shape::Circle(circle) => Some(circle.radius),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        let filled = |shapes: &[egui::Shape], color| {
+            shapes
+                .iter()
+                .any(|shape| matches!(shape, egui::Shape::Rect(rect) if rect.fill == color))
+        };
+
+        draw_frame(&mut state, &ctx);
+        let default_rect = draw_frame(&mut state, &ctx).node_rects[&nodes[0]];
+        let shapes = draw_frame_shapes(&mut state, &ctx, Default::default());
+        assert_eq!(port_radii(shapes.clone()), [5.0, 5.0]);
+        assert!(!filled(&shapes, egui::Color32::RED));
+
+        // A custom style replaces the one following the theme
+        state.style = Some(GraphStyle {
+            background_color: egui::Color32::RED,
+            port_radius: 8.0,
+            title_bar_height: 60.0,
+            ..GraphStyle::default()
+        });
+        draw_frame(&mut state, &ctx);
+        let rect = draw_frame(&mut state, &ctx).node_rects[&nodes[0]];
+        assert!(rect.height() > default_rect.height() + 20.0);
+        let shapes = draw_frame_shapes(&mut state, &ctx, Default::default());
+        assert_eq!(port_radii(shapes.clone()), [8.0, 8.0]);
+        assert!(filled(&shapes, egui::Color32::RED));
+    }
+
+    #[test]
+    pub fn test_connection_decorations() {
+        let ctx = egui::Context::default();
+        let (mut state, nodes) = state_with_nodes(2);
+        *state.node_positions.get_mut(nodes[1]).unwrap() = egui::pos2(300.0, 0.0);
+        let output = state.graph[nodes[0]].get_output("out").unwrap();
+        let input = state.graph[nodes[1]].get_input("in").unwrap();
+        state.graph.add_connection(output, input);
+        let shapes = |state: &mut TestEditorState, time| {
+            let input = egui::RawInput {
+                time: Some(time),
+                ..Default::default()
+            };
+            draw_frame_shapes(state, &ctx, input)
+        };
+        let arrowheads = |shapes: &[egui::Shape]| {
+            shapes
+                .iter()
+                .filter_map(|shape| match shape {
+                    egui::Shape::Path(path) if path.closed && path.fill == egui::Color32::WHITE => {
+                        Some(path.points.clone())
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        let dashes = |shapes: &[egui::Shape]| {
+            shapes
+                .iter()
+                .filter_map(|shape| match shape {
+                    egui::Shape::Path(path) if !path.closed && path.stroke.width == 2.5 => {
+                        Some(path.points[0])
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        let drawn = shapes(&mut state, 0.0);
+        assert!(arrowheads(&drawn).is_empty());
+        assert!(dashes(&drawn).is_empty());
+
+        // The arrowhead points at the input port, its tip on the port's edge
+        state.style = Some(GraphStyle {
+            arrowheads: true,
+            ..GraphStyle::default()
+        });
+        let port = draw_frame(&mut state, &ctx).port_locations[&AnyParameterId::Input(input)];
+        let drawn = shapes(&mut state, 0.0);
+        let arrowheads = arrowheads(&drawn);
+        assert_eq!(arrowheads.len(), 1);
+        let tip = arrowheads[0][0];
+        assert!(tip.distance(port - egui::vec2(5.0, 0.0)) < 0.5);
+        assert!(arrowheads[0][1..].iter().all(|corner| corner.x < tip.x));
+
+        // Active connections get dashes, flowing towards the input
+        state.active_connections.insert((input, output));
+        let first = dashes(&shapes(&mut state, 0.0));
+        let later = dashes(&shapes(&mut state, 0.1));
+        assert!(first.len() > 3);
+        assert_eq!(first.len(), later.len());
+        assert!(first[1].x < later[1].x);
+    }
+
+    #[test]
+    pub fn test_top_bar_ui() {
+        let ctx = egui::Context::default();
+        let (mut state, nodes) = state_with_nodes(1);
+        state.graph[nodes[0]].label = "status".into();
+        draw_frame(&mut state, &ctx);
+        let texts = |state: &mut TestEditorState| {
+            draw_frame_shapes(state, &ctx, Default::default())
+                .into_iter()
+                .filter_map(|shape| match shape {
+                    egui::Shape::Text(text) => {
+                        let rect = text.galley.rect.translate(text.pos.to_vec2());
+                        Some((text.galley.text().to_owned(), rect))
+                    }
+                    _ => None,
+                })
+                .collect::<HashMap<_, _>>()
+        };
+
+        // After the title, on the same row
+        let drawn = texts(&mut state);
+        let (title, dot) = (drawn["status"], drawn["●"]);
+        assert!(dot.left() >= title.right());
+        assert!((dot.center().y - title.center().y).abs() < 2.0);
+        let node_rect = draw_frame(&mut state, &ctx).node_rects[&nodes[0]];
+        assert!(dot.right() < node_rect.right());
+
+        // Also shown while renaming
+        state.renaming_node = Some((nodes[0], "status".into()));
+        draw_frame(&mut state, &ctx);
+        assert!(texts(&mut state).contains_key("●"));
+    }
+
+    #[test]
+    pub fn test_protected_nodes() {
+        let ctx = egui::Context::default();
+        let (mut state, nodes) = state_with_nodes(3);
+        hover_editor(&mut state, &ctx);
+        state.graph[nodes[1]].label = "protected".into();
+        let denied = |responses: &[NodeResponse<TestResponse, TestNodeData>]| {
+            responses
+                .iter()
+                .filter_map(|r| match r {
+                    NodeResponse::DeleteDenied(node) => Some(*node),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // Only the unprotected nodes of the selection are deleted
+        state.selected_nodes = nodes.clone();
+        let input = egui::RawInput {
+            events: vec![egui::Event::Key {
+                key: egui::Key::Delete,
+                pressed: true,
+                repeat: false,
+                modifiers: egui::Modifiers::NONE,
+            }],
+            ..Default::default()
+        };
+        let responses = draw_frame_with_input(&mut state, &ctx, input).node_responses;
+        assert_eq!(state.node_order, [nodes[1]]);
+        assert_eq!(denied(&responses), [nodes[1]]);
+        assert!(!responses
+            .iter()
+            .any(|r| matches!(r, NodeResponse::DeleteNodeUi(node) if *node == nodes[1])));
+
+        // Deletions requested by user code are denied too
+        let responses = draw_frame_with_responses(
+            &mut state,
+            &ctx,
+            Default::default(),
+            vec![NodeResponse::DeleteNodeUi(nodes[1])],
+        )
+        .node_responses;
+        assert_eq!(state.node_order, [nodes[1]]);
+        assert_eq!(denied(&responses), [nodes[1]]);
+    }
+
+    #[test]
+    pub fn test_set_input_kind() {
+        let ctx = egui::Context::default();
+        let (mut state, nodes) = state_with_nodes(2);
+        let output = state.graph[nodes[0]].get_output("out").unwrap();
+        let input = state.graph[nodes[1]].get_input("in").unwrap();
+        state.graph.add_connection(output, input);
+        let shows = |state: &mut TestEditorState, text: &str| {
+            draw_frame_texts(state, &ctx, Default::default())
+                .iter()
+                .any(|t| t == text)
+        };
+
+        // The widget of connected inputs is only shown when asked to
+        assert!(shows(&mut state, "in (connected)"));
+        state.style = Some(GraphStyle {
+            show_widget_when_connected: true,
+            ..Default::default()
+        });
+        assert!(shows(&mut state, "in"));
+        assert!(!shows(&mut state, "in (connected)"));
+        state
+            .graph
+            .set_input_kind(input, InputParamKind::ConnectionOnly);
+        assert!(shows(&mut state, "in (connected)"));
+        assert!(state.graph.is_input_connected(input));
+
+        // Only constants are accepted anymore
+        let responses = draw_frame_with_responses(
+            &mut state,
+            &ctx,
+            Default::default(),
+            vec![NodeResponse::SetInputKind {
+                input,
+                kind: InputParamKind::ConstantOnly,
+            }],
+        )
+        .node_responses;
+        assert!(responses.iter().any(|r| matches!(
+            r,
+            NodeResponse::DisconnectEvent { input: i, output: o } if *i == input && *o == output
+        )));
+        assert!(!state.graph.is_input_connected(input));
+        assert!(matches!(
+            state.graph[input].kind,
+            InputParamKind::ConstantOnly
+        ));
+    }
+
+    #[test]
+    pub fn test_extract_node() {
+        let ctx = egui::Context::default();
+        let (mut state, nodes) = state_with_nodes(3);
+        for pair in nodes.windows(2) {
+            let output = state.graph[pair[0]].get_output("out").unwrap();
+            let input = state.graph[pair[1]].get_input("in").unwrap();
+            state.graph.add_connection(output, input);
+        }
+        let extract = |state: &mut TestEditorState, node| {
+            draw_frame_with_responses(
+                state,
+                &ctx,
+                Default::default(),
+                vec![NodeResponse::ExtractNode(node)],
+            )
+            .node_responses
+        };
+
+        // The first node has nothing upstream
+        let responses = extract(&mut state, nodes[0]);
+        assert_eq!(state.graph.iter_connections().count(), 2);
+        assert!(!responses
+            .iter()
+            .any(|r| matches!(r, NodeResponse::DisconnectEvent { .. })));
+
+        let responses = extract(&mut state, nodes[1]);
+        let output = state.graph[nodes[0]].get_output("out").unwrap();
+        let input = state.graph[nodes[2]].get_input("in").unwrap();
+        assert_eq!(
+            state.graph.iter_connections().collect::<Vec<_>>(),
+            [(input, output)]
+        );
+        let disconnected = responses
+            .iter()
+            .filter(|r| matches!(r, NodeResponse::DisconnectEvent { .. }))
+            .count();
+        assert_eq!(disconnected, 2);
+        assert!(responses.iter().any(|r| matches!(
+            r,
+            NodeResponse::ConnectEventEnded { input: i, output: o } if *i == input && *o == output
+        )));
+    }
+
+    #[test]
+    pub fn test_drop_node_on_connection() {
+        // Drags the middle node onto the connection between the other two
+        let drop_on_connection = |typ| {
+            let ctx = egui::Context::default();
+            let (mut state, nodes) = state_with_nodes(3);
+            state.drop_node_on_connection = true;
+            *state.node_positions.get_mut(nodes[1]).unwrap() = egui::pos2(0.0, 300.0);
+            *state.node_positions.get_mut(nodes[2]).unwrap() = egui::pos2(600.0, 0.0);
+            let node_input = state.graph[nodes[1]].get_input("in").unwrap();
+            state.graph[node_input].typ = typ;
+            let output = state.graph[nodes[0]].get_output("out").unwrap();
+            let input = state.graph[nodes[2]].get_input("in").unwrap();
+            state.graph.add_connection(output, input);
+
+            let response = draw_frame(&mut state, &ctx);
+            let src = response.port_screen_pos(AnyParameterId::Output(output));
+            let dst = response.port_screen_pos(AnyParameterId::Input(input));
+            let midpoint = src.unwrap() + (dst.unwrap() - src.unwrap()) / 2.0;
+            let title = response.graph_to_screen(egui::pos2(76.0, 312.0));
+            let responses = drag(&mut state, &ctx, title, midpoint - title, &[]);
+            (state, nodes, (input, output), responses)
+        };
+
+        // Ports of the wrong type: the connection doesn't react
+        let (state, _, connection, responses) = drop_on_connection(TestDataType::Vector);
+        assert_eq!(
+            state.graph.iter_connections().collect::<Vec<_>>(),
+            [connection]
+        );
+        assert!(!responses
+            .iter()
+            .any(|r| matches!(r, NodeResponse::DisconnectEvent { .. })));
+
+        let (state, nodes, (input, output), responses) = drop_on_connection(TestDataType::Scalar);
+        let node_input = state.graph[nodes[1]].get_input("in").unwrap();
+        let node_output = state.graph[nodes[1]].get_output("out").unwrap();
+        let mut connections: Vec<_> = state.graph.iter_connections().collect();
+        connections.sort();
+        let mut expected = vec![(node_input, output), (input, node_output)];
+        expected.sort();
+        assert_eq!(connections, expected);
+        assert!(responses.iter().any(|r| matches!(
+            r,
+            NodeResponse::DisconnectEvent { input: i, output: o } if *i == input && *o == output
+        )));
+        let connected = responses
+            .iter()
+            .filter(|r| matches!(r, NodeResponse::ConnectEventEnded { .. }))
+            .count();
+        assert_eq!(connected, 2);
+    }
+
+    #[test]
+    pub fn test_node_finder_keyboard() {
+        let ctx = egui::Context::default();
+        let (mut state, _) = state_with_nodes(0);
+        let origin = egui::pos2(300.0, 200.0);
+        state.node_finder = Some(NodeFinder::new_at(origin));
+        let key = |key| egui::Event::Key {
+            key,
+            pressed: true,
+            repeat: false,
+            modifiers: egui::Modifiers::NONE,
+        };
+        let mut responses = Vec::new();
+        for events in [
+            vec![egui::Event::PointerMoved(egui::pos2(20.0, 500.0))],
+            vec![key(egui::Key::Tab)],
+            vec![key(egui::Key::Enter)],
+        ] {
+            let input = egui::RawInput {
+                events,
+                ..Default::default()
+            };
+            responses = draw_frame_with_input(&mut state, &ctx, input).node_responses;
+        }
+
+        // Created where the finder was opened, not under the pointer
+        let node = match responses.as_slice() {
+            [NodeResponse::CreatedNode(node), ..] => *node,
+            _ => panic!("No node created: {:?}", responses.len()),
+        };
+        let response = draw_frame(&mut state, &ctx);
+        assert_eq!(response.graph_to_screen(state.node_positions[node]), origin);
+        assert!(state.node_finder.is_none());
+    }
+
+    #[test]
+    pub fn test_input_bindings() {
+        let button_drag = |state: &mut TestEditorState,
+                           ctx: &egui::Context,
+                           button: egui::PointerButton,
+                           from: egui::Pos2,
+                           delta: egui::Vec2| {
+            let event = |pos, pressed| egui::Event::PointerButton {
+                pos,
+                button,
+                pressed,
+                modifiers: egui::Modifiers::NONE,
+            };
+            let mut responses = Vec::new();
+            for events in [
+                vec![egui::Event::PointerMoved(from)],
+                vec![event(from, true)],
+                vec![egui::Event::PointerMoved(from + delta)],
+                vec![event(from + delta, false)],
+            ] {
+                let input = egui::RawInput {
+                    events,
+                    ..Default::default()
+                };
+                responses.extend(draw_frame_with_input(state, ctx, input).node_responses);
+            }
+            responses
+        };
+        let title = egui::pos2(76.0, 12.0);
+        let background = egui::pos2(400.0, 300.0);
+
+        // Panning with the middle button wins over dragging the node
+        let ctx = egui::Context::default();
+        let (mut state, nodes) = state_with_nodes(1);
+        draw_frame(&mut state, &ctx);
+        let delta = egui::vec2(40.0, 30.0);
+        let responses = button_drag(&mut state, &ctx, egui::PointerButton::Middle, title, delta);
+        assert!(!moves_node(&responses));
+        assert_eq!(state.node_positions[nodes[0]], egui::Pos2::ZERO);
+        assert_eq!(state.pan_zoom.pan, delta);
+        assert!(state.pan_origin.is_none());
+
+        // Right-clicking the background opens the finder on release
+        let zero = egui::Vec2::ZERO;
+        button_drag(
+            &mut state,
+            &ctx,
+            egui::PointerButton::Secondary,
+            background,
+            zero,
+        );
+        assert!(state.node_finder.is_some());
+
+        // Panning with the secondary button doesn't open it
+        let ctx = egui::Context::default();
+        let (mut state, _) = state_with_nodes(0);
+        state.input_bindings.pan_button = egui::PointerButton::Secondary;
+        draw_frame(&mut state, &ctx);
+        button_drag(
+            &mut state,
+            &ctx,
+            egui::PointerButton::Secondary,
+            background,
+            delta,
+        );
+        assert_eq!(state.pan_zoom.pan, delta);
+        assert!(state.node_finder.is_none());
+
+        // Unless the pointer stayed in place
+        button_drag(
+            &mut state,
+            &ctx,
+            egui::PointerButton::Secondary,
+            background,
+            zero,
+        );
+        assert!(state.node_finder.is_some());
+
+        // Right-clicking a port, outside of the node body, opens the finder
+        // too, unless ports show their own context menus
+        for port_context_menus in [false, true] {
+            let ctx = egui::Context::default();
+            let (mut state, port) = single_node_editor(&ctx);
+            state.input_bindings.port_context_menus = port_context_menus;
+            let secondary = egui::PointerButton::Secondary;
+            let outside = port + egui::vec2(6.0, 0.0);
+            button_drag(&mut state, &ctx, secondary, outside, zero);
+            assert_eq!(state.node_finder.is_some(), !port_context_menus);
+        }
+
+        // Scrolling pans instead of zooming
+        let ctx = egui::Context::default();
+        let (mut state, _) = state_with_nodes(0);
+        state.input_bindings.scroll_zooms = false;
+        draw_frame(&mut state, &ctx);
+        let input = egui::RawInput {
+            events: vec![
+                egui::Event::PointerMoved(background),
+                egui::Event::Scroll(egui::vec2(10.0, 20.0)),
+            ],
+            ..Default::default()
+        };
+        draw_frame_with_input(&mut state, &ctx, input);
+        assert_eq!(state.pan_zoom.zoom, 1.0);
+        assert_eq!(state.pan_zoom.pan, egui::vec2(10.0, 20.0));
+    }
+
+    #[test]
+    pub fn test_select_related() {
+        let ctx = egui::Context::default();
+        // 0 -> 1 -> 2, and 3 on its own
+        let (mut state, nodes) = state_with_nodes(4);
+        hover_editor(&mut state, &ctx);
+        for pair in nodes[..3].windows(2) {
+            let output = state.graph[pair[0]].get_output("out").unwrap();
+            let input = state.graph[pair[1]].get_input("in").unwrap();
+            state.graph.add_connection(output, input);
+        }
+
+        let responses = vec![NodeResponse::SelectRelated(
+            nodes[2],
+            NodeRelation::Upstream,
+        )];
+        draw_frame_with_responses(&mut state, &ctx, Default::default(), responses);
+        assert_eq!(state.selected_nodes, nodes[..3]);
+
+        let key = |key, modifiers| egui::Event::Key {
+            key,
+            pressed: true,
+            repeat: false,
+            modifiers,
+        };
+        let shift_command = egui::Modifiers::COMMAND | egui::Modifiers::SHIFT;
+        for (selected, event, expected) in [
+            (
+                vec![nodes[1]],
+                key(egui::Key::L, egui::Modifiers::COMMAND),
+                nodes[..3].to_vec(),
+            ),
+            (
+                vec![nodes[1]],
+                key(egui::Key::ArrowRight, shift_command),
+                vec![nodes[1], nodes[2]],
+            ),
+            (
+                vec![nodes[1], nodes[3]],
+                key(egui::Key::ArrowLeft, shift_command),
+                vec![nodes[0], nodes[1], nodes[3]],
+            ),
+        ] {
+            state.selected_nodes = selected;
+            let input = egui::RawInput {
+                events: vec![event],
+                ..Default::default()
+            };
+            draw_frame_with_input(&mut state, &ctx, input);
+            assert_eq!(state.selected_nodes, expected);
+        }
+    }
+
+    #[test]
+    pub fn test_find_nodes() {
+        let ctx = egui::Context::default();
+        let (mut state, nodes) = state_with_nodes(3);
+        hover_editor(&mut state, &ctx);
+        for (node, label) in nodes.iter().zip(["XLinkIn", "Camera", "XLinkOut"]) {
+            state.graph[*node].label = label.into();
+        }
+        let key = |key, modifiers| egui::Event::Key {
+            key,
+            pressed: true,
+            repeat: false,
+            modifiers,
+        };
+        let frame_with_events = |state: &mut TestEditorState, events| {
+            let input = egui::RawInput {
+                events,
+                ..Default::default()
+            };
+            draw_frame_with_input(state, &ctx, input)
+        };
+
+        // Opt-in
+        frame_with_events(
+            &mut state,
+            vec![key(egui::Key::F, egui::Modifiers::COMMAND)],
+        );
+        assert!(state.node_search.is_none());
+
+        state.find_nodes = true;
+        for events in [
+            vec![key(egui::Key::F, egui::Modifiers::COMMAND)],
+            vec![],
+            vec![egui::Event::Text("xlink".into())],
+            vec![key(egui::Key::ArrowDown, egui::Modifiers::NONE)],
+        ] {
+            frame_with_events(&mut state, events);
+        }
+        let search = state.node_search.as_ref().unwrap();
+        assert_eq!(search.query, "xlink");
+        assert_eq!(search.highlighted, 1);
+
+        let response = frame_with_events(
+            &mut state,
+            vec![key(egui::Key::Enter, egui::Modifiers::NONE)],
+        );
+        assert!(response
+            .node_responses
+            .iter()
+            .any(|r| matches!(r, NodeResponse::SelectNode(node) if *node == nodes[2])));
+        assert_eq!(state.selected_nodes, vec![nodes[2]]);
+        assert!(state.node_search.is_none());
+
+        // The view is centered on the picked node
+        let response = draw_frame(&mut state, &ctx);
+        let rect = response.node_screen_rect(nodes[2]).unwrap();
+        assert!((rect.center() - response.editor_rect.center()).length() < 1.0);
+    }
+
+    #[test]
+    pub fn test_required_inputs() {
+        let ctx = egui::Context::default();
+        let mut state = TestEditorState::new(1.0);
+        let a = state.add_node(&TestTemplate::Scalar, egui::pos2(0.0, 0.0), &mut ());
+        let b = state.add_node(&TestTemplate::Scalar, egui::pos2(300.0, 0.0), &mut ());
+        let input = state.graph[b].get_input("in").unwrap();
+        let ports = draw_frame(&mut state, &ctx).port_locations;
+        let port = ports[&AnyParameterId::Input(input)];
+        let rings = |state: &mut TestEditorState| {
+            draw_frame_shapes(state, &ctx, Default::default())
+                .into_iter()
+                .filter(|shape| {
+                    matches!(shape, egui::Shape::Circle(circle)
+                        if circle.center.distance(port) < 1.0
+                            && circle.stroke.color == egui::Color32::from_rgb(220, 60, 60))
+                })
+                .count()
+        };
+        assert_eq!(rings(&mut state), 0);
+
+        state.graph.set_input_required(input, true);
+        assert!(state.graph.is_input_required(input));
+        assert_eq!(
+            state.graph.missing_required_inputs(b).collect::<Vec<_>>(),
+            [input]
+        );
+        assert_eq!(rings(&mut state), 1);
+
+        // Connected, the input is satisfied
+        let output = state.graph[a].get_output("out").unwrap();
+        state.graph.add_connection(output, input);
+        assert_eq!(state.graph.missing_required_inputs(b).count(), 0);
+        assert_eq!(rings(&mut state), 0);
+    }
+
+    #[test]
+    pub fn test_replaced_connections() {
+        // Drags a connection from the third node onto the connected input of
+        // the second one
+        let replace = |split: bool, typ| {
+            let ctx = egui::Context::default();
+            let (mut state, nodes) = state_with_nodes(3);
+            state.split_replaced_connections = split;
+            *state.node_positions.get_mut(nodes[1]).unwrap() = egui::pos2(400.0, 0.0);
+            *state.node_positions.get_mut(nodes[2]).unwrap() = egui::pos2(0.0, 300.0);
+            let old_output = state.graph[nodes[0]].get_output("out").unwrap();
+            let new_output = state.graph[nodes[2]].get_output("out").unwrap();
+            state.graph[new_output].typ = typ;
+            let input = state.graph[nodes[1]].get_input("in").unwrap();
+            state.graph.add_connection(old_output, input);
+
+            let response = draw_frame(&mut state, &ctx);
+            let from = response
+                .port_screen_pos(AnyParameterId::Output(new_output))
+                .unwrap();
+            let to = response
+                .port_screen_pos(AnyParameterId::Input(input))
+                .unwrap();
+            let responses = drag(&mut state, &ctx, from, to - from, &[]);
+            (state, (input, old_output, new_output), responses)
+        };
+        let is_connection = |r: &&NodeResponse<TestResponse, TestNodeData>| {
+            matches!(
+                r,
+                NodeResponse::ReplacedConnection { .. }
+                    | NodeResponse::DisconnectEvent { .. }
+                    | NodeResponse::ConnectEventEnded { .. }
+            )
+        };
+
+        // A single response by default
+        let (state, (input, old_output, new_output), responses) =
+            replace(false, TestDataType::Scalar);
+        assert_eq!(state.graph.connections(input), [new_output]);
+        let reported: Vec<_> = responses.iter().filter(is_connection).collect();
+        assert_eq!(reported.len(), 1);
+        assert!(matches!(
+            reported[0],
+            NodeResponse::ReplacedConnection { input: i, old_output: o, new_output: n }
+                if *i == input && *o == old_output && *n == new_output
+        ));
+
+        // A disconnection, then a connection, when split
+        let (state, (input, old_output, new_output), responses) =
+            replace(true, TestDataType::Scalar);
+        assert_eq!(state.graph.connections(input), [new_output]);
+        let reported: Vec<_> = responses.iter().filter(is_connection).collect();
+        assert_eq!(reported.len(), 2);
+        assert!(matches!(
+            reported[0],
+            NodeResponse::DisconnectEvent { input: i, output: o } if *i == input && *o == old_output
+        ));
+        assert!(matches!(
+            reported[1],
+            NodeResponse::ConnectEventEnded { input: i, output: o }
+                if *i == input && *o == new_output
+        ));
+
+        // An incompatible replacement keeps the old connection
+        let (state, (input, old_output, _), responses) = replace(false, TestDataType::Vector);
+        assert_eq!(state.graph.connections(input), [old_output]);
+        assert_eq!(responses.iter().filter(is_connection).count(), 0);
+    }
+
+    #[test]
+    pub fn test_reconnecting_is_not_reported() {
+        let ctx = egui::Context::default();
+        let (mut state, nodes) = state_with_nodes(2);
+        let output = state.graph[nodes[0]].get_output("out").unwrap();
+        let input = state.graph[nodes[1]].get_input("in").unwrap();
+        state.graph.add_connection(output, input);
+        state.graph.record_events(true);
+
+        let response = draw_frame_with_responses(
+            &mut state,
+            &ctx,
+            Default::default(),
+            vec![NodeResponse::ConnectEventEnded { output, input }],
+        );
+        assert!(!response
+            .node_responses
+            .iter()
+            .any(|r| matches!(r, NodeResponse::ConnectEventEnded { .. })));
+        assert!(state.graph.drain_events().is_empty());
+        assert_eq!(state.graph.connections(input), [output]);
+    }
+
+    #[test]
+    pub fn test_shortcuts_need_the_pointer_over_the_editor() {
+        let ctx = egui::Context::default();
+        let (mut state, nodes) = state_with_nodes(2);
+        state.selected_nodes = vec![nodes[0]];
+        let delete = |pointer| egui::RawInput {
+            events: vec![
+                pointer,
+                egui::Event::Key {
+                    key: egui::Key::Delete,
+                    pressed: true,
+                    repeat: false,
+                    modifiers: egui::Modifiers::NONE,
+                },
+            ],
+            ..Default::default()
+        };
+
+        // Elsewhere in the app, the key is left to the rest of the UI
+        draw_frame_with_input(&mut state, &ctx, delete(egui::Event::PointerGone));
+        assert_eq!(state.graph.nodes.len(), 2);
 
-        self.zoom += zoom_delta;
-        self.pan += point * zoom_delta;
+        let over_editor = egui::Event::PointerMoved(egui::pos2(1.0, 1.0));
+        draw_frame_with_input(&mut state, &ctx, delete(over_editor));
+        assert_eq!(state.node_order, [nodes[1]]);
     }
 }
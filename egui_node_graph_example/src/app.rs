@@ -1,26 +1,40 @@
-use std::{borrow::Cow, collections::HashMap};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+};
 
 use eframe::egui::{self, DragValue, TextStyle};
 use egui_node_graph::*;
 
+#[cfg(feature = "persistence")]
+use crate::file::{self, FileAction, FileDialog, FileWatcher, Toast};
+use crate::python::PythonExportWindow;
+use crate::relabel::RelabelDialog;
+use crate::validation::{Severity, ValidationIssue, Validator};
+#[cfg(feature = "persistence")]
+use std::path::PathBuf;
+
 // ========= First, define your user data types =============
 
 /// The NodeData holds a custom data struct inside each node. It's useful to
 /// store additional information that doesn't live in parameters. For this
 /// example, the node data stores the template (i.e. the "type") of the node.
+#[derive(Clone)]
 #[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
 pub struct MyNodeData {
-    template: MyNodeTemplate,
+    pub(crate) template: MyNodeTemplate,
 }
 
 /// `DataType`s are what defines the possible range of connections when
 /// attaching two ports together. The graph UI will make sure to not allow
 /// attaching incompatible datatypes.
-#[derive(PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
 pub enum MyDataType {
     Scalar,
     Vec2,
+    /// A wildcard, for nodes working with any type
+    Any,
 }
 
 /// In the graph, input parameters can optionally have a constant value. This
@@ -30,7 +44,7 @@ pub enum MyDataType {
 /// this library makes no attempt to check this consistency. For instance, it is
 /// up to the user code in this example to make sure no parameter is created
 /// with a DataType of Scalar and a ValueType of Vec2.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
 pub enum MyValueType {
     Vec2 { value: egui::Vec2 },
@@ -49,6 +63,9 @@ impl MyValueType {
     /// Tries to downcast this value type to a vector
     pub fn try_to_vec2(self) -> anyhow::Result<egui::Vec2> {
         if let MyValueType::Vec2 { value } = self {
+            if !self.is_finite() {
+                anyhow::bail!("Non-finite vector value {:?}", value)
+            }
             Ok(value)
         } else {
             anyhow::bail!("Invalid cast from {:?} to vec2", self)
@@ -58,26 +75,58 @@ impl MyValueType {
     /// Tries to downcast this value type to a scalar
     pub fn try_to_scalar(self) -> anyhow::Result<f32> {
         if let MyValueType::Scalar { value } = self {
+            if !self.is_finite() {
+                anyhow::bail!("Non-finite scalar value {}", value)
+            }
             Ok(value)
         } else {
             anyhow::bail!("Invalid cast from {:?} to scalar", self)
         }
     }
+
+    /// Returns false if any of the components is NaN or infinite.
+    pub fn is_finite(&self) -> bool {
+        match self {
+            MyValueType::Vec2 { value } => value.x.is_finite() && value.y.is_finite(),
+            MyValueType::Scalar { value } => value.is_finite(),
+        }
+    }
+
+    /// Replaces any NaN or infinite component with zero. Used when loading
+    /// values from storage.
+    #[cfg(any(feature = "persistence", test))]
+    pub fn sanitize(&mut self) {
+        let sanitize = |x: &mut f32| {
+            if !x.is_finite() {
+                *x = 0.0;
+            }
+        };
+        match self {
+            MyValueType::Vec2 { value } => {
+                sanitize(&mut value.x);
+                sanitize(&mut value.y);
+            }
+            MyValueType::Scalar { value } => sanitize(value),
+        }
+    }
 }
 
 /// NodeTemplate is a mechanism to define node templates. It's what the graph
 /// will display in the "new node" popup. The user code needs to tell the
 /// library how to convert a NodeTemplate into a Node.
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
 pub enum MyNodeTemplate {
     MakeScalar,
     AddScalar,
     SubtractScalar,
+    SumScalars,
+    MultiplyScalars,
     MakeVector,
     AddVector,
     SubtractVector,
     VectorTimesScalar,
+    Passthrough,
 }
 
 /// The response type is used to encode side-effects produced when drawing a
@@ -88,15 +137,32 @@ pub enum MyNodeTemplate {
 pub enum MyResponse {
     SetActiveNode(NodeId),
     ClearActiveNode,
+    PinOutput(OutputId),
+    UnpinOutput(OutputId),
 }
 
 /// The graph 'global' state. This state struct is passed around to the node and
 /// parameter drawing callbacks. The contents of this struct are entirely up to
-/// the user. For this example, we use it to keep track of the 'active' node.
+/// the user. For this example, we use it to keep track of the 'active' node
+/// and of the outputs pinned to the streams panel.
 #[derive(Default)]
 #[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
 pub struct MyGraphState {
     pub active_node: Option<NodeId>,
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub pinned_outputs: Vec<OutputId>,
+    /// Whether every sink, i.e. node without connected outputs, is evaluated
+    /// and shows its result at its bottom.
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub evaluate_sinks: bool,
+    /// The results of the sinks, or their error message, while
+    /// `evaluate_sinks` is enabled.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub sink_results: HashMap<NodeId, Result<MyValueType, String>>,
+    /// The issues found by the last validation of the graph, shown on their
+    /// nodes. See [`Validator`].
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub issues: Vec<ValidationIssue>,
 }
 
 // =========== Then, you need to implement some traits ============
@@ -107,6 +173,7 @@ impl DataTypeTrait<MyGraphState> for MyDataType {
         match self {
             MyDataType::Scalar => egui::Color32::from_rgb(38, 109, 211),
             MyDataType::Vec2 => egui::Color32::from_rgb(238, 207, 109),
+            MyDataType::Any => egui::Color32::from_gray(128),
         }
     }
 
@@ -114,8 +181,23 @@ impl DataTypeTrait<MyGraphState> for MyDataType {
         match self {
             MyDataType::Scalar => Cow::Borrowed("scalar"),
             MyDataType::Vec2 => Cow::Borrowed("2d vector"),
+            MyDataType::Any => Cow::Borrowed("any"),
+        }
+    }
+
+    // The math types keep round ports, wildcards stand out as diamonds
+    fn port_shape(&self, _user_state: &mut MyGraphState) -> PortShape {
+        match self {
+            MyDataType::Scalar | MyDataType::Vec2 => PortShape::Circle,
+            MyDataType::Any => PortShape::Diamond,
         }
     }
+
+    // Ports of this type accept any connection, and take the type of the
+    // port they're connected to.
+    fn is_wildcard(&self) -> bool {
+        *self == MyDataType::Any
+    }
 }
 
 // A trait for the node kinds, which tells the library how to build new nodes
@@ -132,23 +214,53 @@ impl NodeTemplateTrait for MyNodeTemplate {
             MyNodeTemplate::MakeScalar => "New scalar",
             MyNodeTemplate::AddScalar => "Scalar add",
             MyNodeTemplate::SubtractScalar => "Scalar subtract",
+            MyNodeTemplate::SumScalars => "Scalar sum",
+            MyNodeTemplate::MultiplyScalars => "Scalar product",
             MyNodeTemplate::MakeVector => "New vector",
             MyNodeTemplate::AddVector => "Vector add",
             MyNodeTemplate::SubtractVector => "Vector subtract",
             MyNodeTemplate::VectorTimesScalar => "Vector times scalar",
+            MyNodeTemplate::Passthrough => "Passthrough",
         })
     }
 
+    fn node_finder_description(&self, _user_state: &mut Self::UserState) -> Option<Cow<'_, str>> {
+        let description = match self {
+            MyNodeTemplate::MakeScalar => "A constant number",
+            MyNodeTemplate::SumScalars => "Adds up any number of scalars",
+            MyNodeTemplate::MultiplyScalars => "Multiplies any number of scalars",
+            MyNodeTemplate::VectorTimesScalar => "Scales a vector",
+            MyNodeTemplate::Passthrough => "Forwards its input, whatever its type",
+            _ => return None,
+        };
+        Some(Cow::Borrowed(description))
+    }
+
+    fn node_finder_icon(&self, _user_state: &mut Self::UserState) -> Option<char> {
+        match self {
+            MyNodeTemplate::AddScalar | MyNodeTemplate::AddVector | MyNodeTemplate::SumScalars => {
+                Some('➕')
+            }
+            MyNodeTemplate::SubtractScalar | MyNodeTemplate::SubtractVector => Some('➖'),
+            MyNodeTemplate::MultiplyScalars | MyNodeTemplate::VectorTimesScalar => Some('✖'),
+            _ => None,
+        }
+    }
+
     // this is what allows the library to show collapsible lists in the node finder.
     fn node_finder_categories(&self, _user_state: &mut Self::UserState) -> Vec<&'static str> {
         match self {
             MyNodeTemplate::MakeScalar
             | MyNodeTemplate::AddScalar
-            | MyNodeTemplate::SubtractScalar => vec!["Scalar"],
+            | MyNodeTemplate::SubtractScalar
+            | MyNodeTemplate::SumScalars
+            | MyNodeTemplate::MultiplyScalars => vec!["Scalar"],
             MyNodeTemplate::MakeVector
             | MyNodeTemplate::AddVector
             | MyNodeTemplate::SubtractVector => vec!["Vector"],
-            MyNodeTemplate::VectorTimesScalar => vec!["Vector", "Scalar"],
+            MyNodeTemplate::VectorTimesScalar | MyNodeTemplate::Passthrough => {
+                vec!["Vector", "Scalar"]
+            }
         }
     }
 
@@ -230,9 +342,34 @@ impl NodeTemplateTrait for MyNodeTemplate {
                 input_scalar(graph, "B");
                 output_scalar(graph, "out");
             }
+            MyNodeTemplate::SumScalars => {
+                // A wide input accepts any number of connections. Pass
+                // `NonZeroU32::new(n)` instead of `None` to limit them.
+                graph.add_wide_input_param(
+                    node_id,
+                    "values".into(),
+                    MyDataType::Scalar,
+                    MyValueType::Scalar { value: 0.0 },
+                    InputParamKind::ConnectionOnly,
+                    None,
+                    true,
+                );
+                output_scalar(graph, "out");
+            }
+            MyNodeTemplate::MultiplyScalars => {
+                // More factors are added with the `+` button of the node, see
+                // `can_add_input` below.
+                input_scalar(graph, "factor");
+                input_scalar(graph, "factor 2");
+                output_scalar(graph, "out");
+            }
             MyNodeTemplate::VectorTimesScalar => {
                 input_scalar(graph, "scalar");
                 input_vector(graph, "vector");
+                // Scaling the constant vector is pointless, so the editor
+                // highlights the input until it's connected
+                let vector = graph[node_id].get_input("vector").unwrap();
+                graph.set_input_required(vector, true);
                 output_vector(graph, "out");
             }
             MyNodeTemplate::AddVector => {
@@ -254,6 +391,34 @@ impl NodeTemplateTrait for MyNodeTemplate {
                 input_scalar(graph, "value");
                 output_scalar(graph, "out");
             }
+            MyNodeTemplate::Passthrough => {
+                // Wildcard ports take the type of their connection. The
+                // value is a placeholder until then.
+                let value = graph.add_input_param(
+                    node_id,
+                    "value".into(),
+                    MyDataType::Any,
+                    MyValueType::default(),
+                    InputParamKind::ConnectionOnly,
+                    true,
+                );
+                graph.set_input_required(value, true);
+                graph.add_output_param(node_id, "out".into(), MyDataType::Any);
+            }
+        }
+    }
+
+    // When a connection is dropped on a port of the wrong type, the library
+    // asks for a template that can convert between the two types. The first
+    // input and output of matching types are connected automatically.
+    fn converter(
+        from: &MyDataType,
+        to: &MyDataType,
+        _user_state: &mut Self::UserState,
+    ) -> Option<Self> {
+        match (from, to) {
+            (MyDataType::Scalar, MyDataType::Vec2) => Some(MyNodeTemplate::MakeVector),
+            _ => None,
         }
     }
 }
@@ -271,9 +436,12 @@ impl NodeTemplateIter for AllMyNodeTemplates {
             MyNodeTemplate::MakeVector,
             MyNodeTemplate::AddScalar,
             MyNodeTemplate::SubtractScalar,
+            MyNodeTemplate::SumScalars,
+            MyNodeTemplate::MultiplyScalars,
             MyNodeTemplate::AddVector,
             MyNodeTemplate::SubtractVector,
             MyNodeTemplate::VectorTimesScalar,
+            MyNodeTemplate::Passthrough,
         ]
     }
 }
@@ -297,15 +465,15 @@ impl WidgetValueTrait for MyValueType {
                 ui.label(param_name);
                 ui.horizontal(|ui| {
                     ui.label("x");
-                    ui.add(DragValue::new(&mut value.x));
+                    finite_drag_value(ui, &mut value.x);
                     ui.label("y");
-                    ui.add(DragValue::new(&mut value.y));
+                    finite_drag_value(ui, &mut value.y);
                 });
             }
             MyValueType::Scalar { value } => {
                 ui.horizontal(|ui| {
                     ui.label(param_name);
-                    ui.add(DragValue::new(value));
+                    finite_drag_value(ui, value);
                 });
             }
         }
@@ -314,6 +482,39 @@ impl WidgetValueTrait for MyValueType {
     }
 }
 
+/// A `DragValue` that refuses NaN or infinite values. When one is entered, the
+/// last valid value is restored and the widget briefly flashes red.
+fn finite_drag_value(ui: &mut egui::Ui, value: &mut f32) -> egui::Response {
+    const FLASH_DURATION: f64 = 0.5;
+
+    let last_valid = *value;
+    let response = ui.add(DragValue::new(value));
+    let now = ui.input(|i| i.time);
+    if !value.is_finite() {
+        *value = if last_valid.is_finite() {
+            last_valid
+        } else {
+            0.0
+        };
+        ui.memory_mut(|mem| mem.data.insert_temp(response.id, now));
+    }
+
+    let rejected_at = ui.memory_mut(|mem| mem.data.get_temp::<f64>(response.id));
+    if let Some(rejected_at) = rejected_at {
+        if now - rejected_at < FLASH_DURATION {
+            ui.painter().rect_stroke(
+                response.rect,
+                2.0,
+                egui::Stroke::new(2.0, egui::Color32::RED),
+            );
+            ui.ctx().request_repaint();
+        } else {
+            ui.memory_mut(|mem| mem.data.remove::<f64>(response.id));
+        }
+    }
+    response
+}
+
 impl UserResponseTrait for MyResponse {}
 impl NodeDataTrait for MyNodeData {
     type Response = MyResponse;
@@ -364,39 +565,591 @@ impl NodeDataTrait for MyNodeData {
             }
         }
 
+        match user_state.sink_results.get(&node_id) {
+            Some(Ok(value)) => {
+                ui.label(format!("= {}", format_value(value)));
+            }
+            Some(Err(err)) => {
+                ui.colored_label(egui::Color32::RED, err);
+            }
+            None => {}
+        }
+
+        responses
+    }
+
+    // The title bar of the active node gets a gold dot, which clears the
+    // active node when clicked
+    fn top_bar_ui(
+        &self,
+        ui: &mut egui::Ui,
+        node_id: NodeId,
+        _graph: &Graph<MyNodeData, MyDataType, MyValueType>,
+        user_state: &mut Self::UserState,
+    ) -> Vec<NodeResponse<MyResponse, MyNodeData>>
+    where
+        MyResponse: UserResponseTrait,
+    {
+        let mut responses = vec![];
+        if user_state.active_node == Some(node_id) {
+            let dot = egui::Label::new(egui::RichText::new("●").color(egui::Color32::GOLD))
+                .sense(egui::Sense::click());
+            if ui.add(dot).on_hover_text("Active node").clicked() {
+                responses.push(NodeResponse::User(MyResponse::ClearActiveNode));
+            }
+        }
+        let issues: Vec<&ValidationIssue> = user_state
+            .issues
+            .iter()
+            .filter(|issue| issue.node == node_id)
+            .collect();
+        if let Some(worst) = issues.iter().map(|issue| issue.severity).max() {
+            let messages: Vec<&str> = issues.iter().map(|issue| issue.message.as_str()).collect();
+            let badge = egui::RichText::new("⚠").color(severity_color(worst));
+            ui.label(badge).on_hover_text(messages.join("\n"));
+        }
+        responses
+    }
+
+    // The context menu of the nodes starts with the entries added here,
+    // followed by the ones of the library.
+    fn context_menu_ui(
+        &self,
+        ui: &mut egui::Ui,
+        node_id: NodeId,
+        _graph: &Graph<MyNodeData, MyDataType, MyValueType>,
+        user_state: &mut Self::UserState,
+    ) -> Vec<NodeResponse<MyResponse, MyNodeData>> {
+        let mut responses = vec![];
+        if user_state.active_node != Some(node_id) {
+            if ui.button("👁 Set active").clicked() {
+                responses.push(NodeResponse::User(MyResponse::SetActiveNode(node_id)));
+            }
+        } else if ui.button("Clear active").clicked() {
+            responses.push(NodeResponse::User(MyResponse::ClearActiveNode));
+        }
+        responses
+    }
+
+    // Params can be described, in the tooltip of their port and when hovering
+    // them in the node.
+    fn param_description(
+        &self,
+        _node_id: NodeId,
+        param_id: AnyParameterId,
+        graph: &Graph<MyNodeData, MyDataType, MyValueType>,
+        _user_state: &mut Self::UserState,
+    ) -> Option<Cow<'_, str>> {
+        let name = graph.param_name(param_id)?;
+        let description = match (self.template, name) {
+            (_, "out") => "The result of the node",
+            (MyNodeTemplate::SubtractScalar, "A") => "The scalar to subtract from",
+            (MyNodeTemplate::SubtractScalar, "B") => "The scalar to subtract",
+            (MyNodeTemplate::SubtractVector, "v1") => "The vector to subtract from",
+            (MyNodeTemplate::SubtractVector, "v2") => "The vector to subtract",
+            (MyNodeTemplate::SumScalars, "values") => "Any number of scalars to add up",
+            (MyNodeTemplate::MultiplyScalars, _) => "A scalar to multiply",
+            (MyNodeTemplate::Passthrough, "value") => "Any value, forwarded as is",
+            (MyNodeTemplate::VectorTimesScalar, "scalar") => "The factor to scale the vector by",
+            (MyNodeTemplate::MakeVector, "x") => "The horizontal component",
+            (MyNodeTemplate::MakeVector, "y") => "The vertical component",
+            _ => return None,
+        };
+        Some(description.into())
+    }
+
+    // Nodes can offer a `+` button adding inputs to them. The library makes
+    // the name unique, so the factors of a product are named `factor 3`,
+    // `factor 4`...
+    fn can_add_input(
+        &self,
+        _node_id: NodeId,
+        _graph: &Graph<MyNodeData, MyDataType, MyValueType>,
+        _user_state: &mut Self::UserState,
+    ) -> Option<(String, MyDataType, MyValueType, InputParamKind)> {
+        match self.template {
+            MyNodeTemplate::MultiplyScalars => Some((
+                "factor".into(),
+                MyDataType::Scalar,
+                MyValueType::Scalar { value: 1.0 },
+                InputParamKind::ConnectionOrConstant,
+            )),
+            _ => None,
+        }
+    }
+
+    // Inputs can be removed from their node. Here, a product keeps its first
+    // factor.
+    fn can_remove_input(
+        &self,
+        node_id: NodeId,
+        input: InputId,
+        graph: &Graph<MyNodeData, MyDataType, MyValueType>,
+        _user_state: &mut Self::UserState,
+    ) -> bool {
+        self.template == MyNodeTemplate::MultiplyScalars
+            && graph[node_id].get_input("factor").ok() != Some(input)
+    }
+
+    // The tooltip of ports lists their type and connections. Here, it also
+    // tells whether an output is pinned to the streams panel.
+    fn port_tooltip_ui(
+        &self,
+        ui: &mut egui::Ui,
+        _node_id: NodeId,
+        param_id: AnyParameterId,
+        _graph: &Graph<MyNodeData, MyDataType, MyValueType>,
+        user_state: &mut Self::UserState,
+    ) {
+        if let AnyParameterId::Output(output) = param_id {
+            if user_state.pinned_outputs.contains(&output) {
+                ui.label("📌 Pinned to streams");
+            }
+        }
+    }
+
+    // Ports can react to interactions the library doesn't handle. Here, the
+    // context menu of output ports allows pinning them to the streams panel.
+    fn port_response(
+        &self,
+        response: &egui::Response,
+        _node_id: NodeId,
+        param_id: AnyParameterId,
+        _graph: &Graph<MyNodeData, MyDataType, MyValueType>,
+        user_state: &mut Self::UserState,
+    ) -> Vec<NodeResponse<MyResponse, MyNodeData>> {
+        let mut responses = vec![];
+        if let AnyParameterId::Output(output) = param_id {
+            let is_pinned = user_state.pinned_outputs.contains(&output);
+            response.clone().context_menu(|ui| {
+                if !is_pinned && ui.button("📌 Pin to streams").clicked() {
+                    responses.push(NodeResponse::User(MyResponse::PinOutput(output)));
+                    ui.close_menu();
+                } else if is_pinned && ui.button("Unpin from streams").clicked() {
+                    responses.push(NodeResponse::User(MyResponse::UnpinOutput(output)));
+                    ui.close_menu();
+                }
+            });
+        }
         responses
     }
+
+    // Renamed nodes can still be found by their template with Ctrl + F
+    fn template_name(
+        &self,
+        _node_id: NodeId,
+        _graph: &Graph<MyNodeData, MyDataType, MyValueType>,
+        user_state: &mut Self::UserState,
+    ) -> Option<Cow<'_, str>> {
+        Some(self.template.node_finder_label(user_state))
+    }
 }
 
-type MyGraph = Graph<MyNodeData, MyDataType, MyValueType>;
-type MyEditorState =
+pub(crate) type MyGraph = Graph<MyNodeData, MyDataType, MyValueType>;
+pub(crate) type MyEditorState =
     GraphEditorState<MyNodeData, MyDataType, MyValueType, MyNodeTemplate, MyGraphState>;
 
-#[derive(Default)]
 pub struct NodeGraphExample {
     // The `GraphEditorState` is the top-level object. You "register" all your
     // custom types by specifying it as its generic parameters.
     state: MyEditorState,
 
     user_state: MyGraphState,
+
+    // The area of the screen taken by the graph editor during the last frame.
+    // Used to center the view on a node.
+    editor_rect: egui::Rect,
+
+    relabel_dialog: RelabelDialog,
+
+    python_export: PythonExportWindow,
+
+    // The connected components of the graph, computed on demand and
+    // invalidated whenever nodes or connections change.
+    components: Option<Vec<Vec<NodeId>>>,
+
+    // Responses of the changes done outside the editor (e.g. undo), reported
+    // along with the graph responses of the next frame.
+    pending_responses: Vec<NodeResponse<MyResponse, MyNodeData>>,
+
+    // The nodes copied with `AppCommand::Copy`.
+    clipboard: Option<Clipboard<MyNodeData, MyDataType, MyValueType>>,
+
+    // The position of the pointer over the editor during the last frame, in
+    // graph space. Pasted nodes go there.
+    pointer_pos: Option<egui::Pos2>,
+
+    // The outputs evaluated for the active node and the pinned outputs, kept
+    // across frames until the graph changes.
+    evaluation_cache: EvaluationCache,
+
+    // The result displayed for the active node, along with the revision of
+    // the graph it was evaluated at.
+    active_result: Option<(NodeId, u64, String)>,
+
+    // Checks the graph after it changes, filling `MyGraphState::issues`.
+    validator: Validator,
+
+    // When to validate the graph next, in seconds, see `egui::InputState::time`.
+    // Pushed back by every change, so that the graph is only validated once it
+    // stopped changing for a moment.
+    validation_due: Option<f64>,
+
+    // The revision of the graph the sink results were evaluated at.
+    sinks_revision: Option<u64>,
+
+    #[cfg(feature = "persistence")]
+    file_dialog: FileDialog,
+
+    // The graph file opened last, if any.
+    #[cfg(feature = "persistence")]
+    open_path: Option<PathBuf>,
+
+    // Whether the open file is reloaded when another program modifies it,
+    // and whether the changes are merged into the graph instead of replacing
+    // it.
+    #[cfg(feature = "persistence")]
+    watch_file: bool,
+    #[cfg(feature = "persistence")]
+    merge_on_reload: bool,
+
+    // Watches the open file while `watch_file` is enabled.
+    #[cfg(feature = "persistence")]
+    watcher: Option<FileWatcher>,
+
+    #[cfg(feature = "persistence")]
+    toast: Option<Toast>,
+
+    // Whether the DOT export pins the nodes to their position in the editor.
+    #[cfg(feature = "persistence")]
+    dot_positions: bool,
+}
+
+/// Sets up the editor features the app relies on. Done whenever the app gets
+/// a new editor state, as loaded states may have them turned off.
+fn configure_state(state: &mut MyEditorState) {
+    // Evaluation follows connections upstream, so it can't deal with cycles
+    state.enforce_acyclic = true;
+    // Dropping a connection on the background offers to create a node for it
+    state.drop_on_background = true;
+    // Dropping a new node on a connection inserts it there
+    state.drop_node_on_connection = true;
+    // Ctrl + F finds nodes by label
+    state.find_nodes = true;
+    // Right-clicking an output port offers to pin it to the streams panel
+    state.input_bindings.port_context_menus = true;
+    // The graph is validated again when it changes
+    state.graph.record_events(true);
+}
+
+impl Default for NodeGraphExample {
+    fn default() -> Self {
+        let mut state = MyEditorState::default();
+        configure_state(&mut state);
+        // Offered at the top of the node finder, until unpinned
+        state.finder_favorites.pin("New scalar");
+        Self {
+            state,
+            user_state: Default::default(),
+            editor_rect: egui::Rect::from_min_size(egui::Pos2::ZERO, egui::Vec2::ZERO),
+            relabel_dialog: Default::default(),
+            python_export: Default::default(),
+            components: None,
+            pending_responses: Vec::new(),
+            clipboard: None,
+            pointer_pos: None,
+            evaluation_cache: Default::default(),
+            validator: Default::default(),
+            validation_due: Some(0.0),
+            active_result: None,
+            sinks_revision: None,
+            #[cfg(feature = "persistence")]
+            file_dialog: Default::default(),
+            #[cfg(feature = "persistence")]
+            open_path: None,
+            #[cfg(feature = "persistence")]
+            watch_file: false,
+            #[cfg(feature = "persistence")]
+            merge_on_reload: true,
+            #[cfg(feature = "persistence")]
+            watcher: None,
+            #[cfg(feature = "persistence")]
+            toast: None,
+            #[cfg(feature = "persistence")]
+            dot_positions: true,
+        }
+    }
 }
 
+/// The actions of the example app. The menu bar, the keyboard shortcuts and
+/// the context menus all go through [`NodeGraphExample::dispatch`], so the
+/// same action behaves the same no matter where it was triggered from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AppCommand {
+    Undo,
+    Redo,
+    ToggleFindReplace,
+    /// Copies the selected nodes and the connections between them.
+    Copy,
+    /// Pastes the copied nodes under the pointer, and selects them.
+    Paste,
+    /// Duplicates the selected nodes next to the originals, and selects the
+    /// copies.
+    Duplicate,
+    /// Wraps the selected nodes in a group frame.
+    GroupSelection,
+    /// Pans and zooms the view so that all the nodes are visible.
+    FitView,
+    /// Zooms back to 100%, keeping the center of the view in place.
+    ResetZoom,
+    /// Arranges the nodes in layers following the connections, then fits
+    /// the view.
+    AutoLayout,
+    /// Shows or hides the frame timings of the editor.
+    ToggleTimings,
+    /// Selects a node and pans the view so it's centered.
+    FocusNode(NodeId),
+    SetActiveNode(NodeId),
+    ClearActiveNode,
+    PinOutput(OutputId),
+    UnpinOutput(OutputId),
+    /// Selects every node in the connected component of the given node.
+    SelectComponent(NodeId),
+    /// Deletes every node in the connected component of the given node.
+    DeleteComponent(NodeId),
+}
+
+impl From<MyResponse> for AppCommand {
+    fn from(response: MyResponse) -> Self {
+        match response {
+            MyResponse::SetActiveNode(node) => AppCommand::SetActiveNode(node),
+            MyResponse::ClearActiveNode => AppCommand::ClearActiveNode,
+            MyResponse::PinOutput(output) => AppCommand::PinOutput(output),
+            MyResponse::UnpinOutput(output) => AppCommand::UnpinOutput(output),
+        }
+    }
+}
+
+/// How long the graph must stay unchanged before it's validated again, in
+/// seconds.
+const VALIDATION_DELAY: f64 = 0.3;
+
+fn severity_color(severity: Severity) -> egui::Color32 {
+    match severity {
+        Severity::Warning => egui::Color32::from_rgb(230, 180, 40),
+        Severity::Error => egui::Color32::from_rgb(220, 60, 60),
+    }
+}
+
+/// The keyboard shortcuts of the app. Shortcuts without modifiers are ignored
+/// while a text field has the keyboard focus.
+const KEYBINDINGS: &[(egui::Modifiers, egui::Key, AppCommand)] = &[
+    (egui::Modifiers::COMMAND, egui::Key::Z, AppCommand::Undo),
+    (egui::Modifiers::COMMAND, egui::Key::Y, AppCommand::Redo),
+    (egui::Modifiers::COMMAND, egui::Key::C, AppCommand::Copy),
+    (egui::Modifiers::COMMAND, egui::Key::V, AppCommand::Paste),
+    (
+        egui::Modifiers::COMMAND,
+        egui::Key::D,
+        AppCommand::Duplicate,
+    ),
+    (
+        egui::Modifiers::COMMAND,
+        egui::Key::H,
+        AppCommand::ToggleFindReplace,
+    ),
+    (egui::Modifiers::NONE, egui::Key::F, AppCommand::FitView),
+    (egui::Modifiers::NONE, egui::Key::Home, AppCommand::FitView),
+    (
+        egui::Modifiers::COMMAND,
+        egui::Key::Num0,
+        AppCommand::ResetZoom,
+    ),
+];
+
 #[cfg(feature = "persistence")]
 const PERSISTENCE_KEY: &str = "egui_node_graph";
 
+#[cfg(feature = "persistence")]
+const USER_STATE_PERSISTENCE_KEY: &str = "egui_node_graph_user_state";
+
+/// The version of the editor state, saved to the app storage and to graph
+/// files. Bump it, and add a migration from the previous version to
+/// `MIGRATIONS`, when older states can no longer be read.
+#[cfg(feature = "persistence")]
+pub(crate) const STATE_VERSION: u32 = 1;
+
+#[cfg(feature = "persistence")]
+pub(crate) const MIGRATIONS: &[Migration] = &[];
+
 #[cfg(feature = "persistence")]
 impl NodeGraphExample {
     /// If the persistence feature is enabled, Called once before the first frame.
     /// Load previous app state (if any).
     pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
-        let state = cc
+        let mut state: MyEditorState = cc
+            .storage
+            .and_then(|storage| storage.get_string(PERSISTENCE_KEY))
+            .and_then(|blob| {
+                MyEditorState::deserialize_with_migrations(&blob, STATE_VERSION, MIGRATIONS, None)
+                    .ok()
+            })
+            .unwrap_or_default();
+        configure_state(&mut state);
+        // Values stored by older versions may contain NaNs
+        for input in state.graph.inputs.values_mut() {
+            input.value.sanitize();
+        }
+        let user_state = cc
             .storage
-            .and_then(|storage| eframe::get_value(storage, PERSISTENCE_KEY))
+            .and_then(|storage| eframe::get_value(storage, USER_STATE_PERSISTENCE_KEY))
             .unwrap_or_default();
         Self {
             state,
-            user_state: MyGraphState::default(),
+            user_state,
+            ..Default::default()
+        }
+    }
+
+    /// Draws the file dialog, and saves or opens the graph file it asks for.
+    fn file_dialog_ui(&mut self, ctx: &egui::Context) {
+        let (action, path) = match self.file_dialog.show(ctx) {
+            Some(confirmed) => confirmed,
+            None => return,
+        };
+        let message = match action {
+            FileAction::SaveAs => match file::save_graph(&path, &self.state) {
+                Ok(()) => {
+                    // Restarted from the saved contents, so that saving isn't
+                    // taken for a change made by another program
+                    if matches!(&self.watcher, Some(watcher) if watcher.path == path) {
+                        self.watcher = None;
+                    }
+                    None
+                }
+                Err(err) => Some((format!("Could not save {}: {}", path.display(), err), true)),
+            },
+            FileAction::Open => match file::load_graph(&path) {
+                Ok(loaded) => {
+                    self.replace_state(loaded.state);
+                    self.open_path = Some(path);
+                    loaded.repaired.then(|| {
+                        let message = "Some node positions were missing or referred to \
+                            missing nodes, and were repaired.";
+                        (message.to_owned(), false)
+                    })
+                }
+                Err(err) => Some((format!("Could not open {}: {}", path.display(), err), true)),
+            },
+            FileAction::ExportDot | FileAction::ExportMermaid => {
+                let diagram = if action == FileAction::ExportDot {
+                    let options = DotOptions {
+                        positions: self.dot_positions,
+                    };
+                    self.state.to_dot(options, &mut self.user_state)
+                } else {
+                    self.state.to_mermaid(&mut self.user_state)
+                };
+                std::fs::write(&path, diagram).err().map(|err| {
+                    let message = format!("Could not export {}: {}", path.display(), err);
+                    (message, true)
+                })
+            }
+        };
+        self.file_dialog.message = message;
+    }
+
+    /// Replaces the whole editor state, e.g. with one read from a file.
+    fn replace_state(&mut self, mut state: MyEditorState) {
+        configure_state(&mut state);
+        self.state = state;
+        let graph = &self.state.graph;
+        self.user_state
+            .pinned_outputs
+            .retain(|output| graph.outputs.contains_key(*output));
+        self.user_state.active_node = None;
+        self.components = None;
+        self.evaluation_cache.clear();
+        self.validation_due = Some(0.0);
+    }
+
+    /// Keeps a watcher on the open file while `watch_file` is enabled, and
+    /// reloads the file when it reports a change.
+    fn watch_open_file(&mut self, ctx: &egui::Context) {
+        let path = self.open_path.as_ref().filter(|_| self.watch_file);
+        if self.watcher.as_ref().map(|watcher| &watcher.path) != path {
+            // Dropping the previous watcher stops it
+            self.watcher = path.map(|path| FileWatcher::new(path.clone(), ctx.clone()));
+        }
+        let (reloaded, path) = match &self.watcher {
+            Some(watcher) => match watcher.poll() {
+                Some(reloaded) => (reloaded, watcher.path.clone()),
+                None => return,
+            },
+            None => return,
+        };
+        let (message, is_error) = match reloaded {
+            Ok(loaded) => {
+                if self.merge_on_reload {
+                    self.merge_state(loaded.state);
+                } else {
+                    self.replace_state(loaded.state);
+                }
+                ("Graph reloaded".to_owned(), false)
+            }
+            // The graph is kept as it was
+            Err(err) => (
+                format!("Could not reload {}: {}", path.display(), err),
+                true,
+            ),
+        };
+        self.toast = Some(Toast {
+            message,
+            is_error,
+            shown_at: ctx.input(|i| i.time),
+        });
+    }
+
+    /// Merges a new version of the open file into the editor, see
+    /// [`GraphEditorState::sync_from`]. The nodes already in the graph keep
+    /// their position and values, and the new ones start from their template.
+    fn merge_state(&mut self, loaded: MyEditorState) {
+        let graph = &loaded.graph;
+        let ids: Vec<NodeId> = graph.iter_nodes().collect();
+        let nodes: Vec<SyncNode<MyNodeTemplate>> = ids
+            .iter()
+            .map(|id| SyncNode {
+                external_id: graph[*id].external_id.clone(),
+                label: graph[*id].label.clone(),
+                template: graph[*id].user_data.template,
+            })
+            .collect();
+        let index = |node: NodeId| ids.iter().position(|id| *id == node);
+        let connections: Vec<SyncConnection> = graph
+            .iter_connections()
+            .filter_map(|(input, output)| {
+                Some(SyncConnection {
+                    output_node: index(graph[output].node)?,
+                    output: graph.param_name(output.into())?.to_owned(),
+                    input_node: index(graph[input].node)?,
+                    input: graph.param_name(input.into())?.to_owned(),
+                })
+            })
+            .collect();
+        self.state
+            .sync_from(&nodes, &connections, &mut self.user_state);
+
+        let graph = &self.state.graph;
+        self.user_state
+            .pinned_outputs
+            .retain(|output| graph.outputs.contains_key(*output));
+        if let Some(node) = self.user_state.active_node {
+            if !graph.nodes.contains_key(node) {
+                self.user_state.active_node = None;
+            }
         }
+        self.components = None;
+        self.evaluation_cache.clear();
     }
 }
 
@@ -405,43 +1158,402 @@ impl eframe::App for NodeGraphExample {
     /// If the persistence function is enabled,
     /// Called by the frame work to save state before shutdown.
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
-        eframe::set_value(storage, PERSISTENCE_KEY, &self.state);
+        if let Ok(blob) = self.state.serialize_versioned(STATE_VERSION) {
+            storage.set_string(PERSISTENCE_KEY, blob);
+        }
+        eframe::set_value(storage, USER_STATE_PERSISTENCE_KEY, &self.user_state);
     }
     /// Called each time the UI needs repainting, which may be many times per second.
     /// Put your widgets into a `SidePanel`, `TopPanel`, `CentralPanel`, `Window` or `Area`.
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let mut commands = Vec::new();
+        let wants_keyboard_input = ctx.wants_keyboard_input();
+        for (modifiers, key, command) in KEYBINDINGS {
+            if wants_keyboard_input && modifiers.is_none() {
+                continue;
+            }
+            if ctx.input_mut(|i| i.consume_key(*modifiers, *key)) {
+                commands.push(*command);
+            }
+        }
+
         egui::TopBottomPanel::top("top").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
                 egui::widgets::global_dark_light_mode_switch(ui);
+                #[cfg(feature = "persistence")]
+                ui.menu_button("File", |ui| {
+                    if ui.button("Open…").clicked() {
+                        self.file_dialog.open(FileAction::Open);
+                        ui.close_menu();
+                    }
+                    if ui.button("Save As…").clicked() {
+                        self.file_dialog.open(FileAction::SaveAs);
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    ui.checkbox(&mut self.watch_file, "Reload on change")
+                        .on_hover_text("Reload the open file when another program modifies it");
+                    ui.add_enabled(
+                        self.watch_file,
+                        egui::Checkbox::new(&mut self.merge_on_reload, "Merge on reload"),
+                    )
+                    .on_hover_text(
+                        "Only add and remove the nodes and connections that changed, keeping \
+                        the layout. Otherwise the file replaces the whole graph",
+                    );
+                });
+                ui.menu_button("Export", |ui| {
+                    if ui.button("Python script…").clicked() {
+                        self.python_export.open = true;
+                        ui.close_menu();
+                    }
+                    #[cfg(feature = "persistence")]
+                    {
+                        ui.separator();
+                        if ui.button("GraphViz DOT…").clicked() {
+                            self.file_dialog.open(FileAction::ExportDot);
+                            ui.close_menu();
+                        }
+                        ui.checkbox(&mut self.dot_positions, "Keep the layout")
+                            .on_hover_text("Pin the nodes to their position, for `neato -n`");
+                        if ui.button("Mermaid…").clicked() {
+                            self.file_dialog.open(FileAction::ExportMermaid);
+                            ui.close_menu();
+                        }
+                    }
+                });
+                ui.menu_button("Edit", |ui| {
+                    let history = &self.state.history;
+                    if ui
+                        .add_enabled(history.can_undo(), egui::Button::new("Undo"))
+                        .clicked()
+                    {
+                        commands.push(AppCommand::Undo);
+                        ui.close_menu();
+                    }
+                    if ui
+                        .add_enabled(history.can_redo(), egui::Button::new("Redo"))
+                        .clicked()
+                    {
+                        commands.push(AppCommand::Redo);
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    let has_selection = !self.state.selected_nodes.is_empty();
+                    if ui
+                        .add_enabled(has_selection, egui::Button::new("Copy"))
+                        .clicked()
+                    {
+                        commands.push(AppCommand::Copy);
+                        ui.close_menu();
+                    }
+                    if ui
+                        .add_enabled(self.clipboard.is_some(), egui::Button::new("Paste"))
+                        .clicked()
+                    {
+                        commands.push(AppCommand::Paste);
+                        ui.close_menu();
+                    }
+                    if ui
+                        .add_enabled(has_selection, egui::Button::new("Duplicate"))
+                        .clicked()
+                    {
+                        commands.push(AppCommand::Duplicate);
+                        ui.close_menu();
+                    }
+                    ui.checkbox(
+                        &mut self.state.duplicate_incoming_connections,
+                        "Duplicate with inputs",
+                    )
+                    .on_hover_text("Connect the duplicates to the same inputs as the originals");
+                    if ui
+                        .add_enabled(has_selection, egui::Button::new("Group"))
+                        .clicked()
+                    {
+                        commands.push(AppCommand::GroupSelection);
+                        ui.close_menu();
+                    }
+                });
+                ui.menu_button("View", |ui| {
+                    if ui.button("Fit view").clicked() {
+                        commands.push(AppCommand::FitView);
+                        ui.close_menu();
+                    }
+                    if ui.button("Reset zoom").clicked() {
+                        commands.push(AppCommand::ResetZoom);
+                        ui.close_menu();
+                    }
+                    if ui.button("Auto layout").clicked() {
+                        commands.push(AppCommand::AutoLayout);
+                        ui.close_menu();
+                    }
+                    ui.checkbox(&mut self.state.minimap, "Minimap");
+                    ui.checkbox(
+                        &mut self.state.hover_highlight.enabled,
+                        "Highlight hovered connections",
+                    )
+                    .on_hover_text("Fade the connections unrelated to the hovered node or port");
+                    ui.horizontal(|ui| {
+                        ui.label("Low detail below");
+                        ui.add(
+                            egui::DragValue::new(&mut self.state.low_detail_width)
+                                .clamp_range(0.0..=200.0)
+                                .suffix(" px"),
+                        )
+                        .on_hover_text(
+                            "Nodes narrower than this on screen only show their title bar",
+                        );
+                    });
+                    ui.checkbox(&mut self.user_state.evaluate_sinks, "Sink results")
+                        .on_hover_text("Show the result of every node without connected outputs");
+                    ui.menu_button("Connections", |ui| {
+                        let style = &mut self.state.connection_style;
+                        ui.radio_value(style, ConnectionStyle::Bezier, "Curved");
+                        ui.radio_value(style, ConnectionStyle::Straight, "Straight");
+                        ui.radio_value(style, ConnectionStyle::Orthogonal, "Orthogonal");
+                    });
+                    ui.menu_button("Grid", |ui| {
+                        let grid = &mut self.state.grid;
+                        ui.radio_value(&mut grid.style, GridStyle::Hidden, "Hidden");
+                        ui.radio_value(&mut grid.style, GridStyle::Dots, "Dots");
+                        ui.radio_value(&mut grid.style, GridStyle::Lines, "Lines");
+                        ui.separator();
+                        ui.horizontal(|ui| {
+                            ui.label("Spacing");
+                            ui.add(
+                                egui::DragValue::new(&mut grid.spacing).clamp_range(5.0..=200.0),
+                            );
+                        });
+                        ui.checkbox(&mut grid.snap, "Snap to grid")
+                            .on_hover_text("Hold Ctrl while dragging to move freely");
+                        ui.checkbox(&mut self.state.alignment_guides.enabled, "Alignment guides")
+                            .on_hover_text("Snap dragged nodes to the edges of the other nodes");
+                    });
+                    ui.menu_button("Style", |ui| {
+                        let mut custom = self.state.style.is_some();
+                        if ui
+                            .checkbox(&mut custom, "Custom style")
+                            .on_hover_text("Otherwise the graph follows the light / dark theme")
+                            .changed()
+                        {
+                            self.state.style =
+                                custom.then(|| GraphStyle::from_egui_visuals(ui.visuals()));
+                        }
+                        if let Some(style) = &mut self.state.style {
+                            ui.add(
+                                egui::Slider::new(&mut style.wire_width, 1.0..=10.0).text("Wires"),
+                            );
+                            ui.add(
+                                egui::Slider::new(&mut style.port_radius, 2.0..=10.0).text("Ports"),
+                            );
+                            ui.add(
+                                egui::Slider::new(&mut style.node_rounding, 0.0..=12.0)
+                                    .text("Rounding"),
+                            );
+                            ui.add(
+                                egui::Slider::new(&mut style.title_bar_height, 0.0..=60.0)
+                                    .text("Title bar"),
+                            );
+                            ui.checkbox(&mut style.arrowheads, "Arrowheads");
+                            ui.checkbox(
+                                &mut style.show_widget_when_connected,
+                                "Widgets of connected inputs",
+                            );
+                            ui.horizontal(|ui| {
+                                ui.color_edit_button_srgba(&mut style.background_color);
+                                ui.label("Background");
+                            });
+                            ui.horizontal(|ui| {
+                                ui.color_edit_button_srgba(&mut style.node_color);
+                                ui.label("Nodes");
+                            });
+                            ui.horizontal(|ui| {
+                                ui.color_edit_button_srgba(&mut style.selection_color);
+                                ui.label("Selection");
+                            });
+                        }
+                    });
+                    ui.menu_button("Editing", |ui| {
+                        let mode = &mut self.state.interaction_mode;
+                        ui.radio_value(mode, InteractionMode::Editable, "Editable");
+                        ui.radio_value(mode, InteractionMode::LayoutOnly, "Layout only")
+                            .on_hover_text("Nodes can be moved, but not changed");
+                        ui.radio_value(mode, InteractionMode::ReadOnly, "Read-only");
+                    });
+                    ui.menu_button("Navigation", |ui| {
+                        let bindings = &mut self.state.input_bindings;
+                        ui.checkbox(&mut bindings.space_drag_pans, "Space + drag pans");
+                        ui.checkbox(&mut bindings.scroll_zooms, "Scroll zooms")
+                            .on_hover_text("Otherwise scrolling pans, and Ctrl + scroll zooms");
+                    });
+                    let mut timings = self.state.collect_timings;
+                    if ui.checkbox(&mut timings, "Frame timings").clicked() {
+                        commands.push(AppCommand::ToggleTimings);
+                    }
+                });
+                ui.menu_button("Statistics", |ui| self.statistics_menu(ui));
+                let unused = self.unused_components().len();
+                let problems = if unused == 0 {
+                    "Problems".to_owned()
+                } else {
+                    format!("Problems ({})", unused)
+                };
+                ui.menu_button(problems, |ui| commands.extend(self.problems_menu(ui)));
+                if ui.button("Find and replace").clicked() {
+                    commands.push(AppCommand::ToggleFindReplace);
+                }
+                if ui
+                    .button("Fit view")
+                    .on_hover_text("Show all the nodes (F)")
+                    .clicked()
+                {
+                    commands.push(AppCommand::FitView);
+                }
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    let zoom = self.state.pan_zoom.zoom;
+                    ui.label(format!("Zoom: {:.0}%", zoom * 100.0));
+                });
             });
         });
-        let graph_response = egui::CentralPanel::default()
-            .show(ctx, |ui| {
-                self.state.draw_graph_editor(
-                    ui,
-                    AllMyNodeTemplates,
-                    &mut self.user_state,
-                    Vec::default(),
-                )
-            })
-            .inner;
-        for node_response in graph_response.node_responses {
-            // Here, we ignore all other graph events. But you may find
-            // some use for them. For example, by playing a sound when a new
-            // connection is created
-            if let NodeResponse::User(user_event) = node_response {
-                match user_event {
-                    MyResponse::SetActiveNode(node) => self.user_state.active_node = Some(node),
-                    MyResponse::ClearActiveNode => self.user_state.active_node = None,
+        if !self.user_state.pinned_outputs.is_empty() {
+            egui::SidePanel::right("streams").show(ctx, |ui| {
+                if let Some(node) = self.streams_panel(ui) {
+                    commands.push(AppCommand::FocusNode(node));
+                }
+            });
+        }
+        if !self.user_state.issues.is_empty() {
+            egui::TopBottomPanel::bottom("issues").show(ctx, |ui| {
+                if let Some(node) = self.issues_panel(ui) {
+                    commands.push(AppCommand::FocusNode(node));
+                }
+            });
+        }
+        // Run the commands before drawing the dialogs and the editor, so their
+        // effects are visible this frame.
+        for command in commands {
+            self.dispatch(command);
+        }
+        #[cfg(feature = "persistence")]
+        {
+            self.file_dialog_ui(ctx);
+            self.watch_open_file(ctx);
+            if let Some(toast) = &self.toast {
+                if !toast.show(ctx) {
+                    self.toast = None;
                 }
             }
         }
-
+        self.python_export.show(ctx, &self.state.graph);
+        if self.state.collect_timings && !self.timings_window(ctx) {
+            self.dispatch(AppCommand::ToggleTimings);
+        }
+        // Changes done outside the editor can be reported along with the rest
+        // of the graph responses.
+        let mut prepend_responses = std::mem::take(&mut self.pending_responses);
+        let renamed_nodes = self
+            .relabel_dialog
+            .show(ctx, &mut self.state.graph, &self.state.selected_nodes)
+            .into_iter()
+            .map(|relabel| NodeResponse::RenamedNode {
+                node: relabel.node,
+                old_label: relabel.old_label,
+                new_label: relabel.new_label,
+            });
+        prepend_responses.extend(renamed_nodes);
+        // The connections the active node is evaluated through flow
+        self.state.active_connections.clear();
         if let Some(node) = self.user_state.active_node {
-            if self.state.graph.nodes.contains_key(node) {
-                let text = match evaluate_node(&self.state.graph, node, &mut HashMap::new()) {
-                    Ok(value) => format!("The result is: {:?}", value),
-                    Err(err) => format!("Execution error: {}", err),
+            let graph = &self.state.graph;
+            if graph.nodes.contains_key(node) {
+                let upstream = graph.upstream_nodes(node);
+                let flowing = graph
+                    .iter_connections()
+                    .filter(|(input, _)| upstream.contains(&graph[*input].node));
+                self.state.active_connections.extend(flowing);
+            }
+        }
+        let graph_panel = egui::CentralPanel::default().show(ctx, |ui| {
+            self.state.draw_graph_editor(
+                ui,
+                AllMyNodeTemplates,
+                &mut self.user_state,
+                prepend_responses,
+            )
+        });
+        self.editor_rect = graph_panel.response.rect;
+        let (pan_zoom, editor_rect) = (self.state.pan_zoom, self.editor_rect);
+        self.pointer_pos = ctx
+            .input(|i| i.pointer.hover_pos())
+            .filter(|pos| editor_rect.contains(*pos))
+            .map(|pos| pan_zoom.screen_to_graph(pos, editor_rect));
+        self.state.record_history(ctx);
+        for node_response in graph_panel.inner.node_responses {
+            self.evaluation_cache
+                .handle_response(&self.state.graph, &node_response);
+            // Here, we ignore most other graph events. But you may find
+            // some use for them. For example, by playing a sound when a new
+            // connection is created
+            match node_response {
+                NodeResponse::User(user_event) => self.dispatch(user_event.into()),
+                // The library can't copy nodes by itself
+                NodeResponse::DuplicateNode(node) => {
+                    self.state.selected_nodes = vec![node];
+                    self.dispatch(AppCommand::Duplicate);
+                }
+                // Double-clicking a node toggles whether it's evaluated
+                NodeResponse::NodeDoubleClicked(node) => {
+                    if self.user_state.active_node == Some(node) {
+                        self.dispatch(AppCommand::ClearActiveNode);
+                    } else {
+                        self.dispatch(AppCommand::SetActiveNode(node));
+                    }
+                }
+                NodeResponse::DeleteNodeFull { node, .. } => {
+                    self.forget_node(&node);
+                    self.components = None;
+                }
+                // A bound wildcard input gets a value of the type it took
+                NodeResponse::WildcardBound {
+                    param: AnyParameterId::Input(input),
+                    ..
+                } => {
+                    let typ = self.state.graph.resolved_type(AnyParameterId::Input(input));
+                    self.state.graph[input].value = match typ {
+                        MyDataType::Vec2 => MyValueType::Vec2 {
+                            value: egui::Vec2::ZERO,
+                        },
+                        _ => MyValueType::Scalar { value: 0.0 },
+                    };
+                }
+                NodeResponse::CreatedNode(_)
+                | NodeResponse::ConnectEventEnded { .. }
+                | NodeResponse::DisconnectEvent { .. }
+                | NodeResponse::ReplacedConnection { .. } => self.components = None,
+                _ => {}
+            }
+        }
+
+        self.update_sink_results();
+        self.update_validation(ctx);
+
+        if let Some(node) = self.user_state.active_node {
+            if self.state.graph.nodes.contains_key(node) {
+                // The result only changes along with the graph
+                let revision = self.evaluation_cache.revision();
+                let text = match &self.active_result {
+                    Some((evaluated, at, text)) if *evaluated == node && *at == revision => {
+                        text.clone()
+                    }
+                    _ => {
+                        let graph = &self.state.graph;
+                        let text = match self.evaluation_cache.evaluate_node(graph, node) {
+                            Ok(value) => format!("The result is: {:?}", value),
+                            Err(err) => format!("Execution error: {}", err),
+                        };
+                        self.active_result = Some((node, revision, text.clone()));
+                        text
+                    }
                 };
                 ctx.debug_painter().text(
                     egui::pos2(10.0, 35.0),
@@ -457,13 +1569,621 @@ impl eframe::App for NodeGraphExample {
     }
 }
 
+impl NodeGraphExample {
+    /// Performs an app action. This doesn't need access to the UI, the effects
+    /// become visible on the next frame.
+    pub fn dispatch(&mut self, command: AppCommand) {
+        match command {
+            AppCommand::Undo => {
+                if let Some(response) = self.state.undo() {
+                    self.history_changed(response);
+                }
+            }
+            AppCommand::Redo => {
+                if let Some(response) = self.state.redo() {
+                    self.history_changed(response);
+                }
+            }
+            AppCommand::ToggleFindReplace => {
+                self.relabel_dialog.open = !self.relabel_dialog.open;
+            }
+            AppCommand::Copy => {
+                if let Some(clipboard) = self.state.copy_selection() {
+                    self.clipboard = Some(clipboard);
+                }
+            }
+            AppCommand::Paste => {
+                if let Some(clipboard) = &self.clipboard {
+                    // Without a pointer over the editor, paste in the middle
+                    let position = self.pointer_pos.unwrap_or_else(|| {
+                        let center = self.editor_rect.center();
+                        self.state
+                            .pan_zoom
+                            .screen_to_graph(center, self.editor_rect)
+                    });
+                    let pasted = self.state.paste(clipboard, position);
+                    self.pending_responses
+                        .extend(pasted.into_iter().map(NodeResponse::CreatedNode));
+                    self.components = None;
+                }
+            }
+            AppCommand::Duplicate => {
+                let copies = self.state.duplicate_selection(egui::vec2(30.0, 30.0));
+                self.pending_responses
+                    .extend(copies.into_iter().map(NodeResponse::CreatedNode));
+                self.components = None;
+            }
+            AppCommand::GroupSelection => {
+                let selected = self.state.selected_nodes.clone();
+                self.state.group_nodes(selected, "Group".to_owned());
+            }
+            AppCommand::FitView => self.state.fit_to_screen(),
+            AppCommand::AutoLayout => {
+                self.state.auto_layout(&LayoutSettings::default());
+                self.state.fit_to_screen();
+            }
+            AppCommand::ResetZoom => {
+                let pan_zoom = &mut self.state.pan_zoom;
+                let center = self.editor_rect.size() / 2.0;
+                pan_zoom.adjust_zoom(1.0 - pan_zoom.zoom, center, MIN_ZOOM, MAX_ZOOM);
+            }
+            AppCommand::ToggleTimings => {
+                self.state.collect_timings = !self.state.collect_timings;
+            }
+            AppCommand::FocusNode(node) => self.focus_node(node),
+            AppCommand::SetActiveNode(node) => self.user_state.active_node = Some(node),
+            AppCommand::ClearActiveNode => self.user_state.active_node = None,
+            AppCommand::PinOutput(output) => {
+                if !self.user_state.pinned_outputs.contains(&output) {
+                    self.user_state.pinned_outputs.push(output);
+                }
+            }
+            AppCommand::UnpinOutput(output) => {
+                self.user_state.pinned_outputs.retain(|o| *o != output)
+            }
+            AppCommand::SelectComponent(node) => {
+                if let Some(component) = self.component_of(node) {
+                    self.state.selected_nodes = component;
+                }
+            }
+            AppCommand::DeleteComponent(node) => {
+                if let Some(component) = self.component_of(node) {
+                    for (node, _) in self.state.remove_nodes(component) {
+                        self.forget_node(&node);
+                    }
+                    self.components = None;
+                }
+            }
+        }
+    }
+
+    /// Reacts to an undo or redo: nodes may have disappeared from the graph.
+    fn history_changed(&mut self, response: NodeResponse<MyResponse, MyNodeData>) {
+        let graph = &self.state.graph;
+        self.user_state
+            .pinned_outputs
+            .retain(|output| graph.outputs.contains_key(*output));
+        if let Some(node) = self.user_state.active_node {
+            if !graph.nodes.contains_key(node) {
+                self.user_state.active_node = None;
+            }
+        }
+        self.components = None;
+        self.pending_responses.push(response);
+    }
+
+    /// Drops the references the app holds to a node that was deleted.
+    fn forget_node(&mut self, node: &Node<MyNodeData>) {
+        // Pins of a deleted node would be left dangling
+        self.user_state
+            .pinned_outputs
+            .retain(|o| !node.output_ids().any(|id| id == *o));
+        if self.user_state.active_node == Some(node.id) {
+            self.user_state.active_node = None;
+        }
+    }
+
+    fn components(&mut self) -> &[Vec<NodeId>] {
+        let graph = &self.state.graph;
+        self.components
+            .get_or_insert_with(|| graph.connected_components())
+    }
+
+    fn component_of(&mut self, node: NodeId) -> Option<Vec<NodeId>> {
+        self.components()
+            .iter()
+            .find(|component| component.contains(&node))
+            .cloned()
+    }
+
+    /// The components whose result is never looked at: they contain neither
+    /// the active node nor a pinned output. These are usually leftovers.
+    fn unused_components(&mut self) -> Vec<Vec<NodeId>> {
+        let mut observed: Vec<NodeId> = self
+            .user_state
+            .pinned_outputs
+            .iter()
+            .filter_map(|output| self.state.graph.try_get_output(*output))
+            .map(|param| param.node)
+            .collect();
+        observed.extend(self.user_state.active_node);
+        self.components()
+            .iter()
+            .filter(|component| !component.iter().any(|node| observed.contains(node)))
+            .cloned()
+            .collect()
+    }
+
+    /// Lists the unused components, offering to select or delete them.
+    fn problems_menu(&mut self, ui: &mut egui::Ui) -> Option<AppCommand> {
+        let unused = self.unused_components();
+        if unused.is_empty() {
+            ui.weak("No problems found");
+            return None;
+        }
+
+        let mut command = None;
+        ui.strong("Unused nodes");
+        for component in unused {
+            let first = &self.state.graph[component[0]];
+            ui.horizontal(|ui| {
+                if component.len() == 1 {
+                    ui.label(&first.label);
+                } else {
+                    ui.label(format!("{} and {} more", first.label, component.len() - 1));
+                }
+                if ui.small_button("Select").clicked() {
+                    command = Some(AppCommand::SelectComponent(first.id));
+                }
+                if ui.small_button("Delete").clicked() {
+                    command = Some(AppCommand::DeleteComponent(first.id));
+                }
+            });
+        }
+        command
+    }
+
+    /// Evaluates the sinks when the graph changed, if enabled. The sinks share
+    /// the evaluation cache, so the nodes upstream of several of them are
+    /// only evaluated once, and an error in one doesn't prevent evaluating the
+    /// others.
+    fn update_sink_results(&mut self) {
+        if !self.user_state.evaluate_sinks {
+            self.user_state.sink_results.clear();
+            self.sinks_revision = None;
+            return;
+        }
+        let revision = self.evaluation_cache.revision();
+        if self.sinks_revision == Some(revision) {
+            return;
+        }
+        let graph = &self.state.graph;
+        let sources: HashSet<NodeId> = graph
+            .connections
+            .values()
+            .flatten()
+            .map(|output| graph[*output].node)
+            .collect();
+        let cache = &mut self.evaluation_cache;
+        self.user_state.sink_results = graph
+            .nodes
+            .keys()
+            .filter(|node| !sources.contains(node))
+            .map(|node| {
+                let result = cache.evaluate_node(graph, node);
+                (node, result.map_err(|err| err.to_string()))
+            })
+            .collect();
+        self.sinks_revision = Some(revision);
+    }
+
+    /// The rules the graph is checked with, e.g. to add custom ones.
+    pub fn validator_mut(&mut self) -> &mut Validator {
+        self.validation_due = Some(0.0);
+        &mut self.validator
+    }
+
+    /// Validates the graph once it stopped changing for [`VALIDATION_DELAY`].
+    fn update_validation(&mut self, ctx: &egui::Context) {
+        let now = ctx.input(|i| i.time);
+        if !self.state.graph.drain_events().is_empty() {
+            self.validation_due = Some(now + VALIDATION_DELAY);
+        }
+        match self.validation_due {
+            Some(due) if due <= now => {
+                self.user_state.issues = self.validator.validate(&self.state.graph);
+                self.validation_due = None;
+            }
+            Some(due) => ctx.request_repaint_after(std::time::Duration::from_secs_f64(due - now)),
+            None => {}
+        }
+    }
+
+    /// Lists the issues found by the last validation, in a collapsible
+    /// section. Returns the node of the issue clicked, if any.
+    fn issues_panel(&mut self, ui: &mut egui::Ui) -> Option<NodeId> {
+        let graph = &self.state.graph;
+        let issues = &self.user_state.issues;
+        let mut jump_to = None;
+        egui::CollapsingHeader::new(format!("Issues ({})", issues.len()))
+            .default_open(true)
+            .show(ui, |ui| {
+                egui::ScrollArea::vertical()
+                    .max_height(150.0)
+                    .show(ui, |ui| {
+                        for issue in issues {
+                            // Nodes removed since the last validation
+                            let node = match graph.nodes.get(issue.node) {
+                                Some(node) => node,
+                                None => continue,
+                            };
+                            ui.horizontal(|ui| {
+                                let icon = match issue.severity {
+                                    Severity::Warning => "⚠",
+                                    Severity::Error => "⛔",
+                                };
+                                ui.colored_label(severity_color(issue.severity), icon);
+                                let place = match &issue.param {
+                                    Some(param) => format!("{}.{}", node.label, param),
+                                    None => node.label.clone(),
+                                };
+                                let link = ui.link(place).on_hover_text("Jump to node");
+                                if link.clicked() {
+                                    jump_to = Some(issue.node);
+                                }
+                                ui.label(&issue.message);
+                            });
+                        }
+                    });
+            });
+        jump_to
+    }
+
+    /// Lists the pinned outputs, along with their latest value. Returns the
+    /// node to jump to, if requested.
+    fn streams_panel(&mut self, ui: &mut egui::Ui) -> Option<NodeId> {
+        let graph = &self.state.graph;
+        let mut jump_to = None;
+
+        ui.heading("Streams");
+        ui.separator();
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for output in self.user_state.pinned_outputs.clone() {
+                let param = match graph.try_get_output(output) {
+                    Some(param) => param,
+                    None => continue,
+                };
+                let node = &graph[param.node];
+                let param_name = node
+                    .outputs
+                    .iter()
+                    .find(|(_, id)| *id == output)
+                    .map(|(name, _)| name.as_str())
+                    .unwrap_or_default();
+                let color = param.typ.data_type_color(&mut self.user_state);
+
+                ui.horizontal(|ui| {
+                    let (swatch, _) =
+                        ui.allocate_exact_size(egui::vec2(10.0, 10.0), egui::Sense::hover());
+                    ui.painter().rect_filled(swatch, 2.0, color);
+                    ui.label(format!("{}.{}", node.label, param_name));
+                    if ui.small_button("➡").on_hover_text("Jump to node").clicked() {
+                        jump_to = Some(param.node);
+                    }
+                });
+                let value = self.evaluation_cache.evaluate_output(graph, output).ok();
+                match value {
+                    Some(value) => ui.weak(format_value(&value)),
+                    None => ui.weak("—"),
+                };
+            }
+        });
+
+        jump_to
+    }
+
+    /// Plots the time spent drawing the editor during the last frames, as
+    /// stacked bars. Returns whether the window is still open.
+    fn timings_window(&mut self, ctx: &egui::Context) -> bool {
+        use egui::plot::{Bar, BarChart, Legend, Plot};
+
+        let timings = self.state.timings();
+        let phase_chart = |name: &str, phase: fn(&FrameTimings) -> std::time::Duration| {
+            let bars = timings
+                .iter()
+                .enumerate()
+                .map(|(frame, t)| {
+                    Bar::new(frame as f64, phase(t).as_secs_f64() * 1000.0).width(1.0)
+                })
+                .collect();
+            BarChart::new(bars).name(name)
+        };
+        let background = phase_chart("Background", |t| t.background);
+        let nodes = phase_chart("Nodes", |t| t.nodes).stack_on(&[&background]);
+        let connections =
+            phase_chart("Connections", |t| t.connections).stack_on(&[&background, &nodes]);
+        let interaction = phase_chart("Interaction", |t| t.interaction).stack_on(&[
+            &background,
+            &nodes,
+            &connections,
+        ]);
+
+        let mut open = true;
+        egui::Window::new("Frame timings")
+            .open(&mut open)
+            .default_size(egui::vec2(400.0, 200.0))
+            .show(ctx, |ui| {
+                if let Some(last) = timings.back() {
+                    ui.label(format!(
+                        "Last frame: {:.2} ms",
+                        last.total().as_secs_f64() * 1000.0
+                    ));
+                }
+                Plot::new("frame_timings")
+                    .legend(Legend::default())
+                    .allow_drag(false)
+                    .allow_zoom(false)
+                    .show(ui, |plot_ui| {
+                        for chart in [background, nodes, connections, interaction] {
+                            plot_ui.bar_chart(chart);
+                        }
+                    });
+            });
+        open
+    }
+
+    /// Shows how many nodes of each kind there are in the graph, and how many
+    /// connections between each pair of kinds.
+    fn statistics_menu(&mut self, ui: &mut egui::Ui) {
+        let graph = &self.state.graph;
+        let mut label = |template: &MyNodeTemplate| {
+            template
+                .node_finder_label(&mut self.user_state)
+                .into_owned()
+        };
+
+        let mut histogram: Vec<_> = graph
+            .histogram_by(|data| data.template)
+            .into_iter()
+            .map(|(template, count)| (label(&template), count))
+            .collect();
+        histogram.sort();
+        let mut matrix: Vec<_> = graph
+            .connection_matrix_by(|data| data.template)
+            .into_iter()
+            .map(|((from, to), count)| (label(&from), label(&to), count))
+            .collect();
+        matrix.sort();
+
+        ui.strong("Nodes");
+        if histogram.is_empty() {
+            ui.weak("The graph is empty");
+        }
+        for (template, count) in histogram {
+            ui.label(format!("{}: {}", template, count));
+        }
+        ui.separator();
+        ui.strong("Connections");
+        if matrix.is_empty() {
+            ui.weak("No connections");
+        }
+        for (from, to, count) in matrix {
+            ui.label(format!("{} → {}: {}", from, to, count));
+        }
+    }
+
+    /// Selects the given node and pans the editor so it's in the center.
+    fn focus_node(&mut self, node: NodeId) {
+        if self.state.graph.nodes.contains_key(node) {
+            self.state.center_on_node(node);
+            self.state.selected_nodes = vec![node];
+        }
+    }
+}
+
 type OutputsCache = HashMap<OutputId, MyValueType>;
 
-/// Recursively evaluates all dependencies of this node, then evaluates the node itself.
+/// Formats a value for display, e.g. in the streams panel.
+fn format_value(value: &MyValueType) -> String {
+    match value {
+        MyValueType::Scalar { value } => format!("{:.3}", value),
+        MyValueType::Vec2 { value } => format!("({:.3}, {:.3})", value.x, value.y),
+    }
+}
+
+/// The values of the outputs evaluated during the previous frames. Changes to
+/// the graph are reported with [`EvaluationCache::handle_response`]: the
+/// outputs of the changed nodes and of the nodes downstream of them are
+/// dropped, so only those are evaluated again.
+#[derive(Default)]
+pub struct EvaluationCache {
+    outputs: OutputsCache,
+    /// The nodes changed since the last evaluation.
+    dirty_nodes: HashSet<NodeId>,
+    /// Counts the changes to the graph reported so far.
+    revision: u64,
+}
+
+impl EvaluationCache {
+    /// The number of changes to the graph reported so far.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Marks a node as changed: its outputs, and the ones downstream of them,
+    /// will be evaluated again.
+    pub fn mark_dirty(&mut self, node: NodeId) {
+        self.dirty_nodes.insert(node);
+        self.revision += 1;
+    }
+
+    /// Forgets all the evaluated outputs, e.g. when the whole graph is
+    /// replaced.
+    pub fn clear(&mut self) {
+        self.outputs.clear();
+        self.dirty_nodes.clear();
+        self.revision += 1;
+    }
+
+    /// Marks the nodes affected by a response of the editor as changed.
+    pub fn handle_response(
+        &mut self,
+        graph: &MyGraph,
+        response: &NodeResponse<MyResponse, MyNodeData>,
+    ) {
+        match response {
+            NodeResponse::ValueChanged { node, .. }
+            | NodeResponse::CreatedNode(node)
+            | NodeResponse::AddedInput { node, .. }
+            | NodeResponse::RemovedInput { node, .. }
+            | NodeResponse::DeleteNodeFull { node_id: node, .. } => self.mark_dirty(*node),
+            NodeResponse::ConnectEventEnded { input, .. }
+            | NodeResponse::DisconnectEvent { input, .. }
+            | NodeResponse::ReplacedConnection { input, .. } => {
+                // The input is gone along with its node when the node was
+                // deleted, which `DeleteNodeFull` reports
+                if let Some(param) = graph.try_get_input(*input) {
+                    self.mark_dirty(param.node);
+                }
+            }
+            NodeResponse::Undone { .. } | NodeResponse::Redone { .. } => self.clear(),
+            _ => {}
+        }
+    }
+
+    /// Evaluates a node like [`evaluate_node`], reusing the outputs that are
+    /// still valid. The node itself is always evaluated.
+    pub fn evaluate_node(&mut self, graph: &MyGraph, node: NodeId) -> anyhow::Result<MyValueType> {
+        self.invalidate(graph);
+        evaluate_node(graph, node, &mut self.outputs)
+    }
+
+    /// The value of an output, only evaluating its node if it isn't cached.
+    pub fn evaluate_output(
+        &mut self,
+        graph: &MyGraph,
+        output: OutputId,
+    ) -> anyhow::Result<MyValueType> {
+        self.invalidate(graph);
+        if !self.outputs.contains_key(&output) {
+            evaluate_node(graph, graph[output].node, &mut self.outputs)?;
+        }
+        cached_output(&self.outputs, output)
+    }
+
+    /// Drops the outputs of the dirty nodes and of the nodes downstream of
+    /// them, along with the outputs that no longer exist.
+    fn invalidate(&mut self, graph: &MyGraph) {
+        if self.dirty_nodes.is_empty() {
+            return;
+        }
+        let mut downstream: HashMap<NodeId, Vec<NodeId>> = HashMap::new();
+        for (input, outputs) in graph.connections.iter() {
+            for output in outputs {
+                downstream
+                    .entry(graph[*output].node)
+                    .or_default()
+                    .push(graph[input].node);
+            }
+        }
+        let mut work_list: Vec<NodeId> = self.dirty_nodes.drain().collect();
+        let mut reached: HashSet<NodeId> = work_list.iter().copied().collect();
+        while let Some(node) = work_list.pop() {
+            if let Some(node) = graph.nodes.get(node) {
+                for output in node.output_ids() {
+                    self.outputs.remove(&output);
+                }
+            }
+            for next in downstream.get(&node).into_iter().flatten() {
+                if reached.insert(*next) {
+                    work_list.push(*next);
+                }
+            }
+        }
+        self.outputs
+            .retain(|output, _| graph.outputs.contains_key(*output));
+    }
+}
+
+/// Evaluates all dependencies of this node, then evaluates the node itself.
+///
+/// The dependencies are evaluated one after the other in topological order,
+/// so long chains of nodes don't overflow the stack. Fails, naming the nodes
+/// involved, if the dependencies form a cycle.
 pub fn evaluate_node(
     graph: &MyGraph,
     node_id: NodeId,
     outputs_cache: &mut OutputsCache,
+) -> anyhow::Result<MyValueType> {
+    let mut value = None;
+    // The node itself comes last
+    for node in evaluation_order(graph, node_id, outputs_cache)? {
+        value = Some(evaluate_single_node(graph, node, outputs_cache)?);
+    }
+    Ok(value.expect("The evaluated node is part of the order"))
+}
+
+/// Lists the nodes to evaluate so that `node_id` can be evaluated: the node
+/// itself and its dependencies whose outputs aren't cached yet, each one
+/// after its own dependencies.
+pub(crate) fn evaluation_order(
+    graph: &MyGraph,
+    node_id: NodeId,
+    outputs_cache: &OutputsCache,
+) -> anyhow::Result<Vec<NodeId>> {
+    enum Visit {
+        Enter(NodeId),
+        Exit(NodeId),
+    }
+
+    let mut order = Vec::new();
+    let mut visited = HashSet::new();
+    // The nodes being visited, from `node_id` down to the current one. A
+    // dependency among them closes a cycle.
+    let mut path = Vec::new();
+    let mut in_progress = HashSet::new();
+    let mut work_list = vec![Visit::Enter(node_id)];
+    while let Some(visit) = work_list.pop() {
+        match visit {
+            Visit::Enter(node) => {
+                if in_progress.contains(&node) {
+                    let start = path.iter().position(|other| *other == node).unwrap_or(0);
+                    let cycle: Vec<String> = path[start..]
+                        .iter()
+                        .chain(std::iter::once(&node))
+                        .map(|node| format!("'{}'", graph[*node].label))
+                        .collect();
+                    anyhow::bail!("The nodes {} form a cycle", cycle.join(" → "))
+                }
+                if !visited.insert(node) {
+                    continue;
+                }
+                path.push(node);
+                in_progress.insert(node);
+                work_list.push(Visit::Exit(node));
+                for (_, input) in &graph[node].inputs {
+                    for output in graph.connections(*input) {
+                        if !outputs_cache.contains_key(output) {
+                            work_list.push(Visit::Enter(graph[*output].node));
+                        }
+                    }
+                }
+            }
+            Visit::Exit(node) => {
+                path.pop();
+                in_progress.remove(&node);
+                order.push(node);
+            }
+        }
+    }
+    Ok(order)
+}
+
+/// Evaluates a single node, whose dependencies must already be evaluated.
+fn evaluate_single_node(
+    graph: &MyGraph,
+    node_id: NodeId,
+    outputs_cache: &mut OutputsCache,
 ) -> anyhow::Result<MyValueType> {
     // To solve a similar problem as creating node types above, we define an
     // Evaluator as a convenience. It may be overkill for this small example,
@@ -484,8 +2204,8 @@ pub fn evaluate_node(
             }
         }
         fn evaluate_input(&mut self, name: &str) -> anyhow::Result<MyValueType> {
-            // Calling `evaluate_input` recursively evaluates other nodes in the
-            // graph until the input value for a paramater has been computed.
+            // The nodes connected to the input were evaluated before this
+            // one, so `evaluate_input` finds their outputs in the cache.
             evaluate_input(self.graph, self.node_id, name, self.outputs_cache)
         }
         fn populate_output(
@@ -508,11 +2228,39 @@ pub fn evaluate_node(
             // the graphs, you can come up with your own evaluation semantics!
             populate_output(self.graph, self.outputs_cache, self.node_id, name, value)
         }
+        // Names the input whose value couldn't be cast
+        fn input_error(&self, name: &str, err: anyhow::Error) -> anyhow::Error {
+            anyhow::anyhow!(
+                "Input '{}' of node '{}': {}",
+                name,
+                self.graph[self.node_id].label,
+                err
+            )
+        }
         fn input_vector(&mut self, name: &str) -> anyhow::Result<egui::Vec2> {
-            self.evaluate_input(name)?.try_to_vec2()
+            let value = self.evaluate_input(name)?;
+            value
+                .try_to_vec2()
+                .map_err(|err| self.input_error(name, err))
         }
         fn input_scalar(&mut self, name: &str) -> anyhow::Result<f32> {
-            self.evaluate_input(name)?.try_to_scalar()
+            let value = self.evaluate_input(name)?;
+            value
+                .try_to_scalar()
+                .map_err(|err| self.input_error(name, err))
+        }
+        fn connected_scalars(&mut self, name: &str) -> anyhow::Result<Vec<f32>> {
+            let input_id = self.graph[self.node_id].get_input(name)?;
+            self.graph
+                .connections(input_id)
+                .iter()
+                .map(|output| {
+                    let value = cached_output(self.outputs_cache, *output)?;
+                    value
+                        .try_to_scalar()
+                        .map_err(|err| self.input_error(name, err))
+                })
+                .collect()
         }
         fn output_vector(&mut self, name: &str, value: egui::Vec2) -> anyhow::Result<MyValueType> {
             self.populate_output(name, MyValueType::Vec2 { value })
@@ -535,6 +2283,18 @@ pub fn evaluate_node(
             let b = evaluator.input_scalar("B")?;
             evaluator.output_scalar("out", a - b)
         }
+        MyNodeTemplate::SumScalars => {
+            let sum = evaluator.connected_scalars("values")?.into_iter().sum();
+            evaluator.output_scalar("out", sum)
+        }
+        MyNodeTemplate::MultiplyScalars => {
+            // The factors are all the inputs of the node, however many
+            let mut product = 1.0;
+            for (name, _) in &node.inputs {
+                product *= evaluator.input_scalar(name)?;
+            }
+            evaluator.output_scalar("out", product)
+        }
         MyNodeTemplate::VectorTimesScalar => {
             let scalar = evaluator.input_scalar("scalar")?;
             let vector = evaluator.input_vector("vector")?;
@@ -559,6 +2319,10 @@ pub fn evaluate_node(
             let value = evaluator.input_scalar("value")?;
             evaluator.output_scalar("out", value)
         }
+        MyNodeTemplate::Passthrough => {
+            let value = evaluator.evaluate_input("value")?;
+            evaluator.populate_output("out", value)
+        }
     }
 }
 
@@ -569,41 +2333,607 @@ fn populate_output(
     param_name: &str,
     value: MyValueType,
 ) -> anyhow::Result<MyValueType> {
+    // Report the node that introduced a bad value, instead of letting it
+    // silently propagate through the rest of the graph.
+    if !value.is_finite() {
+        anyhow::bail!(
+            "Node '{}' ({:?}) produced a non-finite value: {:?}",
+            graph[node_id].label,
+            node_id,
+            value
+        )
+    }
     let output_id = graph[node_id].get_output(param_name)?;
     outputs_cache.insert(output_id, value);
     Ok(value)
 }
 
+// Looks up the value of an output, evaluated along with its node
+fn cached_output(outputs_cache: &OutputsCache, output_id: OutputId) -> anyhow::Result<MyValueType> {
+    outputs_cache
+        .get(&output_id)
+        .copied()
+        .ok_or_else(|| anyhow::anyhow!("Output {:?} was not evaluated", output_id))
+}
+
 // Evaluates the input value of
 fn evaluate_input(
     graph: &MyGraph,
     node_id: NodeId,
     param_name: &str,
-    outputs_cache: &mut OutputsCache,
+    outputs_cache: &OutputsCache,
 ) -> anyhow::Result<MyValueType> {
     let input_id = graph[node_id].get_input(param_name)?;
 
     // The output of another node is connected.
     if let Some(other_output_id) = graph.connection(input_id) {
-        // The value was already computed due to the evaluation of some other
-        // node. We simply return value from the cache.
-        if let Some(other_value) = outputs_cache.get(&other_output_id) {
-            Ok(*other_value)
+        cached_output(outputs_cache, other_output_id)
+    }
+    // No existing connection, take the inline value instead.
+    else {
+        let value = graph[input_id].value;
+        if !value.is_finite() {
+            anyhow::bail!(
+                "Input '{}' of node '{}' ({:?}) has a non-finite value: {:?}",
+                param_name,
+                graph[node_id].label,
+                node_id,
+                value
+            )
+        }
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add_node(graph: &mut MyGraph, template: MyNodeTemplate) -> NodeId {
+        graph.add_node(
+            template.node_graph_label(&mut MyGraphState::default()),
+            MyNodeData { template },
+            |graph, node_id| template.build_node(graph, &mut MyGraphState::default(), node_id),
+        )
+    }
+
+    fn connect(graph: &mut MyGraph, from: NodeId, to: NodeId, input: &str) {
+        let output = graph[from].get_output("out").unwrap();
+        let input = graph[to].get_input(input).unwrap();
+        graph.add_connection(output, input);
+    }
+
+    fn set_scalar(graph: &mut MyGraph, node: NodeId, input: &str, value: f32) {
+        let input = graph[node].get_input(input).unwrap();
+        graph[input].value = MyValueType::Scalar { value };
+    }
+
+    /// Builds `(a + b) - c` where every operand is a `MakeScalar` node.
+    fn add_subtract_chain(graph: &mut MyGraph) -> [NodeId; 5] {
+        let a = add_node(graph, MyNodeTemplate::MakeScalar);
+        let b = add_node(graph, MyNodeTemplate::MakeScalar);
+        let c = add_node(graph, MyNodeTemplate::MakeScalar);
+        let add = add_node(graph, MyNodeTemplate::AddScalar);
+        let subtract = add_node(graph, MyNodeTemplate::SubtractScalar);
+        connect(graph, a, add, "A");
+        connect(graph, b, add, "B");
+        connect(graph, add, subtract, "A");
+        connect(graph, c, subtract, "B");
+        [a, b, c, add, subtract]
+    }
+
+    #[test]
+    fn test_nan_input_reports_source_node() {
+        let mut graph = MyGraph::new();
+        let [a, b, c, _, subtract] = add_subtract_chain(&mut graph);
+        set_scalar(&mut graph, a, "value", 1.0);
+        set_scalar(&mut graph, b, "value", f32::NAN);
+        set_scalar(&mut graph, c, "value", 2.0);
+
+        let err = evaluate_node(&graph, subtract, &mut HashMap::new()).unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains(&format!("{:?}", b)), "{}", message);
+        assert!(!message.contains(&format!("{:?}", subtract)), "{}", message);
+    }
+
+    #[test]
+    fn test_overflow_reports_source_node() {
+        let mut graph = MyGraph::new();
+        let [a, b, c, add, subtract] = add_subtract_chain(&mut graph);
+        set_scalar(&mut graph, a, "value", f32::MAX);
+        set_scalar(&mut graph, b, "value", f32::MAX);
+        set_scalar(&mut graph, c, "value", 1.0);
+
+        let err = evaluate_node(&graph, subtract, &mut HashMap::new()).unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains(&format!("{:?}", add)), "{}", message);
+    }
+
+    #[test]
+    fn test_finite_chain_evaluates() {
+        let mut graph = MyGraph::new();
+        let [a, b, c, _, subtract] = add_subtract_chain(&mut graph);
+        set_scalar(&mut graph, a, "value", 1.0);
+        set_scalar(&mut graph, b, "value", 2.0);
+        set_scalar(&mut graph, c, "value", 4.0);
+
+        let value = evaluate_node(&graph, subtract, &mut HashMap::new()).unwrap();
+        assert_eq!(value.try_to_scalar().unwrap(), -1.0);
+    }
+
+    #[test]
+    fn test_sum_of_wide_input() {
+        let mut graph = MyGraph::new();
+        let [a, b, c, add, _] = add_subtract_chain(&mut graph);
+        let sum = add_node(&mut graph, MyNodeTemplate::SumScalars);
+        set_scalar(&mut graph, a, "value", 1.0);
+        set_scalar(&mut graph, b, "value", 2.0);
+        set_scalar(&mut graph, c, "value", 4.0);
+        let result = |graph: &MyGraph| {
+            evaluate_node(graph, sum, &mut HashMap::new())
+                .unwrap()
+                .try_to_scalar()
+                .unwrap()
+        };
+        assert_eq!(result(&graph), 0.0);
+
+        for node in [add, c, a] {
+            connect(&mut graph, node, sum, "values");
         }
-        // This is the first time encountering this node, so we need to
-        // recursively evaluate it.
-        else {
-            // Calling this will populate the cache
-            evaluate_node(graph, graph[other_output_id].node, outputs_cache)?;
+        assert_eq!(result(&graph), 3.0 + 4.0 + 1.0);
+    }
 
-            // Now that we know the value is cached, return it
-            Ok(*outputs_cache
-                .get(&other_output_id)
-                .expect("Cache should be populated"))
+    #[test]
+    fn test_product_of_added_factors() {
+        let mut graph = MyGraph::new();
+        let product = add_node(&mut graph, MyNodeTemplate::MultiplyScalars);
+        set_scalar(&mut graph, product, "factor", 2.0);
+        set_scalar(&mut graph, product, "factor 2", 3.0);
+        let (name, typ, value, kind) = graph[product]
+            .user_data
+            .can_add_input(product, &graph, &mut MyGraphState::default())
+            .unwrap();
+        let name = graph[product].unique_input_name(&name);
+        assert_eq!(name, "factor 3");
+        graph.add_input_param(product, name, typ, value, kind, true);
+        set_scalar(&mut graph, product, "factor 3", 4.0);
+
+        let value = evaluate_node(&graph, product, &mut HashMap::new()).unwrap();
+        assert_eq!(value.try_to_scalar().unwrap(), 24.0);
+    }
+
+    #[test]
+    fn test_long_chain_evaluates() {
+        let mut graph = MyGraph::new();
+        let first = add_node(&mut graph, MyNodeTemplate::MakeScalar);
+        set_scalar(&mut graph, first, "value", 3.0);
+        let mut last = first;
+        for _ in 0..10_000 {
+            let next = add_node(&mut graph, MyNodeTemplate::MakeScalar);
+            connect(&mut graph, last, next, "value");
+            last = next;
         }
+
+        let mut outputs_cache = HashMap::new();
+        let value = evaluate_node(&graph, last, &mut outputs_cache).unwrap();
+        assert_eq!(value.try_to_scalar().unwrap(), 3.0);
+        assert_eq!(outputs_cache.len(), 10_001);
     }
-    // No existing connection, take the inline value instead.
-    else {
-        Ok(graph[input_id].value)
+
+    #[test]
+    fn test_cycle_reports_nodes() {
+        let mut graph = MyGraph::new();
+        let [a, b, c, add, subtract] = add_subtract_chain(&mut graph);
+        graph[add].label = "Adder".to_owned();
+        graph[subtract].label = "Subtracter".to_owned();
+        graph[c].label = "Feedback".to_owned();
+        connect(&mut graph, subtract, c, "value");
+        set_scalar(&mut graph, a, "value", 1.0);
+        set_scalar(&mut graph, b, "value", 2.0);
+
+        let err = evaluate_node(&graph, subtract, &mut HashMap::new()).unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("cycle"), "{}", message);
+        assert!(message.contains("'Subtracter'"), "{}", message);
+        assert!(message.contains("'Feedback'"), "{}", message);
+        assert!(!message.contains("'Adder'"), "{}", message);
+
+        // Nodes upstream of the cycle still evaluate
+        let value = evaluate_node(&graph, add, &mut HashMap::new()).unwrap();
+        assert_eq!(value.try_to_scalar().unwrap(), 3.0);
+    }
+
+    #[test]
+    fn test_evaluation_cache_follows_changes() {
+        let mut graph = MyGraph::new();
+        let [a, b, c, add, subtract] = add_subtract_chain(&mut graph);
+        set_scalar(&mut graph, a, "value", 1.0);
+        set_scalar(&mut graph, b, "value", 2.0);
+        set_scalar(&mut graph, c, "value", 4.0);
+        let mut cache = EvaluationCache::default();
+        let result = |cache: &mut EvaluationCache, graph: &MyGraph| {
+            cache
+                .evaluate_node(graph, subtract)
+                .unwrap()
+                .try_to_scalar()
+                .unwrap()
+        };
+        assert_eq!(result(&mut cache, &graph), -1.0);
+
+        // Unreported changes are not seen
+        set_scalar(&mut graph, a, "value", 5.0);
+        set_scalar(&mut graph, c, "value", 1.0);
+        assert_eq!(result(&mut cache, &graph), -1.0);
+
+        // Only the changed node and the ones downstream are evaluated again
+        let input = graph[c].get_input("value").unwrap();
+        cache.handle_response(&graph, &NodeResponse::ValueChanged { node: c, input });
+        assert_eq!(cache.revision(), 1);
+        assert_eq!(result(&mut cache, &graph), 2.0);
+        let input = graph[a].get_input("value").unwrap();
+        cache.handle_response(&graph, &NodeResponse::ValueChanged { node: a, input });
+        assert_eq!(result(&mut cache, &graph), 6.0);
+
+        // Disconnecting an input invalidates its node
+        let input = graph[add].get_input("B").unwrap();
+        let output = graph.remove_connection(input).unwrap();
+        cache.handle_response(&graph, &NodeResponse::DisconnectEvent { output, input });
+        assert_eq!(result(&mut cache, &graph), 4.0);
+        assert_eq!(cache.revision(), 3);
+
+        // A node deletion drops its outputs
+        let output = graph[c].get_output("out").unwrap();
+        assert_eq!(
+            cache
+                .evaluate_output(&graph, output)
+                .unwrap()
+                .try_to_scalar()
+                .unwrap(),
+            1.0
+        );
+        let (node, disconnected) = graph.remove_node(c);
+        for (input, output) in disconnected {
+            cache.handle_response(&graph, &NodeResponse::DisconnectEvent { output, input });
+        }
+        cache.handle_response(&graph, &NodeResponse::DeleteNodeFull { node_id: c, node });
+        assert_eq!(result(&mut cache, &graph), 5.0);
+        assert!(!cache.outputs.contains_key(&output));
+    }
+
+    #[test]
+    fn test_cast_error_names_input() {
+        let mut graph = MyGraph::new();
+        let vector = add_node(&mut graph, MyNodeTemplate::MakeVector);
+        let scale = add_node(&mut graph, MyNodeTemplate::VectorTimesScalar);
+        graph[scale].label = "Scale".to_owned();
+        // The editor refuses connections between different types, but the
+        // graph doesn't
+        connect(&mut graph, vector, scale, "scalar");
+
+        let err = evaluate_node(&graph, scale, &mut HashMap::new()).unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("'scalar'"), "{}", message);
+        assert!(message.contains("'Scale'"), "{}", message);
+        assert!(message.contains("Invalid cast"), "{}", message);
+    }
+
+    #[test]
+    fn test_sink_results() {
+        let mut app = NodeGraphExample::default();
+        let graph = &mut app.state.graph;
+        let [a, b, c, _, subtract] = add_subtract_chain(graph);
+        set_scalar(graph, a, "value", 1.0);
+        set_scalar(graph, b, "value", 2.0);
+        set_scalar(graph, c, "value", 4.0);
+        let vector = add_node(graph, MyNodeTemplate::MakeVector);
+        let scale = add_node(graph, MyNodeTemplate::VectorTimesScalar);
+        connect(graph, vector, scale, "scalar");
+        app.update_sink_results();
+        assert!(app.user_state.sink_results.is_empty());
+
+        // An error in one sink doesn't prevent evaluating the others
+        app.user_state.evaluate_sinks = true;
+        app.update_sink_results();
+        let results = &app.user_state.sink_results;
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[&subtract], Ok(MyValueType::Scalar { value: -1.0 }));
+        assert!(results[&scale].as_ref().unwrap_err().contains("'scalar'"));
+
+        // Results follow the changes reported to the cache
+        let input = app.state.graph[c].get_input("value").unwrap();
+        set_scalar(&mut app.state.graph, c, "value", 0.0);
+        app.update_sink_results();
+        assert_eq!(
+            app.user_state.sink_results[&subtract],
+            Ok(MyValueType::Scalar { value: -1.0 })
+        );
+        let response = NodeResponse::ValueChanged { node: c, input };
+        app.evaluation_cache
+            .handle_response(&app.state.graph, &response);
+        app.update_sink_results();
+        assert_eq!(
+            app.user_state.sink_results[&subtract],
+            Ok(MyValueType::Scalar { value: 3.0 })
+        );
+    }
+
+    #[cfg(feature = "persistence")]
+    #[test]
+    fn test_graph_file_round_trip() {
+        let (mut app, nodes) = app_with_nodes(&[egui::pos2(0.0, 0.0), egui::pos2(300.0, 50.0)]);
+        let output = app.state.graph[nodes[0]].get_output("out").unwrap();
+        let input = app.state.graph[nodes[1]].get_input("value").unwrap();
+        app.state.graph.add_connection(output, input);
+        app.state
+            .reroutes
+            .insert((input, output), vec![egui::pos2(150.0, 80.0)]);
+        app.state.pan_zoom.zoom = 1.5;
+
+        let path = std::env::temp_dir().join("egui_node_graph_example_round_trip.ron");
+        file::save_graph(&path, &app.state).unwrap();
+        let loaded = file::load_graph(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(!loaded.repaired);
+        let state = loaded.state;
+        assert_eq!(state.node_order, app.state.node_order);
+        assert_eq!(state.node_positions[nodes[1]], egui::pos2(300.0, 50.0));
+        assert_eq!(state.graph.connections(input), [output]);
+        assert_eq!(state.reroutes[&(input, output)], [egui::pos2(150.0, 80.0)]);
+        assert_eq!(state.pan_zoom.zoom, 1.5);
+    }
+
+    #[cfg(feature = "persistence")]
+    #[test]
+    fn test_graph_file_validation() {
+        let (mut app, nodes) = app_with_nodes(&[egui::pos2(0.0, 0.0), egui::pos2(300.0, 50.0)]);
+        let load = file::graph_from_blob;
+
+        assert!(load("(version: ").is_err());
+        let newer = app.state.serialize_versioned(STATE_VERSION + 1).unwrap();
+        let err = load(&newer).err().unwrap();
+        assert!(err.contains("version"), "{}", err);
+
+        // Positions and order referring to missing nodes are repaired
+        app.state.graph.remove_node(nodes[0]);
+        let blob = app.state.serialize_versioned(STATE_VERSION).unwrap();
+        let loaded = load(&blob).ok().unwrap();
+        assert!(loaded.repaired);
+        assert_eq!(loaded.state.node_order, [nodes[1]]);
+        assert!(!loaded.state.node_positions.contains_key(nodes[0]));
+
+        // An inconsistent graph is rejected
+        let input = app.state.graph[nodes[1]].get_input("value").unwrap();
+        app.state.graph.inputs.remove(input);
+        let blob = app.state.serialize_versioned(STATE_VERSION).unwrap();
+        let err = load(&blob).err().unwrap();
+        assert!(err.contains("inconsistent"), "{}", err);
+    }
+
+    #[cfg(feature = "persistence")]
+    #[test]
+    fn test_reload_watched_file() {
+        let (mut app, nodes) = app_with_nodes(&[egui::pos2(0.0, 0.0), egui::pos2(300.0, 50.0)]);
+        let path = std::env::temp_dir().join("egui_node_graph_example_watched.ron");
+        file::save_graph(&path, &app.state).unwrap();
+        let watcher = FileWatcher::new(path.clone(), egui::Context::default());
+
+        // Another program adds a node connected to the first one
+        let mut changed = file::load_graph(&path).unwrap().state;
+        let added = changed.add_node(
+            &MyNodeTemplate::AddScalar,
+            egui::pos2(900.0, 900.0),
+            &mut MyGraphState::default(),
+        );
+        let output = changed.graph[nodes[0]].get_output("out").unwrap();
+        let input = changed.graph[added].get_input("A").unwrap();
+        changed.graph.add_connection(output, input);
+        file::save_graph(&path, &changed).unwrap();
+        let reloaded = (0..50)
+            .find_map(|_| {
+                std::thread::sleep(std::time::Duration::from_millis(100));
+                watcher.poll()
+            })
+            .expect("the change is reported");
+        drop(watcher);
+
+        // Merged next to its neighbour, while the user's layout is kept
+        app.state.node_positions[nodes[1]] = egui::pos2(-100.0, -100.0);
+        app.merge_state(reloaded.ok().unwrap().state);
+        assert_eq!(app.state.graph.nodes.len(), 3);
+        assert_eq!(
+            app.state.node_positions[nodes[1]],
+            egui::pos2(-100.0, -100.0)
+        );
+        let added = app.state.graph.iter_nodes().find(|id| !nodes.contains(id));
+        let added = added.unwrap();
+        assert_eq!(app.state.node_positions[added], egui::pos2(250.0, 0.0));
+        let input = app.state.graph[added].get_input("A").unwrap();
+        assert_eq!(app.state.graph.connections(input), [output]);
+
+        // A broken file is reported, and doesn't reach the graph
+        let watcher = FileWatcher::new(path.clone(), egui::Context::default());
+        std::fs::write(&path, "(version: ").unwrap();
+        let reloaded = (0..50).find_map(|_| {
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            watcher.poll()
+        });
+        std::fs::remove_file(&path).unwrap();
+        assert!(reloaded.unwrap().is_err());
+    }
+
+    #[test]
+    fn test_sanitize_and_non_finite_casts() {
+        let mut value = MyValueType::Scalar {
+            value: f32::INFINITY,
+        };
+        assert!(value.try_to_scalar().is_err());
+        value.sanitize();
+        assert_eq!(value.try_to_scalar().unwrap(), 0.0);
+
+        let mut value = MyValueType::Vec2 {
+            value: egui::vec2(f32::NAN, 1.0),
+        };
+        assert!(value.try_to_vec2().is_err());
+        value.sanitize();
+        assert_eq!(value.try_to_vec2().unwrap(), egui::vec2(0.0, 1.0));
+    }
+
+    fn app_with_nodes(positions: &[egui::Pos2]) -> (NodeGraphExample, Vec<NodeId>) {
+        let mut app = NodeGraphExample {
+            editor_rect: egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(800.0, 600.0)),
+            ..Default::default()
+        };
+        let nodes = positions
+            .iter()
+            .map(|pos| {
+                app.state
+                    .add_node(&MyNodeTemplate::MakeScalar, *pos, &mut app.user_state)
+            })
+            .collect();
+        (app, nodes)
+    }
+
+    #[test]
+    fn test_dispatch_view_commands() {
+        let (mut app, nodes) = app_with_nodes(&[egui::pos2(0.0, 0.0), egui::pos2(200.0, 100.0)]);
+
+        // The view itself changes when the editor is next drawn
+        app.dispatch(AppCommand::FocusNode(nodes[1]));
+        assert_eq!(app.state.selected_nodes, vec![nodes[1]]);
+
+        // Resetting the zoom keeps the center of the view in place
+        app.state.pan_zoom = PanZoom {
+            pan: egui::vec2(0.0, 100.0),
+            zoom: 2.0,
+        };
+        app.dispatch(AppCommand::ResetZoom);
+        assert_eq!(app.state.pan_zoom.pan, egui::vec2(200.0, 200.0));
+        assert_eq!(app.state.pan_zoom.zoom, 1.0);
+
+        app.dispatch(AppCommand::ToggleFindReplace);
+        assert!(app.relabel_dialog.open);
+        app.dispatch(AppCommand::ToggleFindReplace);
+        assert!(!app.relabel_dialog.open);
+
+        app.dispatch(AppCommand::ToggleTimings);
+        assert!(app.state.collect_timings);
+
+        // The nodes are not connected, so they end up in the same layer
+        app.dispatch(AppCommand::AutoLayout);
+        let positions = &app.state.node_positions;
+        assert_eq!(positions[nodes[0]].x, positions[nodes[1]].x);
+        assert!(positions[nodes[0]].y != positions[nodes[1]].y);
+    }
+
+    #[test]
+    fn test_dispatch_user_state_commands() {
+        let (mut app, nodes) = app_with_nodes(&[egui::Pos2::ZERO]);
+        let output = app.state.graph[nodes[0]].get_output("out").unwrap();
+
+        app.dispatch(MyResponse::SetActiveNode(nodes[0]).into());
+        assert_eq!(app.user_state.active_node, Some(nodes[0]));
+        app.dispatch(AppCommand::ClearActiveNode);
+        assert_eq!(app.user_state.active_node, None);
+
+        app.dispatch(AppCommand::PinOutput(output));
+        app.dispatch(AppCommand::PinOutput(output));
+        assert_eq!(app.user_state.pinned_outputs, vec![output]);
+        app.dispatch(AppCommand::UnpinOutput(output));
+        assert!(app.user_state.pinned_outputs.is_empty());
+    }
+
+    #[test]
+    fn test_unused_components() {
+        let (mut app, nodes) = app_with_nodes(&[egui::Pos2::ZERO; 3]);
+        connect(&mut app.state.graph, nodes[0], nodes[1], "value");
+        let output = app.state.graph[nodes[0]].get_output("out").unwrap();
+        app.dispatch(AppCommand::PinOutput(output));
+        app.components = None;
+
+        assert_eq!(app.unused_components(), vec![vec![nodes[2]]]);
+
+        app.dispatch(AppCommand::SelectComponent(nodes[1]));
+        assert_eq!(app.state.selected_nodes, vec![nodes[0], nodes[1]]);
+
+        app.dispatch(AppCommand::DeleteComponent(nodes[0]));
+        assert_eq!(app.state.graph.iter_nodes().collect::<Vec<_>>(), [nodes[2]]);
+        assert!(app.user_state.pinned_outputs.is_empty());
+        assert_eq!(app.unused_components(), vec![vec![nodes[2]]]);
+    }
+
+    #[test]
+    fn test_dispatch_undo_redo() {
+        let (mut app, nodes) = app_with_nodes(&[egui::Pos2::ZERO]);
+        let ctx = egui::Context::default();
+        app.state.record_history(&ctx);
+        let added = app.state.add_node(
+            &MyNodeTemplate::AddScalar,
+            egui::Pos2::ZERO,
+            &mut app.user_state,
+        );
+        app.state.record_history(&ctx);
+        app.dispatch(AppCommand::SetActiveNode(added));
+
+        app.dispatch(AppCommand::Undo);
+        assert!(!app.state.graph.nodes.contains_key(added));
+        assert_eq!(app.user_state.active_node, None);
+        assert!(app.state.graph.nodes.contains_key(nodes[0]));
+
+        app.dispatch(AppCommand::Redo);
+        assert!(app.state.graph.nodes.contains_key(added));
+        assert_eq!(app.pending_responses.len(), 2);
+    }
+
+    #[test]
+    fn test_dispatch_copy_paste() {
+        let (mut app, nodes) = app_with_nodes(&[egui::pos2(0.0, 0.0), egui::pos2(200.0, 0.0)]);
+        connect(&mut app.state.graph, nodes[0], nodes[1], "value");
+
+        // Nothing selected, nothing copied
+        app.dispatch(AppCommand::Copy);
+        app.dispatch(AppCommand::Paste);
+        assert_eq!(app.state.graph.nodes.len(), 2);
+
+        app.state.selected_nodes = nodes.clone();
+        app.dispatch(AppCommand::Copy);
+        app.pointer_pos = Some(egui::pos2(50.0, 300.0));
+        app.dispatch(AppCommand::Paste);
+
+        let pasted = app.state.selected_nodes.clone();
+        assert_eq!(pasted.len(), 2);
+        assert_eq!(app.state.graph.nodes.len(), 4);
+        assert_eq!(app.state.graph.iter_connections().count(), 2);
+        assert_eq!(
+            app.state.node_positions[pasted[1]],
+            egui::pos2(250.0, 300.0)
+        );
+        assert_eq!(app.pending_responses.len(), 2);
+
+        app.dispatch(AppCommand::Duplicate);
+        let duplicated = app.state.selected_nodes.clone();
+        assert!(duplicated.iter().all(|node| !pasted.contains(node)));
+        assert_eq!(app.state.graph.nodes.len(), 6);
+        assert_eq!(app.state.graph.iter_connections().count(), 3);
+        assert_eq!(
+            app.state.node_positions[duplicated[0]],
+            egui::pos2(80.0, 330.0)
+        );
+        assert_eq!(app.pending_responses.len(), 4);
+    }
+
+    #[cfg(feature = "persistence")]
+    #[test]
+    fn test_replaced_state_is_configured() {
+        let (mut app, _) = app_with_nodes(&[]);
+        app.replace_state(MyEditorState::default());
+        let state = &mut app.state;
+        assert!(state.enforce_acyclic && state.drop_on_background);
+        assert!(state.drop_node_on_connection && state.find_nodes);
+        state.add_node(
+            &MyNodeTemplate::MakeScalar,
+            egui::pos2(0.0, 0.0),
+            &mut app.user_state,
+        );
+        assert!(!state.graph.drain_events().is_empty());
     }
 }
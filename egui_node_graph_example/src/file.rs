@@ -0,0 +1,239 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use eframe::egui;
+
+use crate::app::{MyEditorState, MIGRATIONS, STATE_VERSION};
+
+/// An editor state read from a graph file.
+pub struct LoadedGraph<State> {
+    pub state: State,
+    /// Whether the node positions or order referred to missing nodes, or
+    /// missed some nodes, and had to be repaired.
+    pub repaired: bool,
+}
+
+/// Writes the editor state to a file, versioned like the state kept in the
+/// app storage.
+pub fn save_graph(path: &Path, state: &MyEditorState) -> Result<(), String> {
+    let blob = state
+        .serialize_versioned(STATE_VERSION)
+        .map_err(|err| err.to_string())?;
+    std::fs::write(path, blob).map_err(|err| err.to_string())
+}
+
+/// Reads an editor state from a file written by [`save_graph`].
+pub fn load_graph(path: &Path) -> Result<LoadedGraph<MyEditorState>, String> {
+    let blob = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+    graph_from_blob(&blob)
+}
+
+/// Parses the contents of a graph file, migrating it from older versions.
+/// Files whose graph is inconsistent are rejected, while the positions and
+/// order of the nodes are repaired.
+pub fn graph_from_blob(blob: &str) -> Result<LoadedGraph<MyEditorState>, String> {
+    let mut state =
+        MyEditorState::deserialize_with_migrations(blob, STATE_VERSION, MIGRATIONS, None)
+            .map_err(|err| err.to_string())?;
+    let repaired = state.sync_with_graph();
+    Ok(LoadedGraph { state, repaired })
+}
+
+/// How often [`FileWatcher`] checks whether the file was modified.
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Watches a graph file from a background thread, which parses it again
+/// whenever its contents change. The thread stops when the watcher is
+/// dropped.
+pub struct FileWatcher {
+    pub path: PathBuf,
+    receiver: Receiver<Result<LoadedGraph<MyEditorState>, String>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl FileWatcher {
+    /// Starts watching `path`. The current contents of the file aren't
+    /// reported, only the later changes. `ctx` is repainted when the file
+    /// changes, so that [`Self::poll`] gets called.
+    pub fn new(path: PathBuf, ctx: egui::Context) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let thread_path = path.clone();
+        let modified = |path: &Path| -> Option<SystemTime> {
+            std::fs::metadata(path)
+                .and_then(|meta| meta.modified())
+                .ok()
+        };
+        // Read up front, so that any later change is reported
+        let mut last_modified = modified(&path);
+        let mut last_contents = std::fs::read_to_string(&path).ok();
+        std::thread::spawn(move || {
+            while !thread_stop.load(Ordering::Relaxed) {
+                std::thread::sleep(WATCH_INTERVAL);
+                let now_modified = modified(&thread_path);
+                if now_modified == last_modified {
+                    continue;
+                }
+                last_modified = now_modified;
+                // Missing while being rewritten, or touched without changes
+                let contents = match std::fs::read_to_string(&thread_path) {
+                    Ok(contents) if Some(&contents) != last_contents.as_ref() => contents,
+                    _ => continue,
+                };
+                let loaded = graph_from_blob(&contents);
+                last_contents = Some(contents);
+                if sender.send(loaded).is_err() {
+                    break;
+                }
+                ctx.request_repaint();
+            }
+        });
+        Self {
+            path,
+            receiver,
+            stop,
+        }
+    }
+
+    /// The latest version of the file read since the last call, or why it
+    /// couldn't be parsed. `None` if it didn't change.
+    pub fn poll(&self) -> Option<Result<LoadedGraph<MyEditorState>, String>> {
+        self.receiver.try_iter().last()
+    }
+}
+
+impl Drop for FileWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// How long a [`Toast`] that isn't an error stays visible.
+const TOAST_DURATION: f64 = 2.0;
+
+/// A short message shown at the bottom of the window, without interrupting
+/// the user. Errors stay until clicked.
+pub struct Toast {
+    pub message: String,
+    pub is_error: bool,
+    /// When the toast appeared, in seconds, see [`egui::InputState::time`].
+    pub shown_at: f64,
+}
+
+impl Toast {
+    /// Draws the toast. Returns false once it should disappear.
+    pub fn show(&self, ctx: &egui::Context) -> bool {
+        let elapsed = ctx.input(|i| i.time) - self.shown_at;
+        if !self.is_error && elapsed > TOAST_DURATION {
+            return false;
+        }
+        let response = egui::Area::new("toast")
+            .anchor(egui::Align2::CENTER_BOTTOM, egui::vec2(0.0, -20.0))
+            .interactable(true)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    if self.is_error {
+                        ui.colored_label(egui::Color32::RED, &self.message)
+                            .on_hover_text("Click to dismiss")
+                    } else {
+                        ui.label(&self.message)
+                    }
+                })
+            });
+        if !self.is_error {
+            ctx.request_repaint_after(Duration::from_secs_f64(TOAST_DURATION - elapsed));
+        }
+        !(self.is_error
+            && response
+                .inner
+                .inner
+                .interact(egui::Sense::click())
+                .clicked())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileAction {
+    Open,
+    SaveAs,
+    /// Writes the graph as a GraphViz DOT diagram.
+    ExportDot,
+    /// Writes the graph as a Mermaid flowchart.
+    ExportMermaid,
+}
+
+/// Asks for the path of a graph file to open or save, and shows the outcome.
+#[derive(Default)]
+pub struct FileDialog {
+    /// The action the dialog is open for, if any.
+    pub action: Option<FileAction>,
+    pub path: String,
+    /// The outcome of the last action, shown until dismissed, and whether
+    /// it's an error.
+    pub message: Option<(String, bool)>,
+}
+
+impl FileDialog {
+    pub fn open(&mut self, action: FileAction) {
+        self.action = Some(action);
+    }
+
+    /// Draws the dialog, if open. Returns the action to perform when the user
+    /// confirms it.
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<(FileAction, PathBuf)> {
+        let mut confirmed = None;
+        if let Some(action) = self.action {
+            let (title, button) = match action {
+                FileAction::Open => ("Open graph", "Open"),
+                FileAction::SaveAs => ("Save graph as", "Save"),
+                FileAction::ExportDot => ("Export to GraphViz", "Export"),
+                FileAction::ExportMermaid => ("Export to Mermaid", "Export"),
+            };
+            let mut open = true;
+            egui::Window::new(title)
+                .open(&mut open)
+                .resizable(false)
+                .collapsible(false)
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Path");
+                        ui.text_edit_singleline(&mut self.path);
+                    });
+                    let path = self.path.trim();
+                    if ui
+                        .add_enabled(!path.is_empty(), egui::Button::new(button))
+                        .clicked()
+                    {
+                        confirmed = Some((action, PathBuf::from(path)));
+                    }
+                });
+            if !open || confirmed.is_some() {
+                self.action = None;
+            }
+        }
+
+        if let Some((message, is_error)) = &self.message {
+            let mut dismissed = false;
+            let title = if *is_error { "Error" } else { "Graph file" };
+            egui::Window::new(title)
+                .resizable(false)
+                .collapsible(false)
+                .show(ctx, |ui| {
+                    if *is_error {
+                        ui.colored_label(egui::Color32::RED, message);
+                    } else {
+                        ui.label(message);
+                    }
+                    dismissed = ui.button("OK").clicked();
+                });
+            if dismissed {
+                self.message = None;
+            }
+        }
+        confirmed
+    }
+}
@@ -0,0 +1,48 @@
+# Generated by egui_node_graph_example
+
+import math
+
+
+def vadd(a, b):
+    return (a[0] + b[0], a[1] + b[1])
+
+
+def vsub(a, b):
+    return (a[0] - b[0], a[1] - b[1])
+
+
+def vscale(s, v):
+    return (s * v[0], s * v[1])
+
+
+# New scalar
+new_scalar = 2.0
+# New scalar
+new_scalar_2 = 3.5
+# Scalar add
+scalar_add = new_scalar + new_scalar_2
+# New vector
+new_vector = (scalar_add, 1.5)
+# Vector times scalar
+vector_times_scalar = vscale(new_scalar_2, new_vector)
+# Vector add
+vector_add = vadd(vector_times_scalar, (0.0, 0.0))
+# Vector subtract
+vector_subtract = vsub(vector_add, new_vector)
+# Scalar sum
+scalar_sum = sum([new_scalar, new_scalar_2])
+# Scalar product
+scalar_product = math.prod([scalar_sum, 0.25])
+# Scalar subtract
+scalar_subtract = scalar_product - -math.inf
+# Threshold "max"
+threshold_max = math.nan
+# Passthrough: not exported (input 'value' is not connected)
+passthrough = None
+# Passthrough
+passthrough_2 = passthrough
+
+print("Vector subtract", vector_subtract)
+print("Scalar subtract", scalar_subtract)
+print("Threshold \"max\"", threshold_max)
+print("Passthrough", passthrough_2)
@@ -3,7 +3,13 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
 mod app;
-pub use app::NodeGraphExample;
+#[cfg(feature = "persistence")]
+mod file;
+mod python;
+mod relabel;
+mod validation;
+pub use app::{AppCommand, NodeGraphExample};
+pub use validation::{validate_pipeline, Severity, ValidationIssue, ValidationRule, Validator};
 
 // ----------------------------------------------------------------------------
 // When compiling for web:
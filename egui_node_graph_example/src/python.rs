@@ -0,0 +1,331 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+use eframe::egui;
+use egui_node_graph::{InputParamKind, NodeId};
+
+use crate::app::{evaluation_order, MyGraph, MyNodeTemplate, MyValueType};
+
+/// The helpers the generated scripts rely on, for the vector nodes.
+const PRELUDE: &str = "\
+# Generated by egui_node_graph_example
+
+import math
+
+
+def vadd(a, b):
+    return (a[0] + b[0], a[1] + b[1])
+
+
+def vsub(a, b):
+    return (a[0] - b[0], a[1] - b[1])
+
+
+def vscale(s, v):
+    return (s * v[0], s * v[1])
+
+
+";
+
+/// Generates a Python script computing the same values as the graph, one
+/// variable per node, then printing the result of every node without
+/// connected outputs.
+///
+/// Nodes that can't be exported, e.g. because they are part of a cycle, get
+/// a commented placeholder and evaluate to `None`, instead of failing the
+/// whole export.
+pub fn export_python(graph: &MyGraph) -> String {
+    let names = variable_names(graph);
+    let mut code = PRELUDE.to_owned();
+    let mut exported = HashSet::new();
+    let empty_cache = HashMap::new();
+    for node in graph.iter_nodes() {
+        // The dependencies of every node come before it
+        let order = match evaluation_order(graph, node, &empty_cache) {
+            Ok(order) => order
+                .into_iter()
+                .filter(|node| !exported.contains(node))
+                .map(|node| (node, expression(graph, node, &names)))
+                .collect(),
+            Err(err) => vec![(node, Err(err.to_string()))],
+        };
+        for (node, expression) in order {
+            if !exported.insert(node) {
+                continue;
+            }
+            // A label spanning several lines would end the comment
+            let label = graph[node].label.replace('\n', " ");
+            match expression {
+                Ok(expression) => {
+                    writeln!(code, "# {}", label).unwrap();
+                    writeln!(code, "{} = {}", names[&node], expression).unwrap();
+                }
+                Err(reason) => {
+                    writeln!(code, "# {}: not exported ({})", label, reason).unwrap();
+                    writeln!(code, "{} = None", names[&node]).unwrap();
+                }
+            }
+        }
+    }
+
+    let sinks: Vec<NodeId> = graph
+        .iter_nodes()
+        .filter(|node| {
+            graph[*node]
+                .output_ids()
+                .all(|output| graph.output_connection_count(output) == 0)
+        })
+        .collect();
+    if !sinks.is_empty() {
+        code.push('\n');
+    }
+    for sink in sinks {
+        let label = python_string(&graph[sink].label);
+        writeln!(code, "print({}, {})", label, names[&sink]).unwrap();
+    }
+    code
+}
+
+/// The Python expression computing the output of `node`, or why it can't be
+/// exported.
+fn expression(
+    graph: &MyGraph,
+    node: NodeId,
+    names: &HashMap<NodeId, String>,
+) -> Result<String, String> {
+    let input = |name: &str| -> Result<String, String> {
+        let input = graph[node].get_input(name).map_err(|err| err.to_string())?;
+        match graph.connections(input).first() {
+            Some(output) => Ok(names[&graph[*output].node].clone()),
+            None if matches!(graph[input].kind(), InputParamKind::ConnectionOnly)
+                || graph.is_input_required(input) =>
+            {
+                Err(format!("input '{}' is not connected", name))
+            }
+            None => Ok(python_value(graph[input].value())),
+        }
+    };
+    let expression = match graph[node].user_data.template {
+        MyNodeTemplate::MakeScalar => input("value")?,
+        MyNodeTemplate::AddScalar => format!("{} + {}", input("A")?, input("B")?),
+        MyNodeTemplate::SubtractScalar => format!("{} - {}", input("A")?, input("B")?),
+        MyNodeTemplate::SumScalars => {
+            let values = graph[node].get_input("values").map_err(|e| e.to_string())?;
+            let terms: Vec<&str> = graph
+                .connections(values)
+                .iter()
+                .map(|output| names[&graph[*output].node].as_str())
+                .collect();
+            format!("sum([{}])", terms.join(", "))
+        }
+        MyNodeTemplate::MultiplyScalars => {
+            // Every input is a factor, however many were added
+            let factors = graph[node]
+                .inputs
+                .iter()
+                .map(|(name, _)| input(name))
+                .collect::<Result<Vec<_>, _>>()?;
+            format!("math.prod([{}])", factors.join(", "))
+        }
+        MyNodeTemplate::MakeVector => format!("({}, {})", input("x")?, input("y")?),
+        MyNodeTemplate::AddVector => format!("vadd({}, {})", input("v1")?, input("v2")?),
+        MyNodeTemplate::SubtractVector => format!("vsub({}, {})", input("v1")?, input("v2")?),
+        MyNodeTemplate::VectorTimesScalar => {
+            format!("vscale({}, {})", input("scalar")?, input("vector")?)
+        }
+        MyNodeTemplate::Passthrough => input("value")?,
+    };
+    Ok(expression)
+}
+
+/// A Python variable name for every node, derived from its label, e.g.
+/// `scalar_add_2` for the second node labelled "Scalar add".
+fn variable_names(graph: &MyGraph) -> HashMap<NodeId, String> {
+    let mut taken = HashSet::new();
+    graph
+        .iter_nodes()
+        .map(|node| {
+            let mut base = String::new();
+            for c in graph[node].label.chars() {
+                if c.is_ascii_alphanumeric() {
+                    base.push(c.to_ascii_lowercase());
+                } else if !base.ends_with('_') {
+                    base.push('_');
+                }
+            }
+            let mut base = base.trim_matches('_').to_owned();
+            if !base.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                base.insert_str(0, "node_");
+            }
+            let mut name = base.clone();
+            let mut suffix = 1;
+            while !taken.insert(name.clone()) {
+                suffix += 1;
+                name = format!("{}_{}", base, suffix);
+            }
+            (node, name)
+        })
+        .collect()
+}
+
+fn python_float(value: f32) -> String {
+    if value.is_nan() {
+        "math.nan".to_owned()
+    } else if value.is_infinite() {
+        let sign = if value < 0.0 { "-" } else { "" };
+        format!("{}math.inf", sign)
+    } else {
+        format!("{:?}", value)
+    }
+}
+
+fn python_value(value: &MyValueType) -> String {
+    match value {
+        MyValueType::Scalar { value } => python_float(*value),
+        MyValueType::Vec2 { value } => {
+            format!("({}, {})", python_float(value.x), python_float(value.y))
+        }
+    }
+}
+
+fn python_string(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Shows the Python script generated from the graph, following its changes.
+#[derive(Default)]
+pub struct PythonExportWindow {
+    pub open: bool,
+}
+
+impl PythonExportWindow {
+    pub fn show(&mut self, ctx: &egui::Context, graph: &MyGraph) {
+        if !self.open {
+            return;
+        }
+        let code = export_python(graph);
+        egui::Window::new("Python script")
+            .open(&mut self.open)
+            .default_size(egui::vec2(480.0, 400.0))
+            .show(ctx, |ui| {
+                if ui.button("Copy").clicked() {
+                    ui.output_mut(|o| o.copied_text = code.clone());
+                }
+                egui::ScrollArea::both().show(ui, |ui| {
+                    ui.add(
+                        egui::TextEdit::multiline(&mut code.as_str())
+                            .code_editor()
+                            .desired_width(f32::INFINITY),
+                    );
+                });
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{MyGraphState, MyNodeData};
+    use egui_node_graph::NodeTemplateTrait;
+
+    /// The script generated for [`fixture_graph`]. Set `UPDATE_GOLDEN=1` to
+    /// rewrite it after changing the export.
+    const GOLDEN_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/golden/graph.py");
+
+    fn add_node(graph: &mut MyGraph, template: MyNodeTemplate) -> NodeId {
+        graph.add_node(
+            template.node_graph_label(&mut MyGraphState::default()),
+            MyNodeData { template },
+            |graph, node_id| template.build_node(graph, &mut MyGraphState::default(), node_id),
+        )
+    }
+
+    fn connect(graph: &mut MyGraph, from: NodeId, to: NodeId, input: &str) {
+        let output = graph[from].get_output("out").unwrap();
+        let input = graph[to].get_input(input).unwrap();
+        graph.add_connection(output, input);
+    }
+
+    fn set_scalar(graph: &mut MyGraph, node: NodeId, input: &str, value: f32) {
+        let input = graph[node].get_input(input).unwrap();
+        graph[input].value = MyValueType::Scalar { value };
+    }
+
+    /// Uses every template, a constant that isn't finite, a label that needs
+    /// escaping and a node that can't be exported.
+    fn fixture_graph() -> MyGraph {
+        let mut graph = MyGraph::new();
+        let a = add_node(&mut graph, MyNodeTemplate::MakeScalar);
+        set_scalar(&mut graph, a, "value", 2.0);
+        let b = add_node(&mut graph, MyNodeTemplate::MakeScalar);
+        set_scalar(&mut graph, b, "value", 3.5);
+        let add = add_node(&mut graph, MyNodeTemplate::AddScalar);
+        connect(&mut graph, a, add, "A");
+        connect(&mut graph, b, add, "B");
+        let vector = add_node(&mut graph, MyNodeTemplate::MakeVector);
+        connect(&mut graph, add, vector, "x");
+        set_scalar(&mut graph, vector, "y", 1.5);
+        let scaled = add_node(&mut graph, MyNodeTemplate::VectorTimesScalar);
+        connect(&mut graph, b, scaled, "scalar");
+        connect(&mut graph, vector, scaled, "vector");
+        let offset = add_node(&mut graph, MyNodeTemplate::AddVector);
+        connect(&mut graph, scaled, offset, "v1");
+        let difference = add_node(&mut graph, MyNodeTemplate::SubtractVector);
+        connect(&mut graph, offset, difference, "v1");
+        connect(&mut graph, vector, difference, "v2");
+        let sum = add_node(&mut graph, MyNodeTemplate::SumScalars);
+        connect(&mut graph, a, sum, "values");
+        connect(&mut graph, b, sum, "values");
+        let product = add_node(&mut graph, MyNodeTemplate::MultiplyScalars);
+        connect(&mut graph, sum, product, "factor");
+        set_scalar(&mut graph, product, "factor 2", 0.25);
+        let subtract = add_node(&mut graph, MyNodeTemplate::SubtractScalar);
+        connect(&mut graph, product, subtract, "A");
+        set_scalar(&mut graph, subtract, "B", f32::NEG_INFINITY);
+        let threshold = add_node(&mut graph, MyNodeTemplate::MakeScalar);
+        graph[threshold].label = "Threshold \"max\"".to_owned();
+        set_scalar(&mut graph, threshold, "value", f32::NAN);
+        let passthrough = add_node(&mut graph, MyNodeTemplate::Passthrough);
+        let forwarded = add_node(&mut graph, MyNodeTemplate::Passthrough);
+        connect(&mut graph, passthrough, forwarded, "value");
+        graph
+    }
+
+    #[test]
+    fn test_export_matches_golden_file() {
+        let code = export_python(&fixture_graph());
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::write(GOLDEN_PATH, &code).unwrap();
+        }
+        let golden = std::fs::read_to_string(GOLDEN_PATH).unwrap();
+        assert_eq!(code, golden);
+    }
+
+    #[test]
+    fn test_cycles_are_placeholders() {
+        let mut graph = MyGraph::new();
+        let a = add_node(&mut graph, MyNodeTemplate::Passthrough);
+        let b = add_node(&mut graph, MyNodeTemplate::Passthrough);
+        add_node(&mut graph, MyNodeTemplate::MakeScalar);
+        connect(&mut graph, a, b, "value");
+        connect(&mut graph, b, a, "value");
+        let code = export_python(&graph);
+        assert!(code.contains("# Passthrough: not exported (The nodes"));
+        assert!(code.contains("passthrough = None"));
+        assert!(code.contains("passthrough_2 = None"));
+        // The rest of the graph is still exported
+        assert!(code.contains("new_scalar = 0.0"));
+    }
+}
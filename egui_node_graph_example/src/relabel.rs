@@ -0,0 +1,189 @@
+use eframe::egui;
+use egui_node_graph::{Graph, NodeId};
+use regex::Regex;
+
+/// A find / replace dialog to rename many nodes at once. Toggled with
+/// [`AppCommand::ToggleFindReplace`](crate::AppCommand::ToggleFindReplace).
+#[derive(Default)]
+pub struct RelabelDialog {
+    pub open: bool,
+    pub pattern: String,
+    pub replacement: String,
+    /// Interpret the pattern as a regular expression. The replacement can then
+    /// refer to capture groups, e.g. `$1`.
+    pub use_regex: bool,
+    /// Only rename the selected nodes, instead of the whole graph.
+    pub selection_only: bool,
+}
+
+/// A node that would be renamed by the dialog.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Relabel {
+    pub node: NodeId,
+    pub old_label: String,
+    pub new_label: String,
+}
+
+impl RelabelDialog {
+    /// Computes the renames the dialog would perform, without applying them.
+    /// Returns an error message if the pattern is not a valid regex.
+    pub fn preview<NodeData, DataType, ValueType>(
+        &self,
+        graph: &Graph<NodeData, DataType, ValueType>,
+        selected_nodes: &[NodeId],
+    ) -> Result<Vec<Relabel>, String> {
+        if self.pattern.is_empty() {
+            return Ok(Vec::new());
+        }
+        let regex = if self.use_regex {
+            Some(Regex::new(&self.pattern).map_err(|err| err.to_string())?)
+        } else {
+            None
+        };
+
+        Ok(graph
+            .nodes
+            .iter()
+            .filter(|(id, _)| !self.selection_only || selected_nodes.contains(id))
+            .filter_map(|(node, data)| {
+                let new_label = match &regex {
+                    Some(regex) => regex
+                        .replace_all(&data.label, self.replacement.as_str())
+                        .into_owned(),
+                    None => data.label.replace(&self.pattern, &self.replacement),
+                };
+                (new_label != data.label).then(|| Relabel {
+                    node,
+                    old_label: data.label.clone(),
+                    new_label,
+                })
+            })
+            .collect())
+    }
+
+    /// Draws the dialog, if open. When the user applies the changes, the nodes
+    /// are renamed and the performed renames are returned.
+    pub fn show<NodeData, DataType, ValueType>(
+        &mut self,
+        ctx: &egui::Context,
+        graph: &mut Graph<NodeData, DataType, ValueType>,
+        selected_nodes: &[NodeId],
+    ) -> Vec<Relabel> {
+        if !self.open {
+            return Vec::new();
+        }
+
+        let mut open = self.open;
+        let mut apply = false;
+        let preview = self.preview(graph, selected_nodes);
+        egui::Window::new("Find and replace")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("relabel_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Find");
+                        ui.text_edit_singleline(&mut self.pattern);
+                        ui.end_row();
+                        ui.label("Replace with");
+                        ui.text_edit_singleline(&mut self.replacement);
+                        ui.end_row();
+                    });
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.use_regex, "Regex");
+                    ui.checkbox(&mut self.selection_only, "Selected nodes only");
+                });
+                ui.separator();
+
+                match &preview {
+                    Ok(relabels) if relabels.is_empty() => {
+                        ui.weak("No matching nodes");
+                    }
+                    Ok(relabels) => {
+                        egui::ScrollArea::vertical()
+                            .max_height(200.0)
+                            .show(ui, |ui| {
+                                for relabel in relabels {
+                                    ui.label(format!(
+                                        "{} → {}",
+                                        relabel.old_label, relabel.new_label
+                                    ));
+                                }
+                            });
+                    }
+                    Err(err) => {
+                        ui.colored_label(egui::Color32::RED, err);
+                    }
+                }
+
+                let can_apply = matches!(&preview, Ok(relabels) if !relabels.is_empty());
+                apply = ui
+                    .add_enabled(can_apply, egui::Button::new("Apply"))
+                    .clicked();
+            });
+        self.open = open;
+
+        match preview {
+            Ok(relabels) if apply => {
+                for relabel in &relabels {
+                    graph.rename_node(relabel.node, relabel.new_label.clone());
+                }
+                relabels
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph_with_labels(labels: &[&str]) -> (Graph<(), (), ()>, Vec<NodeId>) {
+        let mut graph = Graph::new();
+        let nodes = labels
+            .iter()
+            .map(|label| graph.add_node(label.to_string(), (), |_, _| {}))
+            .collect();
+        (graph, nodes)
+    }
+
+    #[test]
+    fn test_plain_and_regex_preview() {
+        let (graph, nodes) = graph_with_labels(&["cam_rgb", "manip_rgb", "depth"]);
+        let mut dialog = RelabelDialog {
+            pattern: "_rgb".into(),
+            replacement: "_left".into(),
+            ..Default::default()
+        };
+
+        let preview = dialog.preview(&graph, &[]).unwrap();
+        let mut new_labels: Vec<_> = preview.iter().map(|r| r.new_label.as_str()).collect();
+        new_labels.sort_unstable();
+        assert_eq!(new_labels, ["cam_left", "manip_left"]);
+
+        dialog.selection_only = true;
+        let preview = dialog.preview(&graph, &nodes[1..]).unwrap();
+        assert_eq!(preview.len(), 1);
+        assert_eq!(preview[0].node, nodes[1]);
+
+        dialog.selection_only = false;
+        dialog.use_regex = true;
+        dialog.pattern = "^(\\w+)_rgb$".into();
+        dialog.replacement = "left_$1".into();
+        let preview = dialog.preview(&graph, &[]).unwrap();
+        assert!(preview.iter().any(|r| r.new_label == "left_cam"));
+    }
+
+    #[test]
+    fn test_invalid_regex_is_an_error() {
+        let (graph, _) = graph_with_labels(&["cam_rgb"]);
+        let dialog = RelabelDialog {
+            pattern: "(unclosed".into(),
+            use_regex: true,
+            ..Default::default()
+        };
+        assert!(dialog.preview(&graph, &[]).is_err());
+    }
+}
@@ -0,0 +1,236 @@
+use egui_node_graph::{InputParamKind, NodeId};
+
+use crate::app::{MyGraph, MyNodeTemplate};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The graph can be evaluated, but probably doesn't do what was meant.
+    Warning,
+    /// Evaluating the node fails.
+    Error,
+}
+
+/// A problem found in the graph by a [`ValidationRule`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidationIssue {
+    pub severity: Severity,
+    pub message: String,
+    pub node: NodeId,
+    /// The input or output the issue is about, if any.
+    pub param: Option<String>,
+}
+
+/// A check of the graph, reporting the issues it finds.
+pub type ValidationRule = Box<dyn Fn(&MyGraph, &mut Vec<ValidationIssue>)>;
+
+/// Checks the graph with a list of rules. [`Validator::default`] has the
+/// built-in rules, and [`Validator::add_rule`] adds custom ones.
+pub struct Validator {
+    rules: Vec<ValidationRule>,
+}
+
+impl Default for Validator {
+    fn default() -> Self {
+        let mut validator = Self { rules: Vec::new() };
+        validator.add_rule(unconnected_inputs);
+        validator.add_rule(empty_sums);
+        validator.add_rule(non_finite_constants);
+        validator.add_rule(unused_constants);
+        validator
+    }
+}
+
+impl Validator {
+    pub fn add_rule(&mut self, rule: impl Fn(&MyGraph, &mut Vec<ValidationIssue>) + 'static) {
+        self.rules.push(Box::new(rule));
+    }
+
+    /// The issues found by every rule, errors first.
+    pub fn validate(&self, graph: &MyGraph) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        for rule in &self.rules {
+            rule(graph, &mut issues);
+        }
+        // Stable, so each rule keeps the order of its issues
+        issues.sort_by_key(|issue| std::cmp::Reverse(issue.severity));
+        issues
+    }
+}
+
+/// Checks the graph with the built-in rules, see [`Validator`].
+pub fn validate_pipeline(graph: &MyGraph) -> Vec<ValidationIssue> {
+    Validator::default().validate(graph)
+}
+
+/// Required inputs, and inputs that only take a connection, that have none.
+/// Wide inputs may stay empty, see [`empty_sums`].
+fn unconnected_inputs(graph: &MyGraph, issues: &mut Vec<ValidationIssue>) {
+    for (input_id, input) in &graph.inputs {
+        let connection_only = matches!(input.kind(), InputParamKind::ConnectionOnly);
+        let wide = input.max_connections.map(|max| max.get()) != Some(1);
+        let needs_connection = graph.is_input_required(input_id) || (connection_only && !wide);
+        if needs_connection && !graph.is_input_connected(input_id) {
+            let name = graph.param_name(input_id.into()).unwrap_or_default();
+            issues.push(ValidationIssue {
+                severity: Severity::Error,
+                message: format!("Input '{}' is not connected", name),
+                node: input.node(),
+                param: Some(name.to_owned()),
+            });
+        }
+    }
+}
+
+/// Sums without any value, which are always zero.
+fn empty_sums(graph: &MyGraph, issues: &mut Vec<ValidationIssue>) {
+    for (node_id, node) in &graph.nodes {
+        if node.user_data.template != MyNodeTemplate::SumScalars {
+            continue;
+        }
+        let empty = node
+            .input_ids()
+            .all(|input| !graph.is_input_connected(input));
+        if empty {
+            issues.push(ValidationIssue {
+                severity: Severity::Warning,
+                message: "Nothing to sum, the result is always 0".to_owned(),
+                node: node_id,
+                param: None,
+            });
+        }
+    }
+}
+
+/// Constants that are NaN or infinite, which the results inherit.
+fn non_finite_constants(graph: &MyGraph, issues: &mut Vec<ValidationIssue>) {
+    for (input_id, input) in &graph.inputs {
+        if !input.value().is_finite() && !graph.is_input_connected(input_id) {
+            let name = graph.param_name(input_id.into()).unwrap_or_default();
+            issues.push(ValidationIssue {
+                severity: Severity::Warning,
+                message: format!("Input '{}' is not a finite number", name),
+                node: input.node(),
+                param: Some(name.to_owned()),
+            });
+        }
+    }
+}
+
+/// Constants whose value isn't used by any node.
+fn unused_constants(graph: &MyGraph, issues: &mut Vec<ValidationIssue>) {
+    for (node_id, node) in &graph.nodes {
+        let constant = matches!(
+            node.user_data.template,
+            MyNodeTemplate::MakeScalar | MyNodeTemplate::MakeVector
+        );
+        let unused = node
+            .output_ids()
+            .all(|output| graph.output_connection_count(output) == 0);
+        if constant && unused {
+            issues.push(ValidationIssue {
+                severity: Severity::Warning,
+                message: "The value isn't used by any node".to_owned(),
+                node: node_id,
+                param: Some("out".to_owned()),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{MyGraphState, MyNodeData, MyValueType};
+    use egui_node_graph::NodeTemplateTrait;
+
+    fn add_node(graph: &mut MyGraph, template: MyNodeTemplate) -> NodeId {
+        graph.add_node(
+            template.node_graph_label(&mut MyGraphState::default()),
+            MyNodeData { template },
+            |graph, node_id| template.build_node(graph, &mut MyGraphState::default(), node_id),
+        )
+    }
+
+    fn connect(graph: &mut MyGraph, from: NodeId, to: NodeId, input: &str) {
+        let output = graph[from].get_output("out").unwrap();
+        let input = graph[to].get_input(input).unwrap();
+        graph.add_connection(output, input);
+    }
+
+    fn summary(issues: &[ValidationIssue]) -> Vec<(Severity, NodeId, Option<&str>)> {
+        issues
+            .iter()
+            .map(|issue| (issue.severity, issue.node, issue.param.as_deref()))
+            .collect()
+    }
+
+    #[test]
+    fn test_builtin_rules() {
+        let mut graph = MyGraph::new();
+        let constant = add_node(&mut graph, MyNodeTemplate::MakeScalar);
+        let sum = add_node(&mut graph, MyNodeTemplate::SumScalars);
+        let passthrough = add_node(&mut graph, MyNodeTemplate::Passthrough);
+        let add = add_node(&mut graph, MyNodeTemplate::AddScalar);
+        connect(&mut graph, sum, passthrough, "value");
+        connect(&mut graph, passthrough, add, "A");
+        let b = graph[add].get_input("B").unwrap();
+        graph[b].value = MyValueType::Scalar { value: f32::NAN };
+
+        assert_eq!(
+            summary(&validate_pipeline(&graph)),
+            [
+                (Severity::Warning, sum, None),
+                (Severity::Warning, add, Some("B")),
+                (Severity::Warning, constant, Some("out")),
+            ]
+        );
+
+        // Errors come first
+        graph.remove_connection(graph[passthrough].get_input("value").unwrap());
+        connect(&mut graph, constant, sum, "values");
+        graph[b].value = MyValueType::Scalar { value: 1.0 };
+        let issues = validate_pipeline(&graph);
+        assert_eq!(
+            summary(&issues),
+            [(Severity::Error, passthrough, Some("value"))]
+        );
+        assert_eq!(issues[0].message, "Input 'value' is not connected");
+
+        // Required inputs need a connection, even when they take a constant
+        connect(&mut graph, sum, passthrough, "value");
+        let scaled = add_node(&mut graph, MyNodeTemplate::VectorTimesScalar);
+        connect(&mut graph, passthrough, scaled, "scalar");
+        assert_eq!(
+            summary(&validate_pipeline(&graph)),
+            [(Severity::Error, scaled, Some("vector"))]
+        );
+    }
+
+    #[test]
+    fn test_custom_rule() {
+        let mut graph = MyGraph::new();
+        let node = add_node(&mut graph, MyNodeTemplate::Passthrough);
+        graph[node].label = "TODO".into();
+
+        let mut validator = Validator::default();
+        validator.add_rule(|graph, issues| {
+            for (node, data) in &graph.nodes {
+                if data.label.contains("TODO") {
+                    issues.push(ValidationIssue {
+                        severity: Severity::Warning,
+                        message: "Unfinished node".into(),
+                        node,
+                        param: None,
+                    });
+                }
+            }
+        });
+        assert_eq!(
+            summary(&validator.validate(&graph)),
+            [
+                (Severity::Error, node, Some("value")),
+                (Severity::Warning, node, None),
+            ]
+        );
+    }
+}